@@ -1,21 +1,482 @@
+use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::Instant};
+
+use anyhow::*;
 use bevy_ecs::{schedule::Schedule, world::World};
-use blockworld_server::world::disk_chunk_access::DiskChunkArray;
+use blockworld_server::{
+    block::shape::Aabb,
+    entity::{
+        game_mode::{AdventureTags, GameMode},
+        movement::{Body, MoveInput},
+    },
+    packet::{
+        play::{PlayClientbound, PlayServerbound, PlayerCommandAction},
+        VarInt,
+    },
+    world::{
+        chunk_access::WorldAccess,
+        raycast::{BlockHit, RayContext},
+    },
+};
+use glam::*;
+use tokio::runtime::Runtime;
+
+use super::{
+    client_chunk_cache::ClientChunkCache,
+    client_entities::ClientEntities,
+    connection::{ConnectOptions, ServerConnection},
+    integrated_server::IntegratedServer,
+    interaction::{InteractInput, Interaction},
+    local_player::LocalPlayer,
+};
+
+/// Height of the eyes over the feet, the camera is at the eyes and the server tracks the feet.
+pub use blockworld_server::entity::interaction::EYE_HEIGHT;
+
+/// What the server last said of our health and hunger.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerHealth {
+    pub health: f32,
+    pub food: u32,
+    pub saturation: f32,
+}
+
+/// Where the player is, feet position and rotation in radians.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerPose {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+}
 
 pub struct BlockworldClient {
     ecs: World,
     schedule: Schedule,
 
-    chunks: DiskChunkArray,
+    chunks: ClientChunkCache,
+    entities: ClientEntities,
+    connection: Option<ServerConnection>,
+    /// Dropped after the connection, so the player is saved as having left.
+    integrated_server: Option<IntegratedServer>,
+
+    /// Where the server put us last, until the camera picks it up.
+    teleport: Option<PlayerPose>,
+    /// Our own player, once the server told us where it is.
+    player: Option<LocalPlayer>,
+    interaction: Interaction,
+    flying: bool,
+    health: Option<PlayerHealth>,
+    disconnected: Option<String>,
+    sneaking: bool,
+    sprinting: bool,
+}
+
+/// The runtime connections and the integrated server run on.
+pub fn network_runtime() -> Result<Arc<Runtime>> {
+    Ok(Arc::new(
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("Client IO")
+            .enable_all()
+            .build()?,
+    ))
 }
 
 impl BlockworldClient {
+    /// A client with no server, nothing arrives and nothing is sent.
     pub fn new() -> Self {
         let ecs = World::default();
         let schedule = Schedule::default();
         Self {
             ecs,
             schedule,
-            chunks: DiskChunkArray::new(4),
+            chunks: ClientChunkCache::new(),
+            entities: ClientEntities::new(),
+            connection: None,
+            integrated_server: None,
+            teleport: None,
+            player: None,
+            interaction: Interaction::new(),
+            flying: false,
+            health: None,
+            disconnected: None,
+            sneaking: false,
+            sprinting: false,
+        }
+    }
+
+    /// A client over already loaded chunks, for fixtures.
+    #[cfg(test)]
+    pub fn with_chunks(chunks: ClientChunkCache) -> Self {
+        Self {
+            chunks,
+            ..Self::new()
+        }
+    }
+
+    /// Join the server at `address` (`host:port`).
+    pub fn connect(address: &str, name: &str, options: &ConnectOptions) -> Result<Self> {
+        let connection = ServerConnection::connect(network_runtime()?, address, name, options)?;
+        Ok(Self {
+            connection: Some(connection),
+            ..Self::new()
+        })
+    }
+
+    /// Start an integrated server on `world` and join it, in `game_mode` the first time. With
+    /// `lan`, the server is open to the LAN and announces itself there.
+    pub fn singleplayer(
+        world: PathBuf,
+        name: &str,
+        view_distance: u32,
+        game_mode: GameMode,
+        lan: Option<SocketAddr>,
+        options: &ConnectOptions,
+    ) -> Result<Self> {
+        let runtime = network_runtime()?;
+        let server =
+            IntegratedServer::start(runtime.clone(), world, view_distance, game_mode, lan)?;
+        let address = server.address().to_string();
+        let connection = ServerConnection::connect(runtime, &address, name, options)?;
+        Ok(Self {
+            connection: Some(connection),
+            integrated_server: Some(server),
+            ..Self::new()
+        })
+    }
+
+    pub fn chunks(&self) -> &ClientChunkCache {
+        &self.chunks
+    }
+
+    pub fn chunks_mut(&mut self) -> &mut ClientChunkCache {
+        &mut self.chunks
+    }
+
+    pub fn entities(&self) -> &ClientEntities {
+        &self.entities
+    }
+
+    /// Apply every packet that arrived since the last call.
+    pub fn handle_packets(&mut self) {
+        while let Some(packet) = self.connection.as_mut().and_then(|c| c.poll()) {
+            self.handle_packet(packet);
+        }
+    }
+
+    /// Apply one packet from the server.
+    pub fn handle_packet(&mut self, packet: PlayClientbound) {
+        match packet {
+            PlayClientbound::PlayerPosition {
+                position,
+                yaw,
+                pitch,
+            } => {
+                match &mut self.player {
+                    Some(player) => player.reset(position),
+                    None => self.player = Some(LocalPlayer::new(position)),
+                }
+                self.teleport = Some(PlayerPose {
+                    position,
+                    yaw,
+                    pitch,
+                })
+            }
+            PlayClientbound::PlayerState {
+                sequence,
+                position,
+                velocity,
+                on_ground,
+            } => {
+                if let Some(player) = &mut self.player {
+                    let state = Body {
+                        position,
+                        velocity,
+                        on_ground,
+                    };
+                    player.reconcile(sequence.0, state, &self.chunks);
+                }
+            }
+            PlayClientbound::Abilities {
+                game_mode,
+                can_destroy,
+                can_place_on,
+            } => {
+                let tags = AdventureTags::from_ids(&can_destroy, &can_place_on);
+                self.interaction.set_abilities(game_mode, tags);
+                self.flying = game_mode
+                    .restrict(MoveInput {
+                        flying: self.flying,
+                        ..Default::default()
+                    })
+                    .flying;
+            }
+            PlayClientbound::UpdateHealth {
+                health,
+                food,
+                saturation,
+            } => {
+                self.health = Some(PlayerHealth {
+                    health,
+                    food: food.0.max(0) as u32,
+                    saturation,
+                })
+            }
+            PlayClientbound::Disconnect { reason } => {
+                log::warn!("Disconnected: {}", reason);
+                self.disconnected = Some(reason);
+                self.connection = None;
+                self.entities.clear();
+            }
+            packet @ (PlayClientbound::SpawnEntity { .. }
+            | PlayClientbound::DestroyEntities { .. }
+            | PlayClientbound::EntityMove { .. }
+            | PlayClientbound::EntityMoveRotate { .. }
+            | PlayClientbound::EntityRotate { .. }
+            | PlayClientbound::EntityTeleport { .. }) => {
+                self.entities.update(packet, Instant::now())
+            }
+            packet => self.chunks.update(packet),
+        }
+    }
+
+    /// Where the server moved us, once per move.
+    pub fn take_teleport(&mut self) -> Option<PlayerPose> {
+        self.teleport.take()
+    }
+
+    /// Why the server closed the connection, after it did.
+    pub fn disconnected(&self) -> Option<&str> {
+        self.disconnected.as_deref()
+    }
+
+    /// Run one game tick of the client's systems, and move our player by `input` as the game
+    /// mode allows, at once here and soon on the server.
+    pub fn tick(&mut self, input: MoveInput) {
+        self.schedule.run(&mut self.ecs);
+        if self.connection.is_none() {
+            return;
+        }
+        let input = self.game_mode().restrict(input);
+        let Some(sequence) = self.predict(input) else {
+            return;
+        };
+        self.send(PlayServerbound::PlayerInput {
+            sequence: VarInt(sequence),
+            input,
+        });
+        self.set_sneaking(input.sneak);
+        self.set_sprinting(input.sprint);
+    }
+
+    /// Move our player by one game tick of `input` here only, returning the sequence number
+    /// the input goes to the server with.
+    pub fn predict(&mut self, input: MoveInput) -> Option<i32> {
+        let player = self.player.as_mut()?;
+        Some(player.tick(input, &self.chunks))
+    }
+
+    pub fn player(&self) -> Option<&LocalPlayer> {
+        self.player.as_ref()
+    }
+
+    /// The block looked at from `eye` towards `gaze`, if it's within reach.
+    pub fn pick(&self, eye: Vec3, gaze: Vec3) -> Option<BlockHit> {
+        self.chunks
+            .raycast(eye, gaze, self.game_mode().reach(), RayContext::PICK)
+    }
+
+    /// Run one game tick of breaking and placing on `target`.
+    pub fn interact(&mut self, target: Option<BlockHit>, input: InteractInput) {
+        let Some(player) = &self.player else {
+            return;
+        };
+        let entities: Vec<Aabb> = self
+            .entities
+            .poses(Instant::now())
+            .map(|pose| {
+                let (width, height) = pose.kind.size();
+                Aabb::from_feet(pose.position, width, height)
+            })
+            .collect();
+        let packets =
+            self.interaction
+                .tick(&mut self.chunks, player.body(), &entities, target, input);
+        for packet in packets {
+            self.send(packet);
+        }
+    }
+
+    pub fn interaction(&self) -> &Interaction {
+        &self.interaction
+    }
+
+    pub fn interaction_mut(&mut self) -> &mut Interaction {
+        &mut self.interaction
+    }
+
+    /// Where the camera goes, `partial` of the way from the last tick to this one.
+    pub fn eye_position(&self, partial: f32) -> Option<Vec3> {
+        let player = self.player.as_ref()?;
+        Some(player.position(partial) + Vec3::Y * EYE_HEIGHT)
+    }
+
+    pub fn game_mode(&self) -> GameMode {
+        self.interaction.game_mode()
+    }
+
+    pub fn health(&self) -> Option<PlayerHealth> {
+        self.health
+    }
+
+    pub fn flying(&self) -> bool {
+        self.flying
+    }
+
+    /// Take off or land, in the modes that fly by choice.
+    pub fn toggle_flying(&mut self) {
+        if self.game_mode().may_fly() && !self.game_mode().noclip() {
+            self.flying = !self.flying;
+        }
+    }
+
+    fn set_sneaking(&mut self, sneaking: bool) {
+        if sneaking != self.sneaking {
+            self.sneaking = sneaking;
+            self.send(PlayServerbound::PlayerCommand {
+                action: match sneaking {
+                    true => PlayerCommandAction::StartSneaking,
+                    false => PlayerCommandAction::StopSneaking,
+                },
+            });
+        }
+    }
+
+    fn set_sprinting(&mut self, sprinting: bool) {
+        if sprinting != self.sprinting {
+            self.sprinting = sprinting;
+            self.send(PlayServerbound::PlayerCommand {
+                action: match sprinting {
+                    true => PlayerCommandAction::StartSprinting,
+                    false => PlayerCommandAction::StopSprinting,
+                },
+            });
+        }
+    }
+
+    fn send(&self, packet: PlayServerbound) {
+        if let Some(connection) = &self.connection {
+            connection.send(packet);
+        }
+    }
+
+    /// Leave the server, and save the world if it is ours.
+    pub fn quit(&mut self) {
+        self.connection = None;
+        if let Some(mut server) = self.integrated_server.take() {
+            if let Err(e) = server.stop() {
+                log::error!("Failed to save the world: {:#}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use blockworld_server::network::capture::read_capture;
+    use blockworld_utils::TempDir;
+
+    use super::*;
+    use crate::game::{connection::LinkConditions, replay};
+
+    /// Poll until `done` holds, failing after a few seconds.
+    fn wait_for(
+        game: &mut BlockworldClient,
+        what: &str,
+        done: impl Fn(&mut BlockworldClient) -> bool,
+    ) {
+        let start = Instant::now();
+        while !done(game) {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "timed out waiting for {}",
+                what
+            );
+            std::thread::sleep(Duration::from_millis(10));
+            game.handle_packets();
+        }
+    }
+
+    #[test]
+    fn singleplayer_streams_the_world() {
+        let root = TempDir::new("client").unwrap();
+        {
+            let mut world = blockworld_server::Blockworld::open(&root).unwrap();
+            world.chunks_mut().load_chunk(ivec3(0, 4, 0));
+            world
+                .chunks_mut()
+                .set_block(ivec3(0, 70, 0), &"minecraft:stone".into());
+            world.save().unwrap();
         }
+
+        // far enough that every input is acknowledged a few ticks after it was predicted
+        let options = ConnectOptions {
+            link: LinkConditions {
+                latency: Duration::from_millis(80),
+                jitter: Duration::from_millis(40),
+            },
+            capture: Some(root.join("session.bwcap")),
+        };
+        let mut game = BlockworldClient::singleplayer(
+            root.to_path_buf(),
+            "Steve",
+            1,
+            GameMode::Creative,
+            None,
+            &options,
+        )
+        .unwrap();
+        wait_for(&mut game, "spawn", |g| g.take_teleport().is_some());
+        wait_for(&mut game, "the spawn chunk", |g| {
+            g.chunks().get_block(ivec3(0, 70, 0)) == "minecraft:stone".into()
+        });
+
+        // flying away a tick at a time makes the server take the spawn chunk back
+        let fly = MoveInput {
+            forward: 1,
+            sprint: true,
+            flying: true,
+            yaw: std::f32::consts::FRAC_PI_2,
+            ..Default::default()
+        };
+        for _ in 0..60 {
+            game.tick(fly);
+            std::thread::sleep(Duration::from_millis(50));
+            game.handle_packets();
+        }
+        wait_for(&mut game, "every input acknowledged", |g| {
+            g.player().unwrap().pending() == 0
+        });
+        wait_for(&mut game, "the unload", |g| {
+            !g.chunks().is_chunk_loaded(ivec3(0, 4, 0))
+        });
+        let player = game.player().unwrap();
+        assert_eq!(player.corrections(), 0);
+        let predicted = player.body().position;
+        assert!(predicted.x > 32.0, "{}", predicted);
+
+        game.quit();
+        assert!(game.disconnected().is_none());
+        let storage = blockworld_server::world::storage::WorldStorage::open(&root).unwrap();
+        let player = storage.read_player("Steve").unwrap().unwrap();
+        assert_eq!(player.position, predicted.to_array());
+
+        // the recording, played to a client with no server, gives the same prediction
+        let records = read_capture(&root.join("session.bwcap")).unwrap();
+        let replayed = replay::replay_on_client(&records).unwrap();
+        assert_eq!(replayed.inputs, 60);
+        assert_eq!(replayed.corrections, vec![]);
+        assert_eq!(replayed.position, Some(predicted));
     }
 }
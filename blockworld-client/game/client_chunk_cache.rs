@@ -0,0 +1,328 @@
+//! ```text
+//! package net.minecraft.client.multiplayer
+//! class ClientChunkProvider
+//! version 1.16
+//! ```
+//!
+//! The sections the server sent us. Nothing is loaded or saved here, the cache only changes
+//! when a packet says so.
+
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+
+use blockworld_server::{
+    block::state::{BlockState, StateId},
+    packet::{
+        chunk_data::{ChunkColumnData, LightData, BIOME_CELLS, SECTIONS},
+        play::PlayClientbound,
+    },
+    world::{
+        chunk::{SubChunk, SUBCHUNK_SIZE},
+        chunk_access::{UpdateFlag, WorldAccess},
+    },
+};
+use enumflags2::BitFlags;
+use glam::*;
+
+fn split_blockpos(pos: IVec3) -> (IVec3, IVec3) {
+    let size = IVec3::splat(16);
+    (pos.div_euclid(size), pos.rem_euclid(size))
+}
+
+/// What a column carries besides its blocks.
+struct ColumnInfo {
+    sky_light: Vec<LightData>,
+    block_light: Vec<LightData>,
+    biomes: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct ClientChunkCache {
+    /// Sections with blocks in them, the others are air.
+    chunks: HashMap<IVec3, SubChunk>,
+    columns: HashMap<IVec2, ColumnInfo>,
+    /// Sections changed since the renderer last meshed them.
+    need_rerender: HashSet<IVec3>,
+}
+
+impl ClientChunkCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget which sections changed, once they are meshed.
+    pub fn clear_need_rerender(&mut self) {
+        self.need_rerender.clear();
+    }
+
+    /// Sky light at a block, full outside the columns we have.
+    pub fn sky_light(&self, pos: IVec3) -> u8 {
+        self.light(pos, |c| &c.sky_light).unwrap_or(15)
+    }
+
+    pub fn block_light(&self, pos: IVec3) -> u8 {
+        self.light(pos, |c| &c.block_light).unwrap_or(0)
+    }
+
+    fn light(&self, pos: IVec3, f: impl Fn(&ColumnInfo) -> &Vec<LightData>) -> Option<u8> {
+        let (section, local) = split_blockpos(pos);
+        let column = self.columns.get(&section.xz())?;
+        let light = f(column).get(usize::try_from(section.y).ok()?)?;
+        Some(light.get(SubChunk::index(local.x, local.y, local.z)))
+    }
+
+    /// Biome of the 4x4x4 cell holding a block.
+    pub fn biome(&self, pos: IVec3) -> Option<&str> {
+        let (section, local) = split_blockpos(pos);
+        let column = self.columns.get(&section.xz())?;
+        let cell = ivec3(local.x, pos.y, local.z) / 4;
+        let index = usize::try_from((cell.y * 4 + cell.z) * 4 + cell.x).ok()?;
+        column.biomes.get(index).map(|b| b.as_str())
+    }
+
+    /// Redraw a section and the loaded ones next to it, whose faces against it may change.
+    fn mark_section(&mut self, section: IVec3) {
+        for offset in [
+            IVec3::ZERO,
+            IVec3::X,
+            IVec3::NEG_X,
+            IVec3::Y,
+            IVec3::NEG_Y,
+            IVec3::Z,
+            IVec3::NEG_Z,
+        ] {
+            let pos = section + offset;
+            if offset == IVec3::ZERO || self.chunks.contains_key(&pos) {
+                self.need_rerender.insert(pos);
+            }
+        }
+    }
+
+    /// Redraw the section of a block, and its neighbour too if the block is on the border.
+    fn mark_block(&mut self, pos: IVec3) {
+        let (section, local) = split_blockpos(pos);
+        self.need_rerender.insert(section);
+        let last = SUBCHUNK_SIZE as i32 - 1;
+        for axis in 0..3 {
+            let mut offset = IVec3::ZERO;
+            offset[axis] = match local[axis] {
+                0 => -1,
+                l if l == last => 1,
+                _ => continue,
+            };
+            if self.chunks.contains_key(&(section + offset)) {
+                self.need_rerender.insert(section + offset);
+            }
+        }
+    }
+
+    fn set_state_id(&mut self, pos: IVec3, id: StateId) {
+        let (section, _) = split_blockpos(pos);
+        // sections the server didn't send are air, the update makes one
+        self.load_chunk(section);
+        self.set_state(pos, BlockState::from_id(id).unwrap_or_default());
+    }
+
+    fn load_column(&mut self, column: IVec2, data: ChunkColumnData) {
+        self.unload_column(column);
+        for y in 0..SECTIONS as i32 {
+            match data.sub_chunk(column, y) {
+                Ok(Some(sc)) => {
+                    self.chunks.insert(sc.pos(), sc);
+                }
+                Ok(None) => {}
+                Err(e) => log::error!("Bad chunk column {}: {:#}", column, e),
+            }
+            self.mark_section(ivec3(column.x, y, column.y));
+        }
+        let biomes = match data.biomes.unpack(BIOME_CELLS) {
+            Ok(biomes) => biomes,
+            Err(e) => {
+                log::error!("Bad biomes in chunk column {}: {:#}", column, e);
+                vec![]
+            }
+        };
+        self.columns.insert(
+            column,
+            ColumnInfo {
+                sky_light: data.sky_light,
+                block_light: data.block_light,
+                biomes,
+            },
+        );
+    }
+
+    fn unload_column(&mut self, column: IVec2) {
+        self.columns.remove(&column);
+        self.chunks.retain(|p, _| p.xz() != column);
+        self.need_rerender.retain(|p| p.xz() != column);
+    }
+}
+
+impl WorldAccess for ClientChunkCache {
+    fn get_chunk(&self, pos: IVec3) -> &SubChunk {
+        &self.chunks[&pos]
+    }
+
+    fn is_chunk_loaded(&self, pos: IVec3) -> bool {
+        self.chunks.contains_key(&pos)
+    }
+
+    /// An empty section, until the server sends the real one.
+    fn load_chunk(&mut self, pos: IVec3) {
+        if let Entry::Vacant(entry) = self.chunks.entry(pos) {
+            entry.insert(SubChunk::new(pos));
+            self.need_rerender.insert(pos);
+        }
+    }
+
+    fn unload_chunk(&mut self, pos: IVec3) {
+        self.chunks.remove(&pos);
+        self.need_rerender.remove(&pos);
+    }
+
+    fn need_rerender(&self, pos: IVec3) -> bool {
+        self.need_rerender.contains(&pos)
+    }
+
+    fn update(&mut self, packet: PlayClientbound) {
+        match packet {
+            PlayClientbound::ChunkData { column, data } => self.load_column(column, data.0),
+            PlayClientbound::UnloadChunk { column } => self.unload_column(column),
+            PlayClientbound::BlockUpdate { pos, block } => {
+                self.set_state_id(pos, block.0 as StateId);
+            }
+            PlayClientbound::MultiBlockChange { section, changes } => {
+                for change in changes {
+                    self.set_state_id(section * 16 + change.local, change.block);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn iter_loaded_chunks(&self) -> impl Iterator<Item = &SubChunk> {
+        self.chunks.values()
+    }
+
+    /// Blocks in sections we don't have read as air.
+    fn get_state(&self, pos: IVec3) -> BlockState {
+        let (section, local) = split_blockpos(pos);
+        match self.chunks.get(&section) {
+            Some(sc) => sc.get_state(local),
+            None => BlockState::AIR,
+        }
+    }
+
+    /// Only the server updates neighbours, the client just redraws unless told not to.
+    fn set_state_with(&mut self, pos: IVec3, state: BlockState, flags: BitFlags<UpdateFlag>) {
+        let (section, local) = split_blockpos(pos);
+        if let Some(sc) = self.chunks.get_mut(&section) {
+            sc.set_state(local, state);
+            if !flags.contains(UpdateFlag::NoRerender) {
+                self.mark_block(pos);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blockworld_server::packet::{
+        chunk_data::Compressed,
+        play::{BlockChange, PlayClientbound},
+        VarInt,
+    };
+
+    use super::*;
+
+    fn column_packet(column: IVec2, sections: &[&SubChunk]) -> PlayClientbound {
+        let data = ChunkColumnData::new(|y| sections.iter().find(|s| s.pos().y == y).copied());
+        PlayClientbound::ChunkData {
+            column,
+            data: Compressed(data),
+        }
+    }
+
+    #[test]
+    fn fed_by_packets() {
+        let mut sc = SubChunk::new(ivec3(-1, 4, 2));
+        sc.set_blockid(ivec3(0, 0, 0), "minecraft:stone");
+
+        let mut cache = ClientChunkCache::new();
+        cache.update(column_packet(ivec2(-1, 2), &[&sc]));
+        assert!(cache.need_rerender(ivec3(-1, 4, 2)));
+        assert_eq!(
+            cache.get_block(ivec3(-16, 64, 32)),
+            "minecraft:stone".into()
+        );
+        assert_eq!(cache.iter_loaded_chunks().count(), 1);
+        assert_eq!(cache.sky_light(ivec3(-16, 63, 32)), 0);
+        assert_eq!(cache.sky_light(ivec3(-16, 65, 32)), 15);
+        assert_eq!(cache.biome(ivec3(-16, 64, 32)), Some("minecraft:plains"));
+
+        cache.clear_need_rerender();
+        cache.update(PlayClientbound::BlockUpdate {
+            pos: ivec3(-10, 70, 40),
+            block: VarInt(3),
+        });
+        assert_eq!(cache.get_block(ivec3(-10, 70, 40)), "minecraft:dirt".into());
+        assert!(cache.need_rerender(ivec3(-1, 4, 2)));
+
+        // an update in a section never sent fills it with air first
+        cache.update(PlayClientbound::MultiBlockChange {
+            section: ivec3(0, 0, 0),
+            changes: vec![
+                BlockChange {
+                    local: ivec3(0, 0, 0),
+                    block: 1,
+                },
+                BlockChange {
+                    local: ivec3(1, 0, 0),
+                    block: 1,
+                },
+            ],
+        });
+        assert_eq!(cache.get_block(ivec3(1, 0, 0)), "minecraft:stone".into());
+        assert_eq!(cache.iter_loaded_chunks().count(), 2);
+
+        cache.update(PlayClientbound::UnloadChunk {
+            column: ivec2(-1, 2),
+        });
+        assert_eq!(cache.iter_loaded_chunks().count(), 1);
+        assert!(cache.is_air(ivec3(-16, 64, 32)));
+        assert_eq!(cache.biome(ivec3(-16, 64, 32)), None);
+    }
+
+    #[test]
+    fn only_touched_sections_rerender() {
+        let mut cache = ClientChunkCache::new();
+        let mut a = SubChunk::new(ivec3(0, 4, 0));
+        a.set_blockid(ivec3(0, 0, 0), "minecraft:stone");
+        let mut b = SubChunk::new(ivec3(1, 4, 0));
+        b.set_blockid(ivec3(0, 0, 0), "minecraft:stone");
+        cache.update(column_packet(ivec2(0, 0), &[&a]));
+        cache.clear_need_rerender();
+
+        // a new column redraws its neighbours, whose border faces may now be hidden
+        cache.update(column_packet(ivec2(1, 0), &[&b]));
+        assert!(cache.need_rerender(ivec3(0, 4, 0)));
+        cache.clear_need_rerender();
+
+        // a change inside a section redraws only it
+        cache.update(PlayClientbound::BlockUpdate {
+            pos: ivec3(5, 70, 5),
+            block: VarInt(1),
+        });
+        assert!(cache.need_rerender(ivec3(0, 4, 0)));
+        assert!(!cache.need_rerender(ivec3(1, 4, 0)));
+        cache.clear_need_rerender();
+
+        // on the border it redraws the section next to it too
+        cache.update(PlayClientbound::BlockUpdate {
+            pos: ivec3(15, 70, 5),
+            block: VarInt(1),
+        });
+        assert!(cache.need_rerender(ivec3(0, 4, 0)));
+        assert!(cache.need_rerender(ivec3(1, 4, 0)));
+    }
+}
@@ -0,0 +1,296 @@
+//! ```text
+//! package net.minecraft.client.world
+//! class ClientWorld
+//! version 1.16
+//! ```
+//!
+//! The entities the server shows us. Updates come 20 times a second at best, so every entity
+//! keeps the last few positions it was sent at and is drawn where it was
+//! [`INTERPOLATION_DELAY`] ago, between two of them.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use blockworld_server::{
+    entity::EntityType,
+    packet::play::{dequantize_position, quantize_position, Angle, PlayClientbound},
+};
+use glam::*;
+
+/// How far in the past entities are drawn. Two updates of a player, so there is nearly always
+/// a snapshot on each side.
+pub const INTERPOLATION_DELAY: Duration = Duration::from_millis(100);
+/// Snapshots kept per entity, far more than the delay needs.
+const MAX_SNAPSHOTS: usize = 20;
+/// Teleports further than this jump instead of sliding there.
+const MAX_SLIDE: f32 = 8.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Snapshot {
+    at: Instant,
+    position: Vec3,
+    yaw: f32,
+    pitch: f32,
+}
+
+struct RemoteEntity {
+    kind: EntityType,
+    /// Where the server last said it is, in 1/4096 blocks, what moves are relative to.
+    encoded: I64Vec3,
+    snapshots: VecDeque<Snapshot>,
+}
+
+/// An entity as it should be drawn now.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntityPose {
+    pub id: i32,
+    pub kind: EntityType,
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+fn lerp_angle(a: f32, b: f32, t: f32) -> f32 {
+    let tau = std::f32::consts::TAU;
+    let diff = (b - a + tau / 2.0).rem_euclid(tau) - tau / 2.0;
+    a + diff * t
+}
+
+impl RemoteEntity {
+    fn last(&self) -> Snapshot {
+        *self.snapshots.back().unwrap()
+    }
+
+    fn push(&mut self, at: Instant, yaw: Angle, pitch: Angle) {
+        let position = dequantize_position(self.encoded).as_vec3();
+        if position.distance(self.last().position) > MAX_SLIDE {
+            self.snapshots.clear();
+        }
+        self.snapshots.push_back(Snapshot {
+            at,
+            position,
+            yaw: yaw.to_radians(),
+            pitch: pitch.to_radians(),
+        });
+        if self.snapshots.len() > MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Where it was at `at`, between the snapshots around it.
+    fn sample(&self, at: Instant) -> Snapshot {
+        let next = self.snapshots.iter().position(|s| s.at > at);
+        match next {
+            None => self.last(),
+            Some(0) => self.snapshots[0],
+            Some(i) => {
+                let (a, b) = (self.snapshots[i - 1], self.snapshots[i]);
+                let t = (at - a.at).as_secs_f32() / (b.at - a.at).as_secs_f32();
+                Snapshot {
+                    at,
+                    position: a.position.lerp(b.position, t),
+                    yaw: lerp_angle(a.yaw, b.yaw, t),
+                    pitch: lerp_angle(a.pitch, b.pitch, t),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ClientEntities {
+    entities: HashMap<i32, RemoteEntity>,
+}
+
+impl ClientEntities {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply an entity packet that arrived at `now`, other packets are ignored.
+    pub fn update(&mut self, packet: PlayClientbound, now: Instant) {
+        match packet {
+            PlayClientbound::SpawnEntity {
+                id,
+                kind,
+                position,
+                yaw,
+                pitch,
+                ..
+            } => {
+                let snapshot = Snapshot {
+                    at: now,
+                    position: position.as_vec3(),
+                    yaw: yaw.to_radians(),
+                    pitch: pitch.to_radians(),
+                };
+                let entity = RemoteEntity {
+                    kind,
+                    encoded: quantize_position(position),
+                    snapshots: VecDeque::from([snapshot]),
+                };
+                self.entities.insert(id.0, entity);
+            }
+            PlayClientbound::DestroyEntities { ids } => {
+                for id in ids {
+                    self.entities.remove(&id.0);
+                }
+            }
+            PlayClientbound::EntityMove { id, dx, dy, dz } => {
+                if let Some(entity) = self.entities.get_mut(&id.0) {
+                    let last = entity.last();
+                    entity.encoded += i64vec3(dx as i64, dy as i64, dz as i64);
+                    entity.push(
+                        now,
+                        Angle::from_radians(last.yaw),
+                        Angle::from_radians(last.pitch),
+                    );
+                }
+            }
+            PlayClientbound::EntityMoveRotate {
+                id,
+                dx,
+                dy,
+                dz,
+                yaw,
+                pitch,
+            } => {
+                if let Some(entity) = self.entities.get_mut(&id.0) {
+                    entity.encoded += i64vec3(dx as i64, dy as i64, dz as i64);
+                    entity.push(now, yaw, pitch);
+                }
+            }
+            PlayClientbound::EntityRotate { id, yaw, pitch } => {
+                if let Some(entity) = self.entities.get_mut(&id.0) {
+                    entity.push(now, yaw, pitch);
+                }
+            }
+            PlayClientbound::EntityTeleport {
+                id,
+                position,
+                yaw,
+                pitch,
+            } => {
+                if let Some(entity) = self.entities.get_mut(&id.0) {
+                    entity.encoded = quantize_position(position);
+                    entity.push(now, yaw, pitch);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Every entity where it should be drawn at `now`.
+    pub fn poses(&self, now: Instant) -> impl Iterator<Item = EntityPose> + '_ {
+        let at = now.checked_sub(INTERPOLATION_DELAY).unwrap_or(now);
+        self.entities.iter().map(move |(id, entity)| {
+            let s = entity.sample(at);
+            EntityPose {
+                id: *id,
+                kind: entity.kind,
+                position: s.position,
+                yaw: s.yaw,
+                pitch: s.pitch,
+            }
+        })
+    }
+
+    /// Forget them all, when the connection goes.
+    pub fn clear(&mut self) {
+        self.entities.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blockworld_server::packet::VarInt;
+
+    use super::*;
+
+    fn spawn(at: Vec3) -> PlayClientbound {
+        PlayClientbound::SpawnEntity {
+            id: VarInt(1),
+            uuid: 0,
+            kind: EntityType::PIG,
+            position: at.as_dvec3(),
+            yaw: Angle(0),
+            pitch: Angle(0),
+        }
+    }
+
+    #[test]
+    fn slides_between_snapshots() {
+        let start = Instant::now();
+        let tick = Duration::from_millis(50);
+        let mut entities = ClientEntities::new();
+        entities.update(spawn(vec3(0.0, 64.0, 0.0)), start);
+        // one block in two ticks, turning half way around
+        entities.update(
+            PlayClientbound::EntityMoveRotate {
+                id: VarInt(1),
+                dx: 4096,
+                dy: 0,
+                dz: 0,
+                yaw: Angle(128),
+                pitch: Angle(0),
+            },
+            start + tick * 2,
+        );
+
+        let pose = |at: Instant| entities.poses(at).next().unwrap();
+        // nothing newer than the spawn yet
+        assert_eq!(pose(start + INTERPOLATION_DELAY).position.x, 0.0);
+        // half way between the two
+        let half = pose(start + tick + INTERPOLATION_DELAY);
+        assert!((half.position.x - 0.5).abs() < 1e-4, "{}", half.position);
+        assert!((half.yaw.abs() - std::f32::consts::FRAC_PI_2).abs() < 1e-4);
+        // and it stays where it was last sent
+        assert_eq!(pose(start + tick * 10).position.x, 1.0);
+    }
+
+    #[test]
+    fn deltas_add_up_exactly() {
+        let start = Instant::now();
+        let mut entities = ClientEntities::new();
+        entities.update(spawn(vec3(100.3, 64.0, -7.1)), start);
+        for i in 0..1000 {
+            entities.update(
+                PlayClientbound::EntityMove {
+                    id: VarInt(1),
+                    dx: 3,
+                    dy: 0,
+                    dz: -1,
+                },
+                start + Duration::from_millis(i),
+            );
+        }
+        let expected = quantize_position(dvec3(100.3, 64.0, -7.1)) + i64vec3(3000, 0, -1000);
+        assert_eq!(entities.entities[&1].encoded, expected);
+
+        // a far teleport jumps
+        entities.update(
+            PlayClientbound::EntityTeleport {
+                id: VarInt(1),
+                position: dvec3(500.0, 64.0, 0.0),
+                yaw: Angle(0),
+                pitch: Angle(0),
+            },
+            start + Duration::from_secs(2),
+        );
+        let pose = entities
+            .poses(start + Duration::from_secs(2))
+            .next()
+            .unwrap();
+        assert_eq!(pose.position, vec3(500.0, 64.0, 0.0));
+
+        entities.update(
+            PlayClientbound::DestroyEntities {
+                ids: vec![VarInt(1)],
+            },
+            start,
+        );
+        assert_eq!(entities.poses(start).count(), 0);
+    }
+}
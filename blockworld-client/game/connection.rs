@@ -0,0 +1,184 @@
+//! ```text
+//! package net.minecraft.client.network.play
+//! class ClientPlayNetHandler
+//! version 1.16
+//! ```
+//!
+//! The connection to the server. Packets are read and written by a task on a tokio runtime;
+//! the game thread only touches two queues, once per frame.
+//!
+//! Both queues can go through a delay line that holds packets back by [`LinkConditions`], to
+//! play on loopback as if the server were far away. The connection can also be recorded into a
+//! packet capture; it records the wire, before any delay.
+
+use std::{collections::VecDeque, path::PathBuf, sync::Arc, time::Duration};
+
+use anyhow::*;
+use blockworld_server::{
+    network::{capture::Capture, client, transport},
+    packet::play::{PlayClientbound, PlayServerbound},
+};
+use tokio::{
+    runtime::Runtime,
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::{self, Instant},
+};
+
+/// Conditions to fake on the link once logged in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LinkConditions {
+    /// Added to every packet, each way.
+    pub latency: Duration,
+    /// Up to this much more, at random. Packets still arrive in order.
+    pub jitter: Duration,
+}
+
+/// How to connect, besides where to.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectOptions {
+    pub link: LinkConditions,
+    /// Record the session into this capture file.
+    pub capture: Option<PathBuf>,
+}
+
+impl LinkConditions {
+    /// Forward what is sent to the returned sender to `output`, as late as the conditions say.
+    fn delay<T: Send + 'static>(
+        self,
+        runtime: &Runtime,
+        seed: u64,
+        output: UnboundedSender<T>,
+    ) -> UnboundedSender<T> {
+        if self == LinkConditions::default() {
+            return output;
+        }
+        let (input, mut rx) = mpsc::unbounded_channel::<T>();
+        runtime.spawn(async move {
+            // xorshift, good enough to spread delays around
+            let mut state = seed | 1;
+            let mut last = Instant::now();
+            let mut queue = VecDeque::<(Instant, T)>::new();
+            loop {
+                let due = queue.front().map(|(at, _)| *at);
+                tokio::select! {
+                    packet = rx.recv() => {
+                        let Some(packet) = packet else { break };
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        let jitter = self.jitter.mul_f64((state >> 11) as f64 / (1u64 << 53) as f64);
+                        // never before the packet in front of it
+                        last = last.max(Instant::now() + self.latency + jitter);
+                        queue.push_back((last, packet));
+                    }
+                    _ = time::sleep_until(due.unwrap_or(last)), if due.is_some() => {
+                        let (_, packet) = queue.pop_front().unwrap();
+                        if output.send(packet).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+            // the sender is gone, what is on the wire still arrives
+            for (at, packet) in queue {
+                time::sleep_until(at).await;
+                if output.send(packet).is_err() {
+                    return;
+                }
+            }
+        });
+        input
+    }
+}
+
+pub struct ServerConnection {
+    inbound: UnboundedReceiver<PlayClientbound>,
+    outbound: UnboundedSender<PlayServerbound>,
+    // keeps the connection task alive
+    _runtime: Arc<Runtime>,
+}
+
+impl ServerConnection {
+    /// Connect to `address` (`host:port`) and log in as `name`, blocking until the server let
+    /// us in.
+    pub fn connect(
+        runtime: Arc<Runtime>,
+        address: &str,
+        name: &str,
+        options: &ConnectOptions,
+    ) -> Result<Self> {
+        let (host, port) = address
+            .rsplit_once(':')
+            .and_then(|(host, port)| Some((host, port.parse::<u16>().ok()?)))
+            .ok_or_else(|| anyhow!("{} is not a host:port address", address))?;
+
+        let (mut reader, mut writer) = runtime.block_on(async {
+            let (mut reader, mut writer) = transport::connect_tcp(address)
+                .await
+                .with_context(|| format!("Failed to connect to {}", address))?;
+            if let Some(path) = &options.capture {
+                let capture = Capture::create(path)?;
+                log::info!("Recording the session into {}", path.display());
+                reader.set_capture(capture.clone());
+                writer.set_capture(capture);
+            }
+            client::login(&mut reader, &mut writer, host, port, name, None).await?;
+            Ok((reader, writer))
+        })?;
+        log::info!("Logged in to {} as {}", address, name);
+
+        let (inbound_tx, inbound) = mpsc::unbounded_channel();
+        let conditions = options.link;
+        let inbound_tx = conditions.delay(&runtime, 0x9e37_79b9_7f4a_7c15, inbound_tx);
+        let (outbound, mut outbound_rx) = mpsc::unbounded_channel::<PlayServerbound>();
+        let outbound = conditions.delay(&runtime, 0x2545_f491_4f6c_dd1d, outbound);
+        let keep_alive = outbound.clone();
+        runtime.spawn(async move {
+            let reason = loop {
+                tokio::select! {
+                    packet = reader.recv::<PlayClientbound>() => match packet {
+                        Result::Ok(Some(PlayClientbound::KeepAlive { id })) => {
+                            let _ = keep_alive.send(PlayServerbound::KeepAlive { id });
+                        }
+                        Result::Ok(Some(packet)) => {
+                            let last = matches!(packet, PlayClientbound::Disconnect { .. });
+                            if inbound_tx.send(packet).is_err() || last {
+                                return;
+                            }
+                        }
+                        Result::Ok(None) => break "Connection closed".to_string(),
+                        Err(e) => break format!("Connection lost: {:#}", e),
+                    },
+                    packet = outbound_rx.recv() => {
+                        let Some(packet) = packet else {
+                            // the game went away
+                            let _ = writer.close().await;
+                            return;
+                        };
+                        if let Err(e) = writer.send(&packet).await {
+                            break format!("Connection lost: {:#}", e);
+                        }
+                    }
+                }
+            };
+            let _ = inbound_tx.send(PlayClientbound::Disconnect { reason });
+        });
+
+        Ok(Self {
+            inbound,
+            outbound,
+            _runtime: runtime,
+        })
+    }
+
+    pub fn send(&self, packet: PlayServerbound) {
+        // a dead connection shows up as a Disconnect from `poll`
+        let _ = self.outbound.send(packet);
+    }
+
+    /// The next packet that arrived, if any. The connection ending always shows up as a
+    /// [`PlayClientbound::Disconnect`].
+    pub fn poll(&mut self) -> Option<PlayClientbound> {
+        self.inbound.try_recv().ok()
+    }
+}
@@ -0,0 +1,88 @@
+//! ```text
+//! package net.minecraft.server.integrated
+//! class IntegratedServer
+//! version 1.16
+//! ```
+//!
+//! Singleplayer is a dedicated server in the same process, listening on loopback only. The
+//! client joins it like any other server, so there is one code path to get right. Opened to
+//! the LAN, it listens on every interface instead and announces itself.
+
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+
+use anyhow::*;
+use blockworld_server::{
+    entity::game_mode::GameMode,
+    network::{self, ServerConfig, ServerHandle},
+};
+use tokio::runtime::Runtime;
+
+pub struct IntegratedServer {
+    runtime: Arc<Runtime>,
+    handle: Option<ServerHandle>,
+    address: SocketAddr,
+}
+
+impl IntegratedServer {
+    /// Open `world` and start ticking it on its own thread, with new players in `game_mode`.
+    /// With `lan`, other players can join and the server announces itself there, usually to
+    /// [`LAN_GROUP`].
+    ///
+    /// [`LAN_GROUP`]: blockworld_server::network::lan::LAN_GROUP
+    pub fn start(
+        runtime: Arc<Runtime>,
+        world: PathBuf,
+        view_distance: u32,
+        game_mode: GameMode,
+        lan: Option<SocketAddr>,
+    ) -> Result<Self> {
+        let handle = runtime.block_on(network::start(ServerConfig {
+            motd: match lan {
+                Some(_) => format!(
+                    "{} - LAN",
+                    world.file_name().unwrap_or_default().to_string_lossy()
+                ),
+                None => ServerConfig::default().motd,
+            },
+            world,
+            address: match lan {
+                Some(_) => "0.0.0.0:0".parse().unwrap(),
+                None => "127.0.0.1:0".parse().unwrap(),
+            },
+            websocket_address: None,
+            view_distance,
+            default_game_mode: game_mode,
+            lan,
+            ..Default::default()
+        }))?;
+        if lan.is_some() {
+            log::info!("Opened to LAN on port {}", handle.address().port());
+        }
+        Ok(Self {
+            // we join over loopback either way
+            address: SocketAddr::from(([127, 0, 0, 1], handle.address().port())),
+            runtime,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Disconnect the player and save the world. Does nothing the second time.
+    pub fn stop(&mut self) -> Result<()> {
+        match self.handle.take() {
+            Some(handle) => self.runtime.block_on(handle.shutdown()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for IntegratedServer {
+    fn drop(&mut self) {
+        if let Err(e) = self.stop() {
+            log::error!("Failed to stop the integrated server: {:#}", e);
+        }
+    }
+}
@@ -0,0 +1,383 @@
+//! ```text
+//! package net.minecraft.client.multiplayer
+//! class PlayerController
+//! version 1.16
+//! ```
+//!
+//! Breaking and placing blocks with the mouse. Holding attack on a block digs it a share per
+//! tick, by the same rules the server times the dig with
+//! (`blockworld-server/src/entity/interaction.rs`), and tells the server when the dig starts,
+//! stops and is done. Use places the selected block against the face looked at, once when
+//! pressed and then every few ticks while held.
+//!
+//! Both happen here at once, like the player's moves: the server sends the block back when it
+//! disagrees. The game mode and adventure tags the server sent decide what may be broken and
+//! placed, as they do there.
+
+use blockworld_server::{
+    block::{
+        block_face_direction::BlockFaceDirection, shape::Aabb, state::BlockState, NumberID,
+        BLOCK_REGISTRY,
+    },
+    entity::{
+        game_mode::{AdventureTags, GameMode},
+        interaction::{digging_progress, obstructed, placement, BREAK_DELAY, PLACE_DELAY},
+        movement::Body,
+        EntityType,
+    },
+    packet::{
+        play::{DigAction, PlayServerbound},
+        VarInt,
+    },
+    world::{
+        block_update::placement_state,
+        chunk_access::{BlockPos, WorldAccess},
+        raycast::BlockHit,
+    },
+};
+
+/// The mouse buttons over one tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InteractInput {
+    /// Attack held.
+    pub attack: bool,
+    /// Use held.
+    pub use_held: bool,
+    /// Use pressed since the last tick.
+    pub use_pressed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Destroying {
+    pos: BlockPos,
+    face: BlockFaceDirection,
+    /// From 0 to 1, broken at 1.
+    progress: f32,
+}
+
+#[derive(Debug)]
+pub struct Interaction {
+    destroying: Option<Destroying>,
+    /// Ticks until the next dig may start.
+    destroy_delay: u32,
+    /// Ticks until the held use places again.
+    place_delay: u32,
+    /// The block use places.
+    selected: NumberID,
+    game_mode: GameMode,
+    tags: AdventureTags,
+}
+
+impl Default for Interaction {
+    fn default() -> Self {
+        Self {
+            destroying: None,
+            destroy_delay: 0,
+            place_delay: 0,
+            // stone
+            selected: 1,
+            game_mode: GameMode::default(),
+            tags: AdventureTags::default(),
+        }
+    }
+}
+
+impl Interaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn selected(&self) -> NumberID {
+        self.selected
+    }
+
+    /// Place `block` from now on, if it's a block that can be placed.
+    pub fn select(&mut self, block: NumberID) {
+        if block != 0 && BLOCK_REGISTRY.number_id_to_name(block).is_some() {
+            self.selected = block;
+        }
+    }
+
+    pub fn game_mode(&self) -> GameMode {
+        self.game_mode
+    }
+
+    /// Follow the rules of `mode` with `tags` from now on, dropping any dig.
+    pub fn set_abilities(&mut self, mode: GameMode, tags: AdventureTags) {
+        self.game_mode = mode;
+        self.tags = tags;
+        self.destroying = None;
+    }
+
+    /// The block being broken and how far, from 0 to 9 like the crack textures.
+    pub fn destroy_stage(&self) -> Option<(BlockPos, u8)> {
+        let d = self.destroying?;
+        Some((d.pos, ((d.progress * 10.0) as u8).min(9)))
+    }
+
+    /// One tick of the buttons on `target`, for `player` among `entities`. Changes go to
+    /// `world` at once; the packets returned tell the server.
+    pub fn tick<W: WorldAccess>(
+        &mut self,
+        world: &mut W,
+        player: &Body,
+        entities: &[Aabb],
+        target: Option<BlockHit>,
+        input: InteractInput,
+    ) -> Vec<PlayServerbound> {
+        let mut packets = vec![];
+        self.destroy_delay = self.destroy_delay.saturating_sub(1);
+        self.place_delay = self.place_delay.saturating_sub(1);
+
+        match target.filter(|_| input.attack) {
+            Some(hit) if self.destroy_delay == 0 => self.dig(world, player, hit, &mut packets),
+            _ => self.abort(&mut packets),
+        }
+
+        let place = input.use_pressed || (input.use_held && self.place_delay == 0);
+        if let Some(hit) = target.filter(|_| place && !input.attack) {
+            self.place_delay = PLACE_DELAY;
+            self.place(world, player, entities, hit, &mut packets);
+        }
+        packets
+    }
+
+    fn dig<W: WorldAccess>(
+        &mut self,
+        world: &mut W,
+        player: &Body,
+        hit: BlockHit,
+        packets: &mut Vec<PlayServerbound>,
+    ) {
+        let progress = digging_progress(
+            world,
+            hit.pos,
+            player.position,
+            player.on_ground,
+            self.game_mode,
+            &self.tags,
+        );
+        let send = |action| PlayServerbound::PlayerDig {
+            action,
+            pos: hit.pos,
+            face: hit.face,
+        };
+        let done = match &mut self.destroying {
+            Some(d) if d.pos == hit.pos => {
+                d.progress += progress;
+                d.progress >= 1.0
+            }
+            _ => {
+                self.abort(packets);
+                if progress <= 0.0 {
+                    return;
+                }
+                packets.push(send(DigAction::Start));
+                if progress >= 1.0 {
+                    // broken at once, the start says it all
+                    world.set_block(hit.pos, &"minecraft:air".into());
+                    self.destroy_delay = BREAK_DELAY;
+                    return;
+                }
+                self.destroying = Some(Destroying {
+                    pos: hit.pos,
+                    face: hit.face,
+                    progress,
+                });
+                false
+            }
+        };
+        if done {
+            packets.push(send(DigAction::Finish));
+            world.set_block(hit.pos, &"minecraft:air".into());
+            self.destroying = None;
+            self.destroy_delay = BREAK_DELAY;
+        }
+    }
+
+    fn abort(&mut self, packets: &mut Vec<PlayServerbound>) {
+        if let Some(d) = self.destroying.take() {
+            packets.push(PlayServerbound::PlayerDig {
+                action: DigAction::Abort,
+                pos: d.pos,
+                face: d.face,
+            });
+        }
+    }
+
+    fn place<W: WorldAccess>(
+        &mut self,
+        world: &mut W,
+        player: &Body,
+        entities: &[Aabb],
+        hit: BlockHit,
+        packets: &mut Vec<PlayServerbound>,
+    ) {
+        let Some(pos) = placement(world, hit.pos, hit.face, self.game_mode, &self.tags) else {
+            return;
+        };
+        let Some(block) = BLOCK_REGISTRY.number_id_to_name(self.selected) else {
+            return;
+        };
+        let state = placement_state(world, pos, BlockState::of(block));
+        let (width, height) = EntityType::PLAYER.size();
+        let us = Aabb::from_feet(player.position, width, height);
+        if obstructed(state, pos, entities.iter().copied().chain([us])) {
+            return;
+        }
+        world.set_state(pos, state);
+        packets.push(PlayServerbound::PlaceBlock {
+            pos: hit.pos,
+            face: hit.face,
+            block: VarInt(self.selected as i32),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blockworld_server::world::raycast::RayContext;
+    use glam::*;
+
+    use super::*;
+    use crate::game::client_chunk_cache::ClientChunkCache;
+
+    fn floor() -> ClientChunkCache {
+        let mut world = ClientChunkCache::new();
+        world.load_chunk(ivec3(0, 3, 0));
+        world.load_chunk(ivec3(0, 4, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block(ivec3(x, 63, z), &"minecraft:stone".into());
+            }
+        }
+        world.set_block(ivec3(2, 63, 0), &"minecraft:dirt".into());
+        world
+    }
+
+    fn standing() -> Body {
+        Body {
+            position: vec3(0.5, 64.0, 0.5),
+            on_ground: true,
+            ..Default::default()
+        }
+    }
+
+    fn look_down_at(world: &ClientChunkCache, x: f32) -> Option<BlockHit> {
+        world.raycast(vec3(x, 65.62, 0.5), Vec3::NEG_Y, 4.5, RayContext::PICK)
+    }
+
+    #[test]
+    fn digging_takes_the_block_time() {
+        let mut world = floor();
+        let mut interaction = Interaction::new();
+        let attack = InteractInput {
+            attack: true,
+            ..Default::default()
+        };
+        let mut sent = vec![];
+        // dirt by hand is 15 ticks, the start counting as one
+        for _ in 0..15 {
+            let target = look_down_at(&world, 2.5);
+            sent.extend(interaction.tick(&mut world, &standing(), &[], target, attack));
+        }
+        let actions: Vec<DigAction> = sent
+            .iter()
+            .map(|p| match p {
+                PlayServerbound::PlayerDig { action, .. } => *action,
+                p => panic!("{:?}", p),
+            })
+            .collect();
+        assert_eq!(actions, [DigAction::Start, DigAction::Finish]);
+        assert_eq!(world.get_block(ivec3(2, 63, 0)), "minecraft:air".into());
+        assert_eq!(interaction.destroy_stage(), None);
+
+        // letting go halfway aborts, and the cracks go
+        let target = look_down_at(&world, 3.5);
+        for _ in 0..BREAK_DELAY + 40 {
+            interaction.tick(&mut world, &standing(), &[], target, attack);
+        }
+        assert_eq!(interaction.destroy_stage(), Some((ivec3(3, 63, 0), 2)));
+        let sent = interaction.tick(&mut world, &standing(), &[], target, Default::default());
+        assert!(matches!(
+            sent[..],
+            [PlayServerbound::PlayerDig {
+                action: DigAction::Abort,
+                ..
+            }]
+        ));
+        assert_eq!(interaction.destroy_stage(), None);
+    }
+
+    #[test]
+    fn places_against_faces_but_not_into_us() {
+        let mut world = floor();
+        let mut interaction = Interaction::new();
+        interaction.select(5);
+        let click = InteractInput {
+            use_held: true,
+            use_pressed: true,
+            ..Default::default()
+        };
+        let under_us = look_down_at(&world, 0.5);
+        assert_eq!(
+            interaction.tick(&mut world, &standing(), &[], under_us, click),
+            []
+        );
+
+        let beside = look_down_at(&world, 1.5).unwrap();
+        let sent = interaction.tick(&mut world, &standing(), &[], Some(beside), click);
+        assert_eq!(
+            sent,
+            [PlayServerbound::PlaceBlock {
+                pos: ivec3(1, 63, 0),
+                face: BlockFaceDirection::YP,
+                block: VarInt(5)
+            }]
+        );
+        assert_eq!(world.get_block(ivec3(1, 64, 0)), "minecraft:sand".into());
+
+        // held, the next one waits a few ticks
+        let held = InteractInput {
+            use_held: true,
+            ..Default::default()
+        };
+        let on_top = look_down_at(&world, 1.5);
+        let mut placed = 0;
+        for _ in 0..PLACE_DELAY {
+            placed += interaction
+                .tick(&mut world, &standing(), &[], on_top, held)
+                .len();
+        }
+        assert_eq!(placed, 1);
+        assert_eq!(world.get_block(ivec3(1, 65, 0)), "minecraft:sand".into());
+    }
+
+    #[test]
+    fn game_modes_decide_what_breaks() {
+        let mut world = floor();
+        let mut interaction = Interaction::new();
+        let attack = InteractInput {
+            attack: true,
+            ..Default::default()
+        };
+        interaction.set_abilities(GameMode::Adventure, AdventureTags::default());
+        let target = look_down_at(&world, 2.5);
+        assert_eq!(
+            interaction.tick(&mut world, &standing(), &[], target, attack),
+            []
+        );
+
+        // creative breaks at once, the start saying it all
+        interaction.set_abilities(GameMode::Creative, AdventureTags::default());
+        let sent = interaction.tick(&mut world, &standing(), &[], target, attack);
+        assert!(matches!(
+            sent[..],
+            [PlayServerbound::PlayerDig {
+                action: DigAction::Start,
+                ..
+            }]
+        ));
+        assert_eq!(world.get_block(ivec3(2, 63, 0)), "minecraft:air".into());
+    }
+}
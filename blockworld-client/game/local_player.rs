@@ -0,0 +1,193 @@
+//! ```text
+//! package net.minecraft.client.entity.player
+//! class ClientPlayerEntity
+//! version 1.16
+//! ```
+//!
+//! Our own player. Waiting a round trip for the server before moving would make every key
+//! press feel late, so the client runs the same [`movement::step`] as the server on its own
+//! copy of the world and shows the result at once.
+//!
+//! Every input is numbered and kept until the server acknowledges it with where the player
+//! really ended up. The prediction is then redone from that state over the inputs the server
+//! hasn't seen yet. When client and server agree on the blocks this gives the same body and
+//! nothing shows; when they don't, the player snaps to the corrected position and the
+//! correction is counted.
+
+use std::collections::VecDeque;
+
+use blockworld_server::{
+    entity::movement::{self, Body, MoveInput},
+    world::chunk_access::WorldAccess,
+};
+use glam::*;
+
+/// Inputs kept for replay. Far more than a second of round trip; if the server is further
+/// behind than that, older inputs are forgotten and the next correction simply snaps.
+const MAX_PENDING: usize = 100;
+
+#[derive(Debug)]
+pub struct LocalPlayer {
+    body: Body,
+    /// The body a tick ago, to draw between ticks.
+    previous: Body,
+    next_sequence: i32,
+    /// Inputs sent but not acknowledged, oldest first.
+    pending: VecDeque<(i32, MoveInput)>,
+    corrections: u32,
+}
+
+impl LocalPlayer {
+    pub fn new(position: Vec3) -> Self {
+        let body = Body {
+            position,
+            ..Default::default()
+        };
+        Self {
+            body,
+            previous: body,
+            next_sequence: 0,
+            pending: VecDeque::new(),
+            corrections: 0,
+        }
+    }
+
+    /// Put the player where the server teleported it. Inputs in flight were made for the old
+    /// position, so they aren't replayed.
+    pub fn reset(&mut self, position: Vec3) {
+        self.body = Body {
+            position,
+            ..Default::default()
+        };
+        self.previous = self.body;
+        self.pending.clear();
+    }
+
+    /// Predict one tick of `input`, returning the sequence number to send it with.
+    pub fn tick<W: WorldAccess>(&mut self, input: MoveInput, world: &W) -> i32 {
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+        self.previous = self.body;
+        movement::step(&mut self.body, &input, world);
+        if self.pending.len() == MAX_PENDING {
+            self.pending.pop_front();
+        }
+        self.pending.push_back((sequence, input));
+        sequence
+    }
+
+    /// The server applied inputs up to `sequence` and put the player at `state`.
+    pub fn reconcile<W: WorldAccess>(&mut self, sequence: i32, state: Body, world: &W) {
+        // wrapping, so a long session doesn't break at i32::MAX
+        while let Some(&(oldest, _)) = self.pending.front() {
+            if sequence.wrapping_sub(oldest) < 0 {
+                break;
+            }
+            self.pending.pop_front();
+        }
+        let mut replayed = state;
+        for (_, input) in &self.pending {
+            movement::step(&mut replayed, input, world);
+        }
+        if replayed != self.body {
+            log::debug!(
+                "Corrected the prediction by {}",
+                replayed.position.distance(self.body.position)
+            );
+            self.corrections += 1;
+            // a snap is less confusing than sliding through what we didn't expect
+            self.previous = replayed;
+            self.body = replayed;
+        }
+    }
+
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
+    /// The feet, `partial` of the way from the last tick to this one.
+    pub fn position(&self, partial: f32) -> Vec3 {
+        self.previous
+            .position
+            .lerp(self.body.position, partial.clamp(0.0, 1.0))
+    }
+
+    /// Inputs the server hasn't acknowledged yet.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// How often the server disagreed with the prediction.
+    pub fn corrections(&self) -> u32 {
+        self.corrections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blockworld_server::world::disk_chunk_access::DiskChunkArray;
+
+    use super::*;
+
+    fn floor() -> DiskChunkArray {
+        let mut world = DiskChunkArray::new(1);
+        world.load_chunk(ivec3(0, 3, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block(ivec3(x, 63, z), &"minecraft:stone".into());
+            }
+        }
+        world
+    }
+
+    fn walk(yaw: f32) -> MoveInput {
+        MoveInput {
+            forward: 1,
+            yaw,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn agreeing_server_changes_nothing() {
+        let world = floor();
+        let mut player = LocalPlayer::new(vec3(2.5, 64.0, 2.5));
+        let mut server = *player.body();
+        for tick in 0..6 {
+            player.tick(walk(0.0), &world);
+            // the server is two ticks behind
+            if tick >= 2 {
+                movement::step(&mut server, &walk(0.0), &world);
+                player.reconcile(tick - 2, server, &world);
+            }
+        }
+        assert_eq!(player.pending(), 2);
+        assert_eq!(player.corrections(), 0);
+        assert!(player.body().position.z > server.position.z);
+    }
+
+    #[test]
+    fn corrections_replay_unacknowledged_inputs() {
+        let world = floor();
+        let mut player = LocalPlayer::new(vec3(2.5, 64.0, 2.5));
+        let mut expected = *player.body();
+        for _ in 0..4 {
+            player.tick(walk(0.0), &world);
+        }
+        // the server had us a block further along when it applied the first input
+        expected.position.x += 1.0;
+        movement::step(&mut expected, &walk(0.0), &world);
+        player.reconcile(0, expected, &world);
+        for _ in 0..3 {
+            movement::step(&mut expected, &walk(0.0), &world);
+        }
+        assert_eq!(player.corrections(), 1);
+        assert_eq!(player.pending(), 3);
+        assert_eq!(*player.body(), expected);
+
+        // a teleport forgets the inputs in flight
+        player.reset(vec3(8.5, 64.0, 8.5));
+        assert_eq!(player.pending(), 0);
+        assert_eq!(player.position(0.5), vec3(8.5, 64.0, 8.5));
+    }
+}
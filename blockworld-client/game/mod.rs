@@ -1 +1,9 @@
 pub mod client;
+pub mod client_chunk_cache;
+pub mod client_entities;
+pub mod connection;
+pub mod integrated_server;
+pub mod interaction;
+pub mod local_player;
+pub mod replay;
+pub mod server_list;
@@ -0,0 +1,149 @@
+//! Play a recorded session (see `blockworld_server::network::capture`) to a client with no
+//! window and no server: what the server sent is applied as if it just arrived, and every
+//! input we sent is predicted again when it was sent.
+//!
+//! The client only diverges from the server in its prediction, so that is what is reported:
+//! every `PlayerState` the replayed prediction disagreed with. A capture the prediction
+//! disagrees with replays the same desync every time, with the debugger attached.
+
+use std::time::Duration;
+
+use anyhow::*;
+use blockworld_server::{
+    network::capture::Record,
+    packet::play::{PlayClientbound, PlayServerbound},
+};
+use glam::*;
+
+use super::client::BlockworldClient;
+
+/// The server put the player somewhere else than the prediction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Correction {
+    /// The last input the server applied.
+    pub sequence: i32,
+    /// Since the capture started.
+    pub at: Duration,
+    /// How far the prediction was off.
+    pub distance: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientReplay {
+    /// Packets applied.
+    pub packets: usize,
+    /// Inputs predicted.
+    pub inputs: usize,
+    pub corrections: Vec<Correction>,
+    /// Where the prediction ended up.
+    pub position: Option<Vec3>,
+    /// Why the server closed the connection, if the recording got that far.
+    pub disconnected: Option<String>,
+}
+
+pub fn replay_on_client(records: &[Record]) -> Result<ClientReplay> {
+    let mut game = BlockworldClient::new();
+    let mut replay = ClientReplay::default();
+    for record in records {
+        if let Some(packet) = record.decode::<PlayServerbound>() {
+            let PlayServerbound::PlayerInput { sequence, input } = packet? else {
+                continue;
+            };
+            if let Some(predicted) = game.predict(input) {
+                ensure!(
+                    predicted == sequence.0,
+                    "Input {} was recorded as {}, the capture is missing inputs",
+                    predicted,
+                    sequence.0
+                );
+                replay.inputs += 1;
+            }
+        } else if let Some(packet) = record.decode::<PlayClientbound>() {
+            let packet = packet?;
+            replay.packets += 1;
+            let PlayClientbound::PlayerState { sequence, .. } = packet else {
+                game.handle_packet(packet);
+                continue;
+            };
+            let Some(player) = game.player() else {
+                continue;
+            };
+            let (corrections, predicted) = (player.corrections(), player.position(1.0));
+            game.handle_packet(packet);
+            let player = game.player().unwrap();
+            if player.corrections() > corrections {
+                replay.corrections.push(Correction {
+                    sequence: sequence.0,
+                    at: record.at,
+                    distance: predicted.distance(player.position(1.0)),
+                });
+            }
+        }
+    }
+    replay.position = game.player().map(|p| p.position(1.0));
+    replay.disconnected = game.disconnected().map(str::to_string);
+    Ok(replay)
+}
+
+#[cfg(test)]
+mod tests {
+    use blockworld_server::{
+        entity::movement::MoveInput,
+        packet::{ConnectionState, PacketSet, VarInt},
+    };
+
+    use super::*;
+
+    fn record<P: PacketSet>(millis: u64, packet: P) -> Record {
+        Record {
+            at: Duration::from_millis(millis),
+            direction: P::DIRECTION,
+            state: ConnectionState::Play,
+            payload: packet.encode(),
+        }
+    }
+
+    #[test]
+    fn reports_where_the_server_disagreed() {
+        let spawn = vec3(0.5, 64.0, 0.5);
+        let input = |sequence| PlayServerbound::PlayerInput {
+            sequence: VarInt(sequence),
+            input: MoveInput {
+                forward: 1,
+                flying: true,
+                ..Default::default()
+            },
+        };
+        let state = |sequence, position| PlayClientbound::PlayerState {
+            sequence: VarInt(sequence),
+            position,
+            velocity: Vec3::ZERO,
+            on_ground: false,
+        };
+        let mut records = vec![
+            record(
+                0,
+                PlayClientbound::PlayerPosition {
+                    position: spawn,
+                    yaw: 0.0,
+                    pitch: 0.0,
+                },
+            ),
+            record(50, input(0)),
+            record(100, input(1)),
+        ];
+
+        // a server that didn't move us at all
+        records.push(record(120, state(0, spawn)));
+        let replay = replay_on_client(&records).unwrap();
+        assert_eq!(replay.inputs, 2);
+        assert_eq!(replay.corrections.len(), 1);
+        assert_eq!(replay.corrections[0].sequence, 0);
+        assert_eq!(replay.corrections[0].at, Duration::from_millis(120));
+        assert!(replay.corrections[0].distance > 0.0);
+
+        // a capture with a hole in the inputs can't be predicted
+        records.remove(1);
+        assert!(replay_on_client(&records).is_err());
+    }
+}
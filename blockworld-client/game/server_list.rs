@@ -0,0 +1,224 @@
+//! ```text
+//! package net.minecraft.client.multiplayer
+//! class ServerList, ServerPinger
+//! version 1.16
+//! ```
+//!
+//! The servers on the local network. Each server that announces itself is asked for its
+//! status once, on the network runtime, and the list is read by the game whenever it likes.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::*;
+use blockworld_server::{
+    network::{
+        client,
+        lan::{LanDiscovery, LanServer},
+        transport,
+    },
+    packet::status::ServerStatus,
+};
+use tokio::runtime::Runtime;
+
+/// Give up on a server that doesn't answer a status query within this.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Status queries to one host running at once at most. Announcements from it past that are
+/// dropped until some are answered, so a host changing its MOTD all the time can't make us
+/// open connection after connection.
+const MAX_QUERIES_PER_HOST: usize = 4;
+
+/// Status queries running, by the host they go to.
+type Querying = Arc<Mutex<HashMap<IpAddr, usize>>>;
+
+/// A server in the list.
+#[derive(Debug, Clone)]
+pub struct ListedServer {
+    pub address: SocketAddr,
+    /// What the server announced.
+    pub motd: String,
+    /// Its status, once it answered.
+    pub status: Option<ServerStatus>,
+    pub ping: Option<Duration>,
+    /// Why it didn't answer.
+    pub error: Option<String>,
+}
+
+pub struct ServerList {
+    servers: Arc<Mutex<BTreeMap<SocketAddr, ListedServer>>>,
+    // keeps the discovery task alive
+    _runtime: Arc<Runtime>,
+    task: tokio::task::JoinHandle<()>,
+    local_addr: SocketAddr,
+}
+
+impl ServerList {
+    /// Listen for servers announcing to `group`, usually [`LAN_GROUP`].
+    ///
+    /// [`LAN_GROUP`]: blockworld_server::network::lan::LAN_GROUP
+    pub fn listen(runtime: Arc<Runtime>, group: SocketAddr) -> Result<Self> {
+        let mut discovery = {
+            let _guard = runtime.enter();
+            LanDiscovery::bind(group)?
+        };
+        let local_addr = discovery.local_addr()?;
+        let servers = Arc::new(Mutex::new(BTreeMap::new()));
+        let list = servers.clone();
+        let querying = Querying::default();
+        let task = runtime.spawn(async move {
+            let mut prune = tokio::time::interval(Duration::from_secs(1));
+            loop {
+                tokio::select! {
+                    found = discovery.next() => match found {
+                        Result::Ok(server) => {
+                            if !start_query(&querying, server.address.ip()) {
+                                // heard again once the host's queries are answered
+                                discovery.forget(server.address);
+                                continue;
+                            }
+                            list.lock().unwrap().insert(server.address, ListedServer {
+                                address: server.address,
+                                motd: server.motd.clone(),
+                                status: None,
+                                ping: None,
+                                error: None,
+                            });
+                            tokio::spawn(query(list.clone(), querying.clone(), server));
+                        }
+                        Err(e) => {
+                            log::error!("Stopped listening for LAN servers: {:#}", e);
+                            return;
+                        }
+                    },
+                    _ = prune.tick() => {
+                        let alive = discovery.servers();
+                        list.lock()
+                            .unwrap()
+                            .retain(|address, _| alive.iter().any(|s| s.address == *address));
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            servers,
+            _runtime: runtime,
+            task,
+            local_addr,
+        })
+    }
+
+    /// Where announcements have to go to show up in this list.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// The servers heard from lately, by address.
+    pub fn servers(&self) -> Vec<ListedServer> {
+        self.servers.lock().unwrap().values().cloned().collect()
+    }
+}
+
+impl Drop for ServerList {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Count a query to `host` as running, unless too many already are.
+fn start_query(querying: &Querying, host: IpAddr) -> bool {
+    let mut querying = querying.lock().unwrap();
+    let running = querying.entry(host).or_default();
+    if *running >= MAX_QUERIES_PER_HOST {
+        return false;
+    }
+    *running += 1;
+    true
+}
+
+async fn query(
+    list: Arc<Mutex<BTreeMap<SocketAddr, ListedServer>>>,
+    querying: Querying,
+    server: LanServer,
+) {
+    let address = server.address;
+    let result = tokio::time::timeout(QUERY_TIMEOUT, async {
+        let (mut reader, mut writer) = transport::connect_tcp(address).await?;
+        let host = address.ip().to_string();
+        client::query_status(&mut reader, &mut writer, &host, address.port()).await
+    })
+    .await
+    .unwrap_or_else(|_| Err(anyhow!("Timed out")));
+    {
+        let mut querying = querying.lock().unwrap();
+        if let Some(running) = querying.get_mut(&address.ip()) {
+            *running -= 1;
+            if *running == 0 {
+                querying.remove(&address.ip());
+            }
+        }
+    }
+    let mut list = list.lock().unwrap();
+    // it may have changed its MOTD, or gone, meanwhile
+    let Some(listed) = list.get_mut(&address).filter(|l| l.motd == server.motd) else {
+        return;
+    };
+    match result {
+        Result::Ok((status, ping)) => {
+            listed.status = Some(status);
+            listed.ping = Some(ping);
+        }
+        Err(e) => listed.error = Some(format!("{:#}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blockworld_utils::TempDir;
+
+    use super::*;
+    use crate::game::integrated_server::IntegratedServer;
+
+    #[test]
+    fn lists_servers_opened_to_lan() {
+        let root = TempDir::new("lan").unwrap();
+        let runtime = Arc::new(
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(2)
+                .enable_all()
+                .build()
+                .unwrap(),
+        );
+        let list = ServerList::listen(runtime.clone(), "127.0.0.1:0".parse().unwrap()).unwrap();
+        let group = list.local_addr();
+        let mut server = IntegratedServer::start(
+            runtime.clone(),
+            root.to_path_buf(),
+            1,
+            Default::default(),
+            Some(group),
+        )
+        .unwrap();
+
+        let mut listed = vec![];
+        for _ in 0..50 {
+            listed = list.servers();
+            if listed.iter().any(|s| s.status.is_some()) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        assert_eq!(listed.len(), 1, "{:?}", listed);
+        assert_eq!(listed[0].address.port(), server.address().port());
+        let status = listed[0].status.as_ref().unwrap();
+        assert_eq!(status.description, listed[0].motd);
+        assert_eq!(status.players.online, 0);
+        assert!(listed[0].ping.is_some());
+
+        server.stop().unwrap();
+        drop(list);
+    }
+}
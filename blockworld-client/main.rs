@@ -1,11 +1,182 @@
 #![deny(unused_must_use)]
 
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use blockworld_server::{
+    entity::game_mode::GameMode,
+    network::{capture::read_capture, lan::LAN_GROUP},
+};
 use clap::Parser;
+use game::{
+    client::{network_runtime, BlockworldClient},
+    connection::{ConnectOptions, LinkConditions},
+    replay::replay_on_client,
+    server_list::ServerList,
+};
 use renderer::run;
 
 mod game;
 mod renderer;
 
-fn main() {
-    pollster::block_on(run());
+#[derive(Parser)]
+#[command(name = "blockworld-client", about = "Play Blockworld")]
+struct Args {
+    /// The server to join, as host:port
+    #[arg(long, default_value = "localhost:25565")]
+    server: String,
+
+    /// Play alone on an integrated server instead of joining one
+    #[arg(long)]
+    singleplayer: bool,
+
+    /// The world folder for singleplayer, created if it doesn't exist
+    #[arg(long, default_value = "saves/world")]
+    world: PathBuf,
+
+    /// Let players on the local network join the integrated server
+    #[arg(long)]
+    open_to_lan: bool,
+
+    /// List the servers on the local network and exit
+    #[arg(long)]
+    lan: bool,
+
+    /// Game mode of new players on the integrated server: survival, creative, adventure or
+    /// spectator
+    #[arg(long, default_value = "creative")]
+    gamemode: GameMode,
+
+    /// Radius in chunks of the area the integrated server sends
+    #[arg(long, default_value_t = 8)]
+    view_distance: u32,
+
+    #[arg(long, default_value = "Player")]
+    name: String,
+
+    /// Delay every packet by this many milliseconds each way, to try playing far away
+    #[arg(long, default_value_t = 0)]
+    latency: u64,
+
+    /// Delay packets by up to this many milliseconds more, at random
+    #[arg(long, default_value_t = 0)]
+    jitter: u64,
+
+    /// Record every packet of the session into this capture file
+    #[arg(long)]
+    capture: Option<PathBuf>,
+
+    /// Play a capture file to a client with no window and no server, and report where the
+    /// server disagreed with the prediction
+    #[arg(long)]
+    replay: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    env_logger::init();
+    let args = Args::parse();
+    if let Some(capture) = &args.replay {
+        return replay(capture);
+    }
+    if args.lan {
+        return list_lan_servers();
+    }
+    let options = ConnectOptions {
+        link: LinkConditions {
+            latency: Duration::from_millis(args.latency),
+            jitter: Duration::from_millis(args.jitter),
+        },
+        capture: args.capture,
+    };
+    let game = match args.singleplayer {
+        true => {
+            let lan = args.open_to_lan.then_some(LAN_GROUP);
+            BlockworldClient::singleplayer(
+                args.world,
+                &args.name,
+                args.view_distance,
+                args.gamemode,
+                lan,
+                &options,
+            )
+        }
+        false => BlockworldClient::connect(&args.server, &args.name, &options),
+    };
+    match game {
+        Ok(game) => {
+            pollster::block_on(run(game));
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            log::error!("{:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn replay(capture: &Path) -> ExitCode {
+    let replayed = match read_capture(capture).and_then(|records| replay_on_client(&records)) {
+        Ok(replayed) => replayed,
+        Err(e) => {
+            log::error!("{:#}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!(
+        "applied {} packets, predicted {} inputs, {} corrections",
+        replayed.packets,
+        replayed.inputs,
+        replayed.corrections.len()
+    );
+    for c in &replayed.corrections {
+        println!(
+            "  at {:.3}s input {}: off by {}",
+            c.at.as_secs_f64(),
+            c.sequence,
+            c.distance
+        );
+    }
+    if let Some(reason) = &replayed.disconnected {
+        println!("disconnected: {}", reason);
+    }
+    match replayed.corrections.is_empty() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
+
+fn list_lan_servers() -> ExitCode {
+    let list = match network_runtime().and_then(|runtime| ServerList::listen(runtime, LAN_GROUP)) {
+        Ok(list) => list,
+        Err(e) => {
+            log::error!("{:#}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    log::debug!("Listening for LAN servers on {}", list.local_addr());
+    // servers announce every 1.5 seconds, then need a moment to answer
+    std::thread::sleep(Duration::from_secs(3));
+    let servers = list.servers();
+    if servers.is_empty() {
+        println!("no servers found on the local network");
+    }
+    for server in servers {
+        match (&server.status, &server.error) {
+            (Some(status), _) => println!(
+                "{}  {}  {}/{} players  {} ms  {}",
+                server.address,
+                status.description,
+                status.players.online,
+                status.players.max,
+                server.ping.unwrap_or_default().as_millis(),
+                status.version.name,
+            ),
+            (None, Some(error)) => println!("{}  {}  {}", server.address, server.motd, error),
+            (None, None) => println!("{}  {}  no answer yet", server.address, server.motd),
+        }
+    }
+    ExitCode::SUCCESS
 }
@@ -2,8 +2,6 @@ use std::f32::consts::PI;
 
 use glam::*;
 
-use super::input_manager::InputManager;
-
 #[derive(Debug)]
 pub struct Camera {
     pub position: Vec3,
@@ -14,7 +12,6 @@ pub struct Camera {
     pub fovy: f32,
     pub znear: f32,
     pub zfar: f32,
-    pub speed: f32,
 }
 
 impl Camera {
@@ -28,7 +25,6 @@ impl Camera {
             fovy: PI / 2.0,
             znear: 0.01,
             zfar: 300.0,
-            speed: 0.05,
         }
     }
 
@@ -63,49 +59,10 @@ impl Camera {
         }
     }
 
-    /// Now we use data directly from the input manager to update the camera position
-    /// This is temporary until we define the player's state.
-    pub fn update(&mut self, player_state: &InputManager) {
-        let player_state = player_state.to_key_record();
-        if player_state.forward {
-            self.go_forward(1.0);
-        }
-        if player_state.backward {
-            self.go_forward(-1.0);
-        }
-        if player_state.left {
-            self.go_right(-1.0);
-        }
-        if player_state.right {
-            self.go_right(1.0);
-        }
-        if player_state.ascend {
-            self.go_up(1.0);
-        }
-        if player_state.descend {
-            self.go_up(-1.0);
-        }
-    }
-
     pub fn get_forward_direction(&self) -> Vec3 {
         vec3(self.yaw.sin(), 0.0, self.yaw.cos())
     }
 
-    fn go_forward(&mut self, step: f32) {
-        let f = self.get_forward_direction();
-        self.position += f * step * self.speed;
-    }
-
-    fn go_right(&mut self, step: f32) {
-        let f = self.get_forward_direction();
-        let r = f.cross(self.up).normalize();
-        self.position += r * step * self.speed;
-    }
-
-    fn go_up(&mut self, step: f32) {
-        self.position += self.up * step * self.speed;
-    }
-
     pub fn shift(&mut self, dir: Vec3) {
         self.position += dir;
     }
@@ -0,0 +1,78 @@
+//! Entities drawn as textured boxes of their size, until they have models.
+
+use std::time::Instant;
+
+use blockworld_server::{block::block_face_direction::BlockFaceDirection, entity::EntityType};
+use blockworld_utils::atlas_image::Atlas;
+use glam::*;
+use wgpu::{util::DeviceExt, Device, RenderPass};
+
+use super::{meshing::block_meshing::to_quad_mesh, vertex::TexturedVertex};
+use crate::game::client_entities::ClientEntities;
+
+/// The block sprite standing in for each kind of entity.
+fn placeholder_sprite(kind: EntityType) -> &'static str {
+    match kind {
+        EntityType::PLAYER => "minecraft:stone",
+        EntityType::ITEM => "minecraft:grass_block",
+        EntityType::FALLING_BLOCK => "minecraft:sand",
+        EntityType::PIG => "minecraft:dirt",
+        _ => "minecraft:cobblestone",
+    }
+}
+
+#[derive(Default)]
+pub struct EntityRenderer {
+    /// Rebuilt every frame, entities move every frame.
+    vertex_buffer: Option<wgpu::Buffer>,
+    vertex_count: u32,
+}
+
+impl EntityRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mesh every entity where it is at `now`.
+    pub fn update(
+        &mut self,
+        device: &Device,
+        entities: &ClientEntities,
+        atlas: &Atlas,
+        now: Instant,
+    ) {
+        let mut vertices: Vec<TexturedVertex> = vec![];
+        for pose in entities.poses(now) {
+            let (width, height) = pose.kind.size();
+            let scale = vec3(width, height, width);
+            let turn = Quat::from_rotation_y(-pose.yaw);
+            let center = pose.position + Vec3::Y * height / 2.0;
+            let (a, b) = atlas
+                .query_uv(&placeholder_sprite(pose.kind).into())
+                .unwrap_or((vec2(0.0, 0.0), vec2(1.0, 1.0)));
+            for face in BlockFaceDirection::iter() {
+                vertices.extend(to_quad_mesh(face, Vec3::ZERO, a, b).map(|mut v| {
+                    let local = Vec3::from_array(v.position) * scale;
+                    v.position = (center + turn * local).to_array();
+                    v
+                }));
+            }
+        }
+
+        self.vertex_count = vertices.len() as u32;
+        self.vertex_buffer = (!vertices.is_empty()).then(|| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Entity Vertex Buffer"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            })
+        });
+    }
+
+    pub fn render<'rpass>(&'rpass self, rpass: &mut RenderPass<'rpass>) {
+        if let Some(buffer) = &self.vertex_buffer {
+            rpass.set_vertex_buffer(0, buffer.slice(..));
+            rpass.draw(0..self.vertex_count, 0..1);
+        }
+    }
+}
@@ -3,7 +3,7 @@ use std::collections::HashSet;
 use glam::{vec2, Vec2};
 use once_cell::sync::Lazy;
 use winit::{
-    event::{DeviceEvent, ElementState, KeyEvent},
+    event::{DeviceEvent, ElementState, KeyEvent, MouseButton},
     keyboard::{Key, NamedKey},
 };
 
@@ -15,6 +15,7 @@ pub struct MovementRecord {
     pub descend: bool,
     pub left: bool,
     pub right: bool,
+    pub sprint: bool,
 }
 
 pub static mut GLOBAL_INPUT_MANAGER: Lazy<InputManager> = Lazy::new(|| InputManager::default());
@@ -23,6 +24,10 @@ pub static mut GLOBAL_INPUT_MANAGER: Lazy<InputManager> = Lazy::new(|| InputMana
 #[derive(Default, Debug)]
 pub struct InputManager {
     pressing_keys: HashSet<Key>,
+    pressing_buttons: HashSet<MouseButton>,
+    /// Buttons pressed since the last [`InputManager::take_pressed`], so a click shorter than
+    /// a tick still counts.
+    pressed_buttons: HashSet<MouseButton>,
 }
 
 impl InputManager {
@@ -46,6 +51,9 @@ impl InputManager {
         if self.is_key_pressing(Key::Named(NamedKey::Shift)) {
             s.descend = true;
         }
+        if self.is_key_pressing(Key::Named(NamedKey::Control)) {
+            s.sprint = true;
+        }
         s
     }
 
@@ -65,5 +73,26 @@ impl InputManager {
         }
     }
 
+    pub fn is_button_pressing(&self, button: MouseButton) -> bool {
+        self.pressing_buttons.contains(&button)
+    }
+
+    /// True if `button` was pressed since the last call.
+    pub fn take_pressed(&mut self, button: MouseButton) -> bool {
+        self.pressed_buttons.remove(&button)
+    }
+
+    pub fn handle_mouse_button(&mut self, button: MouseButton, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                self.pressing_buttons.insert(button);
+                self.pressed_buttons.insert(button);
+            }
+            ElementState::Released => {
+                self.pressing_buttons.remove(&button);
+            }
+        }
+    }
+
     pub fn handle_mouse_event(&mut self, event: &DeviceEvent) {}
 }
@@ -1,13 +1,48 @@
+use std::collections::HashMap;
+
 use blockworld_server::{
-    block::block_face_direction::BlockFaceDirection,
-    world::{chunk::SubChunk, chunk_access::WorldAccess, disk_chunk_access::DiskChunkArray},
+    block::{
+        block_face_direction::BlockFaceDirection,
+        fluid::{fluid_height, FluidKind},
+        shape::{outline_shape, FULL_CUBE},
+        state::BlockState,
+    },
+    world::{chunk::SubChunk, chunk_access::WorldAccess},
 };
+use blockworld_utils::atlas_image::Atlas;
 use glam::*;
 use wgpu::{util::DeviceExt, Device, RenderPass};
 
-use crate::renderer::resource_manager::BLOCK_ATLAS;
-
 use super::block_meshing::to_quad_mesh;
+use crate::renderer::vertex::TexturedVertex;
+
+/// Blocks that take up part of their block, like slabs and fences, drawn box by box.
+fn partial(state: BlockState) -> bool {
+    let shape = outline_shape(state);
+    shape != [FULL_CUBE] && !shape.is_empty()
+}
+
+/// Whole blocks, that hide the faces against them. Fluids don't, as they may be lower.
+fn hides(state: BlockState) -> bool {
+    !state.is_air() && !partial(state) && FluidKind::of(state).is_none()
+}
+
+/// The face of a box `scale` big around `center`.
+fn box_face(
+    k: BlockFaceDirection,
+    center: Vec3,
+    scale: Vec3,
+    a: Vec2,
+    b: Vec2,
+) -> impl Iterator<Item = TexturedVertex> {
+    to_quad_mesh(k, Vec3::ZERO, a, b)
+        .into_iter()
+        .map(move |mut v| {
+            let offset = Vec3::from_array(v.position) * scale;
+            v.position = (center + offset).to_array();
+            v
+        })
+}
 
 #[derive(Debug)]
 pub struct RenderChunk {
@@ -16,71 +51,119 @@ pub struct RenderChunk {
 }
 
 pub struct MeshingManager {
-    render_array: Vec<RenderChunk>,
+    render_array: HashMap<IVec3, RenderChunk>,
 }
 
 impl MeshingManager {
-    pub fn update<T: WorldAccess>(&mut self, device: &Device, chunks: T) {
-        // loaded chunks
-        for (ind, chunk) in chunks.iter_loaded_chunks().enumerate() {
-            let pos = chunk.pos();
-            if chunks.need_rerender(chunk.pos()) {
-                let mut vertices = vec![];
+    /// Mesh the loaded chunks waiting for a rerender, and drop the meshes of unloaded ones.
+    pub fn update<T: WorldAccess>(&mut self, device: &Device, chunks: &T, atlas: &Atlas) {
+        self.render_array
+            .retain(|pos, _| chunks.is_chunk_loaded(*pos));
+        let pending: Vec<_> = chunks
+            .iter_loaded_chunks()
+            .filter(|chunk| chunks.need_rerender(chunk.pos()))
+            .collect();
+        for chunk in pending {
+            self.mesh_chunk(device, chunks, chunk, atlas);
+        }
+    }
+
+    /// Mesh every loaded chunk, whether it changed or not.
+    #[cfg(test)]
+    pub fn rebuild<T: WorldAccess>(&mut self, device: &Device, chunks: &T, atlas: &Atlas) {
+        self.render_array.clear();
+        for chunk in chunks.iter_loaded_chunks() {
+            self.mesh_chunk(device, chunks, chunk, atlas);
+        }
+    }
 
-                for x in 0..16 {
-                    for y in 0..16 {
-                        for z in 0..16 {
-                            let block_id = chunk.get_blockid(pos);
-                            let blockpos = pos * 16 + ivec3(x, y, z);
+    fn mesh_chunk<T: WorldAccess>(
+        &mut self,
+        device: &Device,
+        chunks: &T,
+        chunk: &SubChunk,
+        atlas: &Atlas,
+    ) {
+        let pos = chunk.pos();
+        let mut vertices: Vec<TexturedVertex> = vec![];
 
-                            let mut cull = 0b111111 as u32;
+        for x in 0..16 {
+            for y in 0..16 {
+                for z in 0..16 {
+                    let local = ivec3(x, y, z);
+                    let state = chunk.get_state(local);
+                    if state.is_air() {
+                        continue;
+                    }
+                    let blockpos = pos * 16 + local;
 
-                            if block_id != "minecraft:air" {
-                                let (a, b) = BLOCK_ATLAS
-                                    .query_uv(&block_id.into())
-                                    .unwrap_or((vec2(0.0, 0.0), vec2(1.0, 1.0)));
-                                for k in BlockFaceDirection::iter() {
-                                    if !chunks.is_air(blockpos + k.to_vec()) {
-                                        cull -= k as u32;
-                                    }
-                                }
-                                for k in BlockFaceDirection::iter() {
-                                    if k as u32 & cull == 0 {
-                                        let vtxs = to_quad_mesh(
-                                            k,
-                                            vec3(pos.x as f32, pos.y as f32, pos.z as f32),
-                                            a,
-                                            b,
-                                        );
-                                        vertices.extend(vtxs);
-                                    }
-                                }
+                    let (a, b) = atlas
+                        .query_uv(state.name())
+                        .unwrap_or((vec2(0.0, 0.0), vec2(1.0, 1.0)));
+                    if partial(state) {
+                        // every face of every box, they don't hide anything
+                        for aabb in outline_shape(state) {
+                            let center = blockpos.as_vec3() + (aabb.min + aabb.max) / 2.0;
+                            for k in BlockFaceDirection::iter() {
+                                vertices.extend(box_face(k, center, aabb.size(), a, b));
+                            }
+                        }
+                        continue;
+                    }
+                    if let Some(kind) = FluidKind::of(state) {
+                        // as high as its level, and full under more of the same
+                        let above = chunks.get_state(blockpos + IVec3::Y);
+                        let height = fluid_height(state, above);
+                        let center = blockpos.as_vec3() + vec3(0.5, height / 2.0, 0.5);
+                        let scale = vec3(1.0, height, 1.0);
+                        for k in BlockFaceDirection::iter() {
+                            let neighbour = chunks.get_state(blockpos + k.to_vec());
+                            let lower = k == BlockFaceDirection::YP && height < 1.0;
+                            let hidden = FluidKind::of(neighbour) == Some(kind)
+                                || (hides(neighbour) && !lower);
+                            if !hidden {
+                                vertices.extend(box_face(k, center, scale, a, b));
                             }
                         }
+                        continue;
+                    }
+                    let center = blockpos.as_vec3() + Vec3::splat(0.5);
+                    for k in BlockFaceDirection::iter() {
+                        // faces against another whole block can't be seen
+                        let neighbour = chunks.get_state(blockpos + k.to_vec());
+                        if !hides(neighbour) {
+                            vertices.extend(to_quad_mesh(k, center, a, b));
+                        }
                     }
                 }
-
-                let render_chunk = RenderChunk {
-                    vertex_count: 0,
-                    vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some(&format!("Chunk{} Vertex Buffer", pos)),
-                        contents: bytemuck::cast_slice(&vertices),
-                        usage: wgpu::BufferUsages::VERTEX,
-                    }),
-                };
-                self.render_array.push(render_chunk);
             }
         }
+
+        if vertices.is_empty() {
+            self.render_array.remove(&pos);
+            return;
+        }
+        let render_chunk = RenderChunk {
+            vertex_count: vertices.len() as u32,
+            vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("Chunk{} Vertex Buffer", pos)),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+        };
+        self.render_array.insert(pos, render_chunk);
     }
+
     pub fn render<'rpass>(&'rpass self, rpass: &mut RenderPass<'rpass>) {
-        for chunk in self.render_array.iter() {
+        for chunk in self.render_array.values() {
             rpass.set_vertex_buffer(0, chunk.vertex_buffer.slice(..));
             rpass.draw(0..chunk.vertex_count, 0..1);
         }
     }
+
     pub fn new() -> Self {
         Self {
-            render_array: vec![],
+            render_array: HashMap::new(),
         }
     }
 }
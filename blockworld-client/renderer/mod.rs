@@ -1,10 +1,14 @@
-pub mod atlas_image;
+pub use blockworld_utils::atlas_image;
 pub mod bytes_provider;
 pub mod camera;
 mod debug_gui;
+pub mod entity_renderer;
 pub mod gui;
 pub mod meshing;
+#[cfg(test)]
+pub mod offscreen;
 pub mod resource_manager;
+pub mod selection_renderer;
 mod shaders;
 pub mod vertex;
 pub mod world_renderer;
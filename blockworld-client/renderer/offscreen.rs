@@ -0,0 +1,266 @@
+//! Rendering without a window, into a texture that is read back to an image.
+//!
+//! It asks wgpu for the fallback adapter, a software rasteriser such as llvmpipe or WARP, so it
+//! runs on CI machines without a GPU. Only tests use it. The golden-image tests at the bottom
+//! render a fixture world from a fixed camera and compare it against `renderer/golden/`. Set
+//! `BLOCKWORLD_UPDATE_GOLDEN=1` to write the current output as the new golden images.
+
+use std::sync::Arc;
+
+use anyhow::*;
+use blockworld_utils::atlas_image::Atlas;
+use glam::*;
+use pollster::FutureExt;
+
+use super::world_renderer::WorldRenderer;
+use crate::game::client::BlockworldClient;
+
+/// sRGB like the window surface, so both look the same.
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+pub struct OffscreenRenderer {
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    pub world_renderer: WorldRenderer,
+    target: wgpu::Texture,
+    size: UVec2,
+}
+
+/// Create a software adapter, none if the platform doesn't have one.
+pub fn create_fallback_adapter() -> Option<wgpu::Adapter> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+    instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: None,
+            force_fallback_adapter: true,
+        })
+        .block_on()
+}
+
+impl OffscreenRenderer {
+    pub fn new(size: UVec2, atlas: Arc<Atlas>, game: BlockworldClient) -> Result<Self> {
+        let adapter = create_fallback_adapter().ok_or_else(|| anyhow!("No fallback adapter"))?;
+        log::info!("Rendering offscreen on {:?}", adapter.get_info());
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("Blockworld Offscreen Device"),
+                    // wireframes are only drawn if the adapter can
+                    required_features: adapter.features() & wgpu::Features::POLYGON_MODE_LINE,
+                    required_limits: wgpu::Limits::downlevel_defaults()
+                        .using_resolution(adapter.limits()),
+                    ..Default::default()
+                },
+                None,
+            )
+            .block_on()?;
+
+        let target = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Blockworld Offscreen Target"),
+            size: wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let mut world_renderer = WorldRenderer::new(&device, &queue, FORMAT, size, atlas, game);
+        world_renderer.rebuild_meshes(&device);
+
+        Ok(Self {
+            device,
+            queue,
+            world_renderer,
+            target,
+            size,
+        })
+    }
+
+    /// Draw a frame from the current camera and read it back.
+    pub fn render(&mut self) -> Result<image::RgbaImage> {
+        self.world_renderer.upload_camera(&self.queue);
+
+        // rows of a texture copy must be aligned, the padding is cut off again below
+        let row_bytes = self.size.x * 4;
+        let padded_row_bytes = row_bytes.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Blockworld Offscreen Readback"),
+            size: (padded_row_bytes * self.size.y) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let view = self
+            .target
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Blockworld Offscreen Encoder"),
+            });
+        self.world_renderer.encode_frame(&mut encoder, &view);
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.target,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(self.size.y),
+                },
+            },
+            self.target.size(),
+        );
+        self.queue.submit([encoder.finish()]);
+
+        let slice = readback.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |r| {
+            let _ = tx.send(r);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        rx.recv()??;
+
+        let mut pixels = Vec::with_capacity((row_bytes * self.size.y) as usize);
+        for row in slice.get_mapped_range().chunks(padded_row_bytes as usize) {
+            pixels.extend_from_slice(&row[..row_bytes as usize]);
+        }
+        readback.unmap();
+
+        image::RgbaImage::from_raw(self.size.x, self.size.y, pixels)
+            .ok_or_else(|| anyhow!("Readback has the wrong size"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        f32::consts::PI,
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use blockworld_server::world::chunk_access::WorldAccess;
+    use blockworld_utils::TempDir;
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+    use crate::game::client_chunk_cache::ClientChunkCache;
+
+    /// Channels may be off by this much, software rasterisers don't round identically.
+    const TOLERANCE: u8 = 8;
+    /// Share of pixels allowed past the tolerance, for edges that land on other pixels.
+    const MAX_CHANGED: f64 = 0.001;
+
+    /// Two tone checkers, so a wrong UV or a flipped face shows up.
+    fn fixture_atlas(dir: &Path) -> Arc<Atlas> {
+        for (name, a, b) in [
+            ("stone", [125, 125, 125], [90, 90, 90]),
+            ("grass_block", [95, 160, 60], [70, 120, 40]),
+            ("sand", [220, 210, 160], [190, 180, 130]),
+        ] {
+            let tile = RgbaImage::from_fn(16, 16, |x, y| {
+                let [r, g, b] = if (x / 4 + y / 4) % 2 == 0 { a } else { b };
+                Rgba([r, g, b, 255])
+            });
+            tile.save(dir.join(format!("{}.png", name))).unwrap();
+        }
+        Arc::new(Atlas::new(dir))
+    }
+
+    /// A grass floor on stone with a sand pillar and a stone wall.
+    fn fixture_world() -> BlockworldClient {
+        let mut chunks = ClientChunkCache::new();
+        for x in -1..=1 {
+            for z in -1..=1 {
+                chunks.load_chunk(ivec3(x, 0, z));
+            }
+        }
+        for x in -16..32 {
+            for z in -16..32 {
+                chunks.set_block(ivec3(x, 0, z), &"minecraft:stone".into());
+                chunks.set_block(ivec3(x, 1, z), &"minecraft:grass_block".into());
+            }
+        }
+        for y in 2..6 {
+            chunks.set_block(ivec3(4, y, 4), &"minecraft:sand".into());
+            for x in 8..12 {
+                chunks.set_block(ivec3(x, y, 10), &"minecraft:stone".into());
+            }
+        }
+        BlockworldClient::with_chunks(chunks)
+    }
+
+    fn golden(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("renderer/golden")
+            .join(format!("{}.png", name))
+    }
+
+    /// Compare against the golden image, or replace it if we're asked to.
+    fn check_golden(name: &str, image: &RgbaImage) {
+        let path = golden(name);
+        if std::env::var_os("BLOCKWORLD_UPDATE_GOLDEN").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            image.save(&path).unwrap();
+            eprintln!("wrote golden image {}", path.display());
+            return;
+        }
+
+        let expected = image::open(&path)
+            .unwrap_or_else(|e| panic!("no golden image {}: {}", path.display(), e))
+            .to_rgba8();
+        assert_eq!(expected.dimensions(), image.dimensions());
+        let changed = expected
+            .pixels()
+            .zip(image.pixels())
+            .filter(|(a, b)| a.0.iter().zip(b.0).any(|(a, b)| a.abs_diff(b) > TOLERANCE))
+            .count();
+        let share = changed as f64 / (image.width() * image.height()) as f64;
+        if share > MAX_CHANGED {
+            let actual = path.with_extension("actual.png");
+            image.save(&actual).unwrap();
+            panic!(
+                "{} pixels differ from {}, the render was saved to {}",
+                changed,
+                path.display(),
+                actual.display()
+            );
+        }
+    }
+
+    #[test]
+    fn fixture_world_matches_golden() {
+        let dir = TempDir::new("offscreen").unwrap();
+        let atlas = fixture_atlas(&dir);
+        // no skipping without an adapter, CI would stay green without ever comparing
+        let mut renderer = OffscreenRenderer::new(uvec2(256, 192), atlas, fixture_world())
+            .expect("the golden image test needs a software adapter, like Mesa's llvmpipe");
+
+        let camera = &mut renderer.world_renderer.camera;
+        camera.position = vec3(-6.0, 12.0, -6.0);
+        camera.yaw = PI / 4.0;
+        camera.pitch = -PI / 5.0;
+        check_golden("fixture_world", &renderer.render().unwrap());
+
+        // the same scene again must give the same frame
+        let again = renderer.render().unwrap();
+        check_golden("fixture_world", &again);
+    }
+}
@@ -11,7 +11,7 @@ impl RegularPipeline {
         device: &wgpu::Device,
         bind_group_layouts: &[&wgpu::BindGroupLayout],
         shader: &dyn ToWgpuShader,
-        surface_config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
     ) -> Self {
         let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Blockworld Render Pipeline Layout"),
@@ -32,7 +32,7 @@ impl RegularPipeline {
                 module: shader.get_frag().0,
                 entry_point: Some(shader.get_frag().1),
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
+                    format,
                     blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -77,7 +77,7 @@ impl WireframePipeline {
         device: &wgpu::Device,
         bind_group_layouts: &[&wgpu::BindGroupLayout],
         shader: &dyn ToWgpuShader,
-        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
     ) -> Self {
         let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Blockworld Debug Pipeline Layout"),
@@ -98,7 +98,7 @@ impl WireframePipeline {
                 module: shader.get_frag().0,
                 entry_point: Some(shader.get_frag().1),
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
+                    format,
                     blend: Some(wgpu::BlendState::REPLACE),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -131,3 +131,72 @@ impl WireframePipeline {
         Self { layout, pipeline }
     }
 }
+
+/// Lines over what's already drawn, for the outline of the block looked at. Unlike
+/// [`WireframePipeline`] it needs no optional feature.
+#[derive(Debug)]
+pub struct OutlinePipeline {
+    pub layout: wgpu::PipelineLayout,
+    pub pipeline: wgpu::RenderPipeline,
+}
+
+impl OutlinePipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+        shader: &dyn ToWgpuShader,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blockworld Outline Pipeline Layout"),
+            bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Outline Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: shader.get_vert().0,
+                entry_point: Some(shader.get_vert().1),
+                buffers: &[TexturedVertex::desc()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader.get_frag().0,
+                entry_point: Some(shader.get_frag().1),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            // tested against the blocks but not hiding anything
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+        Self { layout, pipeline }
+    }
+}
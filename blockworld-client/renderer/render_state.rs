@@ -1,6 +1,11 @@
+use blockworld_server::block::BLOCK_REGISTRY;
+
+use crate::game::client::BlockworldClient;
 use crate::renderer::init_helpers::*;
+use crate::renderer::resource_manager::BLOCK_ATLAS;
 use crate::renderer::world_renderer::{self, WorldRenderer};
 use egui_winit_platform::Platform;
+use glam::uvec2;
 use std::{sync::Arc, time::Instant};
 use wgpu::{include_wgsl, Device, Queue, Surface, SurfaceConfiguration};
 use winit::{dpi::PhysicalSize, window::Window};
@@ -27,7 +32,7 @@ pub struct RenderState {
 }
 
 impl RenderState {
-    pub fn new(window: Window) -> RenderState {
+    pub fn new(window: Window, game: BlockworldClient) -> RenderState {
         let window_arc = Arc::new(window);
         let size = window_arc.inner_size();
         let instance = create_instance();
@@ -38,7 +43,14 @@ impl RenderState {
         surface.configure(&device, &config);
         let input_manager = InputManager::default();
 
-        let world_renderer = WorldRenderer::new(&device, &config, &queue, size);
+        let world_renderer = WorldRenderer::new(
+            &device,
+            &queue,
+            config.format,
+            uvec2(size.width, size.height),
+            BLOCK_ATLAS.clone(),
+            game,
+        );
 
         Self {
             window: window_arc,
@@ -59,12 +71,8 @@ impl RenderState {
             self.config.width = new_size.width;
             self.config.height = new_size.height;
 
-            self.world_renderer.resize(
-                &self.queue,
-                &self.device,
-                &self.config,
-                new_size.width as f32 / new_size.height as f32,
-            );
+            self.world_renderer
+                .resize(&self.device, uvec2(new_size.width, new_size.height));
             self.surface.configure(&self.device, &self.config);
             self.size = new_size;
         }
@@ -76,15 +84,36 @@ impl RenderState {
         // Set the timer to 0
         self.dt_timer = Instant::now();
 
+        // what the server told us about where the camera is
+        let eye = self.world_renderer.camera.position.floor().as_ivec3();
+        let game = self.world_renderer.game();
+        let chunks = game.chunks();
+        // inputs the server hasn't answered yet, and how often it disagreed with the prediction
+        let (pending, corrections) = game
+            .player()
+            .map_or((0, 0), |p| (p.pending(), p.corrections()));
+        let holding = BLOCK_REGISTRY.number_id_to_name(game.interaction().selected());
+        let health = game.health().map_or("-".to_string(), |h| {
+            format!("health: {:.0} food: {}", h.health, h.food)
+        });
         self.window.set_title(
             format!(
-                "Blockworld Dev [fps: {:.0}]",
-                1.0 / delta_time.as_secs_f32()
+                "Blockworld Dev [fps: {:.0}] [{} sky: {} block: {}] [in flight: {} corrections: {}] [{} {}] [holding: {}]",
+                1.0 / delta_time.as_secs_f32(),
+                chunks.biome(eye).unwrap_or("-"),
+                chunks.sky_light(eye),
+                chunks.block_light(eye),
+                pending,
+                corrections,
+                game.game_mode(),
+                health,
+                holding.map_or("-", |h| &**h),
             )
             .as_str(),
         );
 
-        self.world_renderer.update(&self.queue, &self.input_manager);
+        self.world_renderer
+            .update(&self.device, &self.queue, &mut self.input_manager);
     }
 
     pub fn render(&mut self) {
@@ -103,35 +132,8 @@ impl RenderState {
                 label: Some("Blockworld Render Encoder"),
             });
 
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Blockworld Render Pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &output_texture_view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color {
-                        // #82a9f9 minecraft's sky blue
-                        r: 0.509804,
-                        g: 0.662745,
-                        b: 0.976471,
-                        a: 1.0,
-                    }),
-                    store: wgpu::StoreOp::Store,
-                },
-            })],
-            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.world_renderer.depth_texture.view,
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
-                    store: wgpu::StoreOp::Store,
-                }),
-                stencil_ops: None,
-            }),
-            ..Default::default()
-        });
-
-        self.world_renderer.render(&mut render_pass);
-        drop(render_pass);
+        self.world_renderer
+            .encode_frame(&mut encoder, &output_texture_view);
 
         let command_buffer = encoder.finish();
         self.queue.submit([command_buffer]);
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use blockworld_utils::ResourceLocation;
 use once_cell::sync::Lazy;
 
@@ -6,4 +8,5 @@ use super::atlas_image::Atlas;
 /// temporarily use this global variable to store the block atlas
 ///
 /// TODO: use a resource manager to load and store atlases
-pub static BLOCK_ATLAS: Lazy<Atlas> = Lazy::new(|| Atlas::new("assets/minecraft/textures/block/"));
+pub static BLOCK_ATLAS: Lazy<Arc<Atlas>> =
+    Lazy::new(|| Arc::new(Atlas::new("assets/minecraft/textures/block/")));
@@ -0,0 +1,137 @@
+//! The outline of the block looked at, and the cracks on the block being broken.
+
+use blockworld_server::{
+    block::{block_face_direction::BlockFaceDirection, shape::outline_shape},
+    world::{
+        chunk_access::{BlockPos, WorldAccess},
+        raycast::BlockHit,
+    },
+};
+use blockworld_utils::atlas_image::Atlas;
+use glam::*;
+use wgpu::{util::DeviceExt, Device, RenderPass};
+
+use super::{meshing::block_meshing::to_quad_mesh, vertex::TexturedVertex};
+use crate::game::client_chunk_cache::ClientChunkCache;
+
+/// How much bigger than the block the outline and the cracks are drawn, so they don't fight
+/// with its faces.
+const INFLATE: f32 = 0.002;
+
+#[derive(Default)]
+struct Mesh {
+    buffer: Option<wgpu::Buffer>,
+    count: u32,
+}
+
+impl Mesh {
+    fn new(device: &Device, label: &str, vertices: &[TexturedVertex]) -> Self {
+        let buffer = (!vertices.is_empty()).then(|| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: bytemuck::cast_slice(vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            })
+        });
+        Self {
+            buffer,
+            count: vertices.len() as u32,
+        }
+    }
+
+    fn render<'rpass>(&'rpass self, rpass: &mut RenderPass<'rpass>) {
+        if let Some(buffer) = &self.buffer {
+            rpass.set_vertex_buffer(0, buffer.slice(..));
+            rpass.draw(0..self.count, 0..1);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SelectionRenderer {
+    /// Lines, for the outline pipeline.
+    outline: Mesh,
+    /// Quads, for the main pipeline.
+    cracks: Mesh,
+}
+
+impl SelectionRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mesh the outline of `target` and the cracks of `crack`, a block and its stage from 0
+    /// to 9.
+    pub fn update(
+        &mut self,
+        device: &Device,
+        world: &ClientChunkCache,
+        target: Option<BlockHit>,
+        crack: Option<(BlockPos, u8)>,
+        atlas: &Atlas,
+    ) {
+        let mut lines = vec![];
+        if let Some(hit) = target {
+            for b in outline_shape(world.get_state(hit.pos)) {
+                let min = hit.pos.as_vec3() + b.min - Vec3::splat(INFLATE);
+                let max = hit.pos.as_vec3() + b.max + Vec3::splat(INFLATE);
+                lines.extend(box_edges(min, max).map(|p| TexturedVertex::new(p, Vec2::ZERO)));
+            }
+        }
+        self.outline = Mesh::new(device, "Outline Vertex Buffer", &lines);
+
+        let mut quads = vec![];
+        let sprite = crack.and_then(|(pos, stage)| {
+            let uv = atlas.query_uv(&format!("minecraft:destroy_stage_{}", stage).as_str().into());
+            uv.map(|uv| (pos, uv))
+        });
+        // without the textures there's nothing to draw the cracks with
+        if let Some((pos, (a, b))) = sprite {
+            for shape in outline_shape(world.get_state(pos)) {
+                let scale = shape.max - shape.min + Vec3::splat(INFLATE * 2.0);
+                let center = pos.as_vec3() + (shape.min + shape.max) / 2.0;
+                for face in BlockFaceDirection::iter() {
+                    quads.extend(to_quad_mesh(face, Vec3::ZERO, a, b).map(|mut v| {
+                        v.position = (center + Vec3::from_array(v.position) * scale).to_array();
+                        v
+                    }));
+                }
+            }
+        }
+        self.cracks = Mesh::new(device, "Crack Vertex Buffer", &quads);
+    }
+
+    /// Draw the cracks, with the main pipeline set.
+    pub fn render_cracks<'rpass>(&'rpass self, rpass: &mut RenderPass<'rpass>) {
+        self.cracks.render(rpass);
+    }
+
+    /// Draw the outline, with the outline pipeline set.
+    pub fn render_outline<'rpass>(&'rpass self, rpass: &mut RenderPass<'rpass>) {
+        self.outline.render(rpass);
+    }
+}
+
+/// The 12 edges of the box from `min` to `max`, two points each.
+fn box_edges(min: Vec3, max: Vec3) -> [Vec3; 24] {
+    let corner = |i: usize| {
+        vec3(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        )
+    };
+    // corners differing in one bit share an edge
+    let mut edges = [Vec3::ZERO; 24];
+    let mut n = 0;
+    for i in 0..8 {
+        for bit in [1, 2, 4] {
+            if i & bit == 0 {
+                edges[n] = corner(i);
+                edges[n + 1] = corner(i | bit);
+                n += 2;
+            }
+        }
+    }
+    edges
+}
@@ -11,10 +11,12 @@ use window::{Window, WindowId};
 use winit::*;
 
 use super::render_state::RenderState;
+use crate::game::client::BlockworldClient;
 
 /// The main struct for window initialization and event handling.
-#[derive(Default)]
 struct WindowApplication {
+    /// Handed to the render state once the window exists.
+    game: Option<BlockworldClient>,
     render_state: Option<RenderState>,
 }
 
@@ -32,6 +34,9 @@ impl WindowApplication {
 impl ApplicationHandler for WindowApplication {
     /// Initialize the application.
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(game) = self.game.take() else {
+            return;
+        };
         let window = event_loop.create_window(
             Window::default_attributes()
                 .with_title(blockworld_utils::GAME_NAME)
@@ -39,7 +44,7 @@ impl ApplicationHandler for WindowApplication {
         );
         match window {
             Ok(window) => {
-                self.render_state = Some(RenderState::new(window));
+                self.render_state = Some(RenderState::new(window, game));
             }
             Err(_) => {
                 error!("Failed to create window");
@@ -79,6 +84,16 @@ impl ApplicationHandler for WindowApplication {
             }
             WindowEvent::RedrawRequested => {
                 self.render_state_mut().update();
+                if self
+                    .render_state()
+                    .world_renderer
+                    .game()
+                    .disconnected()
+                    .is_some()
+                {
+                    event_loop.exit();
+                    return;
+                }
                 // use inspect_err to avoid panic so that we can input instruction to display state to debug
                 // self.try_exec_single_instr_from_console().inspect_err(
                 //     |e| {
@@ -91,6 +106,11 @@ impl ApplicationHandler for WindowApplication {
             WindowEvent::Resized(size) => {
                 self.render_state_mut().resize(size);
             }
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.render_state_mut()
+                    .input_manager
+                    .handle_mouse_button(button, state);
+            }
             WindowEvent::KeyboardInput { event, .. } => {
                 if event.physical_key == KeyCode::Escape {
                     event_loop.exit();
@@ -110,6 +130,35 @@ impl ApplicationHandler for WindowApplication {
                         !self.render_state().world_renderer.debug_mode;
                 }
 
+                if key == PhysicalKey::Code(KeyCode::KeyF)
+                    && event.state == event::ElementState::Released
+                {
+                    self.render_state_mut()
+                        .world_renderer
+                        .game_mut()
+                        .toggle_flying();
+                }
+
+                // the number keys pick the block to place, in registry order
+                let digits = [
+                    KeyCode::Digit1,
+                    KeyCode::Digit2,
+                    KeyCode::Digit3,
+                    KeyCode::Digit4,
+                    KeyCode::Digit5,
+                    KeyCode::Digit6,
+                    KeyCode::Digit7,
+                    KeyCode::Digit8,
+                    KeyCode::Digit9,
+                ];
+                if let Some(slot) = digits.iter().position(|d| key == PhysicalKey::Code(*d)) {
+                    self.render_state_mut()
+                        .world_renderer
+                        .game_mut()
+                        .interaction_mut()
+                        .select(slot as u32 + 1);
+                }
+
                 if key == PhysicalKey::Code(KeyCode::F2)
                     && event.state == event::ElementState::Released
                 {
@@ -134,15 +183,23 @@ impl ApplicationHandler for WindowApplication {
             _ => (),
         }
     }
-}
 
-pub async fn run() {
-    env_logger::init();
+    /// Leave the server before the window goes away, saving singleplayer worlds.
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(render_state) = self.render_state.as_mut() {
+            render_state.world_renderer.game_mut().quit();
+        }
+    }
+}
 
+pub async fn run(game: BlockworldClient) {
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(event_loop::ControlFlow::Poll);
 
-    let mut state = WindowApplication::default();
+    let mut state = WindowApplication {
+        game: Some(game),
+        render_state: None,
+    };
 
     event_loop
         .run_app(&mut state)
@@ -1,21 +1,31 @@
+use std::{sync::Arc, time::Instant};
+
+use blockworld_utils::atlas_image::Atlas;
 use bytemuck::{Pod, Zeroable};
-use glam::Mat4;
+use glam::{Mat4, UVec2};
 use wgpu::*;
 
-use crate::game::client::BlockworldClient;
+use blockworld_server::{entity::movement::MoveInput, tick::TickClock};
+use winit::event::MouseButton;
+
+use crate::game::{client::BlockworldClient, interaction::InteractInput};
 
 use super::{
     bytes_provider::StaticBytesProvider,
     camera::Camera,
-    input_manager::InputManager,
+    entity_renderer::EntityRenderer,
+    input_manager::{InputManager, MovementRecord},
     meshing::meshing_manager::{self, MeshingManager},
-    pipeline::{RegularPipeline, WireframePipeline},
-    resource_manager::BLOCK_ATLAS,
+    pipeline::{OutlinePipeline, RegularPipeline, WireframePipeline},
+    selection_renderer::SelectionRenderer,
     shaders::WgslShader,
     texture::{BindableTexture, TextureWithView},
     uniform::{ToBytes, Uniform},
 };
 
+/// Ticks run in one frame at most. After a longer stall the player skips ahead instead.
+const MAX_TICKS_PER_FRAME: u32 = 5;
+
 #[derive(Pod, Zeroable, Clone, Copy)]
 #[repr(C)]
 pub struct RawMat4(pub [[f32; 4]; 4]);
@@ -32,6 +42,7 @@ pub struct WorldRenderer {
     pub debug_mode: bool,
     pub depth_texture: TextureWithView,
 
+    atlas: Arc<Atlas>,
     diffuse_texture: BindableTexture,
     game: BlockworldClient,
 
@@ -39,18 +50,28 @@ pub struct WorldRenderer {
     matrix_uniform: Uniform<RawMat4>,
 
     meshing_manager: MeshingManager,
-    wireframe_pipeline: WireframePipeline,
+    entity_renderer: EntityRenderer,
+    outline_pipeline: OutlinePipeline,
+    selection_renderer: SelectionRenderer,
+    /// Needs `POLYGON_MODE_LINE`, which software adapters may not have.
+    wireframe_pipeline: Option<WireframePipeline>,
+    /// When our player's next tick is due.
+    clock: TickClock,
 }
 
 impl WorldRenderer {
+    /// `format` and `size` are those of the texture frames are drawn to, the window surface or
+    /// an offscreen target.
     pub fn new(
         device: &Device,
-        config: &wgpu::SurfaceConfiguration,
         queue: &Queue,
-        size: winit::dpi::PhysicalSize<u32>,
+        format: TextureFormat,
+        size: UVec2,
+        atlas: Arc<Atlas>,
+        game: BlockworldClient,
     ) -> Self {
         // Camera thingy
-        let camera = Camera::new(size.width as f32 / size.height as f32);
+        let camera = Camera::new(size.x as f32 / size.y as f32);
 
         let mut matrix_uniform = Uniform::new(
             &device,
@@ -63,11 +84,11 @@ impl WorldRenderer {
         let diffuse_texture = BindableTexture::new(
             &device,
             &queue,
-            &image::DynamicImage::ImageRgba8(BLOCK_ATLAS.get_image().clone()),
+            &image::DynamicImage::ImageRgba8(atlas.get_image().clone()),
             Some("Diffuse Texture"),
         );
 
-        let depth_texture = TextureWithView::new_depth(&device, &config);
+        let depth_texture = TextureWithView::new_depth(&device, size.x, size.y);
 
         let shader = WgslShader::new(
             &"minecraft:assets/shaders/default_shader.wgsl".into(),
@@ -91,59 +112,155 @@ impl WorldRenderer {
             &device,
             &[&diffuse_texture.bind_group_layout, &matrix_uniform.layout],
             &shader,
-            &config,
+            format,
         );
 
-        let wireframe_pipeline = WireframePipeline::new(
-            &device,
+        let wireframe_pipeline = device
+            .features()
+            .contains(Features::POLYGON_MODE_LINE)
+            .then(|| {
+                WireframePipeline::new(
+                    device,
+                    &[&diffuse_texture.bind_group_layout, &matrix_uniform.layout],
+                    &wireframe_shader,
+                    format,
+                )
+            });
+
+        let outline_pipeline = OutlinePipeline::new(
+            device,
             &[&diffuse_texture.bind_group_layout, &matrix_uniform.layout],
             &wireframe_shader,
-            &config,
+            format,
         );
 
-        let game = BlockworldClient::new();
         let meshing_manager = MeshingManager::new();
 
         Self {
             debug_mode: false,
             main_pipeline,
             wireframe_pipeline,
+            outline_pipeline,
+            atlas,
             diffuse_texture,
             depth_texture,
             camera,
             matrix_uniform,
             game,
             meshing_manager,
+            entity_renderer: EntityRenderer::new(),
+            selection_renderer: SelectionRenderer::new(),
+            clock: TickClock::new(Instant::now(), MAX_TICKS_PER_FRAME),
         }
     }
 
-    pub fn update(&mut self, queue: &Queue, input: &InputManager) {
-        // Move the camera based on user input
-        self.camera.update(input);
+    pub fn update(&mut self, device: &Device, queue: &Queue, input: &mut InputManager) {
+        self.game.handle_packets();
+        if let Some(pose) = self.game.take_teleport() {
+            self.camera.yaw = pose.yaw;
+            self.camera.pitch = pose.pitch;
+        }
+
+        // the player moves in ticks, the camera follows smoothly between them
+        let now = Instant::now();
+        for _ in 0..self.clock.due(now).run {
+            let moves = move_input(&input.to_key_record(), &self.camera, self.game.flying());
+            self.game.tick(moves);
+            let target = self.game.pick(self.camera.position, self.camera.get_gaze());
+            self.game.interact(
+                target,
+                InteractInput {
+                    attack: input.is_button_pressing(MouseButton::Left),
+                    use_held: input.is_button_pressing(MouseButton::Right),
+                    use_pressed: input.take_pressed(MouseButton::Right),
+                },
+            );
+        }
+        let partial = self.clock.partial(now);
+        if let Some(eye) = self.game.eye_position(partial) {
+            self.camera.position = eye;
+        }
+        self.upload_camera(queue);
+
+        self.meshing_manager
+            .update(device, self.game.chunks(), &self.atlas);
+        self.game.chunks_mut().clear_need_rerender();
+        self.entity_renderer
+            .update(device, self.game.entities(), &self.atlas, Instant::now());
+        let target = self.game.pick(self.camera.position, self.camera.get_gaze());
+        self.selection_renderer.update(
+            device,
+            self.game.chunks(),
+            target,
+            self.game.interaction().destroy_stage(),
+            &self.atlas,
+        );
+    }
 
-        // Update the uniform buffer with the new camera matrix
+    pub fn game(&self) -> &BlockworldClient {
+        &self.game
+    }
+
+    pub fn game_mut(&mut self) -> &mut BlockworldClient {
+        &mut self.game
+    }
+
+    /// Update the uniform buffer with the current camera matrix
+    pub fn upload_camera(&mut self, queue: &Queue) {
         self.matrix_uniform
             .update(queue, self.camera.build_mvp().into());
     }
 
-    pub fn resize(
-        &mut self,
-        _queue: &Queue,
-        device: &Device,
-        config: &SurfaceConfiguration,
-        new_aspect_ratio: f32,
-    ) {
-        self.depth_texture = TextureWithView::new_depth(device, config);
-        self.camera.update_aspect_ratio(new_aspect_ratio);
+    /// Mesh every chunk of the game again.
+    #[cfg(test)]
+    pub fn rebuild_meshes(&mut self, device: &Device) {
+        self.meshing_manager
+            .rebuild(device, self.game.chunks(), &self.atlas);
+    }
+
+    pub fn resize(&mut self, device: &Device, size: UVec2) {
+        self.depth_texture = TextureWithView::new_depth(device, size.x, size.y);
+        self.camera
+            .update_aspect_ratio(size.x as f32 / size.y as f32);
+    }
+
+    /// Record a pass drawing the world over the sky into `target`.
+    pub fn encode_frame(&self, encoder: &mut CommandEncoder, target: &TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Blockworld Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        // #82a9f9 minecraft's sky blue
+                        r: 0.509804,
+                        g: 0.662745,
+                        b: 0.976471,
+                        a: 1.0,
+                    }),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        });
+        self.render(&mut render_pass);
     }
 
     pub fn render<'rpass>(&'rpass self, rpass: &mut RenderPass<'rpass>) {
-        if self.debug_mode {
+        match &self.wireframe_pipeline {
             // render with wireframe
-            rpass.set_pipeline(&self.wireframe_pipeline.pipeline);
-        } else {
+            Some(wireframe) if self.debug_mode => rpass.set_pipeline(&wireframe.pipeline),
             // render with texture
-            rpass.set_pipeline(&self.main_pipeline.pipeline);
+            _ => rpass.set_pipeline(&self.main_pipeline.pipeline),
         }
 
         rpass.set_bind_group(0, &self.diffuse_texture.bind_group, &[]);
@@ -151,6 +268,27 @@ impl WorldRenderer {
 
         {
             self.meshing_manager.render(rpass);
+            self.entity_renderer.render(rpass);
+            self.selection_renderer.render_cracks(rpass);
         }
+
+        rpass.set_pipeline(&self.outline_pipeline.pipeline);
+        self.selection_renderer.render_outline(rpass);
+    }
+}
+
+/// The keys held this tick, pushing the way the camera looks.
+fn move_input(keys: &MovementRecord, camera: &Camera, flying: bool) -> MoveInput {
+    MoveInput {
+        forward: keys.forward as i8 - keys.backward as i8,
+        strafe: keys.right as i8 - keys.left as i8,
+        jump: keys.ascend,
+        sneak: keys.descend,
+        sprint: keys.sprint,
+        flying,
+        // the game mode decides
+        noclip: false,
+        yaw: camera.yaw,
+        pitch: camera.pitch,
     }
 }
@@ -0,0 +1,57 @@
+//! Pixel diffs between two renders, for reviewing generation changes in CI.
+
+use anyhow::*;
+use image::{Rgba, RgbaImage};
+
+/// Differing pixels are painted this colour, the rest is a faded copy of the old image.
+const CHANGED: Rgba<u8> = Rgba([255, 0, 255, 255]);
+
+pub struct ImageDiff {
+    pub changed: usize,
+    pub image: RgbaImage,
+}
+
+/// Compare two images of the same size. A pixel counts as changed if any channel differs by
+/// more than `tolerance`.
+pub fn diff_images(old: &RgbaImage, new: &RgbaImage, tolerance: u8) -> Result<ImageDiff> {
+    if old.dimensions() != new.dimensions() {
+        bail!(
+            "images differ in size, {:?} and {:?}",
+            old.dimensions(),
+            new.dimensions()
+        );
+    }
+    let mut changed = 0;
+    let mut image = RgbaImage::new(old.width(), old.height());
+    for (x, y, out) in image.enumerate_pixels_mut() {
+        let (a, b) = (old.get_pixel(x, y), new.get_pixel(x, y));
+        let differs = a.0.iter().zip(b.0).any(|(a, b)| a.abs_diff(b) > tolerance);
+        *out = if differs {
+            changed += 1;
+            CHANGED
+        } else {
+            let Rgba([r, g, b, alpha]) = *a;
+            Rgba([r, g, b, alpha / 4])
+        };
+    }
+    Ok(ImageDiff { changed, image })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_pixels_past_tolerance() {
+        let old = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+        let mut new = old.clone();
+        new.put_pixel(0, 0, Rgba([102, 100, 100, 255]));
+        new.put_pixel(1, 0, Rgba([100, 100, 110, 255]));
+
+        assert_eq!(diff_images(&old, &new, 0).unwrap().changed, 2);
+        let diff = diff_images(&old, &new, 2).unwrap();
+        assert_eq!(diff.changed, 1);
+        assert_eq!(*diff.image.get_pixel(1, 0), CHANGED);
+        assert!(diff_images(&old, &RgbaImage::new(2, 2), 0).is_err());
+    }
+}
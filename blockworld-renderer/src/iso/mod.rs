@@ -0,0 +1,300 @@
+//! Isometric renders of a box of a saved world, on the CPU.
+//!
+//! We look down from the south east, so only the top, east (X+) and south (Z+) faces of a block
+//! can be seen. Blocks are painted back to front, ordered by `x + y + z`, which is enough for
+//! unit cubes on a grid, and a face is skipped when the block in front of it is opaque.
+//!
+//! Faces are textured from the same block atlas the client uses. Blocks without a texture fall
+//! back to their map colour, so renders still work without any assets, which CI relies on.
+//! The output only depends on the world and the atlas, two runs give identical PNGs.
+
+use std::path::Path;
+
+use anyhow::*;
+use blockworld_server::{
+    block::{map_color::MapColor, BLOCK_REGISTRY},
+    world::storage::WorldStorage,
+};
+use blockworld_utils::{atlas_image::Atlas, ResourceLocation};
+use glam::*;
+use image::{Rgba, RgbaImage};
+
+pub mod raster;
+pub mod volume;
+
+use raster::{draw_face, Texture};
+use volume::BlockVolume;
+
+/// Brightness of each visible face out of 255, the same as Minecraft's flat shading.
+const TOP_SHADE: u32 = 255;
+const SOUTH_SHADE: u32 = 204;
+const EAST_SHADE: u32 = 153;
+
+/// Biome colours of the plains, for the greyscale textures the game tints.
+const GRASS_TINT: [u8; 3] = [0x91, 0xbd, 0x59];
+const FOLIAGE_TINT: [u8; 3] = [0x77, 0xab, 0x2f];
+const WATER_TINT: [u8; 3] = [0x3f, 0x76, 0xe4];
+const NO_TINT: [u8; 3] = [255, 255, 255];
+
+/// Alpha of water drawn from its map colour.
+const FLAT_WATER_ALPHA: u8 = 160;
+
+fn tint_of(sprite: &str) -> [u8; 3] {
+    match sprite {
+        "grass_block_top" | "short_grass" | "grass" | "fern" | "tall_grass_top" => GRASS_TINT,
+        s if s.ends_with("_leaves") || s == "vine" => FOLIAGE_TINT,
+        s if s.starts_with("water_") => WATER_TINT,
+        _ => NO_TINT,
+    }
+}
+
+/// How one kind of block looks.
+struct BlockLook {
+    top: Texture,
+    side: Texture,
+    opaque: bool,
+}
+
+impl BlockLook {
+    fn new(name: &str, atlas: Option<&Atlas>) -> Self {
+        let id = ResourceLocation::new(name);
+        let (namespace, path) = (id.get_namespace(), id.get_path());
+        let find = |suffix: &str| {
+            let atlas = atlas?;
+            // `grass_block_top`, then `grass_block`, then `water_still` for fluids
+            [
+                format!("{}_{}", path, suffix),
+                path.clone(),
+                format!("{}_still", path),
+            ]
+            .into_iter()
+            .find_map(|sprite| {
+                let found = atlas
+                    .query_sprite(&ResourceLocation::new(&format!("{}:{}", namespace, sprite)))?;
+                Some(Texture::from_sprite(&*found, tint_of(&sprite)))
+            })
+        };
+
+        let flat = || {
+            let color = BLOCK_REGISTRY
+                .get(&id)
+                .map(|b| b.map_color)
+                .filter(|c| !c.is_none())
+                .unwrap_or(MapColor::STONE);
+            let alpha = if color == MapColor::WATER {
+                FLAT_WATER_ALPHA
+            } else {
+                255
+            };
+            let [r, g, b] = color.rgb();
+            Texture::Flat(Rgba([r, g, b, alpha]))
+        };
+
+        let top = find("top").unwrap_or_else(flat);
+        let side = find("side").unwrap_or_else(flat);
+        let opaque = top.is_opaque() && side.is_opaque();
+        Self { top, side, opaque }
+    }
+}
+
+pub struct IsoRenderer<'a> {
+    atlas: Option<&'a Atlas>,
+    /// Screen pixels per block along the x axis of the image, a cube is twice that wide.
+    scale: u32,
+}
+
+impl<'a> IsoRenderer<'a> {
+    pub fn new(atlas: Option<&'a Atlas>, scale: u32) -> Self {
+        Self {
+            atlas,
+            scale: scale.max(1),
+        }
+    }
+
+    /// Where world point `p` lands on screen, before moving the image origin.
+    fn project(&self, p: Vec3) -> Vec2 {
+        let s = self.scale as f32;
+        vec2((p.x - p.z) * s, (p.x + p.z) * s / 2.0 - p.y * s)
+    }
+
+    /// Render the blocks between `min` and `max`, both inclusive.
+    pub fn render_world(
+        &self,
+        storage: &mut WorldStorage,
+        min: IVec3,
+        max: IVec3,
+    ) -> Result<RgbaImage> {
+        let (min, max) = (min.min(max), min.max(max));
+        let volume = BlockVolume::load(storage, min, max)?;
+        Ok(self.render(&volume))
+    }
+
+    pub fn render(&self, volume: &BlockVolume) -> RgbaImage {
+        let looks: Vec<_> = volume
+            .names
+            .iter()
+            .map(|name| BlockLook::new(name, self.atlas))
+            .collect();
+
+        // fit the eight corners of the box
+        let (lo, hi) = (volume.min.as_vec3(), (volume.max() + IVec3::ONE).as_vec3());
+        let mut top_left = Vec2::MAX;
+        let mut bottom_right = Vec2::MIN;
+        for i in 0..8 {
+            let corner = vec3(
+                if i & 1 == 0 { lo.x } else { hi.x },
+                if i & 2 == 0 { lo.y } else { hi.y },
+                if i & 4 == 0 { lo.z } else { hi.z },
+            );
+            let p = self.project(corner);
+            top_left = top_left.min(p);
+            bottom_right = bottom_right.max(p);
+        }
+        let size = (bottom_right - top_left).ceil().as_uvec2();
+        let mut image = RgbaImage::new(size.x.max(1), size.y.max(1));
+
+        let s = self.scale as f32;
+        let (x_axis, y_axis, z_axis) = (vec2(s, s / 2.0), vec2(0.0, -s), vec2(-s, s / 2.0));
+        let visible = |id: u16, neighbour: u16| {
+            let look = &looks[neighbour as usize];
+            neighbour == 0 || (!look.opaque && neighbour != id)
+        };
+
+        let size = volume.size;
+        for depth in 0..size.x + size.y + size.z - 2 {
+            for x in 0..size.x {
+                for y in 0..size.y {
+                    let z = depth - x - y;
+                    if !(0..size.z).contains(&z) {
+                        continue;
+                    }
+                    let pos = volume.min + ivec3(x, y, z);
+                    let id = volume.get(pos);
+                    if id == 0 {
+                        continue;
+                    }
+                    let look = &looks[id as usize];
+                    let corner =
+                        |dx, dy, dz| self.project((pos + ivec3(dx, dy, dz)).as_vec3()) - top_left;
+
+                    if visible(id, volume.get(pos + IVec3::Y)) {
+                        draw_face(
+                            &mut image,
+                            corner(0, 1, 0),
+                            x_axis,
+                            z_axis,
+                            &look.top,
+                            TOP_SHADE,
+                        );
+                    }
+                    // side textures run left to right as seen from outside, top to bottom
+                    if visible(id, volume.get(pos + IVec3::X)) {
+                        draw_face(
+                            &mut image,
+                            corner(1, 1, 1),
+                            -z_axis,
+                            -y_axis,
+                            &look.side,
+                            EAST_SHADE,
+                        );
+                    }
+                    if visible(id, volume.get(pos + IVec3::Z)) {
+                        draw_face(
+                            &mut image,
+                            corner(0, 1, 1),
+                            x_axis,
+                            -y_axis,
+                            &look.side,
+                            SOUTH_SHADE,
+                        );
+                    }
+                }
+            }
+        }
+        image
+    }
+}
+
+/// Load the block atlas, or none if there are no textures at `path`.
+pub fn load_atlas(path: &Path) -> Option<Atlas> {
+    if path.is_dir() {
+        Some(Atlas::new(path))
+    } else {
+        log::warn!(
+            "No block textures at {}, drawing map colours instead",
+            path.display()
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blockworld_utils::TempDir;
+
+    use super::*;
+
+    fn single_block(name: &str) -> BlockVolume {
+        let mut volume = BlockVolume::empty(IVec3::ZERO, IVec3::ZERO).unwrap();
+        let id = volume.intern(name);
+        volume.set(IVec3::ZERO, id);
+        volume
+    }
+
+    #[test]
+    fn cube_faces_are_shaded() {
+        let renderer = IsoRenderer::new(None, 8);
+        let image = renderer.render(&single_block("minecraft:stone"));
+        assert_eq!(image.dimensions(), (16, 16));
+
+        let [r, ..] = MapColor::STONE.rgb();
+        let shaded = |shade: u32| (r as u32 * shade / 255) as u8;
+        // top diamond, left (south) and right (east) sides
+        assert_eq!(image.get_pixel(8, 4).0[0], shaded(TOP_SHADE));
+        assert_eq!(image.get_pixel(3, 10).0[0], shaded(SOUTH_SHADE));
+        assert_eq!(image.get_pixel(12, 10).0[0], shaded(EAST_SHADE));
+        // corners outside the hexagon
+        assert_eq!(image.get_pixel(0, 0).0[3], 0);
+        assert_eq!(image.get_pixel(15, 15).0[3], 0);
+    }
+
+    #[test]
+    fn faces_between_water_are_culled() {
+        let mut volume = BlockVolume::empty(IVec3::ZERO, ivec3(1, 0, 0)).unwrap();
+        let water = volume.intern("minecraft:water");
+        volume.set(IVec3::ZERO, water);
+        volume.set(IVec3::X, water);
+        let renderer = IsoRenderer::new(None, 8);
+        let image = renderer.render(&volume);
+
+        // behind the south face of the second block is the east face of the first, which
+        // would make it more opaque if it was drawn
+        assert_eq!(image.get_pixel(10, 12).0[3], FLAT_WATER_ALPHA);
+    }
+
+    #[test]
+    fn textures_come_from_the_atlas() {
+        let dir = TempDir::new("iso").unwrap();
+        RgbaImage::from_pixel(16, 16, Rgba([10, 20, 30, 255]))
+            .save(dir.join("stone.png"))
+            .unwrap();
+        RgbaImage::from_pixel(16, 16, Rgba([200, 200, 200, 255]))
+            .save(dir.join("grass_block_top.png"))
+            .unwrap();
+        let atlas = load_atlas(&dir).unwrap();
+
+        let renderer = IsoRenderer::new(Some(&atlas), 8);
+        let stone = renderer.render(&single_block("minecraft:stone"));
+        assert_eq!(*stone.get_pixel(8, 4), Rgba([10, 20, 30, 255]));
+
+        // tinted top, map coloured sides since there is no grass_block_side
+        let grass = renderer.render(&single_block("minecraft:grass_block"));
+        let top = grass.get_pixel(8, 4);
+        assert_eq!(top.0[0], (200 * GRASS_TINT[0] as u32 / 255) as u8);
+        let [r, ..] = MapColor::GRASS.rgb();
+        assert_eq!(
+            grass.get_pixel(3, 10).0[0],
+            (r as u32 * SOUTH_SHADE / 255) as u8
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Texture mapped parallelograms, which is all an isometric cube face is.
+
+use glam::*;
+use image::{GenericImageView, Rgba, RgbaImage};
+
+/// The pixels a face is painted with, already tinted.
+#[derive(Clone)]
+pub enum Texture {
+    Sprite { size: u32, pixels: Vec<Rgba<u8>> },
+    Flat(Rgba<u8>),
+}
+
+impl Texture {
+    pub fn from_sprite<I: GenericImageView<Pixel = Rgba<u8>>>(sprite: &I, tint: [u8; 3]) -> Self {
+        let size = sprite.width();
+        let pixels = sprite
+            .pixels()
+            .map(|(_, _, Rgba([r, g, b, a]))| {
+                Rgba([
+                    mul(r, tint[0] as u32),
+                    mul(g, tint[1] as u32),
+                    mul(b, tint[2] as u32),
+                    a,
+                ])
+            })
+            .collect();
+        Texture::Sprite { size, pixels }
+    }
+
+    /// Whether nothing behind it can be seen.
+    pub fn is_opaque(&self) -> bool {
+        match self {
+            Texture::Sprite { pixels, .. } => pixels.iter().all(|p| p.0[3] == 255),
+            Texture::Flat(p) => p.0[3] == 255,
+        }
+    }
+
+    /// `u` and `v` in `0..1`.
+    fn sample(&self, u: f32, v: f32) -> Rgba<u8> {
+        match self {
+            Texture::Sprite { size, pixels } => {
+                let x = ((u * *size as f32) as u32).min(size - 1);
+                let y = ((v * *size as f32) as u32).min(size - 1);
+                pixels[(x + y * size) as usize]
+            }
+            Texture::Flat(p) => *p,
+        }
+    }
+}
+
+fn mul(v: u8, by: u32) -> u8 {
+    (v as u32 * by / 255) as u8
+}
+
+/// Paint the parallelogram `origin + u * a + v * b` for `u, v` in `0..1`, with texel `(u, v)`
+/// at brightness `shade` out of 255, blended over what's already there.
+///
+/// A pixel belongs to the face if its centre does, so two faces sharing an edge neither both
+/// paint the pixels on it nor leave a gap.
+pub fn draw_face(
+    image: &mut RgbaImage,
+    origin: Vec2,
+    a: Vec2,
+    b: Vec2,
+    texture: &Texture,
+    shade: u32,
+) {
+    let det = a.perp_dot(b);
+    if det.abs() < f32::EPSILON {
+        return;
+    }
+    let corners = [origin, origin + a, origin + b, origin + a + b];
+    let lo = corners.iter().fold(Vec2::MAX, |m, c| m.min(*c)).floor();
+    let hi = corners.iter().fold(Vec2::MIN, |m, c| m.max(*c)).ceil();
+    let x0 = lo.x.max(0.0) as u32;
+    let y0 = lo.y.max(0.0) as u32;
+    let x1 = (hi.x.max(0.0) as u32).min(image.width());
+    let y1 = (hi.y.max(0.0) as u32).min(image.height());
+
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let d = vec2(x as f32 + 0.5, y as f32 + 0.5) - origin;
+            let u = d.perp_dot(b) / det;
+            let v = a.perp_dot(d) / det;
+            if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+                continue;
+            }
+            let Rgba([r, g, b, alpha]) = texture.sample(u, v);
+            if alpha == 0 {
+                continue;
+            }
+            let src = Rgba([mul(r, shade), mul(g, shade), mul(b, shade), alpha]);
+            blend(image.get_pixel_mut(x, y), src);
+        }
+    }
+}
+
+/// Source over, on straight alpha.
+fn blend(dst: &mut Rgba<u8>, src: Rgba<u8>) {
+    let sa = src.0[3] as u32;
+    if sa == 255 {
+        *dst = src;
+        return;
+    }
+    let da = dst.0[3] as u32 * (255 - sa) / 255;
+    let out_a = sa + da;
+    if out_a == 0 {
+        return;
+    }
+    for i in 0..3 {
+        dst.0[i] = ((src.0[i] as u32 * sa + dst.0[i] as u32 * da) / out_a) as u8;
+    }
+    dst.0[3] = out_a as u8;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_edges_paint_each_pixel_once() {
+        let mut image = RgbaImage::new(8, 8);
+        let half = Texture::Flat(Rgba([255, 255, 255, 128]));
+        draw_face(
+            &mut image,
+            Vec2::ZERO,
+            vec2(4.0, 0.0),
+            vec2(0.0, 8.0),
+            &half,
+            255,
+        );
+        draw_face(
+            &mut image,
+            vec2(4.0, 0.0),
+            vec2(4.0, 0.0),
+            vec2(0.0, 8.0),
+            &half,
+            255,
+        );
+        assert!(image.pixels().all(|p| p.0[3] == 128));
+    }
+
+    #[test]
+    fn skewed_face_samples_texture() {
+        let mut pixels = vec![Rgba([255, 0, 0, 255]); 4];
+        pixels[1] = Rgba([0, 0, 255, 255]);
+        let texture = Texture::Sprite { size: 2, pixels };
+        let mut image = RgbaImage::new(16, 16);
+        // the top face of a cube: u to the lower right, v to the lower left
+        draw_face(
+            &mut image,
+            vec2(8.0, 0.0),
+            vec2(8.0, 4.0),
+            vec2(-8.0, 4.0),
+            &texture,
+            255,
+        );
+        // around u = 0.8, v = 0.3, the blue texel
+        assert_eq!(*image.get_pixel(12, 4), Rgba([0, 0, 255, 255]));
+        assert_eq!(*image.get_pixel(4, 4), Rgba([255, 0, 0, 255]));
+        assert_eq!(image.get_pixel(0, 0).0[3], 0);
+    }
+}
@@ -0,0 +1,106 @@
+//! The blocks inside the bounding box of a render, read straight from the region files.
+
+use anyhow::*;
+use blockworld_server::world::{chunk::SUBCHUNK_SIZE, storage::WorldStorage};
+use glam::*;
+
+/// A dense box of blocks. Names are interned, index 0 is always air, so is everything outside
+/// the box.
+pub struct BlockVolume {
+    pub min: IVec3,
+    pub size: IVec3,
+    pub names: Vec<String>,
+    blocks: Vec<u16>,
+}
+
+impl BlockVolume {
+    pub fn empty(min: IVec3, max: IVec3) -> Result<Self> {
+        let size = max - min + IVec3::ONE;
+        if size.cmple(IVec3::ZERO).any() {
+            bail!("empty bounding box {} .. {}", min, max);
+        }
+        let len = size.x as usize * size.y as usize * size.z as usize;
+        Ok(Self {
+            min,
+            size,
+            names: vec!["minecraft:air".to_string()],
+            blocks: vec![0; len],
+        })
+    }
+
+    /// Read every block between `min` and `max`, both inclusive, in world coords.
+    pub fn load(storage: &mut WorldStorage, min: IVec3, max: IVec3) -> Result<Self> {
+        let mut volume = Self::empty(min, max)?;
+        let s = SUBCHUNK_SIZE as i32;
+
+        for cx in min.x.div_euclid(s)..=max.x.div_euclid(s) {
+            for cz in min.z.div_euclid(s)..=max.z.div_euclid(s) {
+                let Some(chunk) = storage.read_chunk(ivec2(cx, cz))? else {
+                    continue;
+                };
+                for section in &chunk.sections {
+                    let base = ivec3(cx, section.y, cz) * s;
+                    if base.y + s <= min.y || base.y > max.y {
+                        continue;
+                    }
+                    let ids: Vec<u16> = section
+                        .palette
+                        .iter()
+                        .map(|p| {
+                            if p.is_air() {
+                                0
+                            } else {
+                                volume.intern(&p.name)
+                            }
+                        })
+                        .collect();
+                    for (i, index) in section.unpack()?.into_iter().enumerate() {
+                        let local = ivec3(
+                            (i % SUBCHUNK_SIZE) as i32,
+                            (i / (SUBCHUNK_SIZE * SUBCHUNK_SIZE)) as i32,
+                            (i / SUBCHUNK_SIZE % SUBCHUNK_SIZE) as i32,
+                        );
+                        volume.set(base + local, ids[index as usize]);
+                    }
+                }
+            }
+        }
+        Ok(volume)
+    }
+
+    pub fn intern(&mut self, name: &str) -> u16 {
+        match self.names.iter().position(|n| n == name) {
+            Some(i) => i as u16,
+            None => {
+                self.names.push(name.to_string());
+                (self.names.len() - 1) as u16
+            }
+        }
+    }
+
+    fn index(&self, pos: IVec3) -> Option<usize> {
+        let p = pos - self.min;
+        if p.cmplt(IVec3::ZERO).any() || p.cmpge(self.size).any() {
+            return None;
+        }
+        Some(
+            p.x as usize
+                + (p.z as usize + p.y as usize * self.size.z as usize) * self.size.x as usize,
+        )
+    }
+
+    pub fn get(&self, pos: IVec3) -> u16 {
+        self.index(pos).map(|i| self.blocks[i]).unwrap_or(0)
+    }
+
+    /// Positions outside the box are ignored.
+    pub fn set(&mut self, pos: IVec3, id: u16) {
+        if let Some(i) = self.index(pos) {
+            self.blocks[i] = id;
+        }
+    }
+
+    pub fn max(&self) -> IVec3 {
+        self.min + self.size - IVec3::ONE
+    }
+}
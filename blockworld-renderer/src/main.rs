@@ -1,3 +1,231 @@
-fn main() {
-    println!("Hello, world!");
+//! Offline, CPU only renders of saved worlds.
+//!
+//! ```text
+//! blockworld-renderer map <world> <out> [--zoom-levels <n>] [--force]
+//! blockworld-renderer iso <world> <out.png> --from <x y z> --to <x y z> [--scale <px>] [--assets <dir>]
+//! blockworld-renderer diff <old.png> <new.png> [--out <diff.png>] [--tolerance <n>]
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::*;
+use blockworld_server::world::storage::WorldStorage;
+use clap::{Parser, Subcommand};
+use glam::*;
+use iso::IsoRenderer;
+use map::MapRenderer;
+
+mod diff;
+mod iso;
+mod map;
+
+#[derive(Parser)]
+#[command(
+    name = "blockworld-renderer",
+    about = "Render saved Blockworld worlds to images"
+)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Top-down map as zoomable PNG tiles, redrawing only chunks changed since the last run
+    Map {
+        /// The world folder, the one holding level.json
+        world: PathBuf,
+        /// Where the tiles go
+        out: PathBuf,
+        /// How many zoomed out levels to build above the full detail one
+        #[arg(long, default_value_t = 4)]
+        zoom_levels: u32,
+        /// Redraw every chunk, even unchanged ones
+        #[arg(long)]
+        force: bool,
+    },
+    /// Isometric render of a box of the world as one PNG
+    Iso {
+        /// The world folder, the one holding level.json
+        world: PathBuf,
+        /// The PNG to write
+        out: PathBuf,
+        /// One corner of the box, inclusive
+        #[arg(
+            long,
+            num_args = 3,
+            value_names = ["X", "Y", "Z"],
+            required = true,
+            allow_negative_numbers = true
+        )]
+        from: Vec<i32>,
+        /// The opposite corner, inclusive
+        #[arg(
+            long,
+            num_args = 3,
+            value_names = ["X", "Y", "Z"],
+            required = true,
+            allow_negative_numbers = true
+        )]
+        to: Vec<i32>,
+        /// Pixels per block, a cube is twice as wide
+        #[arg(long, default_value_t = 8)]
+        scale: u32,
+        /// Block textures, map colours are drawn if the folder doesn't exist
+        #[arg(long, default_value = "assets/minecraft/textures/block/")]
+        assets: PathBuf,
+    },
+    /// Highlight the pixels two renders differ in, exits with 1 if any do
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+        /// Where to write the highlighted image
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Largest per channel difference still counted as equal
+        #[arg(long, default_value_t = 0)]
+        tolerance: u8,
+    },
+}
+
+/// Open a saved world to render. Only read, so nothing is left in a folder that isn't a world
+/// or in the world of a running server.
+fn open_world(path: &Path) -> Result<WorldStorage> {
+    let storage = WorldStorage::open_read_only(path)
+        .with_context(|| format!("{} is not a world folder", path.display()))?;
+    if storage.read_level()?.is_none() {
+        bail!("{} is not a world folder", path.display());
+    }
+    Ok(storage)
+}
+
+fn main() -> Result<ExitCode> {
+    env_logger::init();
+    let args = Args::parse();
+
+    match args.command {
+        Command::Map {
+            world,
+            out,
+            zoom_levels,
+            force,
+        } => {
+            let mut storage = open_world(&world)?;
+            let stats = MapRenderer::new(&out, zoom_levels).render(&mut storage, force)?;
+            println!(
+                "drew {} chunks, wrote {} tiles to {}",
+                stats.chunks_drawn,
+                stats.tiles_written,
+                out.display()
+            );
+        }
+        Command::Iso {
+            world,
+            out,
+            from,
+            to,
+            scale,
+            assets,
+        } => {
+            let mut storage = open_world(&world)?;
+            let atlas = iso::load_atlas(&assets);
+            let image = IsoRenderer::new(atlas.as_ref(), scale).render_world(
+                &mut storage,
+                IVec3::from_slice(&from),
+                IVec3::from_slice(&to),
+            )?;
+            image
+                .save(&out)
+                .with_context(|| format!("Failed to write {}", out.display()))?;
+            println!(
+                "wrote {}x{} render to {}",
+                image.width(),
+                image.height(),
+                out.display()
+            );
+        }
+        Command::Diff {
+            old,
+            new,
+            out,
+            tolerance,
+        } => {
+            let open = |path: &PathBuf| -> Result<_> {
+                Ok(image::open(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?
+                    .to_rgba8())
+            };
+            let diff = diff::diff_images(&open(&old)?, &open(&new)?, tolerance)?;
+            if let Some(out) = out {
+                diff.image.save(&out)?;
+            }
+            println!("{} pixels differ", diff.changed);
+            if diff.changed > 0 {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use blockworld_server::world::{
+        chunk::SubChunk,
+        storage::{chunk_serializer::ChunkData, LevelData},
+    };
+    use blockworld_utils::TempDir;
+
+    use super::*;
+
+    /// Every file and folder under `root`, sorted.
+    fn listing(root: &Path) -> Vec<PathBuf> {
+        let mut found = vec![];
+        for entry in fs::read_dir(root).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                found.extend(listing(&path));
+            }
+            found.push(path);
+        }
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn only_worlds_open_and_nothing_is_created() {
+        let dir = TempDir::new("renderer-not-a-world").unwrap();
+        let missing = dir.join("missing");
+        assert!(open_world(&missing).is_err());
+        assert!(!missing.exists());
+        assert!(open_world(&dir).is_err());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+    }
+    #[test]
+    fn iso_snapshots_leave_the_world_alone() {
+        let dir = TempDir::new("renderer-iso-world").unwrap();
+        {
+            let mut storage = WorldStorage::open(&*dir).unwrap();
+            storage.write_level(&LevelData::default()).unwrap();
+            let mut sc = SubChunk::new(ivec3(0, 0, 0));
+            sc.set_blockid(ivec3(1, 1, 1), "minecraft:stone");
+            let mut chunk = ChunkData::new(IVec2::ZERO);
+            chunk.put_section(&sc);
+            storage.write_chunk(&chunk).unwrap();
+        }
+        let before = listing(&dir);
+
+        // regions -1 and 1 on x and -1 on z were never saved
+        let mut storage = open_world(&dir).unwrap();
+        let image = IsoRenderer::new(None, 1)
+            .render_world(&mut storage, ivec3(-20, 0, -20), ivec3(530, 3, 4))
+            .unwrap();
+        assert!(image.pixels().any(|p| p.0[3] != 0));
+        assert_eq!(listing(&dir), before);
+    }
 }
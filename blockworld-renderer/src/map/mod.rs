@@ -0,0 +1,307 @@
+//! Offline top-down map of a saved world, cut into zoomable PNG tiles.
+//!
+//! ```text
+//! <out>/0/<region x>_<region z>.png   one pixel per block, one tile per region
+//! <out>/1/<x>_<z>.png                 2x2 tiles of level 0, scaled down
+//! <out>/<n>/...
+//! <out>/manifest.json                 checksums of the chunks drawn last run
+//! ```
+//!
+//! Only chunks whose checksum changed since the last run are drawn again, and only tiles
+//! containing them are rewritten.
+
+use std::{
+    collections::{hash_map::Entry, BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::*;
+use blockworld_server::world::{
+    chunk::SUBCHUNK_SIZE,
+    storage::{
+        region_file::{region_pos, REGION_SIZE},
+        WorldStorage,
+    },
+};
+use glam::*;
+use image::{imageops, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+pub mod surface;
+
+use surface::ChunkSurface;
+
+/// Pixels per tile side, the same at every zoom level.
+pub const TILE_SIZE: u32 = (REGION_SIZE as usize * SUBCHUNK_SIZE) as u32;
+const MANIFEST: &str = "manifest.json";
+
+/// What was drawn last run, keyed by `"x,z"` chunk coords.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    chunks: HashMap<String, u32>,
+}
+
+fn manifest_key(pos: IVec2) -> String {
+    format!("{},{}", pos.x, pos.y)
+}
+
+fn parse_manifest_key(key: &str) -> Option<IVec2> {
+    let (x, z) = key.split_once(',')?;
+    Some(ivec2(x.parse().ok()?, z.parse().ok()?))
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub chunks_drawn: usize,
+    pub tiles_written: usize,
+}
+
+pub struct MapRenderer {
+    out: PathBuf,
+    zoom_levels: u32,
+    /// Surfaces scanned this run, neighbours are needed for shading.
+    surfaces: HashMap<IVec2, Option<ChunkSurface>>,
+}
+
+impl MapRenderer {
+    pub fn new<Q: AsRef<Path>>(out: Q, zoom_levels: u32) -> Self {
+        Self {
+            out: out.as_ref().to_path_buf(),
+            zoom_levels,
+            surfaces: HashMap::new(),
+        }
+    }
+
+    fn tile_path(&self, level: u32, tile: IVec2) -> PathBuf {
+        self.out
+            .join(level.to_string())
+            .join(format!("{}_{}.png", tile.x, tile.y))
+    }
+
+    fn load_manifest(&self) -> Manifest {
+        fs::read(self.out.join(MANIFEST))
+            .ok()
+            .and_then(|b| serde_json::from_slice(&b).ok())
+            .unwrap_or_default()
+    }
+
+    /// Render every chunk changed since the last run. With `force` everything is redrawn.
+    pub fn render(&mut self, storage: &mut WorldStorage, force: bool) -> Result<RenderStats> {
+        let old = if force {
+            Manifest::default()
+        } else {
+            self.load_manifest()
+        };
+
+        let mut new = Manifest::default();
+        for pos in storage.list_chunks()? {
+            if let Some(checksum) = storage.chunk_checksum(pos)? {
+                new.chunks.insert(manifest_key(pos), checksum);
+            }
+        }
+
+        // changed, added and deleted chunks, plus the chunk south of each since its first row
+        // is shaded against the changed one
+        let mut dirty = BTreeSet::new();
+        for key in new.chunks.keys().chain(old.chunks.keys()) {
+            if new.chunks.get(key) != old.chunks.get(key) {
+                let pos = parse_manifest_key(key).ok_or_else(|| anyhow!("bad key {}", key))?;
+                dirty.insert((pos.x, pos.y));
+                if new.chunks.contains_key(&manifest_key(pos + IVec2::Y)) {
+                    dirty.insert((pos.x, pos.y + 1));
+                }
+            }
+        }
+
+        let mut by_tile: HashMap<IVec2, Vec<IVec2>> = HashMap::new();
+        for (x, z) in dirty {
+            let pos = ivec2(x, z);
+            by_tile.entry(region_pos(pos)).or_default().push(pos);
+        }
+
+        let mut stats = RenderStats::default();
+        let mut changed_tiles: BTreeSet<(i32, i32)> = BTreeSet::new();
+        for (tile, chunks) in by_tile {
+            let path = self.tile_path(0, tile);
+            let mut image = image::open(&path)
+                .map(|i| i.to_rgba8())
+                .unwrap_or_else(|_| RgbaImage::new(TILE_SIZE, TILE_SIZE));
+
+            for pos in chunks {
+                self.draw_chunk(storage, &mut image, pos)?;
+                stats.chunks_drawn += 1;
+            }
+            save_tile(&path, &image)?;
+            stats.tiles_written += 1;
+            changed_tiles.insert((tile.x, tile.y));
+        }
+
+        for level in 1..=self.zoom_levels {
+            let parents: BTreeSet<_> = changed_tiles
+                .iter()
+                .map(|(x, z)| (x.div_euclid(2), z.div_euclid(2)))
+                .collect();
+            for (x, z) in &parents {
+                self.compose_zoomed(level, ivec2(*x, *z))?;
+                stats.tiles_written += 1;
+            }
+            changed_tiles = parents;
+        }
+
+        fs::create_dir_all(&self.out)?;
+        fs::write(self.out.join(MANIFEST), serde_json::to_vec(&new)?)?;
+        self.surfaces.clear();
+        Ok(stats)
+    }
+
+    fn surface(&mut self, storage: &mut WorldStorage, pos: IVec2) -> Result<Option<&ChunkSurface>> {
+        if let Entry::Vacant(entry) = self.surfaces.entry(pos) {
+            let surface = match storage.read_chunk(pos) {
+                Result::Ok(Some(chunk)) => Some(ChunkSurface::scan(&chunk)?),
+                Result::Ok(None) => None,
+                Err(e) => {
+                    log::error!("Skipping unreadable chunk {}: {:#}", pos, e);
+                    None
+                }
+            };
+            entry.insert(surface);
+        }
+        Ok(self.surfaces[&pos].as_ref())
+    }
+
+    fn draw_chunk(
+        &mut self,
+        storage: &mut WorldStorage,
+        image: &mut RgbaImage,
+        pos: IVec2,
+    ) -> Result<()> {
+        self.surface(storage, pos - IVec2::Y)?;
+        self.surface(storage, pos)?;
+        let north = self.surfaces[&(pos - IVec2::Y)].as_ref();
+        let pixels = match &self.surfaces[&pos] {
+            Some(surface) => surface.shade(north),
+            // deleted, clear it
+            None => vec![Rgba([0, 0, 0, 0]); SUBCHUNK_SIZE * SUBCHUNK_SIZE],
+        };
+
+        let origin = (pos - region_pos(pos) * REGION_SIZE) * SUBCHUNK_SIZE as i32;
+        for (i, pixel) in pixels.into_iter().enumerate() {
+            let x = origin.x as u32 + (i % SUBCHUNK_SIZE) as u32;
+            let z = origin.y as u32 + (i / SUBCHUNK_SIZE) as u32;
+            image.put_pixel(x, z, pixel);
+        }
+        Ok(())
+    }
+
+    /// Rebuild a tile of `level` from the four tiles of the level below.
+    fn compose_zoomed(&self, level: u32, tile: IVec2) -> Result<()> {
+        let mut canvas = RgbaImage::new(TILE_SIZE * 2, TILE_SIZE * 2);
+        for (dx, dz) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let child = self.tile_path(level - 1, tile * 2 + ivec2(dx, dz));
+            if let Result::Ok(img) = image::open(&child) {
+                imageops::replace(
+                    &mut canvas,
+                    &img.to_rgba8(),
+                    (dx as u32 * TILE_SIZE) as i64,
+                    (dz as u32 * TILE_SIZE) as i64,
+                );
+            }
+        }
+        let scaled = imageops::resize(
+            &canvas,
+            TILE_SIZE,
+            TILE_SIZE,
+            imageops::FilterType::Triangle,
+        );
+        save_tile(&self.tile_path(level, tile), &scaled)
+    }
+}
+
+fn save_tile(path: &Path, image: &RgbaImage) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    image
+        .save(path)
+        .with_context(|| format!("Failed to write tile {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use blockworld_server::{
+        block::map_color::MapColor,
+        world::{
+            chunk::SubChunk,
+            storage::{chunk_serializer::ChunkData, LevelData},
+        },
+    };
+    use blockworld_utils::TempDir;
+
+    use super::*;
+
+    fn flat_chunk(pos: IVec2, top: &str) -> ChunkData {
+        let mut sc = SubChunk::new(ivec3(pos.x, 3, pos.y));
+        for x in 0..16 {
+            for z in 0..16 {
+                sc.set_blockid(ivec3(x, 0, z), "minecraft:stone");
+                sc.set_blockid(ivec3(x, 1, z), top);
+            }
+        }
+        let mut chunk = ChunkData::new(pos);
+        chunk.put_section(&sc);
+        chunk
+    }
+
+    #[test]
+    fn incremental_render() {
+        let dir = TempDir::new("map").unwrap();
+        let mut storage = WorldStorage::open(dir.join("world")).unwrap();
+        storage.write_level(&LevelData::default()).unwrap();
+        for x in -1..=1 {
+            for z in -1..=1 {
+                storage
+                    .write_chunk(&flat_chunk(ivec2(x, z), "minecraft:grass_block"))
+                    .unwrap();
+            }
+        }
+
+        let mut renderer = MapRenderer::new(dir.join("map"), 2);
+        let stats = renderer.render(&mut storage, false).unwrap();
+        assert_eq!(stats.chunks_drawn, 9);
+        // regions (-1,-1), (0,-1), (-1,0), (0,0), which stay four tiles at every zoom level
+        // since the quadtree splits at the origin
+        assert_eq!(stats.tiles_written, 4 * 3);
+
+        let tile = image::open(renderer.tile_path(0, IVec2::ZERO))
+            .unwrap()
+            .to_rgba8();
+        let [r, g, b] = MapColor::GRASS.rgb();
+        // flat ground is drawn at normal brightness or, dithered, at high brightness
+        let p = tile.get_pixel(3, 3);
+        assert!(p.0[3] == 255 && p.0[0] <= r && p.0[1] <= g && p.0[2] <= b);
+        assert_eq!(tile.get_pixel(100, 100).0[3], 0);
+
+        // nothing changed
+        assert_eq!(
+            renderer.render(&mut storage, false).unwrap(),
+            RenderStats {
+                chunks_drawn: 0,
+                tiles_written: 0
+            }
+        );
+
+        // one chunk changed, it and the chunk south of it are redrawn
+        storage
+            .write_chunk(&flat_chunk(ivec2(0, -1), "minecraft:sand"))
+            .unwrap();
+        let stats = renderer.render(&mut storage, false).unwrap();
+        assert_eq!(stats.chunks_drawn, 2);
+        let tile = image::open(renderer.tile_path(0, ivec2(0, -1)))
+            .unwrap()
+            .to_rgba8();
+        let sand = MapColor::SAND.rgb();
+        let p = tile.get_pixel(3, TILE_SIZE - 3);
+        assert!(p.0[0] > r && p.0[2] > b && p.0[0] <= sand[0]);
+    }
+}
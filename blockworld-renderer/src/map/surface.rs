@@ -0,0 +1,186 @@
+//! ```text
+//! package net.minecraft.item
+//! class FilledMapItem
+//! version 1.16
+//! ```
+//!
+//! Finds the block a map shows for every column of a chunk and shades it the way Minecraft's
+//! maps do: brighter where the ground rises towards the south, darker where it falls, and water
+//! darker the deeper it gets.
+
+use anyhow::*;
+use blockworld_server::{
+    block::{map_color::MapColor, BLOCK_REGISTRY},
+    world::{chunk::SUBCHUNK_SIZE, storage::chunk_serializer::ChunkData},
+};
+use blockworld_utils::ResourceLocation;
+use image::Rgba;
+
+const COLUMNS: usize = SUBCHUNK_SIZE * SUBCHUNK_SIZE;
+const WATER: &str = "minecraft:water";
+
+/// Brightness multipliers out of 255, the same steps Minecraft uses.
+const HIGH: u32 = 255;
+const NORMAL: u32 = 220;
+const LOW: u32 = 180;
+
+/// The first block of a column with a map colour, seen from above.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SurfaceColumn {
+    pub height: Option<i32>,
+    pub color: MapColor,
+    /// Water blocks above the ground, 0 if the top block isn't water.
+    pub water_depth: u32,
+}
+
+/// The 16x16 columns of a chunk, indexed `x + z * 16`.
+pub struct ChunkSurface {
+    pub columns: Vec<SurfaceColumn>,
+}
+
+fn map_color_of(name: &str) -> MapColor {
+    BLOCK_REGISTRY
+        .get(&ResourceLocation::new(name))
+        .map(|b| b.map_color)
+        .unwrap_or(MapColor::NONE)
+}
+
+impl ChunkSurface {
+    pub fn scan(chunk: &ChunkData) -> Result<Self> {
+        let mut columns = vec![SurfaceColumn::default(); COLUMNS];
+        let mut done = vec![false; COLUMNS];
+
+        // sections are sorted by y, walk them top down
+        for section in chunk.sections.iter().rev() {
+            let colors: Vec<_> = section
+                .palette
+                .iter()
+                .map(|p| (map_color_of(&p.name), p.name == WATER))
+                .collect();
+            let indices = section.unpack()?;
+            for (i, index) in indices.iter().enumerate().rev() {
+                let column = i % COLUMNS;
+                if done[column] {
+                    continue;
+                }
+                let (color, is_water) = colors[*index as usize];
+                let c = &mut columns[column];
+                if c.height.is_none() {
+                    if color.is_none() {
+                        continue;
+                    }
+                    let y = section.y * SUBCHUNK_SIZE as i32 + (i / COLUMNS) as i32;
+                    c.height = Some(y);
+                    c.color = color;
+                }
+                // keep counting while we're under water
+                if is_water && c.color == MapColor::WATER {
+                    c.water_depth += 1;
+                } else {
+                    done[column] = true;
+                }
+            }
+        }
+        Ok(Self { columns })
+    }
+
+    pub fn column(&self, x: usize, z: usize) -> &SurfaceColumn {
+        &self.columns[x + z * SUBCHUNK_SIZE]
+    }
+
+    /// Shade every column into 16x16 pixels, `x + z * 16`.
+    ///
+    /// `north` is the chunk at z - 1, whose last row decides the shading of our first row.
+    pub fn shade(&self, north: Option<&ChunkSurface>) -> Vec<Rgba<u8>> {
+        let mut pixels = Vec::with_capacity(COLUMNS);
+        for z in 0..SUBCHUNK_SIZE {
+            for x in 0..SUBCHUNK_SIZE {
+                let c = self.column(x, z);
+                let north_height = if z > 0 {
+                    self.column(x, z - 1).height
+                } else {
+                    north.and_then(|n| n.column(x, SUBCHUNK_SIZE - 1).height)
+                };
+                pixels.push(shade_column(c, north_height, x + z));
+            }
+        }
+        pixels
+    }
+}
+
+/// `checker` is `x + z`, used to dither between brightness steps like Minecraft does.
+pub fn shade_column(c: &SurfaceColumn, north_height: Option<i32>, checker: usize) -> Rgba<u8> {
+    let Some(height) = c.height else {
+        return Rgba([0, 0, 0, 0]);
+    };
+    let dither = (checker & 1) as f64;
+
+    let brightness = if c.water_depth > 0 {
+        let d = c.water_depth as f64 * 0.1 + dither * 0.2;
+        if d < 0.5 {
+            HIGH
+        } else if d > 0.9 {
+            LOW
+        } else {
+            NORMAL
+        }
+    } else {
+        let north = north_height.unwrap_or(height);
+        let d = (height - north) as f64 * 0.8 + (dither - 0.5) * 0.4;
+        if d > 0.6 {
+            HIGH
+        } else if d < -0.6 {
+            LOW
+        } else {
+            NORMAL
+        }
+    };
+
+    let [r, g, b] = c.color.rgb().map(|v| (v as u32 * brightness / 255) as u8);
+    Rgba([r, g, b, 255])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(height: i32, color: MapColor, water_depth: u32) -> SurfaceColumn {
+        SurfaceColumn {
+            height: Some(height),
+            color,
+            water_depth,
+        }
+    }
+
+    #[test]
+    fn height_shading() {
+        let grass = column(64, MapColor::GRASS, 0);
+        let [r, ..] = MapColor::GRASS.rgb();
+        // rising towards the south
+        assert_eq!(shade_column(&grass, Some(62), 0).0[0], r);
+        // flat, not dithered up
+        assert_eq!(
+            shade_column(&grass, Some(64), 0).0[0],
+            (r as u32 * NORMAL / 255) as u8
+        );
+        // falling
+        assert_eq!(
+            shade_column(&grass, Some(66), 1).0[0],
+            (r as u32 * LOW / 255) as u8
+        );
+    }
+
+    #[test]
+    fn water_depth_shading() {
+        let b = MapColor::WATER.rgb()[2] as u32;
+        let shallow = shade_column(&column(62, MapColor::WATER, 1), None, 0);
+        let deep = shade_column(&column(62, MapColor::WATER, 12), None, 0);
+        assert_eq!(shallow.0[2] as u32, b * HIGH / 255);
+        assert_eq!(deep.0[2] as u32, b * LOW / 255);
+    }
+
+    #[test]
+    fn empty_column_is_transparent() {
+        assert_eq!(shade_column(&SurfaceColumn::default(), None, 0).0[3], 0);
+    }
+}
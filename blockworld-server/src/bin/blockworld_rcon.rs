@@ -0,0 +1,83 @@
+//! A remote console client.
+//!
+//! ```text
+//! blockworld-rcon [--address localhost:25575] [command...]
+//! ```
+//!
+//! Runs the command given on the command line, or every line of stdin, one after another, and
+//! prints what the server answered. The password comes from `BLOCKWORLD_RCON_PASSWORD`, or
+//! `--password` where nobody else can see the command line.
+
+use std::{
+    env,
+    io::{self, BufRead},
+    process::ExitCode,
+};
+
+use anyhow::*;
+use blockworld_server::network::rcon::{RconClient, PASSWORD_VAR};
+use clap::Parser;
+
+#[derive(Parser)]
+#[command(
+    name = "blockworld-rcon",
+    about = "Run commands on a Blockworld server"
+)]
+struct Args {
+    /// The server's RCON address, as host:port
+    #[arg(long, default_value = "localhost:25575")]
+    address: String,
+
+    /// The RCON password, instead of BLOCKWORLD_RCON_PASSWORD
+    #[arg(long)]
+    password: Option<String>,
+
+    /// The command to run, or nothing to read commands from stdin
+    #[arg(trailing_var_arg = true)]
+    command: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    env_logger::init();
+    let args = Args::parse();
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Result::Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Failed to start the runtime: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    match runtime.block_on(run(args)) {
+        Result::Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: Args) -> Result<()> {
+    let password = match args.password {
+        Some(password) => password,
+        None => env::var(PASSWORD_VAR)
+            .with_context(|| format!("Pass --password or set {}", PASSWORD_VAR))?,
+    };
+    let mut client = RconClient::connect(&args.address, &password)
+        .await
+        .with_context(|| format!("Failed to log in to {}", args.address))?;
+    if !args.command.is_empty() {
+        println!("{}", client.command(&args.command.join(" ")).await?);
+        return Ok(());
+    }
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        println!("{}", client.command(&line).await?);
+    }
+    Ok(())
+}
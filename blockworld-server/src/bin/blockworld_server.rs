@@ -0,0 +1,145 @@
+//! The dedicated server.
+//!
+//! ```text
+//! blockworld-server [--world <dir>] [--port 25565] [--websocket-port <port>] [--view-distance 8]
+//!                   [--encryption] [--allowlist] [--capture-dir <dir>] [--motd <text>]
+//!                   [--max-players 20] [--favicon <png>] [--lan] [--rcon-port <port>]
+//!                   [--gamemode survival]
+//! ```
+//!
+//! Runs until Ctrl-C, then disconnects everyone and saves the world. `allowlist.json` and
+//! `banlist.json` are read from the current folder. The favicon must be a 64x64 PNG.
+//!
+//! With `--rcon-port`, operators can run commands with `blockworld-rcon`. The password comes
+//! from `BLOCKWORLD_RCON_PASSWORD` rather than the command line, where every user on the
+//! machine could read it, and what they do is appended to `rcon-audit.log`.
+
+use std::{
+    env,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use anyhow::*;
+use blockworld_server::{
+    entity::game_mode::GameMode,
+    network::{
+        self, lan,
+        rcon::{RconConfig, PASSWORD_VAR},
+        ServerConfig,
+    },
+};
+use clap::Parser;
+
+#[derive(Parser)]
+#[command(name = "blockworld-server", about = "Run a Blockworld server")]
+struct Args {
+    /// The world folder, created if it doesn't exist
+    #[arg(long, default_value = "world")]
+    world: PathBuf,
+
+    /// Address to listen on
+    #[arg(long, default_value = "0.0.0.0")]
+    bind: IpAddr,
+
+    #[arg(long, default_value_t = 25565)]
+    port: u16,
+
+    /// Also accept WebSocket connections on this port
+    #[arg(long)]
+    websocket_port: Option<u16>,
+
+    /// Radius in chunks of the area sent to players
+    #[arg(long, default_value_t = 8)]
+    view_distance: u32,
+
+    /// Encrypt connections once players logged in
+    #[arg(long)]
+    encryption: bool,
+
+    /// Only let in the players in allowlist.json
+    #[arg(long)]
+    allowlist: bool,
+
+    /// Record every connection into a packet capture in this folder
+    #[arg(long)]
+    capture_dir: Option<PathBuf>,
+
+    /// The message server lists show
+    #[arg(long, default_value = "A Blockworld server")]
+    motd: String,
+
+    #[arg(long, default_value_t = 20)]
+    max_players: u32,
+
+    /// Game mode of players joining for the first time: survival, creative, adventure or
+    /// spectator
+    #[arg(long, default_value = "survival")]
+    gamemode: GameMode,
+
+    /// A 64x64 PNG server lists show
+    #[arg(long)]
+    favicon: Option<PathBuf>,
+
+    /// Announce the server to clients on the local network
+    #[arg(long)]
+    lan: bool,
+
+    /// Accept remote console connections on this port
+    #[arg(long)]
+    rcon_port: Option<u16>,
+}
+
+fn main() -> ExitCode {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+    let runtime = match tokio::runtime::Runtime::new() {
+        Result::Ok(runtime) => runtime,
+        Err(e) => {
+            log::error!("Failed to start the runtime: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    match runtime.block_on(run(args)) {
+        Result::Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            log::error!("{:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: Args) -> Result<()> {
+    let rcon = match args.rcon_port {
+        Some(port) => Some(RconConfig {
+            address: SocketAddr::new(args.bind, port),
+            password: env::var(PASSWORD_VAR)
+                .with_context(|| format!("RCON needs a password in {}", PASSWORD_VAR))?,
+            audit_log: Some(PathBuf::from("rcon-audit.log")),
+        }),
+        None => None,
+    };
+    let server = network::start(ServerConfig {
+        world: args.world,
+        address: SocketAddr::new(args.bind, args.port),
+        websocket_address: args.websocket_port.map(|p| SocketAddr::new(args.bind, p)),
+        view_distance: args.view_distance,
+        encryption: args.encryption,
+        access_lists: Some(PathBuf::from(".")),
+        allowlist: args.allowlist,
+        capture_dir: args.capture_dir,
+        motd: args.motd,
+        max_players: args.max_players,
+        default_game_mode: args.gamemode,
+        favicon: args.favicon,
+        lan: args.lan.then_some(lan::LAN_GROUP),
+        rcon,
+        ..Default::default()
+    })
+    .await?;
+    log::info!("Done! Press Ctrl-C to stop");
+
+    tokio::signal::ctrl_c().await?;
+    server.shutdown().await
+}
@@ -0,0 +1,568 @@
+//! Offline inspection of saved worlds, no running game needed.
+//!
+//! ```text
+//! blockworld-tool <world> info
+//! blockworld-tool <world> chunk <x> <z>
+//! blockworld-tool <world> block <x> <y> <z>
+//! blockworld-tool <world> stats
+//! blockworld-tool <world> prune [--radius <r>] [--unvisited] [--dry-run]
+//! blockworld-tool <world> verify
+//! blockworld-tool <world> replay <capture> [--ignore <packet>]... [--view-distance 8]
+//! ```
+//!
+//! Every subcommand prints human readable text, or JSON with `--json`. `replay` is the one that
+//! runs a server, on a copy of the world, which is left as it was.
+
+use std::{
+    collections::HashMap,
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::*;
+use blockworld_server::{
+    block::BLOCK_REGISTRY,
+    network::{
+        capture::read_capture,
+        replay::{replay_on_server, Divergence},
+        ServerConfig,
+    },
+    world::{
+        chunk::{SubChunk, SUBCHUNK_SIZE},
+        storage::{LevelData, WorldStorage},
+    },
+};
+use blockworld_utils::TempDir;
+use clap::{Parser, Subcommand};
+use glam::*;
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Parser)]
+#[command(
+    name = "blockworld-tool",
+    about = "Inspect and repair saved Blockworld worlds"
+)]
+struct Args {
+    /// The world folder, the one holding level.json
+    world: PathBuf,
+
+    /// Print JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Seed, spawn and chunk count
+    Info,
+    /// Dump the palettes and heightmap of a chunk column
+    #[command(allow_negative_numbers = true)]
+    Chunk { x: i32, z: i32 },
+    /// Show the block at a world position
+    #[command(allow_negative_numbers = true)]
+    Block { x: i32, y: i32, z: i32 },
+    /// Histogram of every block in the world
+    Stats,
+    /// Delete chunks outside a radius around spawn or never visited by a player
+    Prune {
+        /// Keep chunks at most this many chunks away from the spawn chunk
+        #[arg(long)]
+        radius: Option<i32>,
+        /// Delete chunks no player ever stood near
+        #[arg(long)]
+        unvisited: bool,
+        /// Only report what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check the checksum and decode every chunk
+    Verify,
+    /// Play a packet capture against a copy of the world and report what the server answers
+    /// differently
+    Replay {
+        /// A capture file, recorded by the server or the client
+        capture: PathBuf,
+        /// Don't compare packets of this type, like EntityTeleport
+        #[arg(long)]
+        ignore: Vec<String>,
+        /// The view distance of the recording server
+        #[arg(long, default_value_t = 8)]
+        view_distance: u32,
+    },
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(args) {
+        Result::Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<ExitCode> {
+    if !args.world.is_dir() {
+        bail!("{} is not a world folder", args.world.display());
+    }
+    // only looking, so a folder that isn't a world is left as it was
+    let mut storage = WorldStorage::open_read_only(&args.world)?;
+    let level = storage
+        .read_level()?
+        .ok_or_else(|| anyhow!("{} has no level data", args.world.display()))?;
+    if matches!(args.command, Command::Prune { dry_run: false, .. }) {
+        storage = WorldStorage::open(&args.world)?;
+    }
+
+    let report = match args.command {
+        Command::Info => info(&mut storage, &level)?,
+        Command::Chunk { x, z } => chunk(&mut storage, ivec2(x, z))?,
+        Command::Block { x, y, z } => block(&mut storage, ivec3(x, y, z))?,
+        Command::Stats => stats(&mut storage)?,
+        Command::Prune {
+            radius,
+            unvisited,
+            dry_run,
+        } => prune(&mut storage, &level, radius, unvisited, dry_run)?,
+        Command::Verify => verify(&mut storage)?,
+        Command::Replay {
+            capture,
+            ignore,
+            view_distance,
+        } => replay(&args.world, &capture, &ignore, view_distance)?,
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report.json)?);
+    } else {
+        print!("{}", report.text);
+    }
+    Ok(if report.ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// What a subcommand prints, in both formats.
+struct Report {
+    text: String,
+    json: Value,
+    ok: bool,
+}
+
+impl Report {
+    fn new<T: Serialize>(text: String, json: &T) -> Result<Self> {
+        Ok(Self {
+            text,
+            json: serde_json::to_value(json)?,
+            ok: true,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct Info {
+    seed: u64,
+    spawn: [i32; 3],
+    time: u64,
+    regions: usize,
+    chunks: usize,
+}
+
+fn info(storage: &mut WorldStorage, level: &LevelData) -> Result<Report> {
+    let info = Info {
+        seed: level.seed,
+        spawn: level.spawn,
+        time: level.time,
+        regions: storage.list_regions()?.len(),
+        chunks: storage.list_chunks()?.len(),
+    };
+    let text = format!(
+        "seed:    {}\nspawn:   {} {} {}\ntime:    {} ticks\nregions: {}\nchunks:  {}\n",
+        info.seed,
+        info.spawn[0],
+        info.spawn[1],
+        info.spawn[2],
+        info.time,
+        info.regions,
+        info.chunks
+    );
+    Report::new(text, &info)
+}
+
+#[derive(Serialize)]
+struct ChunkDump {
+    x: i32,
+    z: i32,
+    inhabited_time: u64,
+    last_written: Option<u32>,
+    sections: Vec<SectionDump>,
+    /// World y of the highest non-air block, indexed `x + z * 16`
+    heightmap: Vec<Option<i32>>,
+}
+
+#[derive(Serialize)]
+struct SectionDump {
+    y: i32,
+    bits: u32,
+    /// Palette entries with how many blocks use each
+    palette: Vec<(String, usize)>,
+}
+
+fn chunk(storage: &mut WorldStorage, pos: IVec2) -> Result<Report> {
+    let data = storage
+        .read_chunk(pos)?
+        .ok_or_else(|| anyhow!("chunk {} {} was never saved", pos.x, pos.y))?;
+
+    let mut sections = Vec::new();
+    for section in &data.sections {
+        let mut counts = vec![0; section.palette.len()];
+        for index in section.unpack()? {
+            counts[index as usize] += 1;
+        }
+        sections.push(SectionDump {
+            y: section.y,
+            bits: section.bits,
+            palette: section
+                .palette
+                .iter()
+                .map(|p| p.to_string())
+                .zip(counts)
+                .collect(),
+        });
+    }
+    let dump = ChunkDump {
+        x: data.x,
+        z: data.z,
+        inhabited_time: data.inhabited_time,
+        last_written: storage.chunk_timestamp(pos)?,
+        sections,
+        heightmap: data.heightmap()?,
+    };
+
+    let mut text = format!(
+        "chunk {} {}\ninhabited time: {} ticks\nlast written:   {}\n",
+        dump.x,
+        dump.z,
+        dump.inhabited_time,
+        dump.last_written
+            .map(|t| format!("{} (unix seconds)", t))
+            .unwrap_or_else(|| "never".into())
+    );
+    for section in &dump.sections {
+        text += &format!("\nsection y={} ({} bits)\n", section.y, section.bits);
+        for (i, (name, count)) in section.palette.iter().enumerate() {
+            text += &format!("  {:>3}  {:<40} {:>5}\n", i, name, count);
+        }
+    }
+    text += "\nheightmap (x ->, z v)\n";
+    for row in dump.heightmap.chunks(SUBCHUNK_SIZE) {
+        let cells: Vec<_> = row
+            .iter()
+            .map(|h| h.map(|h| format!("{:>4}", h)).unwrap_or("   -".into()))
+            .collect();
+        text += &format!("{}\n", cells.join(""));
+    }
+    Report::new(text, &dump)
+}
+
+#[derive(Serialize)]
+struct BlockAt {
+    pos: [i32; 3],
+    block: String,
+    saved: bool,
+}
+
+fn block(storage: &mut WorldStorage, pos: IVec3) -> Result<Report> {
+    let size = SUBCHUNK_SIZE as i32;
+    let column = ivec2(pos.x.div_euclid(size), pos.z.div_euclid(size));
+    let local = ivec3(
+        pos.x.rem_euclid(size),
+        pos.y.rem_euclid(size),
+        pos.z.rem_euclid(size),
+    );
+
+    let chunk = storage.read_chunk(column)?;
+    let block = match chunk
+        .as_ref()
+        .and_then(|c| c.section(pos.y.div_euclid(size)))
+    {
+        Some(section) => {
+            let index = section.unpack()?[SubChunk::index(local.x, local.y, local.z)];
+            section.palette[index as usize].to_string()
+        }
+        None => "minecraft:air".to_string(),
+    };
+    let at = BlockAt {
+        pos: pos.to_array(),
+        block,
+        saved: chunk.is_some(),
+    };
+    let text = format!(
+        "{} {} {}: {}{}\n",
+        pos.x,
+        pos.y,
+        pos.z,
+        at.block,
+        if at.saved { "" } else { " (chunk never saved)" }
+    );
+    Report::new(text, &at)
+}
+
+#[derive(Serialize)]
+struct Stats {
+    chunks: usize,
+    total: u64,
+    blocks: Vec<(String, u64)>,
+}
+
+fn stats(storage: &mut WorldStorage) -> Result<Report> {
+    let chunks = storage.list_chunks()?;
+    let mut histogram: HashMap<String, u64> = HashMap::new();
+    for pos in &chunks {
+        let Some(data) = storage.read_chunk(*pos)? else {
+            continue;
+        };
+        for section in &data.sections {
+            let names: Vec<_> = section.palette.iter().map(|p| p.to_string()).collect();
+            for index in section.unpack()? {
+                *histogram.entry(names[index as usize].clone()).or_default() += 1;
+            }
+        }
+    }
+
+    let mut blocks: Vec<_> = histogram.into_iter().collect();
+    blocks.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let stats = Stats {
+        chunks: chunks.len(),
+        total: blocks.iter().map(|b| b.1).sum(),
+        blocks,
+    };
+
+    let mut text = format!(
+        "{} blocks in {} saved sections of {} chunks\n\n",
+        stats.total,
+        stats.total / (SUBCHUNK_SIZE as u64).pow(3),
+        stats.chunks
+    );
+    for (name, count) in &stats.blocks {
+        text += &format!(
+            "{:<40} {:>12} {:>7.3}%\n",
+            name,
+            count,
+            *count as f64 * 100.0 / stats.total.max(1) as f64
+        );
+    }
+    Report::new(text, &stats)
+}
+
+#[derive(Serialize)]
+struct Prune {
+    dry_run: bool,
+    kept: usize,
+    deleted: Vec<[i32; 2]>,
+}
+
+fn prune(
+    storage: &mut WorldStorage,
+    level: &LevelData,
+    radius: Option<i32>,
+    unvisited: bool,
+    dry_run: bool,
+) -> Result<Report> {
+    if radius.is_none() && !unvisited {
+        bail!("prune needs --radius, --unvisited or both");
+    }
+    let size = SUBCHUNK_SIZE as i32;
+    let spawn = ivec2(
+        level.spawn[0].div_euclid(size),
+        level.spawn[2].div_euclid(size),
+    );
+
+    let mut deleted = Vec::new();
+    let mut kept = 0;
+    for pos in storage.list_chunks()? {
+        let outside = radius.is_some_and(|r| (pos - spawn).abs().max_element() > r);
+        let never_visited = unvisited
+            && storage
+                .read_chunk(pos)?
+                .is_some_and(|c| c.inhabited_time == 0);
+        if outside || never_visited {
+            if !dry_run {
+                storage.delete_chunk(pos)?;
+            }
+            deleted.push(pos.to_array());
+        } else {
+            kept += 1;
+        }
+    }
+
+    let prune = Prune {
+        dry_run,
+        kept,
+        deleted,
+    };
+    let text = format!(
+        "{} {} chunks, kept {}\n",
+        if dry_run { "would delete" } else { "deleted" },
+        prune.deleted.len(),
+        prune.kept
+    );
+    Report::new(text, &prune)
+}
+
+#[derive(Serialize)]
+struct Verify {
+    checked: usize,
+    failures: Vec<Failure>,
+    unknown_blocks: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Failure {
+    chunk: [i32; 2],
+    error: String,
+}
+
+fn verify(storage: &mut WorldStorage) -> Result<Report> {
+    let chunks = storage.list_chunks()?;
+    let mut failures = Vec::new();
+    let mut unknown_blocks = Vec::new();
+
+    for pos in &chunks {
+        let decoded = storage.read_chunk(*pos).and_then(|c| {
+            let c = c.ok_or_else(|| anyhow!("listed but missing"))?;
+            for section in &c.sections {
+                section.unpack()?;
+            }
+            Ok(c)
+        });
+        match decoded {
+            Result::Ok(c) => {
+                for entry in c.sections.iter().flat_map(|s| &s.palette) {
+                    if BLOCK_REGISTRY.get(&entry.name.as_str().into()).is_none()
+                        && !unknown_blocks.contains(&entry.name)
+                    {
+                        unknown_blocks.push(entry.name.clone());
+                    }
+                }
+            }
+            Err(e) => failures.push(Failure {
+                chunk: pos.to_array(),
+                error: format!("{:#}", e),
+            }),
+        }
+    }
+
+    let verify = Verify {
+        checked: chunks.len(),
+        failures,
+        unknown_blocks,
+    };
+    let mut text = format!(
+        "checked {} chunks, {} failed\n",
+        verify.checked,
+        verify.failures.len()
+    );
+    for f in &verify.failures {
+        text += &format!("  chunk {} {}: {}\n", f.chunk[0], f.chunk[1], f.error);
+    }
+    for name in &verify.unknown_blocks {
+        text += &format!("  warning: unknown block {} loads as air\n", name);
+    }
+    let mut report = Report::new(text, &verify)?;
+    report.ok = verify.failures.is_empty();
+    Ok(report)
+}
+
+#[derive(Serialize)]
+struct Replay {
+    sent: usize,
+    compared: usize,
+    missing: Vec<Diverged>,
+    extra: Vec<Diverged>,
+}
+
+#[derive(Serialize)]
+struct Diverged {
+    /// Since the login succeeded.
+    at_ms: u128,
+    packet: String,
+}
+
+impl From<&Divergence> for Diverged {
+    fn from(d: &Divergence) -> Self {
+        Self {
+            at_ms: d.at.as_millis(),
+            packet: d.packet.clone(),
+        }
+    }
+}
+
+fn replay(world: &Path, capture: &Path, ignore: &[String], view_distance: u32) -> Result<Report> {
+    let records = read_capture(capture)?;
+    // joining saves the player and the chunks around it
+    let copy = TempDir::new("replay")?;
+    copy_dir(world, &copy)?;
+    let config = ServerConfig {
+        world: copy.to_path_buf(),
+        address: SocketAddr::from(([127, 0, 0, 1], 0)),
+        view_distance,
+        ..Default::default()
+    };
+    let replayed =
+        tokio::runtime::Runtime::new()?.block_on(replay_on_server(config, &records, ignore));
+    drop(copy);
+    let replayed = replayed?;
+
+    let replay = Replay {
+        sent: replayed.sent,
+        compared: replayed.compared,
+        missing: replayed.missing.iter().map(Diverged::from).collect(),
+        extra: replayed.extra.iter().map(Diverged::from).collect(),
+    };
+    let mut text = format!(
+        "sent {} packets, compared {}, {} missing, {} extra\n",
+        replay.sent,
+        replay.compared,
+        replay.missing.len(),
+        replay.extra.len()
+    );
+    for (what, divergences) in [("missing", &replay.missing), ("extra", &replay.extra)] {
+        for d in divergences {
+            text += &format!(
+                "  {} at {}.{:03}s: {}\n",
+                what,
+                d.at_ms / 1000,
+                d.at_ms % 1000,
+                d.packet
+            );
+        }
+    }
+    let mut report = Report::new(text, &replay)?;
+    report.ok = !replayed.diverged();
+    Ok(report)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)
+                .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,226 @@
+//! ```text
+//! package net.minecraft.block
+//! class AbstractBlock, GrassBlock, FallingBlock, FenceBlock, WallBlock
+//! version 1.16
+//! ```
+//!
+//! What blocks do by themselves. A block gets called back when a tick scheduled for it is due,
+//! and, if it [`ticks_randomly`](BlockBehaviour::ticks_randomly), when a random tick picks it
+//! (`world/block_tick.rs`). It also hears of the blocks beside it changing, and may change
+//! its state to fit them (`world/block_update.rs`). Most blocks do nothing and keep [`Inert`].
+
+use blockworld_utils::ResourceLocation;
+use glam::*;
+
+use crate::{
+    entity::{falling_block::start_falling, item::drop_item, EntityType},
+    world::{
+        block_tick::BlockTickContext,
+        chunk::SUBCHUNK_SIZE,
+        chunk_access::{BlockPos, WorldAccess},
+    },
+};
+
+use super::{
+    block_face_direction::BlockFaceDirection,
+    shape::{collision_shape, fluid_shape, FULL_CUBE},
+    state::{BlockState, EAST, NORTH, SOUTH, WEST},
+};
+
+pub trait BlockBehaviour: Send + Sync {
+    /// Worth calling [`BlockBehaviour::random_tick`] on.
+    fn ticks_randomly(&self) -> bool {
+        false
+    }
+
+    /// A tick scheduled for this block at `pos` is due, and the block is still there.
+    fn tick(&self, _ctx: &mut BlockTickContext, _pos: BlockPos) {}
+
+    /// A random tick picked this block at `pos`.
+    fn random_tick(&self, _ctx: &mut BlockTickContext, _pos: BlockPos) {}
+
+    /// This block, in `state` at `pos`, was just replaced by `new`, of another block or
+    /// another state of this one.
+    fn on_remove(
+        &self,
+        _ctx: &mut BlockTickContext,
+        _pos: BlockPos,
+        _state: BlockState,
+        _new: BlockState,
+    ) {
+    }
+
+    /// This block was just set at `pos` in `state`, where `old` was.
+    fn on_place(
+        &self,
+        _ctx: &mut BlockTickContext,
+        _pos: BlockPos,
+        _state: BlockState,
+        _old: BlockState,
+    ) {
+    }
+
+    /// The block at `from` beside this one, in `state` at `pos`, changed and said so.
+    fn neighbor_changed(
+        &self,
+        _ctx: &mut BlockTickContext,
+        _pos: BlockPos,
+        _state: BlockState,
+        _from: BlockPos,
+    ) {
+    }
+
+    /// The state to go in now that the block towards `direction` is `neighbour`. Air breaks
+    /// the block.
+    fn update_shape(
+        &self,
+        state: BlockState,
+        _direction: BlockFaceDirection,
+        _neighbour: BlockState,
+    ) -> BlockState {
+        state
+    }
+
+    /// A player used this block, in `state` at `pos`, as a lever is flipped. True if it did
+    /// something.
+    fn use_block(&self, _ctx: &mut BlockTickContext, _pos: BlockPos, _state: BlockState) -> bool {
+        false
+    }
+
+    /// Gives redstone power out, see `redstone/mod.rs`.
+    fn is_signal_source(&self) -> bool {
+        false
+    }
+
+    /// Power `state` gives the block that finds it towards `side`.
+    fn signal(&self, _state: BlockState, _side: BlockFaceDirection) -> u8 {
+        0
+    }
+
+    /// Power `state` gives the block that finds it towards `side` strongly, so that block
+    /// passes it on if it's a conductor.
+    fn direct_signal(&self, _state: BlockState, _side: BlockFaceDirection) -> u8 {
+        0
+    }
+
+    /// This block, in `state` at `pos`, broke, by a shape update, a piston or a player. Most
+    /// blocks drop themselves.
+    fn spawn_drops(&self, ctx: &mut BlockTickContext, pos: BlockPos, state: BlockState) {
+        drop_block_item(ctx, pos, state.name(), 1);
+    }
+}
+
+/// Drop `count` of `item` in the middle of the block at `pos`.
+pub fn drop_block_item(
+    ctx: &mut BlockTickContext,
+    pos: BlockPos,
+    item: &ResourceLocation,
+    count: u32,
+) {
+    let (_, height) = EntityType::ITEM.size();
+    let position = pos.as_vec3() + vec3(0.5, 0.5 - height / 2.0, 0.5);
+    drop_item(ctx.chunks, ctx.random, position, item, count);
+}
+
+/// Does nothing by itself.
+pub struct Inert;
+
+impl BlockBehaviour for Inert {}
+
+/// Grass dies back to dirt under a full block or a fluid, and otherwise spreads to dirt
+/// nearby that isn't covered. Minecraft also wants light, which the server doesn't compute yet.
+pub struct Grass;
+
+/// Tries to spread per random tick.
+const GRASS_SPREAD_TRIES: usize = 4;
+
+/// Nothing on top of `pos` keeps grass from growing there.
+fn uncovered(ctx: &BlockTickContext, pos: BlockPos) -> bool {
+    let above = ctx.chunks.get_state(pos + IVec3::Y);
+    collision_shape(above) != [FULL_CUBE] && fluid_shape(above).is_empty()
+}
+
+impl BlockBehaviour for Grass {
+    fn ticks_randomly(&self) -> bool {
+        true
+    }
+
+    fn random_tick(&self, ctx: &mut BlockTickContext, pos: BlockPos) {
+        if !uncovered(ctx, pos) {
+            ctx.chunks.set_block(pos, &"minecraft:dirt".into());
+            return;
+        }
+        let dirt: ResourceLocation = "minecraft:dirt".into();
+        for _ in 0..GRASS_SPREAD_TRIES {
+            let offset = ivec3(
+                ctx.random.next_int(3) as i32 - 1,
+                ctx.random.next_int(5) as i32 - 3,
+                ctx.random.next_int(3) as i32 - 1,
+            );
+            let target = pos + offset;
+            if ctx.chunks.get_block(target) == dirt && uncovered(ctx, target) {
+                ctx.chunks
+                    .set_block(target, &"minecraft:grass_block".into());
+            }
+        }
+    }
+}
+
+/// Falls while there's air or a fluid under it, as a falling block entity, a few ticks after a
+/// block beside it changes.
+pub struct Falling;
+
+/// Ticks from a block beside it changing to it starting to fall.
+pub const FALL_DELAY: u64 = 2;
+
+impl BlockBehaviour for Falling {
+    fn neighbor_changed(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        state: BlockState,
+        _from: BlockPos,
+    ) {
+        ctx.schedule_tick(pos, state.name(), FALL_DELAY, 0);
+    }
+
+    fn tick(&self, ctx: &mut BlockTickContext, pos: BlockPos) {
+        let below = pos - IVec3::Y;
+        let under = ctx.chunks.get_state(below);
+        // blocks that aren't loaded read as air, but there's nowhere to fall to
+        let loaded = ctx
+            .chunks
+            .is_chunk_loaded(below.div_euclid(IVec3::splat(SUBCHUNK_SIZE as i32)));
+        if !loaded || !collision_shape(under).is_empty() {
+            return;
+        }
+        start_falling(ctx, pos);
+    }
+}
+
+/// Fences and walls, connecting to the blocks of their kind beside them and to full blocks.
+/// Blocks are of a kind if their names end the same, as Minecraft's block tags would have it.
+pub struct Connecting {
+    /// What the names of the blocks of the kind end with, like `_fence`.
+    pub suffix: &'static str,
+}
+
+impl BlockBehaviour for Connecting {
+    fn update_shape(
+        &self,
+        state: BlockState,
+        direction: BlockFaceDirection,
+        neighbour: BlockState,
+    ) -> BlockState {
+        let side = match direction {
+            BlockFaceDirection::ZN => NORTH,
+            BlockFaceDirection::XP => EAST,
+            BlockFaceDirection::ZP => SOUTH,
+            BlockFaceDirection::XN => WEST,
+            _ => return state,
+        };
+        let connects =
+            neighbour.name().ends_with(self.suffix) || collision_shape(neighbour) == [FULL_CUBE];
+        state.with_bool(side.name, connects)
+    }
+}
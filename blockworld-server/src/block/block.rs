@@ -1,9 +1,31 @@
 use blockworld_utils::{HasResourceLocation, ResourceLocation};
 
+use super::{
+    behaviour::{BlockBehaviour, Inert},
+    map_color::MapColor,
+    state::Property,
+};
+
 pub type NumberID = u32;
 
 pub struct Block {
     pub id: ResourceLocation,
+    pub map_color: MapColor,
+    /// How long it takes to break, about a second and a half per point by hand. Negative
+    /// for blocks that can't be broken.
+    pub hardness: f32,
+    /// The tool that breaks it fast, `None` if none does.
+    pub tool: Option<ToolKind>,
+    /// Breaking it without its tool is slower still.
+    pub requires_tool: bool,
+    /// What it does by itself.
+    pub behaviour: Box<dyn BlockBehaviour>,
+    /// What its states differ by, see `state.rs`.
+    pub properties: Vec<Property>,
+    /// Passes redstone power on when it's a whole block, see `redstone/mod.rs`.
+    pub redstone_conductor: bool,
+    /// What a piston pushing it does, see `redstone/piston.rs`.
+    pub push_reaction: PushReaction,
 }
 
 impl HasResourceLocation for Block {
@@ -14,8 +36,86 @@ impl HasResourceLocation for Block {
 
 impl Block {
     pub fn new(id: ResourceLocation) -> Self {
-        Self { id }
+        Self {
+            id,
+            map_color: MapColor::NONE,
+            hardness: 0.0,
+            tool: None,
+            requires_tool: false,
+            behaviour: Box::new(Inert),
+            properties: vec![],
+            redstone_conductor: true,
+            push_reaction: PushReaction::Normal,
+        }
+    }
+
+    pub fn with_map_color(mut self, map_color: MapColor) -> Self {
+        self.map_color = map_color;
+        self
+    }
+
+    pub fn with_hardness(mut self, hardness: f32) -> Self {
+        self.hardness = hardness;
+        self
+    }
+
+    pub fn with_tool(mut self, tool: ToolKind) -> Self {
+        self.tool = Some(tool);
+        self
+    }
+
+    pub fn with_required_tool(mut self, tool: ToolKind) -> Self {
+        self.tool = Some(tool);
+        self.requires_tool = true;
+        self
     }
+
+    pub fn with_redstone_conductor(mut self, conductor: bool) -> Self {
+        self.redstone_conductor = conductor;
+        self
+    }
+
+    pub fn with_push_reaction(mut self, reaction: PushReaction) -> Self {
+        self.push_reaction = reaction;
+        self
+    }
+
+    pub fn with_behaviour(mut self, behaviour: impl BlockBehaviour + 'static) -> Self {
+        self.behaviour = Box::new(behaviour);
+        self
+    }
+
+    pub fn with_property(mut self, property: Property) -> Self {
+        self.properties.push(property);
+        self
+    }
+
+    /// How many states it has, one for every combination of its property values.
+    pub fn state_count(&self) -> u32 {
+        self.properties
+            .iter()
+            .map(|p| p.values.len() as u32)
+            .product()
+    }
+}
+
+/// The kinds of tools that break blocks faster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolKind {
+    Pickaxe,
+    Shovel,
+    Axe,
+}
+
+/// What a piston pushing a block does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushReaction {
+    /// Moves it, unless it can't be broken either.
+    Normal,
+    /// Breaks it, and what's behind moves into its place.
+    Destroy,
+    /// Can't move it, so the piston doesn't move.
+    Block,
 }
 
 #[derive(Debug, Default, Clone, Copy)]
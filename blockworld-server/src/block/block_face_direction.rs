@@ -1,8 +1,8 @@
 use enumflags2::bitflags;
-use glam::IVec3;
+use glam::{IVec3, Vec3};
 #[bitflags]
 #[repr(u8)]
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BlockFaceDirection {
     /// X+ (east)
     XP = 0b000001,
@@ -41,4 +41,67 @@ impl BlockFaceDirection {
             BlockFaceDirection::ZN => IVec3::NEG_Z,
         }
     }
+
+    pub fn opposite(&self) -> Self {
+        match self {
+            BlockFaceDirection::XP => BlockFaceDirection::XN,
+            BlockFaceDirection::YP => BlockFaceDirection::YN,
+            BlockFaceDirection::ZP => BlockFaceDirection::ZN,
+            BlockFaceDirection::XN => BlockFaceDirection::XP,
+            BlockFaceDirection::YN => BlockFaceDirection::YP,
+            BlockFaceDirection::ZN => BlockFaceDirection::ZP,
+        }
+    }
+
+    /// Minecraft's name of the direction, as block states spell it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlockFaceDirection::XP => "east",
+            BlockFaceDirection::YP => "up",
+            BlockFaceDirection::ZP => "south",
+            BlockFaceDirection::XN => "west",
+            BlockFaceDirection::YN => "down",
+            BlockFaceDirection::ZN => "north",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::iter().find(|d| d.name() == name)
+    }
+
+    /// The next horizontal direction clockwise seen from above, north to east. Up and down
+    /// stay.
+    pub fn clockwise(&self) -> Self {
+        match self {
+            BlockFaceDirection::ZN => BlockFaceDirection::XP,
+            BlockFaceDirection::XP => BlockFaceDirection::ZP,
+            BlockFaceDirection::ZP => BlockFaceDirection::XN,
+            BlockFaceDirection::XN => BlockFaceDirection::ZN,
+            vertical => *vertical,
+        }
+    }
+
+    /// The face whose normal points along `axis` (0 x, 1 y, 2 z), to + if `positive`.
+    pub fn from_axis(axis: usize, positive: bool) -> Self {
+        match (axis, positive) {
+            (0, true) => BlockFaceDirection::XP,
+            (0, false) => BlockFaceDirection::XN,
+            (1, true) => BlockFaceDirection::YP,
+            (1, false) => BlockFaceDirection::YN,
+            (2, true) => BlockFaceDirection::ZP,
+            (2, false) => BlockFaceDirection::ZN,
+            _ => panic!("no axis {}", axis),
+        }
+    }
+
+    /// The face pointing the closest to `v`.
+    pub fn nearest(v: Vec3) -> Self {
+        let a = v.abs();
+        let axis = match (a.x >= a.y && a.x >= a.z, a.y >= a.z) {
+            (true, _) => 0,
+            (false, true) => 1,
+            (false, false) => 2,
+        };
+        Self::from_axis(axis, v[axis] >= 0.0)
+    }
 }
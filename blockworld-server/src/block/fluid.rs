@@ -0,0 +1,608 @@
+//! ```text
+//! package net.minecraft.fluid
+//! class FlowingFluid, WaterFluid, LavaFluid, block.FlowingFluidBlock
+//! version 1.16
+//! ```
+//!
+//! Water and lava. A fluid block keeps how far it is from its source in its `level`: 0 for the
+//! source, 1 to 7 flowing away from it, a step lower each block, and 8 falling. In a tick a
+//! flowing block works out its level again from the fluid beside and above it, drying up with
+//! nothing left to feed it, then the fluid spreads: down if it can, and else sideways, only
+//! towards the nearest drop within a few blocks if there is one. Water flows a level per block
+//! every 5 ticks and looks 4 blocks for a drop; lava two levels per block every 30 ticks and
+//! looks 2. Flowing water between two sources over something solid becomes a source itself.
+//!
+//! Lava touching water from above or a side hardens, a source to obsidian and flowing lava to
+//! cobblestone, and lava flowing down into water turns it to stone.
+
+use glam::*;
+
+use crate::world::{
+    block_tick::BlockTickContext,
+    chunk::SUBCHUNK_SIZE,
+    chunk_access::{BlockPos, WorldAccess},
+};
+
+use super::{
+    behaviour::BlockBehaviour, block_face_direction::BlockFaceDirection, shape::collision_shape,
+    state::BlockState,
+};
+
+/// The directions fluids spread sideways in: north, east, south, west.
+const HORIZONTAL: [BlockFaceDirection; 4] = [
+    BlockFaceDirection::ZN,
+    BlockFaceDirection::XP,
+    BlockFaceDirection::ZP,
+    BlockFaceDirection::XN,
+];
+
+/// Farther than any drop is looked for.
+const NO_DROP: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FluidKind {
+    Water,
+    Lava,
+}
+
+impl FluidKind {
+    /// The kind of fluid `state` is, if it is one.
+    pub fn of(state: BlockState) -> Option<Self> {
+        match &**state.name() {
+            "minecraft:water" => Some(Self::Water),
+            "minecraft:lava" => Some(Self::Lava),
+            _ => None,
+        }
+    }
+
+    pub fn block(self) -> &'static str {
+        match self {
+            Self::Water => "minecraft:water",
+            Self::Lava => "minecraft:lava",
+        }
+    }
+
+    /// Ticks between two steps of the fluid spreading.
+    pub fn tick_delay(self) -> u64 {
+        match self {
+            Self::Water => 5,
+            Self::Lava => 30,
+        }
+    }
+
+    /// Levels lost per block flowed sideways.
+    fn drop_off(self) -> u8 {
+        match self {
+            Self::Water => 1,
+            Self::Lava => 2,
+        }
+    }
+
+    /// How many blocks away a drop is looked for.
+    fn slope_find_distance(self) -> u32 {
+        match self {
+            Self::Water => 4,
+            Self::Lava => 2,
+        }
+    }
+
+    /// Two sources make a third between them.
+    fn makes_sources(self) -> bool {
+        self == Self::Water
+    }
+}
+
+/// The fluid in a block, as Minecraft counts it: an amount from 8 for a source or falling
+/// fluid down to 1 for the last block flowed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FluidState {
+    pub kind: FluidKind,
+    pub amount: u8,
+    pub source: bool,
+    pub falling: bool,
+}
+
+impl FluidState {
+    pub fn source(kind: FluidKind) -> Self {
+        Self {
+            kind,
+            amount: 8,
+            source: true,
+            falling: false,
+        }
+    }
+
+    pub fn flowing(kind: FluidKind, amount: u8, falling: bool) -> Self {
+        Self {
+            kind,
+            amount,
+            source: false,
+            falling,
+        }
+    }
+
+    /// The fluid in `state`, if it's a fluid block.
+    pub fn of(state: BlockState) -> Option<Self> {
+        let kind = FluidKind::of(state)?;
+        Some(match state.get_int("level") {
+            0 => Self::source(kind),
+            level @ 1..=7 => Self::flowing(kind, 8 - level as u8, false),
+            _ => Self::flowing(kind, 8, true),
+        })
+    }
+
+    /// The block holding this fluid.
+    pub fn block_state(self) -> BlockState {
+        let level = match (self.source, self.falling) {
+            (true, _) => 0,
+            (false, true) => 8,
+            (false, false) => 8 - self.amount as u32,
+        };
+        BlockState::of(&self.kind.block().into()).with_int("level", level)
+    }
+
+    /// How high the fluid stands in its block, from 0 to 1.
+    pub fn height(self) -> f32 {
+        self.amount as f32 / 9.0
+    }
+}
+
+/// How high the surface of the fluid in `state` is drawn, with `above` on top of it: to the
+/// top of the block under more of the same fluid, else by its amount. 0 if it's no fluid.
+pub fn fluid_height(state: BlockState, above: BlockState) -> f32 {
+    match FluidState::of(state) {
+        Some(fluid) if FluidKind::of(above) == Some(fluid.kind) => 1.0,
+        Some(fluid) => fluid.height(),
+        None => 0.0,
+    }
+}
+
+/// A fluid can flow into the block at `pos`: air or another fluid, where the chunk is loaded.
+fn can_hold_fluid<W: WorldAccess>(world: &W, pos: BlockPos) -> bool {
+    let state = world.get_state(pos);
+    let loaded = world.is_chunk_loaded(pos.div_euclid(IVec3::splat(SUBCHUNK_SIZE as i32)));
+    loaded && (state.is_air() || FluidKind::of(state).is_some())
+}
+
+/// Water and lava blocks, flowing as [`FlowingFluid`](self) does.
+pub struct LiquidBlock(pub FluidKind);
+
+impl LiquidBlock {
+    fn is_source(&self, state: BlockState) -> bool {
+        FluidState::of(state).is_some_and(|f| f.kind == self.0 && f.source)
+    }
+
+    /// The fluid can look for a way through `pos`, which isn't a source of its own.
+    fn can_pass_through<W: WorldAccess>(&self, world: &W, pos: BlockPos) -> bool {
+        !self.is_source(world.get_state(pos)) && can_hold_fluid(world, pos)
+    }
+
+    /// Falling into the block under `pos` is a drop for the fluid.
+    fn is_hole<W: WorldAccess>(&self, world: &W, pos: BlockPos) -> bool {
+        let below = pos - IVec3::Y;
+        FluidKind::of(world.get_state(below)) == Some(self.0) || can_hold_fluid(world, below)
+    }
+
+    /// The fluid flowing towards `direction` may take the place of `target`: air, or water
+    /// under lava.
+    fn can_spread_to(&self, target: BlockState, direction: BlockFaceDirection) -> bool {
+        match FluidKind::of(target) {
+            None => target.is_air(),
+            Some(FluidKind::Water) => {
+                self.0 == FluidKind::Lava && direction == BlockFaceDirection::YN
+            }
+            Some(FluidKind::Lava) => false,
+        }
+    }
+
+    fn source_neighbours<W: WorldAccess>(&self, world: &W, pos: BlockPos) -> usize {
+        HORIZONTAL
+            .iter()
+            .filter(|d| self.is_source(world.get_state(pos + d.to_vec())))
+            .count()
+    }
+
+    /// The fluid that should be at `pos` given the fluid around it, `None` for none.
+    fn new_liquid<W: WorldAccess>(&self, world: &W, pos: BlockPos) -> Option<FluidState> {
+        let mut amount = 0;
+        let mut sources = 0;
+        for direction in HORIZONTAL {
+            let Some(fluid) = FluidState::of(world.get_state(pos + direction.to_vec())) else {
+                continue;
+            };
+            if fluid.kind == self.0 {
+                sources += fluid.source as usize;
+                amount = amount.max(fluid.amount);
+            }
+        }
+        if self.0.makes_sources() && sources >= 2 {
+            let below = world.get_state(pos - IVec3::Y);
+            if !collision_shape(below).is_empty() || self.is_source(below) {
+                return Some(FluidState::source(self.0));
+            }
+        }
+        if FluidKind::of(world.get_state(pos + IVec3::Y)) == Some(self.0) {
+            return Some(FluidState::flowing(self.0, 8, true));
+        }
+        let amount = amount.saturating_sub(self.0.drop_off());
+        (amount > 0).then(|| FluidState::flowing(self.0, amount, false))
+    }
+
+    /// How many blocks from `pos` the nearest drop is, not going back towards `from`, or
+    /// [`NO_DROP`] if there's none in reach.
+    fn slope_distance<W: WorldAccess>(
+        &self,
+        world: &W,
+        pos: BlockPos,
+        depth: u32,
+        from: BlockFaceDirection,
+    ) -> u32 {
+        let mut nearest = NO_DROP;
+        for direction in HORIZONTAL {
+            if direction == from {
+                continue;
+            }
+            let next = pos + direction.to_vec();
+            if !self.can_pass_through(world, next) {
+                continue;
+            }
+            if self.is_hole(world, next) {
+                return depth;
+            }
+            if depth < self.0.slope_find_distance() {
+                nearest =
+                    nearest.min(self.slope_distance(world, next, depth + 1, direction.opposite()));
+            }
+        }
+        nearest
+    }
+
+    /// Where the fluid at `pos` flows sideways and what it becomes there: every way it can if
+    /// there's no drop near, else the ways towards the nearest ones.
+    fn spread_targets<W: WorldAccess>(
+        &self,
+        world: &W,
+        pos: BlockPos,
+    ) -> Vec<(BlockFaceDirection, FluidState)> {
+        let mut nearest = NO_DROP;
+        let mut targets = vec![];
+        for direction in HORIZONTAL {
+            let next = pos + direction.to_vec();
+            if !self.can_pass_through(world, next) {
+                continue;
+            }
+            let Some(fluid) = self.new_liquid(world, next) else {
+                continue;
+            };
+            let distance = match self.is_hole(world, next) {
+                true => 0,
+                false => self.slope_distance(world, next, 1, direction.opposite()),
+            };
+            if distance < nearest {
+                targets.clear();
+            }
+            if distance <= nearest {
+                targets.push((direction, fluid));
+                nearest = distance;
+            }
+        }
+        targets
+    }
+
+    fn spread_to(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        direction: BlockFaceDirection,
+        fluid: FluidState,
+    ) {
+        let water = FluidKind::of(ctx.chunks.get_state(pos)) == Some(FluidKind::Water);
+        if self.0 == FluidKind::Lava && direction == BlockFaceDirection::YN && water {
+            ctx.chunks.set_block(pos, &"minecraft:stone".into());
+            return;
+        }
+        ctx.chunks.set_state(pos, fluid.block_state());
+    }
+
+    fn spread_to_sides(&self, ctx: &mut BlockTickContext, pos: BlockPos, fluid: FluidState) {
+        let amount = match fluid.falling {
+            true => 7,
+            false => fluid.amount.saturating_sub(self.0.drop_off()),
+        };
+        if amount == 0 {
+            return;
+        }
+        for (direction, new) in self.spread_targets(&*ctx.chunks, pos) {
+            let target = pos + direction.to_vec();
+            if self.can_spread_to(ctx.chunks.get_state(target), direction) {
+                self.spread_to(ctx, target, direction, new);
+            }
+        }
+    }
+
+    fn spread(&self, ctx: &mut BlockTickContext, pos: BlockPos, fluid: FluidState) {
+        let below = pos - IVec3::Y;
+        let under = ctx.chunks.get_state(below);
+        match self.new_liquid(&*ctx.chunks, below) {
+            Some(new) if self.can_spread_to(under, BlockFaceDirection::YN) => {
+                self.spread_to(ctx, below, BlockFaceDirection::YN, new);
+                if self.source_neighbours(&*ctx.chunks, pos) >= 3 {
+                    self.spread_to_sides(ctx, pos, fluid);
+                }
+            }
+            _ if fluid.source || !self.is_hole(&*ctx.chunks, pos) => {
+                self.spread_to_sides(ctx, pos, fluid)
+            }
+            _ => {}
+        }
+    }
+
+    /// Lava beside water hardens. False if the block at `pos` did.
+    fn react(&self, ctx: &mut BlockTickContext, pos: BlockPos, state: BlockState) -> bool {
+        if self.0 != FluidKind::Lava {
+            return true;
+        }
+        let sides = [
+            BlockFaceDirection::YP,
+            BlockFaceDirection::ZN,
+            BlockFaceDirection::ZP,
+            BlockFaceDirection::XN,
+            BlockFaceDirection::XP,
+        ];
+        for direction in sides {
+            let neighbour = ctx.chunks.get_state(pos + direction.to_vec());
+            if FluidKind::of(neighbour) == Some(FluidKind::Water) {
+                let block = match self.is_source(state) {
+                    true => "minecraft:obsidian",
+                    false => "minecraft:cobblestone",
+                };
+                ctx.chunks.set_block(pos, &block.into());
+                return false;
+            }
+        }
+        true
+    }
+
+    fn schedule(&self, ctx: &mut BlockTickContext, pos: BlockPos, state: BlockState) {
+        if self.react(ctx, pos, state) {
+            ctx.schedule_tick(pos, state.name(), self.0.tick_delay(), 0);
+        }
+    }
+}
+
+impl BlockBehaviour for LiquidBlock {
+    fn on_place(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        state: BlockState,
+        _old: BlockState,
+    ) {
+        self.schedule(ctx, pos, state);
+    }
+
+    fn neighbor_changed(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        state: BlockState,
+        _from: BlockPos,
+    ) {
+        self.schedule(ctx, pos, state);
+    }
+
+    fn tick(&self, ctx: &mut BlockTickContext, pos: BlockPos) {
+        let Some(mut fluid) = FluidState::of(ctx.chunks.get_state(pos)) else {
+            return;
+        };
+        if !fluid.source {
+            match self.new_liquid(&*ctx.chunks, pos) {
+                None => {
+                    ctx.chunks.set_state(pos, BlockState::AIR);
+                    return;
+                }
+                Some(new) if new != fluid => {
+                    fluid = new;
+                    ctx.chunks.set_state(pos, new.block_state());
+                    ctx.schedule_tick(pos, &self.0.block().into(), self.0.tick_delay(), 0);
+                }
+                Some(_) => {}
+            }
+        }
+        self.spread(ctx, pos, fluid);
+    }
+
+    /// Fluids leave nothing behind.
+    fn spawn_drops(&self, _ctx: &mut BlockTickContext, _pos: BlockPos, _state: BlockState) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Blockworld;
+
+    /// Two loaded sections with a stone floor at y 64, where a space in a row of `floor` is a
+    /// hole down to another floor at 62, and on it at 65 the blocks of `layer`: `W` a water
+    /// source, `L` a lava source, `#` stone.
+    fn fixture(floor: &[&str], layer: &[&str]) -> Blockworld {
+        let mut world = Blockworld::new();
+        let chunks = world.chunks_mut();
+        chunks.load_chunk(ivec3(0, 3, 0));
+        chunks.load_chunk(ivec3(0, 4, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                chunks.set_block(ivec3(x, 62, z), &"minecraft:stone".into());
+                let hole = floor
+                    .get(z as usize)
+                    .and_then(|r| r.chars().nth(x as usize))
+                    == Some(' ');
+                if !hole {
+                    chunks.set_block(ivec3(x, 64, z), &"minecraft:stone".into());
+                }
+            }
+        }
+        for (z, row) in layer.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let block = match c {
+                    'W' => "minecraft:water",
+                    'L' => "minecraft:lava",
+                    '#' => "minecraft:stone",
+                    _ => continue,
+                };
+                chunks.set_block(ivec3(x as i32, 65, z as i32), &block.into());
+            }
+        }
+        world
+    }
+
+    /// The blocks at height `y` in the first `width` by `depth` columns: water by its level,
+    /// falling water `v`, lava by a letter, `A` for its source and on to `G` for level 6, `.`
+    /// air, `#` stone, `c` cobblestone and `o` obsidian.
+    fn levels(world: &Blockworld, y: i32, width: i32, depth: i32) -> Vec<String> {
+        (0..depth)
+            .map(|z| {
+                (0..width)
+                    .map(|x| {
+                        let state = world.chunks().get_state(ivec3(x, y, z));
+                        let level = state.get_int("level").min(8);
+                        match (FluidKind::of(state), &**state.name()) {
+                            (Some(FluidKind::Water), _) if level == 8 => 'v',
+                            (Some(FluidKind::Water), _) => char::from_digit(level, 10).unwrap(),
+                            (Some(FluidKind::Lava), _) if level == 8 => 'V',
+                            (Some(FluidKind::Lava), _) => (b'A' + level as u8) as char,
+                            (_, "minecraft:air") => '.',
+                            (_, "minecraft:cobblestone") => 'c',
+                            (_, "minecraft:obsidian") => 'o',
+                            _ => '#',
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn water_spreads_a_level_per_block_on_flat_ground() {
+        let mut world = fixture(&[], &["", "", "", "", "", "", "", "", "........W"]);
+        world.step(1);
+        assert_eq!(levels(&world, 65, 16, 1), ["................"]);
+        // a block further every 5 ticks
+        world.step(10);
+        assert_eq!(levels(&world, 65, 16, 17)[8], "......21012.....");
+        world.step(60);
+        assert_eq!(
+            levels(&world, 65, 16, 17),
+            [
+                "................",
+                "........7.......",
+                ".......767......",
+                "......76567.....",
+                ".....7654567....",
+                "....765434567...",
+                "...76543234567..",
+                "..7654321234567.",
+                ".765432101234567",
+                "..7654321234567.",
+                "...76543234567..",
+                "....765434567...",
+                ".....7654567....",
+                "......76567.....",
+                ".......767......",
+                "........7.......",
+                "................",
+            ]
+        );
+
+        // and dries up once the source is gone
+        world
+            .chunks_mut()
+            .set_block(ivec3(8, 65, 8), &"minecraft:air".into());
+        world.step(100);
+        assert!(levels(&world, 65, 16, 16)
+            .iter()
+            .all(|r| r == "................"));
+    }
+
+    #[test]
+    fn water_flows_towards_the_nearest_drop() {
+        let mut world = fixture(
+            &["", "", "", "", "", "", "", "", "###### ###", "", ""],
+            &["", "", "", "", "", "", "", "", "...W......"],
+        );
+        world.step(60);
+        // only east, where the hole is three blocks away, then down it and out at the bottom
+        assert_eq!(
+            levels(&world, 65, 10, 11),
+            [
+                "..........",
+                "..........",
+                "..........",
+                "..........",
+                "..........",
+                "..........",
+                "..........",
+                "..........",
+                "...0123...",
+                "..........",
+                "..........",
+            ]
+        );
+        assert_eq!(levels(&world, 64, 10, 11)[8], "######v###");
+        assert_eq!(levels(&world, 63, 10, 11)[8], "654321v123");
+    }
+
+    #[test]
+    fn water_between_two_sources_becomes_one() {
+        let mut world = fixture(&[], &["W.W"]);
+        world.step(20);
+        assert_eq!(levels(&world, 65, 3, 1), ["000"]);
+        // not over air
+        let mut world = fixture(&["# #"], &["W.W"]);
+        world.step(20);
+        assert_eq!(levels(&world, 65, 3, 1), ["010"]);
+    }
+
+    #[test]
+    fn lava_is_slower_and_shorter() {
+        let mut world = fixture(&[], &["L"]);
+        world.step(31);
+        assert_eq!(levels(&world, 65, 5, 1), ["AC..."]);
+        world.step(150);
+        assert_eq!(levels(&world, 65, 5, 3), ["ACEG.", "CEG..", "EG..."]);
+    }
+
+    #[test]
+    fn lava_and_water_make_stone() {
+        // flowing lava meeting water turns to cobblestone, the source to obsidian
+        let mut world = fixture(&[], &["L.........W"]);
+        world.step(100);
+        assert_eq!(levels(&world, 65, 11, 1), ["ACc76543210"]);
+        let mut world = fixture(&[], &["LW"]);
+        world.step(1);
+        assert_eq!(levels(&world, 65, 2, 1), ["o0"]);
+
+        // lava flowing down into water makes stone
+        let mut world = fixture(&[], &["W"]);
+        world
+            .chunks_mut()
+            .set_block(ivec3(0, 67, 0), &"minecraft:lava".into());
+        world.step(31);
+        assert_eq!(levels(&world, 66, 1, 1), ["V"]);
+        world.step(40);
+        assert_eq!(levels(&world, 65, 1, 1), ["#"]);
+    }
+
+    #[test]
+    fn levels_round_trip_through_states() {
+        for level in 0..=8 {
+            let state = BlockState::of(&"minecraft:water".into()).with_int("level", level);
+            assert_eq!(FluidState::of(state).unwrap().block_state(), state);
+        }
+        let falling = BlockState::of(&"minecraft:lava".into()).with_int("level", 12);
+        assert!(FluidState::of(falling).unwrap().falling);
+        let source = BlockState::of(&"minecraft:water".into());
+        assert_eq!(fluid_height(source, BlockState::AIR), 8.0 / 9.0);
+        assert_eq!(fluid_height(source, source), 1.0);
+        assert_eq!(fluid_height(BlockState::AIR, source), 0.0);
+    }
+}
@@ -0,0 +1,31 @@
+//! ```text
+//! package net.minecraft.block.material
+//! class MaterialColor
+//! version 1.16
+//! ```
+
+/// The colour a block shows on top-down maps, `0xRRGGBB`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MapColor(pub u32);
+
+impl MapColor {
+    /// Not drawn at all, the map looks through it.
+    pub const NONE: MapColor = MapColor(0x000000);
+    pub const GRASS: MapColor = MapColor(0x7fb238);
+    pub const SAND: MapColor = MapColor(0xf7e9a3);
+    pub const STONE: MapColor = MapColor(0x707070);
+    pub const WATER: MapColor = MapColor(0x4040ff);
+    pub const DIRT: MapColor = MapColor(0x976d4d);
+    pub const WOOD: MapColor = MapColor(0x8f7748);
+    pub const FIRE: MapColor = MapColor(0xff0000);
+    pub const BLACK: MapColor = MapColor(0x191919);
+
+    pub fn is_none(&self) -> bool {
+        *self == Self::NONE
+    }
+
+    pub fn rgb(&self) -> [u8; 3] {
+        let [_, r, g, b] = self.0.to_be_bytes();
+        [r, g, b]
+    }
+}
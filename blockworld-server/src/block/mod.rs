@@ -1,15 +1,161 @@
+pub mod behaviour;
 pub mod block;
 pub mod block_face_direction;
+pub mod fluid;
+pub mod map_color;
+pub mod redstone;
+pub mod shape;
+pub mod state;
+use behaviour::{Connecting, Falling, Grass};
 pub use block::*;
 use blockworld_utils::Registry;
+use fluid::{FluidKind, LiquidBlock};
+use map_color::MapColor;
 use once_cell::sync::Lazy;
+use redstone::{
+    lever::{Button, Lever},
+    piston::{Piston, PistonHead},
+    repeater::Repeater,
+    torch::RedstoneTorch,
+    wire::RedstoneWire,
+};
+use state::{
+    DELAY, EAST, EAST_WIRE, EXTENDED, FACE, FACING, HORIZONTAL_FACING, LEVEL, LIT, LOCKED, NORTH,
+    NORTH_WIRE, POWER, POWERED, SOUTH, SOUTH_WIRE, WEST, WEST_WIRE,
+};
 
 pub static BLOCK_REGISTRY: Lazy<Registry<Block>> = Lazy::new(|| {
     let mut r = Registry::new();
     let a0 = Block::new("minecraft:air".into());
     r.register(a0);
-    let a1 = Block::new("minecraft:stone".into());
+    let a1 = Block::new("minecraft:stone".into())
+        .with_map_color(MapColor::STONE)
+        .with_hardness(1.5)
+        .with_required_tool(ToolKind::Pickaxe);
     r.register(a1);
+    let a2 = Block::new("minecraft:grass_block".into())
+        .with_map_color(MapColor::GRASS)
+        .with_hardness(0.6)
+        .with_tool(ToolKind::Shovel)
+        .with_behaviour(Grass);
+    r.register(a2);
+    let a3 = Block::new("minecraft:dirt".into())
+        .with_map_color(MapColor::DIRT)
+        .with_hardness(0.5)
+        .with_tool(ToolKind::Shovel);
+    r.register(a3);
+    let a4 = Block::new("minecraft:water".into())
+        .with_map_color(MapColor::WATER)
+        .with_hardness(-1.0)
+        .with_behaviour(LiquidBlock(FluidKind::Water))
+        .with_push_reaction(PushReaction::Destroy)
+        .with_property(LEVEL);
+    r.register(a4);
+    let a5 = Block::new("minecraft:sand".into())
+        .with_map_color(MapColor::SAND)
+        .with_hardness(0.5)
+        .with_tool(ToolKind::Shovel)
+        .with_behaviour(Falling);
+    r.register(a5);
+    let a6 = Block::new("minecraft:smooth_stone_slab".into())
+        .with_map_color(MapColor::STONE)
+        .with_hardness(2.0)
+        .with_required_tool(ToolKind::Pickaxe);
+    r.register(a6);
+    let a7 = Block::new("minecraft:oak_fence".into())
+        .with_map_color(MapColor::WOOD)
+        .with_hardness(2.0)
+        .with_tool(ToolKind::Axe)
+        .with_behaviour(Connecting { suffix: "_fence" })
+        .with_property(NORTH)
+        .with_property(EAST)
+        .with_property(SOUTH)
+        .with_property(WEST);
+    r.register(a7);
+    let a8 = Block::new("minecraft:cobblestone_wall".into())
+        .with_map_color(MapColor::STONE)
+        .with_hardness(2.0)
+        .with_required_tool(ToolKind::Pickaxe)
+        .with_behaviour(Connecting { suffix: "_wall" })
+        .with_property(NORTH)
+        .with_property(EAST)
+        .with_property(SOUTH)
+        .with_property(WEST);
+    r.register(a8);
+    let a9 = Block::new("minecraft:lava".into())
+        .with_map_color(MapColor::FIRE)
+        .with_hardness(-1.0)
+        .with_behaviour(LiquidBlock(FluidKind::Lava))
+        .with_push_reaction(PushReaction::Destroy)
+        .with_property(LEVEL);
+    r.register(a9);
+    let a10 = Block::new("minecraft:cobblestone".into())
+        .with_map_color(MapColor::STONE)
+        .with_hardness(2.0)
+        .with_required_tool(ToolKind::Pickaxe);
+    r.register(a10);
+    let a11 = Block::new("minecraft:obsidian".into())
+        .with_map_color(MapColor::BLACK)
+        .with_hardness(50.0)
+        .with_required_tool(ToolKind::Pickaxe)
+        .with_push_reaction(PushReaction::Block);
+    r.register(a11);
+    let a12 = Block::new("minecraft:redstone_wire".into())
+        .with_behaviour(RedstoneWire)
+        .with_push_reaction(PushReaction::Destroy)
+        .with_property(NORTH_WIRE)
+        .with_property(EAST_WIRE)
+        .with_property(SOUTH_WIRE)
+        .with_property(WEST_WIRE)
+        .with_property(POWER);
+    r.register(a12);
+    let a13 = Block::new("minecraft:redstone_torch".into())
+        .with_behaviour(RedstoneTorch)
+        .with_push_reaction(PushReaction::Destroy)
+        .with_property(LIT);
+    r.register(a13);
+    let a14 = Block::new("minecraft:repeater".into())
+        .with_behaviour(Repeater)
+        .with_push_reaction(PushReaction::Destroy)
+        .with_property(HORIZONTAL_FACING)
+        .with_property(DELAY)
+        .with_property(LOCKED)
+        .with_property(POWERED);
+    r.register(a14);
+    let a15 = Block::new("minecraft:lever".into())
+        .with_hardness(0.5)
+        .with_behaviour(Lever)
+        .with_push_reaction(PushReaction::Destroy)
+        .with_property(FACE)
+        .with_property(HORIZONTAL_FACING)
+        .with_property(POWERED);
+    r.register(a15);
+    let a16 = Block::new("minecraft:stone_button".into())
+        .with_hardness(0.5)
+        .with_behaviour(Button)
+        .with_push_reaction(PushReaction::Destroy)
+        .with_property(FACE)
+        .with_property(HORIZONTAL_FACING)
+        .with_property(POWERED);
+    r.register(a16);
+    let a17 = Block::new("minecraft:piston".into())
+        .with_map_color(MapColor::STONE)
+        .with_hardness(1.5)
+        .with_tool(ToolKind::Pickaxe)
+        .with_redstone_conductor(false)
+        .with_behaviour(Piston)
+        .with_property(FACING)
+        .with_property(EXTENDED);
+    r.register(a17);
+    let a18 = Block::new("minecraft:piston_head".into())
+        .with_map_color(MapColor::STONE)
+        .with_hardness(1.5)
+        .with_tool(ToolKind::Pickaxe)
+        .with_redstone_conductor(false)
+        .with_behaviour(PistonHead)
+        .with_push_reaction(PushReaction::Block)
+        .with_property(FACING);
+    r.register(a18);
 
     r
 });
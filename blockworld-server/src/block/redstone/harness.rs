@@ -0,0 +1,145 @@
+//! Circuits built from text schematics, for tests. A schematic is layers from the bottom up,
+//! each of rows from north to south, each a character a block from west to east, starting at
+//! the origin. [`LEGEND`] says which character is which block, and a circuit may add its own.
+//! The circuit runs in a world of its own with no players, a tick at a time, and
+//! [`Circuit::watch`] writes down what some blocks give out every tick.
+
+use anyhow::anyhow;
+use glam::*;
+
+use crate::{
+    block::state::BlockState,
+    world::{
+        block_update::run_block_updates,
+        chunk_access::{BlockPos, WorldAccess},
+    },
+    Blockworld,
+};
+
+use super::has_neighbour_signal;
+
+/// The blocks the characters of a schematic stand for, unless a circuit says otherwise.
+/// Repeaters point the way their arrow does, and levers and buttons stand on the floor.
+pub const LEGEND: &[(char, &str)] = &[
+    (' ', "minecraft:air"),
+    ('.', "minecraft:air"),
+    ('#', "minecraft:stone"),
+    ('-', "minecraft:redstone_wire"),
+    ('*', "minecraft:redstone_torch"),
+    ('L', "minecraft:lever[face=floor]"),
+    ('B', "minecraft:stone_button[face=floor]"),
+    ('>', "minecraft:repeater[facing=west]"),
+    ('<', "minecraft:repeater[facing=east]"),
+    ('^', "minecraft:repeater[facing=south]"),
+    ('v', "minecraft:repeater[facing=north]"),
+    ('c', "minecraft:cobblestone"),
+    ('o', "minecraft:obsidian"),
+    ('s', "minecraft:sand"),
+];
+
+/// The sections loaded around the origin, on each axis.
+const SECTIONS: std::ops::RangeInclusive<i32> = -1..=1;
+
+pub struct Circuit {
+    world: Blockworld,
+}
+
+impl Circuit {
+    /// Build the circuit in `layers`, with `legend` before [`LEGEND`], and let it settle
+    /// without time going by.
+    pub fn build(layers: &[&[&str]], legend: &[(char, &str)]) -> anyhow::Result<Self> {
+        let mut world = Blockworld::new();
+        for x in SECTIONS {
+            for y in SECTIONS {
+                for z in SECTIONS {
+                    world.chunks_mut().load_chunk(ivec3(x, y, z));
+                }
+            }
+        }
+        for (y, rows) in layers.iter().enumerate() {
+            for (z, row) in rows.iter().enumerate() {
+                for (x, c) in row.chars().enumerate() {
+                    let (_, text) = legend
+                        .iter()
+                        .chain(LEGEND)
+                        .find(|(key, _)| *key == c)
+                        .ok_or_else(|| anyhow!("No block for {:?}", c))?;
+                    let pos = ivec3(x as i32, y as i32, z as i32);
+                    world.chunks_mut().set_state(pos, BlockState::parse(text)?);
+                }
+            }
+        }
+        let mut circuit = Self { world };
+        circuit.settle();
+        Ok(circuit)
+    }
+
+    pub fn world(&self) -> &Blockworld {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut Blockworld {
+        &mut self.world
+    }
+
+    /// Run the block updates waiting.
+    fn settle(&mut self) {
+        self.world.block_context(run_block_updates);
+    }
+
+    pub fn state(&self, pos: BlockPos) -> BlockState {
+        self.world.chunks().get_state(pos)
+    }
+
+    /// Set the block written as `text` at `pos`, as in [`BlockState::parse`], and let the
+    /// circuit settle.
+    pub fn set(&mut self, pos: BlockPos, text: &str) -> anyhow::Result<()> {
+        let state = BlockState::parse(text)?;
+        self.world.chunks_mut().set_state(pos, state);
+        self.settle();
+        Ok(())
+    }
+
+    /// Use the block at `pos` as a player would, flipping a lever or pressing a button.
+    pub fn use_block(&mut self, pos: BlockPos) -> bool {
+        self.world.use_block(pos)
+    }
+
+    /// Run `ticks` ticks.
+    pub fn step(&mut self, ticks: u32) {
+        self.world.step(ticks);
+    }
+
+    /// What the block at `pos` gives out: its power in hex for wire, `1` or `0` for a torch
+    /// that's lit, a repeater, lever or button that's powered, or a piston that's extended, and
+    /// for any other block whether something powers it.
+    pub fn output(&self, pos: BlockPos) -> char {
+        let state = self.state(pos);
+        let on = match &**state.name() {
+            "minecraft:redstone_wire" => {
+                return char::from_digit(state.get_int("power"), 16).unwrap_or('?');
+            }
+            "minecraft:redstone_torch" => state.get_bool("lit"),
+            "minecraft:repeater" | "minecraft:lever" | "minecraft:stone_button" => {
+                state.get_bool("powered")
+            }
+            "minecraft:piston" => state.get_bool("extended"),
+            _ => has_neighbour_signal(self.world.chunks(), pos),
+        };
+        match on {
+            true => '1',
+            false => '0',
+        }
+    }
+
+    /// Run `ticks` ticks, writing down the [`output`](Circuit::output) of each of `probes`
+    /// after each.
+    pub fn watch(&mut self, ticks: u32, probes: &[BlockPos]) -> Vec<String> {
+        (0..ticks)
+            .map(|_| {
+                self.world.tick();
+                probes.iter().map(|&pos| self.output(pos)).collect()
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,200 @@
+//! ```text
+//! package net.minecraft.block
+//! class HorizontalFaceBlock, LeverBlock, AbstractButtonBlock, StoneButtonBlock
+//! version 1.16
+//! ```
+//!
+//! Levers and buttons, stuck to the floor, a wall or the ceiling. Powered, they give full
+//! power to every side and strongly to the block they're stuck to. A lever flips when used;
+//! a button stays pressed for a second, then turns itself off.
+
+use crate::{
+    block::{
+        behaviour::BlockBehaviour, block_face_direction::BlockFaceDirection, state::BlockState,
+    },
+    world::{
+        block_tick::BlockTickContext,
+        chunk_access::{BlockPos, WorldAccess},
+    },
+};
+
+use super::{facing, is_sturdy, MAX_POWER};
+
+/// Ticks a stone button stays pressed.
+pub const BUTTON_PRESS: u64 = 20;
+
+/// The way from the block a lever or button is stuck to towards it.
+fn connected(state: BlockState) -> BlockFaceDirection {
+    match state.get("face") {
+        Some("floor") => BlockFaceDirection::YP,
+        Some("ceiling") => BlockFaceDirection::YN,
+        _ => facing(state),
+    }
+}
+
+fn signal(state: BlockState) -> u8 {
+    match state.get_bool("powered") {
+        true => MAX_POWER,
+        false => 0,
+    }
+}
+
+fn direct_signal(state: BlockState, side: BlockFaceDirection) -> u8 {
+    match connected(state) == side {
+        true => signal(state),
+        false => 0,
+    }
+}
+
+/// Falls off when the block it's stuck to goes.
+fn attached_shape(
+    state: BlockState,
+    direction: BlockFaceDirection,
+    neighbour: BlockState,
+) -> BlockState {
+    match direction == connected(state).opposite() && !is_sturdy(neighbour) {
+        true => BlockState::AIR,
+        false => state,
+    }
+}
+
+/// Tell the blocks around the one at `pos` and around the block it's stuck to.
+fn notify(ctx: &mut BlockTickContext, pos: BlockPos, state: BlockState) {
+    ctx.chunks.updates.notify_neighbours(pos, None);
+    ctx.chunks
+        .updates
+        .notify_neighbours(pos - connected(state).to_vec(), None);
+}
+
+fn removed(ctx: &mut BlockTickContext, pos: BlockPos, state: BlockState, new: BlockState) {
+    if state.get_bool("powered") && new.block_id() != state.block_id() {
+        notify(ctx, pos, state);
+    }
+}
+
+pub struct Lever;
+
+impl BlockBehaviour for Lever {
+    fn is_signal_source(&self) -> bool {
+        true
+    }
+
+    fn signal(&self, state: BlockState, _side: BlockFaceDirection) -> u8 {
+        signal(state)
+    }
+
+    fn direct_signal(&self, state: BlockState, side: BlockFaceDirection) -> u8 {
+        direct_signal(state, side)
+    }
+
+    fn update_shape(
+        &self,
+        state: BlockState,
+        direction: BlockFaceDirection,
+        neighbour: BlockState,
+    ) -> BlockState {
+        attached_shape(state, direction, neighbour)
+    }
+
+    fn use_block(&self, ctx: &mut BlockTickContext, pos: BlockPos, state: BlockState) -> bool {
+        let state = state.with_bool("powered", !state.get_bool("powered"));
+        ctx.chunks.set_state(pos, state);
+        notify(ctx, pos, state);
+        true
+    }
+
+    fn on_remove(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        state: BlockState,
+        new: BlockState,
+    ) {
+        removed(ctx, pos, state, new);
+    }
+}
+
+pub struct Button;
+
+impl BlockBehaviour for Button {
+    fn is_signal_source(&self) -> bool {
+        true
+    }
+
+    fn signal(&self, state: BlockState, _side: BlockFaceDirection) -> u8 {
+        signal(state)
+    }
+
+    fn direct_signal(&self, state: BlockState, side: BlockFaceDirection) -> u8 {
+        direct_signal(state, side)
+    }
+
+    fn update_shape(
+        &self,
+        state: BlockState,
+        direction: BlockFaceDirection,
+        neighbour: BlockState,
+    ) -> BlockState {
+        attached_shape(state, direction, neighbour)
+    }
+
+    /// Pressing a pressed button does nothing.
+    fn use_block(&self, ctx: &mut BlockTickContext, pos: BlockPos, state: BlockState) -> bool {
+        if state.get_bool("powered") {
+            return true;
+        }
+        let state = state.with_bool("powered", true);
+        ctx.chunks.set_state(pos, state);
+        notify(ctx, pos, state);
+        ctx.schedule_tick(pos, state.name(), BUTTON_PRESS, 0);
+        true
+    }
+
+    fn on_remove(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        state: BlockState,
+        new: BlockState,
+    ) {
+        removed(ctx, pos, state, new);
+    }
+
+    fn tick(&self, ctx: &mut BlockTickContext, pos: BlockPos) {
+        let state = ctx.chunks.get_state(pos);
+        if state.get_bool("powered") {
+            let state = state.with_bool("powered", false);
+            ctx.chunks.set_state(pos, state);
+            notify(ctx, pos, state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+
+    use crate::block::redstone::harness::Circuit;
+
+    #[test]
+    fn levers_stay_and_buttons_spring_back() {
+        let mut circuit = Circuit::build(&[&["####"], &["L-B-"]], &[]).unwrap();
+        let probes = [ivec3(1, 1, 0), ivec3(3, 1, 0)];
+        circuit.use_block(ivec3(0, 1, 0));
+        assert_eq!(circuit.watch(30, &probes), ["f0"; 30]);
+        circuit.use_block(ivec3(0, 1, 0));
+        circuit.use_block(ivec3(2, 1, 0));
+        let watched = circuit.watch(21, &probes);
+        assert_eq!(watched[..20], ["ff"; 20]);
+        assert_eq!(watched[20], "00");
+    }
+
+    #[test]
+    fn levers_fall_off_without_their_block() {
+        let mut circuit =
+            Circuit::build(&[&["#"], &["#L"]], &[('L', "lever[face=wall,facing=east]")]).unwrap();
+        circuit.use_block(ivec3(1, 1, 0));
+        circuit.set(ivec3(0, 1, 0), "air").unwrap();
+        assert!(circuit.state(ivec3(1, 1, 0)).is_air());
+    }
+}
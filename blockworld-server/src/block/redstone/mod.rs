@@ -0,0 +1,175 @@
+//! ```text
+//! package net.minecraft.world
+//! class World (getSignal, getDirectSignalTo, hasNeighborSignal), block.RedstoneWireBlock
+//! version 1.16
+//! ```
+//!
+//! Redstone: wire, torches, repeaters, levers, buttons and pistons, working as in Minecraft.
+//! A signal source gives each neighbour some power from 0 to 15 through
+//! [`signal`](super::behaviour::BlockBehaviour::signal), and some of them power the block
+//! they point into or sit on strongly through
+//! [`direct_signal`](super::behaviour::BlockBehaviour::direct_signal). A whole block that
+//! conducts, like stone, gives out the strongest direct power going into it to every side,
+//! but never to wire beside it, as only wire on top or pointing into a block powers it. Wire
+//! loses a level a block along the ground and up and down the sides of blocks.
+//!
+//! Pistons keep quasi-connectivity: they are powered by what would power the block over them
+//! too, but only find out when something next to the piston itself changes.
+//!
+//! The order things happen in is fixed, and circuits behave the same every time:
+//!
+//! - A block set queues its own update first, then tells its six neighbours in
+//!   [`NEIGHBOUR_ORDER`](crate::world::block_update::NEIGHBOUR_ORDER), west, east, down, up,
+//!   north, south, and queued updates run one after the other, breadth first.
+//! - Wire that changes power sets itself without notifying, then notifies around itself and
+//!   around each of its six neighbours, in that order. Minecraft picks those in hash order.
+//! - Torches, repeaters and buttons act in scheduled ticks, which run by due time, then
+//!   priority, then the order they were scheduled. Repeaters go first, those feeding another
+//!   repeater before those that turn off before those that turn on, as in Minecraft.
+//! - Pistons move at once when they find out they are powered, without the two ticks
+//!   Minecraft's moving blocks take. Torches don't burn out.
+//!
+//! `harness.rs` builds circuits from text schematics for tests.
+
+use glam::*;
+
+use crate::world::chunk_access::{BlockPos, WorldAccess};
+
+use super::{
+    block_face_direction::BlockFaceDirection,
+    shape::{collision_shape, FULL_CUBE},
+    state::BlockState,
+};
+
+pub mod harness;
+pub mod lever;
+pub mod piston;
+pub mod repeater;
+pub mod torch;
+pub mod wire;
+
+/// The strongest power there is.
+pub const MAX_POWER: u8 = 15;
+
+/// Every direction, in the order Minecraft looks for power: down, up, north, south, west, east.
+const ALL: [BlockFaceDirection; 6] = [
+    BlockFaceDirection::YN,
+    BlockFaceDirection::YP,
+    BlockFaceDirection::ZN,
+    BlockFaceDirection::ZP,
+    BlockFaceDirection::XN,
+    BlockFaceDirection::XP,
+];
+
+/// The directions along the ground: north, east, south, west.
+const HORIZONTAL: [BlockFaceDirection; 4] = [
+    BlockFaceDirection::ZN,
+    BlockFaceDirection::XP,
+    BlockFaceDirection::ZP,
+    BlockFaceDirection::XN,
+];
+
+/// A whole block that passes strong power on.
+pub fn is_conductor(state: BlockState) -> bool {
+    state.block().redstone_conductor && collision_shape(state) == [FULL_CUBE]
+}
+
+/// Whole, so wire and components can stand on it.
+fn is_sturdy(state: BlockState) -> bool {
+    collision_shape(state) == [FULL_CUBE]
+}
+
+fn is_wire(state: BlockState) -> bool {
+    &**state.name() == "minecraft:redstone_wire"
+}
+
+/// The direction `state` points in by its `facing`, north if it has none.
+fn facing(state: BlockState) -> BlockFaceDirection {
+    state
+        .get("facing")
+        .and_then(BlockFaceDirection::from_name)
+        .unwrap_or(BlockFaceDirection::ZN)
+}
+
+/// Power the block at `pos` gives the block that finds it towards `side`. With `wires`
+/// false wire gives none, which is how wire works out its own power.
+fn state_signal(state: BlockState, side: BlockFaceDirection, wires: bool) -> u8 {
+    match wires || !is_wire(state) {
+        true => state.block().behaviour.signal(state, side),
+        false => 0,
+    }
+}
+
+fn state_direct_signal(state: BlockState, side: BlockFaceDirection, wires: bool) -> u8 {
+    match wires || !is_wire(state) {
+        true => state.block().behaviour.direct_signal(state, side),
+        false => 0,
+    }
+}
+
+/// The strongest power going strongly into the block at `pos`.
+pub fn direct_signal_to<W: WorldAccess>(world: &W, pos: BlockPos, wires: bool) -> u8 {
+    ALL.iter()
+        .map(|&d| state_direct_signal(world.get_state(pos + d.to_vec()), d, wires))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Power the block at `pos` gives the block that finds it towards `side`: its own, or if it's
+/// a conductor, what goes into it strongly.
+pub fn signal<W: WorldAccess>(
+    world: &W,
+    pos: BlockPos,
+    side: BlockFaceDirection,
+    wires: bool,
+) -> u8 {
+    let state = world.get_state(pos);
+    let own = state_signal(state, side, wires);
+    match is_conductor(state) {
+        true => own.max(direct_signal_to(world, pos, wires)),
+        false => own,
+    }
+}
+
+pub fn has_signal<W: WorldAccess>(world: &W, pos: BlockPos, side: BlockFaceDirection) -> bool {
+    signal(world, pos, side, true) > 0
+}
+
+/// The strongest power any neighbour gives the block at `pos`.
+pub fn best_neighbour_signal<W: WorldAccess>(world: &W, pos: BlockPos, wires: bool) -> u8 {
+    ALL.iter()
+        .map(|&d| signal(world, pos + d.to_vec(), d, wires))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Some neighbour powers the block at `pos`.
+pub fn has_neighbour_signal<W: WorldAccess>(world: &W, pos: BlockPos) -> bool {
+    ALL.iter().any(|&d| has_signal(world, pos + d.to_vec(), d))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{harness::Circuit, *};
+
+    #[test]
+    fn conductors_pass_strong_power_on() {
+        // a lever on stone powers the stone strongly, and the stone powers what's around it
+        let mut circuit =
+            Circuit::build(&[&["#", ""], &["L", "#"]], &[('L', "lever[face=floor]")]).unwrap();
+        let stone = ivec3(0, 0, 0);
+        assert!(!has_neighbour_signal(
+            circuit.world().chunks(),
+            stone + IVec3::Z
+        ));
+        circuit.use_block(ivec3(0, 1, 0));
+        let chunks = circuit.world().chunks();
+        assert_eq!(direct_signal_to(chunks, stone, true), MAX_POWER);
+        assert!(has_neighbour_signal(chunks, stone + IVec3::Z));
+        // the stone beside the lever only gets weak power, and passes none on
+        let beside = ivec3(0, 1, 1);
+        assert!(has_neighbour_signal(chunks, beside));
+        assert_eq!(direct_signal_to(chunks, beside, true), 0);
+        assert!(!has_neighbour_signal(chunks, beside + IVec3::Z));
+    }
+}
@@ -0,0 +1,296 @@
+//! ```text
+//! package net.minecraft.block
+//! class PistonBlock, PistonHeadBlock, PistonBlockStructureHelper, material.PushReaction
+//! version 1.16
+//! ```
+//!
+//! Pistons, pushing the line of blocks in front of them one block on when powered, and pulling
+//! their head back in when not. A line longer than [`PUSH_LIMIT`], or one stopped by a block
+//! that won't move, keeps the piston from moving at all; a block that breaks when pushed ends
+//! the line, and the block before it moves into its place. Blocks move at once, the far end
+//! of the line first.
+//!
+//! A piston is powered by what would power a block over it too, quasi-connectivity, but only
+//! looks when a block next to it changes, so a lever beside the block over it switches it
+//! only once something around the piston itself changes.
+
+use glam::*;
+
+use crate::{
+    block::{
+        behaviour::BlockBehaviour, block::PushReaction, block_face_direction::BlockFaceDirection,
+        state::BlockState,
+    },
+    world::{
+        block_tick::BlockTickContext,
+        chunk::SUBCHUNK_SIZE,
+        chunk_access::{BlockPos, WorldAccess},
+    },
+};
+
+use super::{facing, has_signal, ALL};
+
+/// The most blocks a piston moves.
+pub const PUSH_LIMIT: usize = 12;
+
+pub struct Piston;
+
+pub struct PistonHead;
+
+fn is_piston(state: BlockState) -> bool {
+    &**state.name() == "minecraft:piston"
+}
+
+fn is_head(state: BlockState) -> bool {
+    &**state.name() == "minecraft:piston_head"
+}
+
+/// What pushing `state` does. Extended pistons and blocks that can't be broken stay put.
+pub fn push_reaction(state: BlockState) -> PushReaction {
+    let block = state.block();
+    if is_piston(state) && state.get_bool("extended") {
+        return PushReaction::Block;
+    }
+    match block.push_reaction {
+        PushReaction::Normal if block.hardness < 0.0 => PushReaction::Block,
+        reaction => reaction,
+    }
+}
+
+/// Something powers the piston at `pos` facing `facing`, or would power the block over it.
+fn powered<W: WorldAccess>(world: &W, pos: BlockPos, facing: BlockFaceDirection) -> bool {
+    if ALL
+        .iter()
+        .any(|&d| d != facing && has_signal(world, pos + d.to_vec(), d))
+    {
+        return true;
+    }
+    if has_signal(world, pos, BlockFaceDirection::YN) {
+        return true;
+    }
+    let above = pos + IVec3::Y;
+    ALL.iter()
+        .any(|&d| d != BlockFaceDirection::YN && has_signal(world, above + d.to_vec(), d))
+}
+
+/// The blocks the piston at `pos` moves towards `facing`, nearest first, and the block it
+/// breaks at the end of them, if any. `None` if it can't move.
+fn resolve<W: WorldAccess>(
+    world: &W,
+    pos: BlockPos,
+    facing: BlockFaceDirection,
+) -> Option<(Vec<BlockPos>, Option<BlockPos>)> {
+    let mut line = vec![];
+    let mut at = pos + facing.to_vec();
+    loop {
+        // pushing into chunks that aren't loaded would lose the blocks
+        if !world.is_chunk_loaded(at.div_euclid(IVec3::splat(SUBCHUNK_SIZE as i32))) {
+            return None;
+        }
+        let state = world.get_state(at);
+        if state.is_air() {
+            return Some((line, None));
+        }
+        match push_reaction(state) {
+            PushReaction::Destroy => return Some((line, Some(at))),
+            PushReaction::Block => return None,
+            PushReaction::Normal if line.len() >= PUSH_LIMIT => return None,
+            PushReaction::Normal => line.push(at),
+        }
+        at += facing.to_vec();
+    }
+}
+
+impl Piston {
+    fn check(&self, ctx: &mut BlockTickContext, pos: BlockPos, state: BlockState) {
+        let facing = facing(state);
+        let powered = powered(&*ctx.chunks, pos, facing);
+        match (powered, state.get_bool("extended")) {
+            (true, false) => self.extend(ctx, pos, state, facing),
+            (false, true) => self.retract(ctx, pos, state, facing),
+            _ => {}
+        }
+    }
+
+    fn extend(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        state: BlockState,
+        facing: BlockFaceDirection,
+    ) {
+        let Some((line, broken)) = resolve(&*ctx.chunks, pos, facing) else {
+            return;
+        };
+        if let Some(at) = broken {
+            let broken = ctx.chunks.get_state(at);
+            broken.block().behaviour.spawn_drops(ctx, at, broken);
+            ctx.chunks.set_state(at, BlockState::AIR);
+        }
+        let moved: Vec<BlockState> = line.iter().map(|&at| ctx.chunks.get_state(at)).collect();
+        for (at, moved) in line.into_iter().zip(moved).rev() {
+            ctx.chunks.set_state(at + facing.to_vec(), moved);
+        }
+        let head = BlockState::of(&"minecraft:piston_head".into()).with("facing", facing.name());
+        ctx.chunks.set_state(pos + facing.to_vec(), head);
+        ctx.chunks.set_state(pos, state.with_bool("extended", true));
+    }
+
+    fn retract(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        state: BlockState,
+        facing: BlockFaceDirection,
+    ) {
+        ctx.chunks
+            .set_state(pos, state.with_bool("extended", false));
+        let front = pos + facing.to_vec();
+        if is_head(ctx.chunks.get_state(front)) {
+            ctx.chunks.set_state(front, BlockState::AIR);
+        }
+    }
+}
+
+impl BlockBehaviour for Piston {
+    /// An extended piston breaks without its head.
+    fn update_shape(
+        &self,
+        state: BlockState,
+        direction: BlockFaceDirection,
+        neighbour: BlockState,
+    ) -> BlockState {
+        let facing = facing(state);
+        let headed = is_head(neighbour) && super::facing(neighbour) == facing;
+        match state.get_bool("extended") && direction == facing && !headed {
+            true => BlockState::AIR,
+            false => state,
+        }
+    }
+
+    fn on_place(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        state: BlockState,
+        old: BlockState,
+    ) {
+        if !is_piston(old) {
+            self.check(ctx, pos, state);
+        }
+    }
+
+    fn neighbor_changed(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        state: BlockState,
+        _from: BlockPos,
+    ) {
+        self.check(ctx, pos, state);
+    }
+}
+
+impl BlockBehaviour for PistonHead {
+    /// A head breaks without its piston.
+    fn update_shape(
+        &self,
+        state: BlockState,
+        direction: BlockFaceDirection,
+        neighbour: BlockState,
+    ) -> BlockState {
+        let facing = facing(state);
+        let based = is_piston(neighbour)
+            && neighbour.get_bool("extended")
+            && super::facing(neighbour) == facing;
+        match direction == facing.opposite() && !based {
+            true => BlockState::AIR,
+            false => state,
+        }
+    }
+
+    /// The piston drops, not its head.
+    fn spawn_drops(&self, _ctx: &mut BlockTickContext, _pos: BlockPos, _state: BlockState) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::redstone::harness::Circuit;
+
+    /// The row at `y` and `z` from `x` 0 on: `L` a lever, `P` a piston, `H` its head, `c`
+    /// cobblestone, `-` redstone wire, `o` obsidian and `.` air.
+    fn row(circuit: &Circuit, y: i32, z: i32, length: i32) -> String {
+        (0..length)
+            .map(|x| match &**circuit.state(ivec3(x, y, z)).name() {
+                "minecraft:piston" => 'P',
+                "minecraft:piston_head" => 'H',
+                "minecraft:cobblestone" => 'c',
+                "minecraft:redstone_wire" => '-',
+                "minecraft:obsidian" => 'o',
+                "minecraft:lever" => 'L',
+                _ => '.',
+            })
+            .collect()
+    }
+
+    fn pushed(line: &str) -> String {
+        let mut circuit = Circuit::build(
+            &[&["################"], &[line]],
+            &[('L', "lever[face=floor]"), ('P', "piston[facing=east]")],
+        )
+        .unwrap();
+        circuit.use_block(ivec3(0, 1, 0));
+        row(&circuit, 1, 0, 16)
+    }
+
+    #[test]
+    fn pistons_push_twelve_blocks() {
+        assert_eq!(pushed("LPcccccccccccc"), "LPHcccccccccccc.");
+        // thirteen is too many, and obsidian doesn't move
+        assert_eq!(pushed("LPccccccccccccc"), "LPccccccccccccc.");
+        assert_eq!(pushed("LPccco"), "LPccco..........");
+        // wire in the way breaks
+        assert_eq!(pushed("LPccc-c"), "LPHcccc.........");
+    }
+
+    #[test]
+    fn pistons_pull_their_head_back() {
+        let mut circuit = Circuit::build(
+            &[&["#####"], &["LPc"]],
+            &[('L', "lever[face=floor]"), ('P', "piston[facing=east]")],
+        )
+        .unwrap();
+        let piston = ivec3(1, 1, 0);
+        circuit.use_block(ivec3(0, 1, 0));
+        assert_eq!(circuit.output(piston), '1');
+        assert_eq!(row(&circuit, 1, 0, 5), "LPHc.");
+        circuit.use_block(ivec3(0, 1, 0));
+        assert_eq!(circuit.output(piston), '0');
+        assert_eq!(row(&circuit, 1, 0, 5), "LP.c.");
+    }
+
+    #[test]
+    fn pistons_are_quasi_connected() {
+        // the lever hangs beside the block over the piston, and tells nothing next to it
+        let mut circuit = Circuit::build(
+            &[&["###"], &[".P"], &["..C"], &["..#"]],
+            &[('P', "piston[facing=south]"), ('C', "lever[face=ceiling]")],
+        )
+        .unwrap();
+        let piston = ivec3(1, 1, 0);
+        circuit.use_block(ivec3(2, 2, 0));
+        assert_eq!(circuit.output(piston), '0');
+        // until a block beside it changes
+        circuit.set(ivec3(0, 1, 0), "stone").unwrap();
+        assert_eq!(circuit.output(piston), '1');
+        assert_eq!(
+            circuit.state(piston + IVec3::Z).to_string(),
+            "minecraft:piston_head[facing=south]"
+        );
+        circuit.use_block(ivec3(2, 2, 0));
+        assert_eq!(circuit.output(piston), '1');
+        circuit.set(ivec3(0, 1, 0), "air").unwrap();
+        assert_eq!(circuit.output(piston), '0');
+    }
+}
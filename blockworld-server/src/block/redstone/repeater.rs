@@ -0,0 +1,245 @@
+//! ```text
+//! package net.minecraft.block
+//! class RedstoneDiodeBlock, RepeaterBlock
+//! version 1.16
+//! ```
+//!
+//! A redstone repeater, taking power in at its back and giving out full power at its front
+//! after its delay of 1 to 4 redstone ticks. Its `facing` is the side its input is on. A
+//! powered repeater pointing into its side locks it, and a locked repeater keeps its output
+//! whatever its input does.
+
+use enumflags2::BitFlags;
+
+use crate::{
+    block::{
+        behaviour::BlockBehaviour, block_face_direction::BlockFaceDirection, state::BlockState,
+    },
+    world::{
+        block_tick::{BlockTickContext, TickPriority},
+        block_update::BlockUpdate,
+        chunk_access::{BlockPos, WorldAccess},
+    },
+};
+
+use super::{facing, is_sturdy, is_wire, signal, MAX_POWER};
+
+pub struct Repeater;
+
+fn is_repeater(state: BlockState) -> bool {
+    &**state.name() == "minecraft:repeater"
+}
+
+/// Ticks the repeater in `state` waits.
+fn delay(state: BlockState) -> u64 {
+    state.get_int("delay") as u64 * 2
+}
+
+/// The power going into the back of the repeater at `pos`.
+fn input<W: WorldAccess>(world: &W, pos: BlockPos, state: BlockState) -> u8 {
+    let facing = facing(state);
+    let back = pos + facing.to_vec();
+    let power = signal(world, back, facing, true);
+    let behind = world.get_state(back);
+    match is_wire(behind) {
+        true => power.max(behind.get_int("power") as u8),
+        false => power,
+    }
+}
+
+/// A repeater beside the one at `pos` is powering it from the side.
+fn locked<W: WorldAccess>(world: &W, pos: BlockPos, state: BlockState) -> bool {
+    let facing = facing(state);
+    [facing.clockwise(), facing.clockwise().opposite()]
+        .into_iter()
+        .any(|side| {
+            let beside = world.get_state(pos + side.to_vec());
+            is_repeater(beside) && beside.block().behaviour.direct_signal(beside, side) > 0
+        })
+}
+
+impl Repeater {
+    /// Tell the block in front, then the blocks around it but this one.
+    fn notify_front(&self, ctx: &mut BlockTickContext, pos: BlockPos, state: BlockState) {
+        let facing = facing(state);
+        let front = pos - facing.to_vec();
+        ctx.chunks.updates.push(BlockUpdate::Neighbour {
+            pos: front,
+            from: pos,
+        });
+        ctx.chunks.updates.notify_neighbours(front, Some(facing));
+    }
+
+    /// Repeaters feeding another go first, then those turning off, then those turning on.
+    fn priority<W: WorldAccess>(
+        &self,
+        world: &W,
+        pos: BlockPos,
+        state: BlockState,
+    ) -> TickPriority {
+        let facing = facing(state);
+        let front = world.get_state(pos - facing.to_vec());
+        if is_repeater(front) && super::facing(front) != facing.opposite() {
+            -3
+        } else if state.get_bool("powered") {
+            -2
+        } else {
+            -1
+        }
+    }
+}
+
+impl BlockBehaviour for Repeater {
+    fn is_signal_source(&self) -> bool {
+        true
+    }
+
+    fn signal(&self, state: BlockState, side: BlockFaceDirection) -> u8 {
+        match state.get_bool("powered") && facing(state) == side {
+            true => MAX_POWER,
+            false => 0,
+        }
+    }
+
+    fn direct_signal(&self, state: BlockState, side: BlockFaceDirection) -> u8 {
+        self.signal(state, side)
+    }
+
+    fn update_shape(
+        &self,
+        state: BlockState,
+        direction: BlockFaceDirection,
+        neighbour: BlockState,
+    ) -> BlockState {
+        match direction == BlockFaceDirection::YN && !is_sturdy(neighbour) {
+            true => BlockState::AIR,
+            false => state,
+        }
+    }
+
+    /// Steps its delay round.
+    fn use_block(&self, ctx: &mut BlockTickContext, pos: BlockPos, state: BlockState) -> bool {
+        let delay = state.get_int("delay") % 4 + 1;
+        ctx.chunks.set_state(pos, state.with_int("delay", delay));
+        true
+    }
+
+    fn on_place(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        state: BlockState,
+        _old: BlockState,
+    ) {
+        self.notify_front(ctx, pos, state);
+    }
+
+    fn on_remove(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        state: BlockState,
+        new: BlockState,
+    ) {
+        if new.block_id() != state.block_id() {
+            self.notify_front(ctx, pos, state);
+        }
+    }
+
+    fn neighbor_changed(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        state: BlockState,
+        _from: BlockPos,
+    ) {
+        let locked = locked(&*ctx.chunks, pos, state);
+        if locked != state.get_bool("locked") {
+            ctx.chunks
+                .set_state_with(pos, state.with_bool("locked", locked), BitFlags::empty());
+        }
+        if locked {
+            return;
+        }
+        let on = input(&*ctx.chunks, pos, state) > 0;
+        if on != state.get_bool("powered") && !ctx.chunks.is_tick_scheduled(pos, state.name()) {
+            let priority = self.priority(&*ctx.chunks, pos, state);
+            ctx.schedule_tick(pos, state.name(), delay(state), priority);
+        }
+    }
+
+    fn tick(&self, ctx: &mut BlockTickContext, pos: BlockPos) {
+        let state = ctx.chunks.get_state(pos);
+        if locked(&*ctx.chunks, pos, state) {
+            return;
+        }
+        let on = input(&*ctx.chunks, pos, state) > 0;
+        let powered = state.get_bool("powered");
+        if powered && !on {
+            ctx.chunks
+                .set_state_with(pos, state.with_bool("powered", false), BitFlags::empty());
+        } else if !powered {
+            ctx.chunks
+                .set_state_with(pos, state.with_bool("powered", true), BitFlags::empty());
+            // a pulse shorter than the delay still comes out as long as the delay
+            if !on {
+                ctx.schedule_tick(pos, state.name(), delay(state), -2);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+
+    use crate::block::redstone::harness::Circuit;
+
+    #[test]
+    fn repeaters_wait_their_delay() {
+        let mut circuit = Circuit::build(
+            &[&["####"], &["LR-"]],
+            &[
+                ('L', "lever[face=floor]"),
+                ('R', "repeater[facing=west,delay=3]"),
+            ],
+        )
+        .unwrap();
+        let probes = [ivec3(1, 1, 0), ivec3(2, 1, 0)];
+        circuit.use_block(ivec3(0, 1, 0));
+        assert_eq!(
+            circuit.watch(7, &probes),
+            ["00", "00", "00", "00", "00", "00", "1f"]
+        );
+        circuit.use_block(ivec3(0, 1, 0));
+        assert_eq!(
+            circuit.watch(7, &probes),
+            ["1f", "1f", "1f", "1f", "1f", "1f", "00"]
+        );
+    }
+
+    #[test]
+    fn repeaters_lock_from_the_side() {
+        // the repeater going south points into the side of the one going east
+        let mut circuit = Circuit::build(
+            &[&["###", "###", "###"], &[".M", ".v", "L>-"]],
+            &[('L', "lever[face=floor]"), ('M', "lever[face=floor]")],
+        )
+        .unwrap();
+        let (main, side) = (ivec3(1, 1, 2), ivec3(1, 1, 1));
+        let probes = [main, side, ivec3(2, 1, 2)];
+        circuit.use_block(ivec3(1, 1, 0));
+        assert_eq!(circuit.watch(3, &probes), ["000", "000", "010"]);
+        assert!(circuit.state(main).get_bool("locked"));
+        // locked, it doesn't pass the lever on
+        circuit.use_block(ivec3(0, 1, 2));
+        assert_eq!(circuit.watch(4, &probes), ["010"; 4]);
+        // unlocked, it does after its delay
+        circuit.use_block(ivec3(1, 1, 0));
+        assert_eq!(
+            circuit.watch(5, &probes),
+            ["010", "010", "000", "000", "10f"]
+        );
+        assert!(!circuit.state(main).get_bool("locked"));
+    }
+}
@@ -0,0 +1,141 @@
+//! ```text
+//! package net.minecraft.block
+//! class RedstoneTorchBlock
+//! version 1.16
+//! ```
+//!
+//! A redstone torch standing on a block, lit while that block isn't powered. It turns over a
+//! redstone tick after the block under it does, and powers every side but down, the block over
+//! it strongly.
+
+use glam::*;
+
+use crate::{
+    block::{
+        behaviour::BlockBehaviour, block_face_direction::BlockFaceDirection, state::BlockState,
+    },
+    world::{
+        block_tick::BlockTickContext,
+        block_update::NEIGHBOUR_ORDER,
+        chunk_access::{BlockPos, WorldAccess},
+    },
+};
+
+use super::{has_signal, is_sturdy, MAX_POWER};
+
+/// Ticks a torch takes to turn over.
+pub const TORCH_DELAY: u64 = 2;
+
+pub struct RedstoneTorch;
+
+impl RedstoneTorch {
+    /// The block the torch at `pos` stands on is powered.
+    fn powered(&self, ctx: &BlockTickContext, pos: BlockPos) -> bool {
+        has_signal(&*ctx.chunks, pos - IVec3::Y, BlockFaceDirection::YN)
+    }
+
+    /// Everything next to the torch and the blocks around it hears of it.
+    fn notify_around(&self, ctx: &mut BlockTickContext, pos: BlockPos) {
+        for direction in NEIGHBOUR_ORDER {
+            ctx.chunks
+                .updates
+                .notify_neighbours(pos + direction.to_vec(), None);
+        }
+    }
+}
+
+impl BlockBehaviour for RedstoneTorch {
+    fn is_signal_source(&self) -> bool {
+        true
+    }
+
+    fn signal(&self, state: BlockState, side: BlockFaceDirection) -> u8 {
+        match state.get_bool("lit") && side != BlockFaceDirection::YP {
+            true => MAX_POWER,
+            false => 0,
+        }
+    }
+
+    fn direct_signal(&self, state: BlockState, side: BlockFaceDirection) -> u8 {
+        match side {
+            BlockFaceDirection::YN => self.signal(state, side),
+            _ => 0,
+        }
+    }
+
+    fn update_shape(
+        &self,
+        state: BlockState,
+        direction: BlockFaceDirection,
+        neighbour: BlockState,
+    ) -> BlockState {
+        match direction == BlockFaceDirection::YN && !is_sturdy(neighbour) {
+            true => BlockState::AIR,
+            false => state,
+        }
+    }
+
+    fn on_place(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        _state: BlockState,
+        _old: BlockState,
+    ) {
+        self.notify_around(ctx, pos);
+    }
+
+    fn on_remove(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        state: BlockState,
+        new: BlockState,
+    ) {
+        if new.block_id() != state.block_id() {
+            self.notify_around(ctx, pos);
+        }
+    }
+
+    fn neighbor_changed(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        state: BlockState,
+        _from: BlockPos,
+    ) {
+        if state.get_bool("lit") == self.powered(ctx, pos) {
+            ctx.schedule_tick(pos, state.name(), TORCH_DELAY, 0);
+        }
+    }
+
+    fn tick(&self, ctx: &mut BlockTickContext, pos: BlockPos) {
+        let state = ctx.chunks.get_state(pos);
+        let lit = !self.powered(ctx, pos);
+        if state.get_bool("lit") != lit {
+            ctx.chunks.set_state(pos, state.with_bool("lit", lit));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::redstone::harness::Circuit;
+
+    #[test]
+    fn torches_invert_a_redstone_tick_later() {
+        // a lever on the side of the stone a torch stands on, the torch powering dust beside it
+        let mut circuit = Circuit::build(
+            &[&["###"], &["L##"], &[".*-"]],
+            &[('L', "lever[face=wall,facing=west]")],
+        )
+        .unwrap();
+        let (torch, dust) = (ivec3(1, 2, 0), ivec3(2, 2, 0));
+        assert_eq!(circuit.watch(1, &[torch, dust]), ["1f"]);
+        circuit.use_block(ivec3(0, 1, 0));
+        assert_eq!(circuit.watch(3, &[torch, dust]), ["1f", "1f", "00"]);
+        circuit.use_block(ivec3(0, 1, 0));
+        assert_eq!(circuit.watch(3, &[torch, dust]), ["00", "00", "1f"]);
+    }
+}
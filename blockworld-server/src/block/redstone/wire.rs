@@ -0,0 +1,283 @@
+//! ```text
+//! package net.minecraft.block
+//! class RedstoneWireBlock, RedstoneSide
+//! version 1.16
+//! ```
+//!
+//! Redstone dust. Its power is the strongest of what its neighbours give it, other than wire,
+//! and of the wire it connects to less one. Each side goes `none`, `side` along the ground, or
+//! `up` the block there to wire on top of it; dust with nothing on two facing sides runs on
+//! across them, so a lone dust is a cross and a line powers the block it points into.
+
+use enumflags2::BitFlags;
+use glam::*;
+
+use crate::{
+    block::{
+        behaviour::BlockBehaviour, block_face_direction::BlockFaceDirection, state::BlockState,
+    },
+    world::{
+        block_tick::BlockTickContext,
+        block_update::NEIGHBOUR_ORDER,
+        chunk_access::{BlockPos, WorldAccess},
+    },
+};
+
+use super::{best_neighbour_signal, is_conductor, is_sturdy, is_wire, HORIZONTAL, MAX_POWER};
+
+pub struct RedstoneWire;
+
+/// Power of `state` if it's wire, else 0.
+fn wire_power(state: BlockState) -> u8 {
+    match is_wire(state) {
+        true => state.get_int("power") as u8,
+        false => 0,
+    }
+}
+
+/// Wire connects to `state` when it's towards `direction`, or, without a direction, above or
+/// under the block beside.
+fn connects_to(state: BlockState, direction: Option<BlockFaceDirection>) -> bool {
+    if is_wire(state) {
+        return true;
+    }
+    if &**state.name() == "minecraft:repeater" {
+        let facing = super::facing(state);
+        return direction.is_some_and(|d| d == facing || d == facing.opposite());
+    }
+    state.block().behaviour.is_signal_source() && direction.is_some()
+}
+
+/// How the wire at `pos` goes towards `direction`. `open_above` if nothing over the wire cuts
+/// off wire going up.
+fn side<W: WorldAccess>(
+    world: &W,
+    pos: BlockPos,
+    direction: BlockFaceDirection,
+    open_above: bool,
+) -> &'static str {
+    let beside = pos + direction.to_vec();
+    let state = world.get_state(beside);
+    if open_above && is_sturdy(state) && connects_to(world.get_state(beside + IVec3::Y), None) {
+        return "up";
+    }
+    let down = connects_to(world.get_state(beside - IVec3::Y), None);
+    match !connects_to(state, Some(direction)) && (is_conductor(state) || !down) {
+        true => "none",
+        false => "side",
+    }
+}
+
+/// `state` with its sides set to what's around it at `pos`.
+fn connections<W: WorldAccess>(world: &W, pos: BlockPos, state: BlockState) -> BlockState {
+    let open_above = !is_conductor(world.get_state(pos + IVec3::Y));
+    let mut state = state;
+    for direction in HORIZONTAL {
+        state = state.with(direction.name(), side(world, pos, direction, open_above));
+    }
+    let [north, east, south, west] = HORIZONTAL.map(|d| state.get(d.name()) != Some("none"));
+    // a dead end runs on to the other side, and lone dust goes every way
+    let runs_on = [
+        (BlockFaceDirection::ZN, north, !east && !west),
+        (BlockFaceDirection::XP, east, !north && !south),
+        (BlockFaceDirection::ZP, south, !east && !west),
+        (BlockFaceDirection::XN, west, !north && !south),
+    ];
+    for (direction, connected, across_open) in runs_on {
+        if !connected && across_open {
+            state = state.with(direction.name(), "side");
+        }
+    }
+    state
+}
+
+/// The power the wire at `pos` should have.
+fn target_power<W: WorldAccess>(world: &W, pos: BlockPos) -> u8 {
+    let strongest = best_neighbour_signal(world, pos, false);
+    if strongest >= MAX_POWER {
+        return strongest;
+    }
+    let covered = is_conductor(world.get_state(pos + IVec3::Y));
+    let mut wire = 0;
+    for direction in HORIZONTAL {
+        let beside = pos + direction.to_vec();
+        let state = world.get_state(beside);
+        wire = wire.max(wire_power(state));
+        if is_conductor(state) {
+            if !covered {
+                wire = wire.max(wire_power(world.get_state(beside + IVec3::Y)));
+            }
+        } else {
+            wire = wire.max(wire_power(world.get_state(beside - IVec3::Y)));
+        }
+    }
+    strongest.max(wire.saturating_sub(1))
+}
+
+impl RedstoneWire {
+    /// Fit the wire at `pos` to its neighbours and take the power it should have. Wire that
+    /// changes power notifies around itself and around each of its neighbours.
+    fn update(&self, ctx: &mut BlockTickContext, pos: BlockPos, state: BlockState) {
+        let power = target_power(&*ctx.chunks, pos);
+        let new = connections(&*ctx.chunks, pos, state).with_int("power", power as u32);
+        if new == state {
+            return;
+        }
+        ctx.chunks.set_state_with(pos, new, BitFlags::empty());
+        if power != wire_power(state) {
+            self.notify_around(ctx, pos);
+        }
+    }
+
+    fn notify_around(&self, ctx: &mut BlockTickContext, pos: BlockPos) {
+        let updates = &mut ctx.chunks.updates;
+        updates.notify_neighbours(pos, None);
+        for direction in NEIGHBOUR_ORDER {
+            updates.notify_neighbours(pos + direction.to_vec(), None);
+        }
+    }
+
+    /// Wire over or under the blocks beside `pos` hears of a change there, as it may have
+    /// gone up or down to it.
+    fn notify_corners(&self, ctx: &mut BlockTickContext, pos: BlockPos) {
+        for direction in HORIZONTAL {
+            let beside = pos + direction.to_vec();
+            let corner = match is_conductor(ctx.chunks.get_state(beside)) {
+                true => beside + IVec3::Y,
+                false => beside - IVec3::Y,
+            };
+            for at in [beside, corner] {
+                if is_wire(ctx.chunks.get_state(at)) {
+                    ctx.chunks.updates.notify_neighbours(at, None);
+                }
+            }
+        }
+    }
+}
+
+impl BlockBehaviour for RedstoneWire {
+    fn is_signal_source(&self) -> bool {
+        true
+    }
+
+    /// Wire powers the block under it and those it points into.
+    fn signal(&self, state: BlockState, side: BlockFaceDirection) -> u8 {
+        let power = wire_power(state);
+        match side {
+            BlockFaceDirection::YN => 0,
+            BlockFaceDirection::YP => power,
+            _ if state.get(side.opposite().name()) == Some("none") => 0,
+            _ => power,
+        }
+    }
+
+    fn direct_signal(&self, state: BlockState, side: BlockFaceDirection) -> u8 {
+        self.signal(state, side)
+    }
+
+    fn update_shape(
+        &self,
+        state: BlockState,
+        direction: BlockFaceDirection,
+        neighbour: BlockState,
+    ) -> BlockState {
+        match direction == BlockFaceDirection::YN && !is_sturdy(neighbour) {
+            true => BlockState::AIR,
+            false => state,
+        }
+    }
+
+    fn on_place(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        state: BlockState,
+        old: BlockState,
+    ) {
+        if is_wire(old) {
+            return;
+        }
+        self.update(ctx, pos, state);
+        ctx.chunks.updates.notify_neighbours(pos + IVec3::Y, None);
+        ctx.chunks.updates.notify_neighbours(pos - IVec3::Y, None);
+        self.notify_corners(ctx, pos);
+    }
+
+    fn on_remove(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        _state: BlockState,
+        new: BlockState,
+    ) {
+        if is_wire(new) {
+            return;
+        }
+        self.notify_around(ctx, pos);
+        self.notify_corners(ctx, pos);
+    }
+
+    fn neighbor_changed(
+        &self,
+        ctx: &mut BlockTickContext,
+        pos: BlockPos,
+        state: BlockState,
+        _from: BlockPos,
+    ) {
+        self.update(ctx, pos, state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::redstone::harness::Circuit;
+
+    #[test]
+    fn wire_loses_a_level_a_block() {
+        let mut circuit = Circuit::build(
+            &[
+                &["#################", "#################"],
+                &["L----------------", ".#"],
+                &["", ".-"],
+            ],
+            &[],
+        )
+        .unwrap();
+        let line = |c: &Circuit| {
+            (1..17)
+                .map(|x| c.output(ivec3(x, 1, 0)))
+                .collect::<String>()
+        };
+        assert_eq!(line(&circuit), "0000000000000000");
+        circuit.use_block(ivec3(0, 1, 0));
+        assert_eq!(line(&circuit), "fedcba9876543210");
+        // dust climbs the block beside and goes on on top of it
+        assert_eq!(circuit.output(ivec3(1, 2, 1)), 'e');
+        assert_eq!(
+            circuit.state(ivec3(1, 1, 0)).to_string(),
+            "minecraft:redstone_wire[north=none,east=side,south=up,west=side,power=15]"
+        );
+        assert_eq!(
+            circuit.state(ivec3(1, 2, 1)).to_string(),
+            "minecraft:redstone_wire[north=side,east=none,south=side,west=none,power=14]"
+        );
+        circuit.use_block(ivec3(0, 1, 0));
+        assert_eq!(line(&circuit), "0000000000000000");
+        assert_eq!(circuit.output(ivec3(1, 2, 1)), '0');
+    }
+
+    #[test]
+    fn dust_runs_on_from_a_dead_end() {
+        let circuit = Circuit::build(&[&["###", "###", "###"], &["-", "", "--"]], &[]).unwrap();
+        // alone it's a cross, a line runs on past its end
+        assert_eq!(
+            circuit.state(ivec3(0, 1, 0)).to_string(),
+            "minecraft:redstone_wire[north=side,east=side,south=side,west=side,power=0]"
+        );
+        assert_eq!(
+            circuit.state(ivec3(0, 1, 2)).to_string(),
+            "minecraft:redstone_wire[north=none,east=side,south=none,west=side,power=0]"
+        );
+    }
+}
@@ -0,0 +1,284 @@
+//! ```text
+//! package net.minecraft.util.math
+//! class AxisAlignedBB, shapes.VoxelShapes
+//! version 1.16
+//! ```
+//!
+//! Boxes, and the boxes blocks are made of, in coords relative to the block's min corner. A
+//! block's collision shape is what entities bump into and its outline shape is what the
+//! player points at; most blocks are the full cube for both and fluids have neither, only a
+//! fluid shape that rays may ask for. Shapes go by block state, so a fence has arms only
+//! where it connects.
+
+use glam::*;
+use once_cell::sync::Lazy;
+
+use super::{block_face_direction::BlockFaceDirection, fluid::FluidState, state::BlockState};
+
+/// Slack so a box resting on a face doesn't count as inside what it rests on.
+pub const EPSILON: f32 = 1e-5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+pub const FULL_CUBE: Aabb = Aabb::new(Vec3::ZERO, Vec3::ONE);
+const BOTTOM_SLAB: Aabb = Aabb::new(Vec3::ZERO, vec3(1.0, 0.5, 1.0));
+
+impl Aabb {
+    pub const fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// A box standing on `feet`, centered around them.
+    pub fn from_feet(feet: Vec3, width: f32, height: f32) -> Self {
+        let half = vec3(width / 2.0, 0.0, width / 2.0);
+        Self::new(feet - half, feet + half + Vec3::Y * height)
+    }
+
+    pub fn offset(&self, by: Vec3) -> Self {
+        Self::new(self.min + by, self.max + by)
+    }
+
+    /// The box grown to cover everything it passes moving by `delta`.
+    pub fn expand_towards(&self, delta: Vec3) -> Self {
+        Self::new(
+            self.min + delta.min(Vec3::ZERO),
+            self.max + delta.max(Vec3::ZERO),
+        )
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// True if the boxes overlap by more than touching.
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|i| self.overlaps_on(other, i))
+    }
+
+    fn overlaps_on(&self, other: &Aabb, axis: usize) -> bool {
+        self.min[axis] < other.max[axis] - EPSILON && self.max[axis] > other.min[axis] + EPSILON
+    }
+
+    /// How far `moving` can go along `axis` towards `delta` before it runs into this box.
+    pub fn clip(&self, moving: &Aabb, axis: usize, delta: f32) -> f32 {
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        if !self.overlaps_on(moving, a) || !self.overlaps_on(moving, b) {
+            return delta;
+        }
+        if delta > 0.0 && moving.max[axis] <= self.min[axis] + EPSILON {
+            delta.min(self.min[axis] - moving.max[axis]).max(0.0)
+        } else if delta < 0.0 && moving.min[axis] >= self.max[axis] - EPSILON {
+            delta.max(self.max[axis] - moving.min[axis]).min(0.0)
+        } else {
+            delta
+        }
+    }
+
+    /// Where the ray from `origin` towards `direction` enters the box, as how many `direction`s
+    /// away and the face it goes through. A ray starting inside hits at once, on the face
+    /// facing back at it.
+    pub fn clip_ray(&self, origin: Vec3, direction: Vec3) -> Option<(f32, BlockFaceDirection)> {
+        let (mut near, mut far, mut axis) = (f32::NEG_INFINITY, f32::INFINITY, None);
+        for i in 0..3 {
+            if direction[i] == 0.0 {
+                if origin[i] < self.min[i] || origin[i] > self.max[i] {
+                    return None;
+                }
+                continue;
+            }
+            let a = (self.min[i] - origin[i]) / direction[i];
+            let b = (self.max[i] - origin[i]) / direction[i];
+            if a.min(b) > near {
+                near = a.min(b);
+                axis = Some(i);
+            }
+            far = far.min(a.max(b));
+        }
+        if near > far || far < 0.0 {
+            return None;
+        }
+        match axis {
+            Some(axis) if near >= 0.0 => Some((
+                near,
+                BlockFaceDirection::from_axis(axis, direction[axis] < 0.0),
+            )),
+            _ => Some((0.0, BlockFaceDirection::nearest(-direction))),
+        }
+    }
+}
+
+/// The shapes of a block state, worked out once for every state.
+struct Shapes {
+    collision: Vec<Aabb>,
+    outline: Vec<Aabb>,
+    fluid: Vec<Aabb>,
+}
+
+static SHAPES: Lazy<Vec<Shapes>> = Lazy::new(|| BlockState::all().map(shapes_of).collect());
+
+fn shapes_of(state: BlockState) -> Shapes {
+    let (collision, outline) = match &**state.name() {
+        "minecraft:air" | "minecraft:water" | "minecraft:lava" => (vec![], vec![]),
+        "minecraft:smooth_stone_slab" => (vec![BOTTOM_SLAB], vec![BOTTOM_SLAB]),
+        // jumped over by nothing, but pointed at like a block
+        "minecraft:oak_fence" => (
+            connecting(state, 4.0, 2.0, 24.0),
+            connecting(state, 4.0, 2.0, 16.0),
+        ),
+        "minecraft:cobblestone_wall" => (
+            connecting(state, 8.0, 6.0, 24.0),
+            connecting(state, 8.0, 6.0, 14.0),
+        ),
+        "minecraft:redstone_wire" => (vec![], vec![sixteenths(0.0, 0.0, 0.0, 16.0, 1.0, 16.0)]),
+        "minecraft:redstone_torch" => (vec![], vec![sixteenths(6.0, 0.0, 6.0, 10.0, 10.0, 10.0)]),
+        "minecraft:repeater" => {
+            let plate = sixteenths(0.0, 0.0, 0.0, 16.0, 2.0, 16.0);
+            (vec![plate], vec![plate])
+        }
+        "minecraft:lever" => {
+            let floor = sixteenths(5.0, 0.0, 4.0, 11.0, 6.0, 12.0);
+            let wall = sixteenths(5.0, 3.0, 10.0, 11.0, 13.0, 16.0);
+            (vec![], vec![attached(state, floor, wall)])
+        }
+        "minecraft:stone_button" => {
+            let depth = if state.get_bool("powered") { 1.0 } else { 2.0 };
+            let floor = sixteenths(5.0, 0.0, 6.0, 11.0, depth, 10.0);
+            let wall = sixteenths(5.0, 6.0, 16.0 - depth, 11.0, 10.0, 16.0);
+            (vec![], vec![attached(state, floor, wall)])
+        }
+        // the head fills the rest of the block in front
+        "minecraft:piston" if state.get_bool("extended") => {
+            let base = facing_box(sixteenths(0.0, 0.0, 4.0, 16.0, 16.0, 16.0), state);
+            (vec![base], vec![base])
+        }
+        "minecraft:piston_head" => {
+            let head = vec![
+                facing_box(sixteenths(0.0, 0.0, 0.0, 16.0, 16.0, 4.0), state),
+                facing_box(sixteenths(6.0, 6.0, 4.0, 10.0, 10.0, 16.0), state),
+            ];
+            (head.clone(), head)
+        }
+        _ => (vec![FULL_CUBE], vec![FULL_CUBE]),
+    };
+    // sources and falling fluid fill their block, whatever is drawn of them
+    let fluid = match FluidState::of(state) {
+        Some(fluid) if fluid.source || fluid.falling => vec![FULL_CUBE],
+        Some(fluid) => vec![Aabb::new(Vec3::ZERO, vec3(1.0, fluid.height(), 1.0))],
+        None => vec![],
+    };
+    Shapes {
+        collision,
+        outline,
+        fluid,
+    }
+}
+
+/// A post in the middle with an arm to every side it connects to, in sixteenths of a block.
+fn connecting(state: BlockState, post: f32, arm: f32, height: f32) -> Vec<Aabb> {
+    let (p0, p1) = ((8.0 - post / 2.0) / 16.0, (8.0 + post / 2.0) / 16.0);
+    let (a0, a1) = ((8.0 - arm / 2.0) / 16.0, (8.0 + arm / 2.0) / 16.0);
+    let h = height / 16.0;
+    let mut boxes = vec![Aabb::new(vec3(p0, 0.0, p0), vec3(p1, h, p1))];
+    let arms = [
+        ("north", vec3(a0, 0.0, 0.0), vec3(a1, h, p0)),
+        ("east", vec3(p1, 0.0, a0), vec3(1.0, h, a1)),
+        ("south", vec3(a0, 0.0, p1), vec3(a1, h, 1.0)),
+        ("west", vec3(0.0, 0.0, a0), vec3(p0, h, a1)),
+    ];
+    for (side, min, max) in arms {
+        if state.get_bool(side) {
+            boxes.push(Aabb::new(min, max));
+        }
+    }
+    boxes
+}
+
+/// A box from and to some sixteenths of a block.
+fn sixteenths(x0: f32, y0: f32, z0: f32, x1: f32, y1: f32, z1: f32) -> Aabb {
+    Aabb::new(vec3(x0, y0, z0) / 16.0, vec3(x1, y1, z1) / 16.0)
+}
+
+/// `b`, drawn for a block facing north, turned the way `state` faces.
+fn facing_box(b: Aabb, state: BlockState) -> Aabb {
+    let turn = |p: Vec3| match state.get("facing") {
+        Some("east") => vec3(1.0 - p.z, p.y, p.x),
+        Some("south") => vec3(1.0 - p.x, p.y, 1.0 - p.z),
+        Some("west") => vec3(p.z, p.y, 1.0 - p.x),
+        Some("up") => vec3(p.x, 1.0 - p.z, p.y),
+        Some("down") => vec3(p.x, p.z, 1.0 - p.y),
+        _ => p,
+    };
+    let (a, c) = (turn(b.min), turn(b.max));
+    Aabb::new(a.min(c), a.max(c))
+}
+
+/// A lever or button, `floor` drawn on the floor and `wall` on the wall to the south, both
+/// facing north, turned to where `state` is stuck.
+fn attached(state: BlockState, floor: Aabb, wall: Aabb) -> Aabb {
+    match state.get("face") {
+        Some("floor") => facing_box(floor, state),
+        Some("ceiling") => {
+            let b = facing_box(floor, state);
+            Aabb::new(
+                vec3(b.min.x, 1.0 - b.max.y, b.min.z),
+                vec3(b.max.x, 1.0 - b.min.y, b.max.z),
+            )
+        }
+        _ => facing_box(wall, state),
+    }
+}
+
+/// The boxes entities collide with in a block, relative to its min corner.
+pub fn collision_shape(state: BlockState) -> &'static [Aabb] {
+    SHAPES.get(state.0 as usize).map_or(&[], |s| &s.collision)
+}
+
+/// The boxes the player targets in a block. Only blocks that can be walked through, like
+/// flowers, or over, like fences, have a different one than their collision shape.
+pub fn outline_shape(state: BlockState) -> &'static [Aabb] {
+    SHAPES.get(state.0 as usize).map_or(&[], |s| &s.outline)
+}
+
+/// The box of the fluid in a block, if there's one.
+pub fn fluid_shape(state: BlockState) -> &'static [Aabb] {
+    SHAPES.get(state.0 as usize).map_or(&[], |s| &s.fluid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boxes_clip_what_moves_into_them() {
+        let block = FULL_CUBE.offset(vec3(2.0, 0.0, 0.0));
+        let player = Aabb::from_feet(vec3(0.5, 0.0, 0.5), 0.6, 1.8);
+        assert!(player.min.abs_diff_eq(vec3(0.2, 0.0, 0.2), 1e-6));
+        assert!((block.clip(&player, 0, 3.0) - 1.2).abs() < 1e-6);
+        // moving away or beside it isn't clipped
+        assert_eq!(block.clip(&player, 0, -3.0), -3.0);
+        assert_eq!(block.clip(&player, 2, 3.0), 3.0);
+        let above = player.offset(vec3(1.5, 1.0, 0.0));
+        assert_eq!(block.clip(&above, 1, -2.0), 0.0);
+        // touching isn't overlapping
+        assert!(!block.intersects(&player.offset(vec3(1.2, 0.0, 0.0))));
+        assert!(block.intersects(&player.offset(vec3(1.3, 0.0, 0.0))));
+    }
+
+    #[test]
+    fn rays_enter_through_a_face() {
+        let slab = BOTTOM_SLAB.offset(vec3(0.0, 64.0, 0.0));
+        let down = slab.clip_ray(vec3(0.5, 66.0, 0.5), Vec3::NEG_Y);
+        assert_eq!(down, Some((1.5, BlockFaceDirection::YP)));
+        let across = slab.clip_ray(vec3(-2.0, 64.25, 0.5), Vec3::X);
+        assert_eq!(across, Some((2.0, BlockFaceDirection::XN)));
+        // over it, and away from it
+        assert_eq!(slab.clip_ray(vec3(-2.0, 64.75, 0.5), Vec3::X), None);
+        assert_eq!(slab.clip_ray(vec3(-2.0, 64.25, 0.5), Vec3::NEG_X), None);
+        let inside = slab.clip_ray(vec3(0.5, 64.25, 0.5), vec3(0.8, 0.0, 0.6));
+        assert_eq!(inside, Some((0.0, BlockFaceDirection::XN)));
+    }
+}
@@ -0,0 +1,297 @@
+//! ```text
+//! package net.minecraft.state
+//! class StateContainer, Property, block.Block (BLOCK_STATE_IDS)
+//! version 1.16
+//! ```
+//!
+//! A block with a value for each of its properties, like a fence's `north=true`. Every state
+//! of every block has a global [`StateId`]: the blocks in registry order, each taking as many
+//! ids as it has states, so a block without properties has the same id as in the registry.
+//! Chunks, saved palettes and block updates hold state ids.
+//!
+//! The first value of each property is the default.
+
+use anyhow::{anyhow, ensure};
+use blockworld_utils::ResourceLocation;
+use once_cell::sync::Lazy;
+
+use super::{Block, NumberID, BLOCK_REGISTRY};
+
+/// Global id of a block state.
+pub type StateId = u32;
+
+pub const BOOLEAN: &[&str] = &["false", "true"];
+
+/// Whether a fence or wall connects to each side.
+pub const NORTH: Property = Property::new("north", BOOLEAN);
+pub const EAST: Property = Property::new("east", BOOLEAN);
+pub const SOUTH: Property = Property::new("south", BOOLEAN);
+pub const WEST: Property = Property::new("west", BOOLEAN);
+
+const UP_TO_15: &[&str] = &[
+    "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14", "15",
+];
+
+/// How far a fluid is from its source: 0 for the source, 1 to 7 flowing away from it and 8 on
+/// falling. Minecraft keeps 9 to 15 for falling fluid too, and they read as 8.
+pub const LEVEL: Property = Property::new("level", UP_TO_15);
+
+/// Redstone power, 0 to 15.
+pub const POWER: Property = Property::new("power", UP_TO_15);
+pub const POWERED: Property = Property::new("powered", BOOLEAN);
+/// Lit by default, as torches are.
+pub const LIT: Property = Property::new("lit", &["true", "false"]);
+pub const LOCKED: Property = Property::new("locked", BOOLEAN);
+pub const EXTENDED: Property = Property::new("extended", BOOLEAN);
+/// A repeater's delay, in redstone ticks of 2 game ticks.
+pub const DELAY: Property = Property::new("delay", &["1", "2", "3", "4"]);
+
+pub const HORIZONTAL_FACING: Property =
+    Property::new("facing", &["north", "east", "south", "west"]);
+pub const FACING: Property =
+    Property::new("facing", &["north", "east", "south", "west", "up", "down"]);
+/// What a lever or button is stuck to: the block under it, behind it or over it.
+pub const FACE: Property = Property::new("face", &["floor", "wall", "ceiling"]);
+
+/// How redstone dust goes to each side: not at all, along the ground, or up the block there.
+pub const REDSTONE_SIDE: &[&str] = &["none", "side", "up"];
+pub const NORTH_WIRE: Property = Property::new("north", REDSTONE_SIDE);
+pub const EAST_WIRE: Property = Property::new("east", REDSTONE_SIDE);
+pub const SOUTH_WIRE: Property = Property::new("south", REDSTONE_SIDE);
+pub const WEST_WIRE: Property = Property::new("west", REDSTONE_SIDE);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Property {
+    pub name: &'static str,
+    pub values: &'static [&'static str],
+}
+
+impl Property {
+    pub const fn new(name: &'static str, values: &'static [&'static str]) -> Self {
+        Self { name, values }
+    }
+}
+
+/// Where each block's states start, and whose each state is.
+struct StateTable {
+    first: Vec<StateId>,
+    blocks: Vec<NumberID>,
+}
+
+static STATES: Lazy<StateTable> = Lazy::new(|| {
+    let mut table = StateTable {
+        first: vec![],
+        blocks: vec![],
+    };
+    for id in 0.. {
+        let Some(name) = BLOCK_REGISTRY.number_id_to_name(id) else {
+            break;
+        };
+        table.first.push(table.blocks.len() as StateId);
+        let count = BLOCK_REGISTRY.get(name).unwrap().state_count();
+        table.blocks.extend(std::iter::repeat_n(id, count as usize));
+    }
+    table
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BlockState(pub StateId);
+
+impl BlockState {
+    pub const AIR: BlockState = BlockState(0);
+
+    /// The default state of the block called `name`, air if there's none.
+    pub fn of(name: &ResourceLocation) -> Self {
+        match BLOCK_REGISTRY.get(name) {
+            Some(_) => Self::of_block(BLOCK_REGISTRY.name_to_number_id(name)),
+            None => Self::AIR,
+        }
+    }
+
+    /// The default state of the block with registry id `block`.
+    pub fn of_block(block: NumberID) -> Self {
+        Self(STATES.first.get(block as usize).copied().unwrap_or(0))
+    }
+
+    /// The state with this id, if there's one.
+    pub fn from_id(id: StateId) -> Option<Self> {
+        ((id as usize) < STATES.blocks.len()).then_some(Self(id))
+    }
+
+    /// The state written as `minecraft:repeater[facing=east,delay=2]`, the properties left out
+    /// at their default. The namespace may be left out too.
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let (name, properties) = match text.split_once('[') {
+            Some((name, rest)) => {
+                let properties = rest
+                    .strip_suffix(']')
+                    .ok_or_else(|| anyhow!("Missing ] in {}", text))?;
+                (name, properties)
+            }
+            None => (text, ""),
+        };
+        let id: ResourceLocation = match name.contains(':') {
+            true => name.into(),
+            false => format!("minecraft:{}", name).as_str().into(),
+        };
+        ensure!(BLOCK_REGISTRY.get(&id).is_some(), "Unknown block {}", name);
+        let mut state = Self::of(&id);
+        for pair in properties.split(',').filter(|p| !p.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Expected property=value, got {}", pair))?;
+            let changed = state.with(key, value);
+            ensure!(
+                state.get(key) == Some(value) || changed != state,
+                "{} has no {}={}",
+                name,
+                key,
+                value
+            );
+            state = changed;
+        }
+        Ok(state)
+    }
+
+    /// Every state of every block, by id.
+    pub fn all() -> impl Iterator<Item = BlockState> {
+        (0..STATES.blocks.len() as StateId).map(BlockState)
+    }
+
+    /// Registry id of the block.
+    pub fn block_id(self) -> NumberID {
+        STATES.blocks.get(self.0 as usize).copied().unwrap_or(0)
+    }
+
+    pub fn name(self) -> &'static ResourceLocation {
+        BLOCK_REGISTRY.number_id_to_name(self.block_id()).unwrap()
+    }
+
+    pub fn block(self) -> &'static Block {
+        BLOCK_REGISTRY.get(self.name()).unwrap()
+    }
+
+    pub fn is_air(self) -> bool {
+        self.block_id() == 0
+    }
+
+    /// Which state of its block this is, counting with the last property fastest.
+    fn index(self) -> u32 {
+        self.0 - STATES.first[self.block_id() as usize]
+    }
+
+    /// How many states of its block the states of properties after `i` take.
+    fn stride(block: &Block, i: usize) -> u32 {
+        block.properties[i + 1..]
+            .iter()
+            .map(|p| p.values.len() as u32)
+            .product()
+    }
+
+    /// The value of `property`, if the block has it.
+    pub fn get(self, property: &str) -> Option<&'static str> {
+        let block = self.block();
+        let i = block.properties.iter().position(|p| p.name == property)?;
+        let values = block.properties[i].values;
+        let value = self.index() / Self::stride(block, i) % values.len() as u32;
+        Some(values[value as usize])
+    }
+
+    pub fn get_bool(self, property: &str) -> bool {
+        self.get(property) == Some("true")
+    }
+
+    /// A number property, 0 if the block hasn't got it.
+    pub fn get_int(self, property: &str) -> u32 {
+        self.get(property).and_then(|v| v.parse().ok()).unwrap_or(0)
+    }
+
+    /// This state with `property` set to `value`, or unchanged if the block has no such
+    /// property or value.
+    pub fn with(self, property: &str, value: &str) -> Self {
+        let block = self.block();
+        let Some(i) = block.properties.iter().position(|p| p.name == property) else {
+            return self;
+        };
+        let values = block.properties[i].values;
+        let Some(new) = values.iter().position(|v| *v == value) else {
+            return self;
+        };
+        let stride = Self::stride(block, i);
+        let old = self.index() / stride % values.len() as u32;
+        Self(self.0 - old * stride + new as u32 * stride)
+    }
+
+    pub fn with_bool(self, property: &str, value: bool) -> Self {
+        self.with(property, if value { "true" } else { "false" })
+    }
+
+    pub fn with_int(self, property: &str, value: u32) -> Self {
+        self.with(property, &value.to_string())
+    }
+
+    /// Every property with its value, in the block's order.
+    pub fn properties(self) -> impl Iterator<Item = (&'static str, &'static str)> {
+        let block = self.block();
+        block
+            .properties
+            .iter()
+            .map(move |p| (p.name, self.get(p.name).unwrap()))
+    }
+}
+
+impl std::fmt::Display for BlockState {
+    /// `minecraft:stone` or `minecraft:oak_fence[north=true,east=false,...]`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let props: Vec<String> = self
+            .properties()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect();
+        match props.is_empty() {
+            true => write!(f, "{}", &**self.name()),
+            false => write!(f, "{}[{}]", &**self.name(), props.join(",")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn states_count_properties() {
+        let stone = BlockState::of(&"minecraft:stone".into());
+        assert_eq!(
+            stone.0,
+            BLOCK_REGISTRY.name_to_number_id(&"minecraft:stone".into())
+        );
+        assert_eq!(stone.with("north", "true"), stone);
+
+        let fence = BlockState::of(&"minecraft:oak_fence".into());
+        assert!(!fence.get_bool("north"));
+        let north_west = fence.with_bool("north", true).with_bool("west", true);
+        assert_eq!(north_west.name(), fence.name());
+        assert!(north_west.get_bool("north") && north_west.get_bool("west"));
+        assert!(!north_west.get_bool("east"));
+        assert_eq!(
+            north_west
+                .with_bool("north", false)
+                .with_bool("west", false),
+            fence
+        );
+        assert_eq!(
+            north_west.to_string(),
+            "minecraft:oak_fence[north=true,east=false,south=false,west=true]"
+        );
+        assert_eq!(BlockState::from_id(north_west.0), Some(north_west));
+
+        assert_eq!(
+            BlockState::parse("oak_fence[north=true,west=true]").unwrap(),
+            north_west
+        );
+        assert_eq!(BlockState::parse("minecraft:oak_fence").unwrap(), fence);
+        assert!(BlockState::parse("oak_fence[north=maybe]").is_err());
+        assert!(BlockState::parse("oak_fence[up=true]").is_err());
+        assert!(BlockState::parse("nothing").is_err());
+    }
+}
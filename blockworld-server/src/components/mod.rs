@@ -16,3 +16,29 @@ pub struct HasView {
 
 #[derive(Component)]
 pub struct Player;
+
+/// Where an entity is, in world coords. For players it's the feet.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Position(pub Vec3);
+
+/// Blocks per tick.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Velocity(pub Vec3);
+
+/// Standing on a block, as of the last move.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OnGround(pub bool);
+
+/// Where an entity looks, in radians.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rotation {
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+/// Movement keys a player holds that change how it moves.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MovementState {
+    pub sneaking: bool,
+    pub sprinting: bool,
+}
@@ -0,0 +1,202 @@
+//! The actual migrations. Each fix only knows the shape of the version right before it, so it
+//! works on raw JSON instead of the typed structs, which always follow the current version.
+
+use anyhow::*;
+use blockworld_utils::BitStorage;
+use serde_json::{Map, Value};
+
+use super::{DataFixType, DataFixer};
+use crate::world::chunk::SUBCHUNK_BLOCK_NUM;
+
+pub(super) fn register_all(r: &mut DataFixer) {
+    r.register(
+        2,
+        DataFixType::Chunk,
+        "palette_entry_objects",
+        palette_entry_objects,
+    );
+    r.register(
+        3,
+        DataFixType::Chunk,
+        "pack_block_indices",
+        pack_block_indices,
+    );
+    r.register(4, DataFixType::Chunk, "rename_grass_block", |v| {
+        rename_block(v, "minecraft:grass", "minecraft:grass_block")
+    });
+    r.register(
+        5,
+        DataFixType::Chunk,
+        "add_inhabited_time",
+        add_inhabited_time,
+    );
+    r.register(
+        6,
+        DataFixType::Player,
+        "add_player_abilities",
+        add_player_abilities,
+    );
+    r.register(7, DataFixType::Chunk, "add_block_ticks", add_block_ticks);
+    r.register(7, DataFixType::Level, "add_game_rules", add_game_rules);
+    r.register(8, DataFixType::Chunk, "add_entities", add_entities);
+}
+
+fn sections_mut(chunk: &mut Value) -> Result<&mut Vec<Value>> {
+    chunk
+        .get_mut("sections")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| anyhow!("chunk has no sections"))
+}
+
+fn field_mut<'a>(section: &'a mut Value, key: &str) -> Result<&'a mut Value> {
+    section
+        .get_mut(key)
+        .ok_or_else(|| anyhow!("section has no {}", key))
+}
+
+/// v1 -> v2: `"minecraft:stone[variant=granite]"` becomes
+/// `{ "name": "minecraft:stone", "properties": { "variant": "granite" } }`.
+fn palette_entry_objects(chunk: &mut Value) -> Result<()> {
+    for section in sections_mut(chunk)? {
+        let palette = field_mut(section, "palette")?
+            .as_array_mut()
+            .ok_or_else(|| anyhow!("palette is not an array"))?;
+        for entry in palette.iter_mut() {
+            let s = entry
+                .as_str()
+                .ok_or_else(|| anyhow!("palette entry is not a string: {}", entry))?;
+            *entry = split_legacy_state(s)?;
+        }
+    }
+    Ok(())
+}
+
+fn split_legacy_state(s: &str) -> Result<Value> {
+    let mut obj = Map::new();
+    let Some((name, rest)) = s.split_once('[') else {
+        obj.insert("name".into(), s.into());
+        return Ok(Value::Object(obj));
+    };
+    let props = rest
+        .strip_suffix(']')
+        .ok_or_else(|| anyhow!("unterminated properties in {}", s))?;
+
+    let mut properties = Map::new();
+    for kv in props.split(',').filter(|kv| !kv.is_empty()) {
+        let (k, v) = kv
+            .split_once('=')
+            .ok_or_else(|| anyhow!("malformed property {} in {}", kv, s))?;
+        properties.insert(k.trim().into(), v.trim().into());
+    }
+    obj.insert("name".into(), name.into());
+    obj.insert("properties".into(), Value::Object(properties));
+    Ok(Value::Object(obj))
+}
+
+/// v2 -> v3: the 4096 `blocks` indices are packed into `data` words of `bits` bits each.
+fn pack_block_indices(chunk: &mut Value) -> Result<()> {
+    for section in sections_mut(chunk)? {
+        let palette_len = field_mut(section, "palette")?
+            .as_array()
+            .map(Vec::len)
+            .unwrap_or(0);
+        let blocks = section
+            .as_object_mut()
+            .and_then(|o| o.remove("blocks"))
+            .ok_or_else(|| anyhow!("section has no blocks"))?;
+        let blocks = blocks
+            .as_array()
+            .ok_or_else(|| anyhow!("blocks is not an array"))?;
+        if blocks.len() != SUBCHUNK_BLOCK_NUM {
+            bail!(
+                "section has {} blocks, expected {}",
+                blocks.len(),
+                SUBCHUNK_BLOCK_NUM
+            );
+        }
+
+        let bits = BitStorage::bits_for(palette_len, 4);
+        let mut storage = BitStorage::new(bits, SUBCHUNK_BLOCK_NUM);
+        for (i, b) in blocks.iter().enumerate() {
+            let b = b
+                .as_u64()
+                .filter(|b| (*b as usize) < palette_len)
+                .ok_or_else(|| anyhow!("invalid palette index {}", b))?;
+            storage.set(i, b as u32);
+        }
+
+        let obj = section.as_object_mut().unwrap();
+        obj.insert("bits".into(), bits.into());
+        obj.insert("data".into(), storage.into_raw().into());
+    }
+    Ok(())
+}
+
+/// Rename a block id in every palette, keeping its properties.
+fn rename_block(chunk: &mut Value, from: &str, to: &str) -> Result<()> {
+    for section in sections_mut(chunk)? {
+        let palette = field_mut(section, "palette")?
+            .as_array_mut()
+            .ok_or_else(|| anyhow!("palette is not an array"))?;
+        for entry in palette.iter_mut() {
+            if entry.get("name").and_then(Value::as_str) == Some(from) {
+                entry["name"] = to.into();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// v4 -> v5: chunks saved before we tracked visits count as never visited.
+fn add_inhabited_time(chunk: &mut Value) -> Result<()> {
+    chunk
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("chunk is not an object"))?
+        .insert("inhabited_time".into(), 0.into());
+    Ok(())
+}
+
+/// v5 -> v6: players saved before game modes were survival players in full health.
+fn add_player_abilities(player: &mut Value) -> Result<()> {
+    let obj = player
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("player is not an object"))?;
+    obj.insert("game_mode".into(), "survival".into());
+    obj.insert("can_destroy".into(), Value::Array(vec![]));
+    obj.insert("can_place_on".into(), Value::Array(vec![]));
+    obj.insert("health".into(), 20.0.into());
+    obj.insert("food".into(), 20.into());
+    obj.insert("saturation".into(), 5.0.into());
+    obj.insert("exhaustion".into(), 0.0.into());
+    Ok(())
+}
+
+/// v6 -> v7: chunks saved before block ticks had none scheduled.
+fn add_block_ticks(chunk: &mut Value) -> Result<()> {
+    chunk
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("chunk is not an object"))?
+        .insert("block_ticks".into(), Value::Array(vec![]));
+    Ok(())
+}
+
+/// v6 -> v7: levels saved before game rules get the defaults.
+fn add_game_rules(level: &mut Value) -> Result<()> {
+    level
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("level is not an object"))?
+        .insert(
+            "game_rules".into(),
+            serde_json::json!({ "randomTickSpeed": 3 }),
+        );
+    Ok(())
+}
+
+/// v7 -> v8: chunks saved before entities had none in them.
+fn add_entities(chunk: &mut Value) -> Result<()> {
+    chunk
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("chunk is not an object"))?
+        .insert("entities".into(), Value::Array(vec![]));
+    Ok(())
+}
@@ -0,0 +1,356 @@
+//! ```text
+//! package net.minecraft.util.datafix
+//! class DataFixesManager
+//! version 1.16
+//! ```
+//!
+//! Upgrades saved documents (chunks, level data, player data) from the data version they were
+//! written with to [`CURRENT_DATA_VERSION`]. Fixes run lazily, right after a file is read and
+//! before it is deserialized into the typed structs in [`crate::world::storage`].
+
+use anyhow::*;
+use once_cell::sync::Lazy;
+use serde_json::Value;
+
+mod fixes;
+
+/// Version stamp of a saved document. Bump it whenever a save format changes and register a fix
+/// for the new version in [`fixes::register_all`].
+pub type DataVersion = u32;
+
+/// The version every document is written with.
+///
+/// History:
+/// - 1: first format. Section palettes are plain strings, blocks are an array of 4096 indices.
+/// - 2: palette entries are `{ name, properties }` objects.
+/// - 3: block indices are packed into `u64` words (`bits` + `data`).
+/// - 4: `minecraft:grass` is renamed to `minecraft:grass_block`.
+/// - 5: chunks record `inhabited_time`, the ticks players spent nearby.
+/// - 6: players record their game mode, adventure tags, health and hunger.
+/// - 7: chunks keep their scheduled `block_ticks`, the level its `game_rules`.
+/// - 8: chunks keep the `entities` in them.
+pub const CURRENT_DATA_VERSION: DataVersion = 8;
+
+/// Documents written before versions were stamped are treated as this version.
+pub const FIRST_DATA_VERSION: DataVersion = 1;
+
+/// The key holding the version in every document.
+pub const DATA_VERSION_KEY: &str = "data_version";
+
+/// Same as Minecraft's `TypeReferences`: which kind of document a fix applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DataFixType {
+    Chunk,
+    Level,
+    Player,
+}
+
+pub type FixFn = fn(&mut Value) -> Result<()>;
+
+/// A single migration step. It upgrades a document of `ty` to `version`.
+pub struct DataFix {
+    pub version: DataVersion,
+    pub ty: DataFixType,
+    pub name: &'static str,
+    pub fix: FixFn,
+}
+
+/// The ordered list of every registered [`DataFix`].
+pub struct DataFixer {
+    fixes: Vec<DataFix>,
+}
+
+pub static DATA_FIXER: Lazy<DataFixer> = Lazy::new(|| {
+    let mut r = DataFixer::new();
+    fixes::register_all(&mut r);
+    r
+});
+
+impl DataFixer {
+    pub fn new() -> Self {
+        Self { fixes: Vec::new() }
+    }
+
+    pub fn register(
+        &mut self,
+        version: DataVersion,
+        ty: DataFixType,
+        name: &'static str,
+        fix: FixFn,
+    ) {
+        assert!(
+            version > FIRST_DATA_VERSION && version <= CURRENT_DATA_VERSION,
+            "fix {} targets unknown data version {}",
+            name,
+            version
+        );
+        self.fixes.push(DataFix {
+            version,
+            ty,
+            name,
+            fix,
+        });
+        // stable, so fixes for the same version keep their registration order
+        self.fixes.sort_by_key(|f| f.version);
+    }
+
+    /// Read the version stamped on a document.
+    pub fn version_of(value: &Value) -> Result<DataVersion> {
+        match value.get(DATA_VERSION_KEY) {
+            None => Ok(FIRST_DATA_VERSION),
+            Some(v) => v
+                .as_u64()
+                .and_then(|v| DataVersion::try_from(v).ok())
+                .ok_or_else(|| anyhow!("{} is not a valid version: {}", DATA_VERSION_KEY, v)),
+        }
+    }
+
+    /// Stamp a document with a version.
+    pub fn stamp(value: &mut Value, version: DataVersion) -> Result<()> {
+        let obj = value
+            .as_object_mut()
+            .ok_or_else(|| anyhow!("saved document is not an object"))?;
+        obj.insert(DATA_VERSION_KEY.to_string(), version.into());
+        Ok(())
+    }
+
+    /// Upgrade `value` in place to [`CURRENT_DATA_VERSION`].
+    pub fn update(&self, ty: DataFixType, value: &mut Value) -> Result<()> {
+        self.update_to(ty, value, CURRENT_DATA_VERSION)
+    }
+
+    /// Upgrade `value` in place to `target`. Documents from the future are rejected rather than
+    /// silently loaded, since we can't know what we'd lose on the next save.
+    pub fn update_to(&self, ty: DataFixType, value: &mut Value, target: DataVersion) -> Result<()> {
+        let from = Self::version_of(value)?;
+        if from > CURRENT_DATA_VERSION {
+            bail!(
+                "{:?} data version {} is newer than supported version {}",
+                ty,
+                from,
+                CURRENT_DATA_VERSION
+            );
+        }
+        if from == target {
+            return Ok(());
+        }
+
+        for fix in self
+            .fixes
+            .iter()
+            .filter(|f| f.ty == ty && f.version > from && f.version <= target)
+        {
+            log::debug!(
+                "Applying data fix {} ({:?} -> {})",
+                fix.name,
+                ty,
+                fix.version
+            );
+            (fix.fix)(value).with_context(|| format!("data fix {} failed", fix.name))?;
+        }
+        Self::stamp(value, target)
+    }
+
+    /// Names of every fix that runs for `ty` starting at `from`, in order.
+    pub fn pending(&self, ty: DataFixType, from: DataVersion) -> Vec<&'static str> {
+        self.fixes
+            .iter()
+            .filter(|f| f.ty == ty && f.version > from)
+            .map(|f| f.name)
+            .collect()
+    }
+}
+
+impl Default for DataFixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        entity::game_mode::GameMode,
+        world::storage::{chunk_serializer::ChunkData, LevelData, PlayerData},
+    };
+
+    fn upgrade<T: serde::de::DeserializeOwned>(ty: DataFixType, src: &str) -> T {
+        let mut value: Value = serde_json::from_str(src).unwrap();
+        DATA_FIXER.update(ty, &mut value).unwrap();
+        assert_eq!(DataFixer::version_of(&value).unwrap(), CURRENT_DATA_VERSION);
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// Every chunk fixture holds the same column: a bottom layer of granite, stone above it and
+    /// a single grass block on top. Each is written in the format of its data version.
+    fn upgrade_chunk_fixture(src: &str) -> ChunkData {
+        let chunk: ChunkData = upgrade(DataFixType::Chunk, src);
+        assert_eq!((chunk.x, chunk.z), (3, -2));
+        assert_eq!(chunk.inhabited_time, 0);
+        assert_eq!(chunk.sections.len(), 1);
+
+        let section = &chunk.sections[0];
+        let names: Vec<_> = section.palette.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "minecraft:air",
+                "minecraft:stone",
+                "minecraft:grass_block",
+                "minecraft:stone"
+            ]
+        );
+        assert_eq!(section.palette[3].properties["variant"], "granite");
+        assert!(section.palette[1].properties.is_empty());
+
+        let indices = section.unpack().unwrap();
+        assert_eq!(indices[0], 3);
+        assert_eq!(indices[256], 1);
+        assert_eq!(indices[512], 2);
+        assert_eq!(indices[513], 0);
+        assert_eq!(indices[4095], 0);
+        chunk
+    }
+
+    /// A fixture from before block ticks and entities were saved, which has neither.
+    fn check_chunk_fixture(src: &str) {
+        let chunk = upgrade_chunk_fixture(src);
+        assert!(chunk.block_ticks.is_empty());
+        assert!(chunk.entities.is_empty());
+    }
+
+    #[test]
+    fn upgrade_chunk_v1() {
+        check_chunk_fixture(include_str!("fixtures/chunk_v1.json"));
+    }
+
+    #[test]
+    fn upgrade_chunk_v2() {
+        check_chunk_fixture(include_str!("fixtures/chunk_v2.json"));
+    }
+
+    #[test]
+    fn upgrade_chunk_v3() {
+        check_chunk_fixture(include_str!("fixtures/chunk_v3.json"));
+    }
+
+    #[test]
+    fn upgrade_chunk_v4() {
+        check_chunk_fixture(include_str!("fixtures/chunk_v4.json"));
+    }
+
+    #[test]
+    fn upgrade_chunk_v5() {
+        check_chunk_fixture(include_str!("fixtures/chunk_v5.json"));
+    }
+
+    #[test]
+    fn upgrade_chunk_v6() {
+        // from before block ticks, which an upgraded chunk has none of
+        check_chunk_fixture(include_str!("fixtures/chunk_v6.json"));
+    }
+
+    #[test]
+    fn upgrade_chunk_v7() {
+        // from before entities, the block ticks it kept come through
+        let chunk = upgrade_chunk_fixture(include_str!("fixtures/chunk_v7.json"));
+        assert!(chunk.entities.is_empty());
+        let ticks: Vec<_> = chunk
+            .block_ticks
+            .iter()
+            .map(|t| (t.pos(), t.block.as_str(), t.due, t.priority))
+            .collect();
+        assert_eq!(
+            ticks,
+            [
+                (ivec3(48, 3, -32), "minecraft:sand", 205, 0),
+                (ivec3(49, 1, -31), "minecraft:water", 210, -1)
+            ]
+        );
+    }
+
+    #[test]
+    fn upgrade_chunk_v8() {
+        let chunk = upgrade_chunk_fixture(include_str!("fixtures/chunk_v8.json"));
+        assert_eq!(chunk.block_ticks.len(), 1);
+        assert_eq!(chunk.block_ticks[0].pos(), ivec3(48, 2, -32));
+        assert_eq!(chunk.block_ticks[0].due, 1300);
+        assert_eq!(chunk.entities.len(), 1);
+        let item = chunk.entities[0].item.as_ref().unwrap();
+        assert_eq!((item.id.as_str(), item.count), ("minecraft:dirt", 5));
+        assert_eq!(chunk.entities[0].age, 40);
+    }
+
+    #[test]
+    fn upgrade_level_v1() {
+        let level: LevelData = upgrade(DataFixType::Level, include_str!("fixtures/level_v1.json"));
+        assert_eq!(level.seed, 8675309);
+        assert_eq!(level.spawn, [0, 64, 0]);
+        assert_eq!(level.game_rules.random_tick_speed, 3);
+    }
+
+    #[test]
+    fn upgrade_level_v6() {
+        let level: LevelData = upgrade(DataFixType::Level, include_str!("fixtures/level_v6.json"));
+        assert_eq!(level.spawn, [16, 70, -8]);
+        assert_eq!(level.time, 24000);
+        assert_eq!(level.game_rules.random_tick_speed, 3);
+    }
+
+    #[test]
+    fn upgrade_player_v1() {
+        let player: PlayerData =
+            upgrade(DataFixType::Player, include_str!("fixtures/player_v1.json"));
+        assert_eq!(player.position, [0.5, 65.0, 0.5]);
+        assert_eq!(player.game_mode, GameMode::Survival);
+        assert!(player.can_destroy.is_empty());
+        assert_eq!((player.health, player.food), (20.0, 20));
+    }
+
+    #[test]
+    fn upgrade_player_v5() {
+        let player: PlayerData =
+            upgrade(DataFixType::Player, include_str!("fixtures/player_v5.json"));
+        assert_eq!(player.yaw, 90.0);
+        assert_eq!(player.game_mode, GameMode::Survival);
+        assert!(player.can_place_on.is_empty());
+        assert_eq!((player.health, player.food), (20.0, 20));
+    }
+
+    #[test]
+    fn reject_future_version() {
+        let mut value = json!({ "data_version": CURRENT_DATA_VERSION + 1 });
+        assert!(DATA_FIXER.update(DataFixType::Level, &mut value).is_err());
+    }
+
+    #[test]
+    fn reject_version_past_u32() {
+        // would be 1 if it was truncated, and every fix would run over it
+        let mut value = json!({ "data_version": u32::MAX as u64 + 2 });
+        assert!(DATA_FIXER.update(DataFixType::Chunk, &mut value).is_err());
+        assert_eq!(value["data_version"], u32::MAX as u64 + 2);
+    }
+
+    #[test]
+    fn fixes_are_ordered() {
+        let pending = DATA_FIXER.pending(DataFixType::Chunk, FIRST_DATA_VERSION);
+        assert_eq!(
+            pending,
+            [
+                "palette_entry_objects",
+                "pack_block_indices",
+                "rename_grass_block",
+                "add_inhabited_time",
+                "add_block_ticks",
+                "add_entities"
+            ]
+        );
+        assert!(DATA_FIXER
+            .pending(DataFixType::Chunk, CURRENT_DATA_VERSION)
+            .is_empty());
+    }
+}
@@ -0,0 +1,226 @@
+//! ```text
+//! package net.minecraft.entity
+//! class EntityType, EntityClassification
+//! version 1.16
+//! ```
+//!
+//! What kinds of entities there are. Each [`EntityKind`] in [`ENTITY_REGISTRY`] has its size,
+//! how far players see it from, how it moves on its own and its [`SpawnGroup`]. Entities carry
+//! an [`EntityType`], the registry id of their kind, which is also what packets send.
+
+use anyhow::*;
+use bevy_ecs::component::Component;
+use blockworld_utils::{HasResourceLocation, Registry, ResourceLocation};
+use once_cell::sync::Lazy;
+
+use crate::packet::{Decode, Encode, VarInt};
+
+use super::physics::Physics;
+
+pub static ENTITY_REGISTRY: Lazy<Registry<EntityKind>> = Lazy::new(|| {
+    let mut r = Registry::new();
+    let e0 = EntityKind::new("minecraft:player".into())
+        .with_size(0.6, 1.8)
+        .with_tracking_range(32)
+        .with_update_interval(2);
+    r.register(e0);
+    let e1 = EntityKind::new("minecraft:item".into())
+        .with_size(0.25, 0.25)
+        .with_tracking_range(6)
+        .with_update_interval(20)
+        .with_physics(Physics::ITEM);
+    r.register(e1);
+    let e2 = EntityKind::new("minecraft:falling_block".into())
+        .with_size(0.98, 0.98)
+        .with_tracking_range(10)
+        .with_update_interval(20)
+        .with_physics(Physics::FALLING_BLOCK);
+    r.register(e2);
+    let e3 = EntityKind::new("minecraft:pig".into())
+        .with_size(0.9, 0.9)
+        .with_tracking_range(10)
+        .with_update_interval(3)
+        .with_physics(Physics::MOB)
+        .with_spawn_group(SpawnGroup::Creature);
+    r.register(e3);
+    r
+});
+
+/// What a kind of entity counts as, which decides whether it outlives its chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpawnGroup {
+    Monster,
+    Creature,
+    Ambient,
+    WaterCreature,
+    WaterAmbient,
+    /// Players, items and everything else that isn't a mob.
+    Misc,
+}
+
+impl SpawnGroup {
+    /// Saved with its chunk when the chunk unloads. The others are culled, as new ones spawn
+    /// wherever players go.
+    pub fn persistent(self) -> bool {
+        matches!(self, SpawnGroup::Creature | SpawnGroup::Misc)
+    }
+}
+
+pub struct EntityKind {
+    pub id: ResourceLocation,
+    /// Width and height of the bounding box, in blocks.
+    pub width: f32,
+    pub height: f32,
+    /// Players further than this many chunks don't see the entity. It never goes past the view
+    /// distance either.
+    pub tracking_range: i32,
+    /// Ticks between two movement updates. Things that mostly lie still are sent less often.
+    pub update_interval: u64,
+    /// How it moves on its own, `None` for players who move by their keys.
+    pub physics: Option<Physics>,
+    pub spawn_group: SpawnGroup,
+}
+
+impl HasResourceLocation for EntityKind {
+    fn get_id(&self) -> ResourceLocation {
+        self.id.clone()
+    }
+}
+
+impl EntityKind {
+    pub fn new(id: ResourceLocation) -> Self {
+        Self {
+            id,
+            width: 0.6,
+            height: 1.8,
+            tracking_range: 5,
+            update_interval: 3,
+            physics: None,
+            spawn_group: SpawnGroup::Misc,
+        }
+    }
+
+    pub fn with_size(mut self, width: f32, height: f32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_tracking_range(mut self, chunks: i32) -> Self {
+        self.tracking_range = chunks;
+        self
+    }
+
+    pub fn with_update_interval(mut self, ticks: u64) -> Self {
+        self.update_interval = ticks;
+        self
+    }
+
+    pub fn with_physics(mut self, physics: Physics) -> Self {
+        self.physics = Some(physics);
+        self
+    }
+
+    pub fn with_spawn_group(mut self, group: SpawnGroup) -> Self {
+        self.spawn_group = group;
+        self
+    }
+}
+
+/// The kind of an entity, by its id in [`ENTITY_REGISTRY`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntityType(pub u32);
+
+impl EntityType {
+    pub const PLAYER: EntityType = EntityType(0);
+    pub const ITEM: EntityType = EntityType(1);
+    pub const FALLING_BLOCK: EntityType = EntityType(2);
+    pub const PIG: EntityType = EntityType(3);
+
+    /// The kind registered as `name`, if there is one.
+    pub fn of(name: &ResourceLocation) -> Option<Self> {
+        ENTITY_REGISTRY.get(name)?;
+        Some(EntityType(ENTITY_REGISTRY.name_to_number_id(name)))
+    }
+
+    pub fn name(&self) -> &'static ResourceLocation {
+        ENTITY_REGISTRY
+            .number_id_to_name(self.0)
+            .expect("entity types are only made from registered ids")
+    }
+
+    pub fn kind(&self) -> &'static EntityKind {
+        ENTITY_REGISTRY.get(self.name()).unwrap()
+    }
+
+    /// Width and height of the bounding box, in blocks.
+    pub fn size(&self) -> (f32, f32) {
+        let kind = self.kind();
+        (kind.width, kind.height)
+    }
+
+    pub fn tracking_range(&self) -> i32 {
+        self.kind().tracking_range
+    }
+
+    pub fn update_interval(&self) -> u64 {
+        self.kind().update_interval
+    }
+
+    pub fn spawn_group(&self) -> SpawnGroup {
+        self.kind().spawn_group
+    }
+}
+
+impl Encode for EntityType {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        VarInt(self.0 as i32).encode(buf);
+    }
+}
+
+impl Decode for EntityType {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        let id = VarInt::decode(buf)?.0;
+        match u32::try_from(id)
+            .ok()
+            .filter(|id| ENTITY_REGISTRY.number_id_to_name(*id).is_some())
+        {
+            Some(id) => Ok(EntityType(id)),
+            None => bail!("invalid entity type {}", id),
+        }
+    }
+}
+
+#[cfg(test)]
+impl crate::packet::codec::Sample for EntityType {
+    fn sample() -> Self {
+        EntityType::PIG
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constants_match_the_registry() {
+        for (kind, name) in [
+            (EntityType::PLAYER, "minecraft:player"),
+            (EntityType::ITEM, "minecraft:item"),
+            (EntityType::FALLING_BLOCK, "minecraft:falling_block"),
+            (EntityType::PIG, "minecraft:pig"),
+        ] {
+            assert_eq!(EntityType::of(&name.into()), Some(kind));
+            assert_eq!(&**kind.name(), name);
+        }
+        assert_eq!(EntityType::of(&"minecraft:creeper".into()), None);
+        assert!(EntityType::decode(&mut &[4u8][..]).is_err());
+    }
+
+    #[test]
+    fn mobs_outlive_their_chunk() {
+        assert!(EntityType::PIG.spawn_group().persistent());
+        assert!(EntityType::ITEM.spawn_group().persistent());
+        assert!(!SpawnGroup::Monster.persistent());
+    }
+}
@@ -0,0 +1,135 @@
+//! ```text
+//! package net.minecraft.entity.item
+//! class FallingBlockEntity
+//! version 1.16
+//! ```
+//!
+//! Blocks like sand falling as entities. A block that starts to fall turns to air and its
+//! entity takes over; where it lands it becomes the block again, if the place it lands in can
+//! be replaced. If it can't, or it falls for longer than [`FALL_TIMEOUT`], it breaks into an
+//! item instead.
+
+use bevy_ecs::{
+    bundle::Bundle,
+    component::Component,
+    entity::Entity,
+    system::{Commands, Query, ResMut},
+};
+use glam::*;
+
+use crate::{
+    block::state::BlockState,
+    components::{OnGround, Position},
+    world::{
+        block_tick::BlockTickContext,
+        chunk_access::{BlockPos, WorldAccess},
+        disk_chunk_access::DiskChunkArray,
+        random::WorldRandom,
+        storage::chunk_serializer::PaletteEntry,
+    },
+};
+
+use super::{
+    interaction::replaceable, item::drop_item, persistence::new_data, physics::Physics, Age,
+    EntityBundle, EntityType, Uuid,
+};
+
+/// Ticks a block falls before it gives up and breaks.
+pub const FALL_TIMEOUT: u32 = 600;
+
+/// The block a falling block entity is.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FallingBlock(pub BlockState);
+
+#[derive(Bundle)]
+pub struct FallingBlockBundle {
+    pub entity: EntityBundle,
+    pub block: FallingBlock,
+    pub physics: Physics,
+}
+
+/// Take the block at `pos` out of the world and let it fall.
+pub fn start_falling(ctx: &mut BlockTickContext, pos: BlockPos) {
+    let state = ctx.chunks.get_state(pos);
+    let position = pos.as_vec3() + vec3(0.5, 0.0, 0.5);
+    let mut data = new_data(
+        EntityType::FALLING_BLOCK,
+        Uuid::random(ctx.random),
+        position,
+    );
+    data.block = Some(PaletteEntry::of_state(state));
+    ctx.chunks.set_state(pos, BlockState::AIR);
+    ctx.chunks.add_entity(data);
+}
+
+/// Falling blocks that landed become blocks again, or items, in [`TickSet::EntityAi`].
+///
+/// [`TickSet::EntityAi`]: crate::tick::TickSet::EntityAi
+pub fn land(
+    mut commands: Commands,
+    mut chunks: ResMut<DiskChunkArray>,
+    mut random: ResMut<WorldRandom>,
+    falling: Query<(Entity, &FallingBlock, &Position, &OnGround, &Age)>,
+) {
+    for (entity, block, position, on_ground, age) in &falling {
+        if !on_ground.0 && age.0 <= FALL_TIMEOUT {
+            continue;
+        }
+        commands.entity(entity).despawn();
+        // the block its middle is in
+        let pos = (position.0 + Vec3::Y * 0.49).floor().as_ivec3();
+        if on_ground.0 && replaceable(&chunks.get_block(pos)) {
+            chunks.set_state(pos, block.0);
+        } else {
+            let item = block.0.name().clone();
+            drop_item(&mut chunks, &mut random, position.0, &item, 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::redstone::harness::Circuit;
+
+    fn falling(circuit: &mut Circuit) -> Vec<Vec3> {
+        let ecs = circuit.world_mut().ecs_mut();
+        ecs.query::<(&FallingBlock, &Position)>()
+            .iter(ecs)
+            .map(|(_, position)| position.0)
+            .collect()
+    }
+
+    #[test]
+    fn sand_falls_as_an_entity_and_lands() {
+        let mut circuit = Circuit::build(&[&["#"], &["."], &["."], &["."], &["s"]], &[]).unwrap();
+        // the sand has nothing under it once a block beside it changes
+        circuit.set(ivec3(1, 4, 0), "stone").unwrap();
+        circuit.step(3);
+        assert!(circuit.state(ivec3(0, 4, 0)).is_air());
+        assert_eq!(falling(&mut circuit).len(), 1);
+        circuit.step(20);
+        assert!(falling(&mut circuit).is_empty());
+        assert_eq!(&**circuit.state(ivec3(0, 1, 0)).name(), "minecraft:sand");
+    }
+
+    #[test]
+    fn sand_landing_on_a_slab_breaks() {
+        let mut circuit = Circuit::build(
+            &[&["#"], &["h"], &["."], &["."], &["s"]],
+            &[('h', "smooth_stone_slab")],
+        )
+        .unwrap();
+        circuit.set(ivec3(1, 4, 0), "stone").unwrap();
+        circuit.step(30);
+        assert!(falling(&mut circuit).is_empty());
+        assert!(circuit.state(ivec3(0, 2, 0)).is_air());
+        let ecs = circuit.world_mut().ecs_mut();
+        let items: Vec<_> = ecs
+            .query::<&crate::entity::item::ItemEntity>()
+            .iter(ecs)
+            .map(|item| item.item.to_string())
+            .collect();
+        assert_eq!(items, ["minecraft:sand"]);
+    }
+}
@@ -0,0 +1,247 @@
+//! ```text
+//! package net.minecraft.world
+//! class GameType, entity.player.PlayerAbilities
+//! version 1.16
+//! ```
+//!
+//! What a player may do. Creative players fly, break blocks at once, reach a little further
+//! and take no damage. Survival players walk, take the block's time to break it and have
+//! health and hunger (`health.rs`). Spectators fly through blocks and touch nothing. Adventure
+//! players are survival players who break and place only the blocks their [`AdventureTags`]
+//! name, like Minecraft's `CanDestroy` and `CanPlaceOn` item tags.
+//!
+//! Players hold no items yet, so every mode places blocks without running out.
+//!
+//! The client learns its mode and tags from the server and follows the same rules, so what it
+//! predicts is what the server does.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::*;
+use bevy_ecs::component::Component;
+use blockworld_utils::ResourceLocation;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    block::{NumberID, BLOCK_REGISTRY},
+    packet::{play::PlayClientbound, Decode, Encode, VarInt},
+};
+
+use super::{interaction::REACH, movement::MoveInput};
+
+/// Reach of creative players, over the [`REACH`] of the others.
+pub const CREATIVE_REACH: f32 = 5.0;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GameMode {
+    #[default]
+    Survival = 0,
+    Creative = 1,
+    Adventure = 2,
+    Spectator = 3,
+}
+
+impl GameMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Survival => "survival",
+            GameMode::Creative => "creative",
+            GameMode::Adventure => "adventure",
+            GameMode::Spectator => "spectator",
+        }
+    }
+
+    pub fn may_fly(self) -> bool {
+        matches!(self, GameMode::Creative | GameMode::Spectator)
+    }
+
+    /// Through blocks, and always flying.
+    pub fn noclip(self) -> bool {
+        self == GameMode::Spectator
+    }
+
+    /// No damage, and no hunger either.
+    pub fn invulnerable(self) -> bool {
+        matches!(self, GameMode::Creative | GameMode::Spectator)
+    }
+
+    /// Blocks break on the first hit.
+    pub fn instant_break(self) -> bool {
+        self == GameMode::Creative
+    }
+
+    /// Breaks and places blocks at all.
+    pub fn may_interact(self) -> bool {
+        self != GameMode::Spectator
+    }
+
+    /// How far the player points at blocks.
+    pub fn reach(self) -> f32 {
+        match self {
+            GameMode::Creative => CREATIVE_REACH,
+            _ => REACH,
+        }
+    }
+
+    /// May break `block`.
+    pub fn may_break(self, tags: &AdventureTags, block: &ResourceLocation) -> bool {
+        match self {
+            GameMode::Spectator => false,
+            GameMode::Adventure => tags.can_destroy.contains(block),
+            _ => true,
+        }
+    }
+
+    /// May place blocks against `block`.
+    pub fn may_place_on(self, tags: &AdventureTags, block: &ResourceLocation) -> bool {
+        match self {
+            GameMode::Spectator => false,
+            GameMode::Adventure => tags.can_place_on.contains(block),
+            _ => true,
+        }
+    }
+
+    /// `input` as this mode lets it move: flying only if it may, spectators always flying
+    /// and through blocks.
+    pub fn restrict(self, input: MoveInput) -> MoveInput {
+        MoveInput {
+            flying: (input.flying && self.may_fly()) || self.noclip(),
+            noclip: self.noclip(),
+            ..input
+        }
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A name or its number, as in `gamemode creative` or `gamemode 1`.
+impl FromStr for GameMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mode = match s.to_ascii_lowercase().as_str() {
+            "survival" | "0" => GameMode::Survival,
+            "creative" | "1" => GameMode::Creative,
+            "adventure" | "2" => GameMode::Adventure,
+            "spectator" | "3" => GameMode::Spectator,
+            _ => bail!("Unknown game mode {}", s),
+        };
+        Ok(mode)
+    }
+}
+
+impl Encode for GameMode {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        VarInt(*self as i32).encode(buf);
+    }
+}
+
+impl Decode for GameMode {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        Ok(match VarInt::decode(buf)?.0 {
+            0 => GameMode::Survival,
+            1 => GameMode::Creative,
+            2 => GameMode::Adventure,
+            3 => GameMode::Spectator,
+            m => bail!("invalid game mode {}", m),
+        })
+    }
+}
+
+#[cfg(test)]
+impl crate::packet::codec::Sample for GameMode {
+    fn sample() -> Self {
+        GameMode::Adventure
+    }
+}
+
+/// The blocks an adventure player may break, and those it may place blocks against.
+#[derive(Component, Debug, Clone, PartialEq, Eq, Default)]
+pub struct AdventureTags {
+    pub can_destroy: Vec<ResourceLocation>,
+    pub can_place_on: Vec<ResourceLocation>,
+}
+
+impl AdventureTags {
+    /// Tags from block ids, leaving out those the registry doesn't know.
+    pub fn from_names(can_destroy: &[String], can_place_on: &[String]) -> Self {
+        let known = |names: &[String]| {
+            names
+                .iter()
+                .map(|n| ResourceLocation::from(n.as_str()))
+                .filter(|id| BLOCK_REGISTRY.get(id).is_some())
+                .collect()
+        };
+        Self {
+            can_destroy: known(can_destroy),
+            can_place_on: known(can_place_on),
+        }
+    }
+
+    /// Tags from the registry ids of a [`PlayClientbound::Abilities`].
+    pub fn from_ids(can_destroy: &[VarInt], can_place_on: &[VarInt]) -> Self {
+        let names = |ids: &[VarInt]| {
+            ids.iter()
+                .filter_map(|id| BLOCK_REGISTRY.number_id_to_name(id.0 as NumberID))
+                .cloned()
+                .collect()
+        };
+        Self {
+            can_destroy: names(can_destroy),
+            can_place_on: names(can_place_on),
+        }
+    }
+
+    /// The packet telling a player in `mode` with these tags what it may do.
+    pub fn abilities(&self, mode: GameMode) -> PlayClientbound {
+        let ids = |names: &[ResourceLocation]| {
+            names
+                .iter()
+                .map(|n| VarInt(BLOCK_REGISTRY.name_to_number_id(n) as i32))
+                .collect()
+        };
+        PlayClientbound::Abilities {
+            game_mode: mode,
+            can_destroy: ids(&self.can_destroy),
+            can_place_on: ids(&self.can_place_on),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_decide_flying_and_blocks() {
+        let fly = MoveInput {
+            flying: true,
+            ..Default::default()
+        };
+        assert!(!GameMode::Survival.restrict(fly).flying);
+        assert!(GameMode::Creative.restrict(fly).flying);
+        let spectating = GameMode::Spectator.restrict(MoveInput::default());
+        assert!(spectating.flying && spectating.noclip);
+
+        let tags = AdventureTags {
+            can_destroy: vec!["minecraft:stone".into()],
+            can_place_on: vec![],
+        };
+        let stone = "minecraft:stone".into();
+        let dirt = "minecraft:dirt".into();
+        assert!(GameMode::Survival.may_break(&tags, &dirt));
+        assert!(GameMode::Adventure.may_break(&tags, &stone));
+        assert!(!GameMode::Adventure.may_break(&tags, &dirt));
+        assert!(!GameMode::Adventure.may_place_on(&tags, &stone));
+        assert!(!GameMode::Spectator.may_break(&tags, &stone));
+
+        assert_eq!("Creative".parse::<GameMode>().unwrap(), GameMode::Creative);
+        assert_eq!("3".parse::<GameMode>().unwrap(), GameMode::Spectator);
+        assert!("hardcore".parse::<GameMode>().is_err());
+    }
+}
@@ -0,0 +1,214 @@
+//! ```text
+//! package net.minecraft.util
+//! class FoodStats, entity.LivingEntity (causeFallDamage)
+//! version 1.16
+//! ```
+//!
+//! Health and hunger of players in survival and adventure. Moving, jumping and breaking blocks
+//! tire a player; every 4 points of exhaustion take saturation, or food once saturation is
+//! gone. A player with 18 food or more heals one point every 4 seconds, which tires it too, and
+//! one with none starves a point every 4 seconds down to the last one. Falls of more than 3
+//! blocks hurt a point a block, unless they end in water. Constants are Minecraft's, on normal
+//! difficulty.
+
+use bevy_ecs::{component::Component, system::Query};
+
+use super::game_mode::GameMode;
+
+pub const MAX_HEALTH: f32 = 20.0;
+pub const MAX_FOOD: u32 = 20;
+pub const START_SATURATION: f32 = 5.0;
+/// Exhaustion per block sprinted.
+pub const SPRINT_EXHAUSTION: f32 = 0.1;
+pub const JUMP_EXHAUSTION: f32 = 0.05;
+pub const SPRINT_JUMP_EXHAUSTION: f32 = 0.2;
+pub const BREAK_EXHAUSTION: f32 = 0.005;
+/// Blocks a player falls without getting hurt.
+const SAFE_FALL: f32 = 3.0;
+/// Exhaustion that takes a point of saturation or food.
+const EXHAUSTION_PER_POINT: f32 = 4.0;
+const MAX_EXHAUSTION: f32 = 40.0;
+/// Food it takes to heal.
+const REGEN_FOOD: u32 = 18;
+const REGEN_EXHAUSTION: f32 = 6.0;
+/// Ticks between two points healed or starved.
+const HUNGER_INTERVAL: u32 = 80;
+/// Starving stops here.
+const STARVE_LIMIT: f32 = 1.0;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Health(pub f32);
+
+impl Default for Health {
+    fn default() -> Self {
+        Self(MAX_HEALTH)
+    }
+}
+
+impl Health {
+    /// Take `amount` of damage, unless `mode` doesn't. True if that killed.
+    pub fn hurt(&mut self, amount: f32, mode: GameMode) -> bool {
+        if mode.invulnerable() || amount <= 0.0 {
+            return false;
+        }
+        self.0 = (self.0 - amount).max(0.0);
+        self.is_dead()
+    }
+
+    pub fn heal(&mut self, amount: f32) {
+        self.0 = (self.0 + amount).min(MAX_HEALTH);
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.0 <= 0.0
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Hunger {
+    pub food: u32,
+    pub saturation: f32,
+    pub exhaustion: f32,
+    /// Ticks towards the next point healed or starved.
+    timer: u32,
+}
+
+impl Default for Hunger {
+    fn default() -> Self {
+        Self::new(MAX_FOOD, START_SATURATION, 0.0)
+    }
+}
+
+impl Hunger {
+    pub fn new(food: u32, saturation: f32, exhaustion: f32) -> Self {
+        Self {
+            food: food.min(MAX_FOOD),
+            saturation: saturation.clamp(0.0, food as f32),
+            exhaustion: exhaustion.clamp(0.0, MAX_EXHAUSTION),
+            timer: 0,
+        }
+    }
+
+    pub fn exhaust(&mut self, amount: f32) {
+        self.exhaustion = (self.exhaustion + amount).min(MAX_EXHAUSTION);
+    }
+
+    /// One tick of spending exhaustion, healing `health` on a full bar and starving it on an
+    /// empty one.
+    pub fn tick(&mut self, health: &mut Health) {
+        if self.exhaustion > EXHAUSTION_PER_POINT {
+            self.exhaustion -= EXHAUSTION_PER_POINT;
+            match self.saturation > 0.0 {
+                true => self.saturation = (self.saturation - 1.0).max(0.0),
+                false => self.food = self.food.saturating_sub(1),
+            }
+        }
+
+        let hurt = health.0 < MAX_HEALTH;
+        if self.food >= REGEN_FOOD && hurt {
+            self.timer += 1;
+            if self.timer >= HUNGER_INTERVAL {
+                health.heal(1.0);
+                self.exhaust(REGEN_EXHAUSTION);
+                self.timer = 0;
+            }
+        } else if self.food == 0 {
+            self.timer += 1;
+            if self.timer >= HUNGER_INTERVAL {
+                if health.0 > STARVE_LIMIT {
+                    health.0 = (health.0 - 1.0).max(STARVE_LIMIT);
+                }
+                self.timer = 0;
+            }
+        } else {
+            self.timer = 0;
+        }
+    }
+}
+
+/// Blocks fallen since the player last stood on something.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default)]
+pub struct FallDistance(pub f32);
+
+impl FallDistance {
+    /// Count a move of `dy` blocks up, ending `on_ground` or not. Water and flying break the
+    /// fall. The damage of the fall, once it lands.
+    pub fn update(&mut self, dy: f32, on_ground: bool, cushioned: bool) -> f32 {
+        if cushioned {
+            self.0 = 0.0;
+            return 0.0;
+        }
+        if dy < 0.0 {
+            self.0 -= dy;
+        }
+        if !on_ground {
+            return 0.0;
+        }
+        let damage = (self.0 - SAFE_FALL).ceil().max(0.0);
+        self.0 = 0.0;
+        damage
+    }
+}
+
+/// Tick the hunger of every player that has it.
+pub fn hunger(mut players: Query<(&GameMode, &mut Health, &mut Hunger)>) {
+    for (mode, mut health, mut hunger) in players.iter_mut() {
+        if !mode.invulnerable() && !health.is_dead() {
+            hunger.tick(&mut health);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_hurt_past_three_blocks() {
+        let mut fall = FallDistance::default();
+        for _ in 0..10 {
+            assert_eq!(fall.update(-0.5, false, false), 0.0);
+        }
+        // five blocks, two of them too many
+        assert_eq!(fall.update(0.0, true, false), 2.0);
+        assert_eq!(fall.0, 0.0);
+
+        fall.update(-3.0, false, false);
+        assert_eq!(fall.update(0.0, true, false), 0.0);
+        fall.update(-20.0, false, false);
+        assert_eq!(fall.update(-1.0, true, true), 0.0);
+
+        let mut health = Health::default();
+        assert!(!health.hurt(30.0, GameMode::Creative));
+        assert!(health.hurt(30.0, GameMode::Survival));
+        assert_eq!(health.0, 0.0);
+    }
+
+    #[test]
+    fn hunger_heals_then_starves() {
+        let mut health = Health(10.0);
+        let mut hunger = Hunger::default();
+        for _ in 0..HUNGER_INTERVAL {
+            hunger.tick(&mut health);
+        }
+        assert_eq!(health.0, 11.0);
+        assert_eq!(hunger.exhaustion, REGEN_EXHAUSTION);
+        // the healing spent a point of saturation
+        hunger.tick(&mut health);
+        assert_eq!((hunger.saturation, hunger.food), (4.0, MAX_FOOD));
+
+        // sprinting the saturation away and then the food
+        let mut hunger = Hunger::default();
+        let mut health = Health::default();
+        for _ in 0..(5 + MAX_FOOD) {
+            hunger.exhaust(EXHAUSTION_PER_POINT + 0.1);
+            hunger.tick(&mut health);
+            hunger.exhaustion = 0.0;
+        }
+        assert_eq!((hunger.saturation, hunger.food), (0.0, 0));
+        for _ in 0..HUNGER_INTERVAL * 30 {
+            hunger.tick(&mut health);
+        }
+        assert_eq!(health.0, STARVE_LIMIT);
+    }
+}
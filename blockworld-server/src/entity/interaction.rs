@@ -0,0 +1,329 @@
+//! ```text
+//! package net.minecraft.server.management
+//! class PlayerInteractionManager, block.AbstractBlock (getDestroyProgress)
+//! version 1.16
+//! ```
+//!
+//! Breaking and placing blocks. The client digs for as many ticks as the block takes and says
+//! when it started and when it finished; the server times the same dig, and only breaks the
+//! block if most of that time went by, so lag doesn't cost a block but a hacked client can't
+//! break faster. A block placed goes against a solid face of the block clicked, into a block
+//! that can be replaced and where no entity stands. Both sides check the same rules here.
+
+use bevy_ecs::component::Component;
+use glam::*;
+
+use crate::{
+    block::{
+        block_face_direction::BlockFaceDirection,
+        shape::{collision_shape, Aabb},
+        state::BlockState,
+        Block, ToolKind, BLOCK_REGISTRY,
+    },
+    world::chunk_access::{BlockPos, WorldAccess},
+};
+
+use super::game_mode::{AdventureTags, GameMode};
+
+/// Height of a player's eyes over its feet.
+pub const EYE_HEIGHT: f32 = 1.62;
+/// How far players point at blocks.
+pub const REACH: f32 = 4.5;
+/// How far from the eyes to the block's center the server lets players act, more than
+/// [`REACH`] for the lag.
+pub const MAX_REACH: f32 = 6.0;
+/// Share of the break time the server wants to have seen itself.
+pub const BREAK_TOLERANCE: f32 = 0.7;
+/// Ticks between breaking a block and starting on the next one.
+pub const BREAK_DELAY: u32 = 5;
+/// Ticks between two blocks placed while the button is held.
+pub const PLACE_DELAY: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolTier {
+    Wood,
+    Stone,
+    Iron,
+    Diamond,
+    Gold,
+}
+
+impl ToolTier {
+    /// How many times faster than a hand it breaks the blocks it's for.
+    pub fn speed(self) -> f32 {
+        match self {
+            ToolTier::Wood => 2.0,
+            ToolTier::Stone => 4.0,
+            ToolTier::Iron => 6.0,
+            ToolTier::Diamond => 8.0,
+            ToolTier::Gold => 12.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tool {
+    pub kind: ToolKind,
+    pub tier: ToolTier,
+}
+
+/// The block a player digs on the server, and the tick it started.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Digging {
+    pub pos: BlockPos,
+    pub since: u64,
+}
+
+/// Share of `block` a player breaks in one tick with `tool`, `None` being the hand. 1 or
+/// more breaks it at once, 0 never.
+pub fn destroy_progress(block: &Block, tool: Option<Tool>, on_ground: bool, in_water: bool) -> f32 {
+    if block.hardness < 0.0 {
+        return 0.0;
+    }
+    if block.hardness == 0.0 {
+        return 1.0;
+    }
+    let fits = tool.filter(|t| Some(t.kind) == block.tool);
+    let mut speed = fits.map_or(1.0, |t| t.tier.speed());
+    if in_water {
+        speed /= 5.0;
+    }
+    if !on_ground {
+        speed /= 5.0;
+    }
+    let penalty = match block.requires_tool && fits.is_none() {
+        true => 100.0,
+        false => 30.0,
+    };
+    speed / block.hardness / penalty
+}
+
+/// Ticks it takes to break `block`, `None` if it can't be.
+pub fn break_ticks(
+    block: &Block,
+    tool: Option<Tool>,
+    on_ground: bool,
+    in_water: bool,
+) -> Option<u32> {
+    let progress = destroy_progress(block, tool, on_ground, in_water);
+    (progress > 0.0).then(|| (1.0 / progress).ceil() as u32)
+}
+
+/// How fast the player in `mode` with its feet at `feet` breaks the block at `pos`, with the
+/// hand as players hold nothing yet. 0 if its mode or `tags` don't let it.
+pub fn digging_progress<W: WorldAccess>(
+    world: &W,
+    pos: BlockPos,
+    feet: Vec3,
+    on_ground: bool,
+    mode: GameMode,
+    tags: &AdventureTags,
+) -> f32 {
+    let name = world.get_block(pos);
+    let Some(block) = BLOCK_REGISTRY.get(&name) else {
+        return 0.0;
+    };
+    if &*name == "minecraft:air" || !mode.may_break(tags, &name) {
+        return 0.0;
+    }
+    if mode.instant_break() {
+        return 1.0;
+    }
+    let eyes = (feet + Vec3::Y * EYE_HEIGHT).floor().as_ivec3();
+    let in_water = &*world.get_block(eyes) == "minecraft:water";
+    destroy_progress(block, None, on_ground, in_water)
+}
+
+/// True if the block at `pos` is close enough to the eyes at `eyes` to act on.
+pub fn within_reach(eyes: Vec3, pos: BlockPos) -> bool {
+    eyes.distance_squared(pos.as_vec3() + Vec3::splat(0.5)) <= MAX_REACH * MAX_REACH
+}
+
+/// Blocks a block placed there takes the place of.
+pub fn replaceable(block: &str) -> bool {
+    matches!(
+        block,
+        "minecraft:air" | "minecraft:water" | "minecraft:lava"
+    )
+}
+
+/// Where a block placed by a player in `mode` against `face` of the block at `against` goes,
+/// if it can.
+pub fn placement<W: WorldAccess>(
+    world: &W,
+    against: BlockPos,
+    face: BlockFaceDirection,
+    mode: GameMode,
+    tags: &AdventureTags,
+) -> Option<BlockPos> {
+    let clicked = world.get_state(against);
+    if collision_shape(clicked).is_empty() || !mode.may_place_on(tags, clicked.name()) {
+        return None;
+    }
+    let pos = against + face.to_vec();
+    replaceable(&world.get_block(pos)).then_some(pos)
+}
+
+/// True if `state` at `pos` would be inside one of `entities`.
+pub fn obstructed(
+    state: BlockState,
+    pos: BlockPos,
+    entities: impl IntoIterator<Item = Aabb>,
+) -> bool {
+    let shape: Vec<Aabb> = collision_shape(state)
+        .iter()
+        .map(|b| b.offset(pos.as_vec3()))
+        .collect();
+    entities
+        .into_iter()
+        .any(|entity| shape.iter().any(|b| b.intersects(&entity)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entity::movement::tests::floor, entity::EntityType};
+
+    const SURVIVAL: GameMode = GameMode::Survival;
+
+    fn block(name: &str) -> &'static Block {
+        BLOCK_REGISTRY.get(&name.into()).unwrap()
+    }
+
+    #[test]
+    fn break_times_follow_hardness_and_tools() {
+        // Minecraft's: 7.5 seconds for stone by hand, 1.15 with a wooden pickaxe
+        assert_eq!(
+            break_ticks(block("minecraft:stone"), None, true, false),
+            Some(150)
+        );
+        let pickaxe = Tool {
+            kind: ToolKind::Pickaxe,
+            tier: ToolTier::Wood,
+        };
+        let shovel = Tool {
+            kind: ToolKind::Shovel,
+            ..pickaxe
+        };
+        assert_eq!(
+            break_ticks(block("minecraft:stone"), Some(pickaxe), true, false),
+            Some(23)
+        );
+        // the wrong tool is a hand
+        assert_eq!(
+            break_ticks(block("minecraft:stone"), Some(shovel), true, false),
+            Some(150)
+        );
+        assert_eq!(
+            break_ticks(block("minecraft:dirt"), None, true, false),
+            Some(15)
+        );
+        assert_eq!(
+            break_ticks(block("minecraft:dirt"), Some(shovel), true, false),
+            Some(8)
+        );
+        // five times slower in the air, and again under water
+        assert_eq!(
+            break_ticks(block("minecraft:dirt"), None, false, true),
+            Some(375)
+        );
+        assert_eq!(
+            break_ticks(block("minecraft:water"), None, true, false),
+            None
+        );
+    }
+
+    #[test]
+    fn blocks_go_against_solid_faces_and_not_into_entities() {
+        let mut world = floor();
+        world.set_block(ivec3(3, 64, 0), &"minecraft:water".into());
+        let none = AdventureTags::default();
+        assert_eq!(
+            placement(
+                &world,
+                ivec3(0, 63, 0),
+                BlockFaceDirection::YP,
+                SURVIVAL,
+                &none
+            ),
+            Some(ivec3(0, 64, 0))
+        );
+        // water can be replaced but not clicked
+        assert_eq!(
+            placement(
+                &world,
+                ivec3(3, 64, 0),
+                BlockFaceDirection::XN,
+                SURVIVAL,
+                &none
+            ),
+            None
+        );
+        assert_eq!(
+            placement(
+                &world,
+                ivec3(3, 63, 0),
+                BlockFaceDirection::YP,
+                SURVIVAL,
+                &none
+            ),
+            Some(ivec3(3, 64, 0))
+        );
+        assert_eq!(
+            placement(
+                &world,
+                ivec3(0, 62, 0),
+                BlockFaceDirection::YP,
+                SURVIVAL,
+                &none
+            ),
+            None
+        );
+
+        // adventure players place against the blocks their tags name only
+        let tags = AdventureTags {
+            can_destroy: vec![],
+            can_place_on: vec!["minecraft:stone".into()],
+        };
+        let placed = |mode| placement(&world, ivec3(0, 63, 0), BlockFaceDirection::YP, mode, &tags);
+        assert_eq!(placed(GameMode::Adventure), Some(ivec3(0, 64, 0)));
+        assert_eq!(placed(GameMode::Spectator), None);
+        let feet = vec3(0.5, 64.0, 0.5);
+        let dig = |mode| digging_progress(&world, ivec3(0, 63, 0), feet, true, mode, &tags);
+        assert_eq!(dig(GameMode::Adventure), 0.0);
+        assert_eq!(dig(GameMode::Creative), 1.0);
+        // stone by hand, 150 ticks
+        assert!((dig(GameMode::Survival) - 1.0 / 150.0).abs() < 1e-6);
+
+        let (width, height) = EntityType::PLAYER.size();
+        let player = Aabb::from_feet(vec3(0.5, 64.0, 0.5), width, height);
+        let state = |name: &str| BlockState::of(&name.into());
+        assert!(obstructed(
+            state("minecraft:stone"),
+            ivec3(0, 65, 0),
+            [player]
+        ));
+        assert!(!obstructed(
+            state("minecraft:stone"),
+            ivec3(1, 64, 0),
+            [player]
+        ));
+        assert!(!obstructed(
+            state("minecraft:water"),
+            ivec3(0, 64, 0),
+            [player]
+        ));
+        // a player astride two blocks misses the fence post, not its arm
+        let astride = player.offset(vec3(0.5, 0.0, 0.0));
+        let fence = state("minecraft:oak_fence");
+        assert!(!obstructed(fence, ivec3(1, 64, 0), [astride]));
+        assert!(obstructed(
+            fence.with_bool("west", true),
+            ivec3(1, 64, 0),
+            [astride]
+        ));
+
+        assert!(within_reach(vec3(0.5, 65.62, 0.5), ivec3(4, 63, 3)));
+        assert!(!within_reach(vec3(0.5, 65.62, 0.5), ivec3(6, 63, 0)));
+    }
+}
@@ -0,0 +1,126 @@
+//! ```text
+//! package net.minecraft.entity.item
+//! class ItemEntity
+//! version 1.16
+//! ```
+//!
+//! Items lying around, dropped by blocks that break. They float in water and go away after
+//! [`ITEM_LIFETIME`] ticks. There are no items besides blocks yet, so an item is a stack of
+//! some block.
+
+use bevy_ecs::{
+    bundle::Bundle,
+    component::Component,
+    entity::Entity,
+    query::With,
+    system::{Commands, Query},
+};
+use blockworld_utils::ResourceLocation;
+use glam::*;
+
+use crate::world::{
+    disk_chunk_access::DiskChunkArray, random::WorldRandom, storage::chunk_serializer::ItemData,
+};
+
+use super::{persistence::new_data, physics::Physics, Age, EntityBundle, EntityType, Uuid};
+
+/// Ticks an item lies around before it goes, five minutes.
+pub const ITEM_LIFETIME: u32 = 6000;
+
+/// A stack of `count` of `item`.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct ItemEntity {
+    pub item: ResourceLocation,
+    pub count: u32,
+}
+
+#[derive(Bundle)]
+pub struct ItemBundle {
+    pub entity: EntityBundle,
+    pub item: ItemEntity,
+    pub physics: Physics,
+}
+
+/// Drop `count` of `item` at `position`, tossed a little up and to a random side. It's spawned
+/// with the others waiting in `chunks`.
+pub fn drop_item(
+    chunks: &mut DiskChunkArray,
+    random: &mut WorldRandom,
+    position: Vec3,
+    item: &ResourceLocation,
+    count: u32,
+) {
+    let mut data = new_data(EntityType::ITEM, Uuid::random(random), position);
+    data.velocity = [
+        random.next_float() * 0.2 - 0.1,
+        0.2,
+        random.next_float() * 0.2 - 0.1,
+    ];
+    data.item = Some(ItemData {
+        id: item.to_string(),
+        count,
+    });
+    chunks.add_entity(data);
+}
+
+/// Items past their lifetime go, in [`TickSet::EntityAi`].
+///
+/// [`TickSet::EntityAi`]: crate::tick::TickSet::EntityAi
+pub fn expire(mut commands: Commands, items: Query<(Entity, &Age), With<ItemEntity>>) {
+    for (entity, age) in &items {
+        if age.0 >= ITEM_LIFETIME {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::query::With;
+
+    use super::*;
+    use crate::{
+        block::redstone::harness::Circuit, components::Position, world::chunk_access::WorldAccess,
+    };
+
+    fn items(circuit: &mut Circuit) -> Vec<(ItemEntity, Vec3)> {
+        let ecs = circuit.world_mut().ecs_mut();
+        ecs.query::<(&ItemEntity, &Position)>()
+            .iter(ecs)
+            .map(|(item, position)| (item.clone(), position.0))
+            .collect()
+    }
+
+    #[test]
+    fn broken_blocks_drop_and_items_expire() {
+        // the lever falls off its wall
+        let mut circuit = Circuit::build(
+            &[&["###", "###"], &["#L"]],
+            &[('L', "lever[face=wall,facing=east]")],
+        )
+        .unwrap();
+        circuit.set(ivec3(0, 1, 0), "air").unwrap();
+        circuit.step(1);
+        let dropped = items(&mut circuit);
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(&*dropped[0].0.item, "minecraft:lever");
+        assert_eq!(dropped[0].0.count, 1);
+        assert!(dropped[0].1.distance(vec3(1.5, 1.375, 0.5)) < 0.01);
+
+        // it lands on the floor, and lies there until it's old
+        circuit.step(40);
+        let (_, position) = items(&mut circuit)[0].clone();
+        assert_eq!(position.y, 1.0);
+        assert!(circuit.world().chunks().is_air(position.floor().as_ivec3()));
+        circuit.step(ITEM_LIFETIME - 42);
+        assert_eq!(items(&mut circuit).len(), 1);
+        circuit.step(1);
+        let ecs = circuit.world_mut().ecs_mut();
+        assert_eq!(
+            ecs.query_filtered::<(), With<ItemEntity>>()
+                .iter(ecs)
+                .count(),
+            0
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! ```text
+//! package net.minecraft.entity
+//! class passive.PigEntity, ai.goal.RandomWalkingGoal
+//! version 1.16
+//! ```
+//!
+//! Passive mobs, for now pigs that wander about. Now and then a mob standing around picks a
+//! way and walks it for a second or few, turning to face it.
+
+use std::f32::consts::TAU;
+
+use bevy_ecs::{
+    bundle::Bundle,
+    component::Component,
+    system::{Query, ResMut},
+};
+use glam::*;
+
+use crate::{
+    components::{OnGround, Rotation, Velocity},
+    world::random::WorldRandom,
+};
+
+use super::{physics::Physics, EntityBundle};
+
+/// One in this many ticks a mob standing around starts walking.
+pub const WANDER_CHANCE: u32 = 120;
+/// Added to the velocity of a walking mob every tick on the ground, about a tenth of a block a
+/// tick once it's going.
+pub const WALK_ACCELERATION: f32 = 0.045;
+
+/// Where a mob is walking, if it is. Never saved, a mob loaded stands around at first.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Wander {
+    /// The way it walks, horizontal and 1 long.
+    pub heading: Vec3,
+    /// Ticks it keeps walking.
+    pub ticks_left: u32,
+}
+
+#[derive(Bundle)]
+pub struct MobBundle {
+    pub entity: EntityBundle,
+    pub physics: Physics,
+    pub wander: Wander,
+}
+
+/// Mobs walk about, in [`TickSet::EntityAi`].
+///
+/// [`TickSet::EntityAi`]: crate::tick::TickSet::EntityAi
+pub fn wander(
+    mut random: ResMut<WorldRandom>,
+    mut mobs: Query<(&mut Wander, &mut Velocity, &mut Rotation, &OnGround)>,
+) {
+    for (mut wander, mut velocity, mut rotation, on_ground) in &mut mobs {
+        if wander.ticks_left == 0 {
+            if random.next_int(WANDER_CHANCE) != 0 {
+                continue;
+            }
+            let yaw = random.next_float() * TAU;
+            rotation.yaw = yaw;
+            wander.heading = vec3(yaw.sin(), 0.0, yaw.cos());
+            wander.ticks_left = 20 + random.next_int(60);
+        }
+        wander.ticks_left -= 1;
+        if on_ground.0 {
+            velocity.0 += wander.heading * WALK_ACCELERATION;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        components::Position,
+        entity::{movement::tests::floor, persistence::new_data, EntityType, Uuid},
+        Blockworld,
+    };
+
+    #[test]
+    fn pigs_wander_on_their_own() {
+        let mut world = Blockworld::new();
+        *world.chunks_mut() = floor();
+        let data = new_data(EntityType::PIG, Uuid(1), vec3(0.5, 64.0, 0.5));
+        let pig = crate::entity::persistence::spawn(world.ecs_mut(), &data).unwrap();
+        world.step(WANDER_CHANCE * 10);
+        let moved = world.ecs().get::<Position>(pig).unwrap().0;
+        assert_eq!(moved.y, 64.0);
+        assert!(moved.xz().distance(vec2(0.5, 0.5)) > 1.0, "{}", moved);
+        let yaw = world.ecs().get::<Rotation>(pig).unwrap().yaw;
+        assert!((0.0..TAU).contains(&yaw));
+    }
+}
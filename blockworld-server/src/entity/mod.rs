@@ -0,0 +1,131 @@
+//! ```text
+//! package net.minecraft.entity
+//! class Entity
+//! version 1.16
+//! ```
+//!
+//! What every entity is, whatever else it has. Entities live in the server's ECS world, built
+//! from an [`EntityBundle`] and the components of their kind: [`item::ItemEntity`],
+//! [`falling_block::FallingBlock`] and [`mob::Wander`]. Players are spawned by the network
+//! instead. The others are saved with the section they're in, see `persistence.rs`.
+
+pub mod entity_type;
+pub mod falling_block;
+pub mod game_mode;
+pub mod health;
+pub mod interaction;
+pub mod item;
+pub mod mob;
+pub mod movement;
+pub mod persistence;
+pub mod physics;
+
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use bevy_ecs::{bundle::Bundle, component::Component, system::Query};
+use glam::*;
+use physics::Collider;
+
+pub use entity_type::{EntityType, SpawnGroup, ENTITY_REGISTRY};
+
+use crate::{
+    components::{OnGround, Position, Rotation, Velocity},
+    world::random::WorldRandom,
+};
+
+/// The id packets use for an entity. Only valid while the server runs, never saved.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntityId(pub i32);
+
+impl EntityId {
+    pub fn next() -> Self {
+        static NEXT: AtomicI32 = AtomicI32::new(1);
+        EntityId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// The id of an entity that stays the same across saves and servers.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Uuid(pub u128);
+
+impl Uuid {
+    /// A new version 4 UUID, drawn from the world's randomness.
+    pub fn random(random: &mut WorldRandom) -> Self {
+        let bits = (0..4).fold(0u128, |bits, _| bits << 32 | random.next_u32() as u128);
+        let version = 0x4000_u128 << 64;
+        let variant = 0x8000_0000_0000_0000_u128;
+        Uuid(bits & !(0xf000_u128 << 64) & !(0xc000_0000_0000_0000_u128) | version | variant)
+    }
+
+    /// The four ints Minecraft saves it as, most significant first.
+    pub fn to_ints(self) -> [i32; 4] {
+        [96, 64, 32, 0].map(|shift| (self.0 >> shift) as u32 as i32)
+    }
+
+    pub fn from_ints(ints: [i32; 4]) -> Self {
+        Uuid(
+            ints.iter()
+                .fold(0, |bits, &i| bits << 32 | i as u32 as u128),
+        )
+    }
+}
+
+/// Ticks an entity has lived.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Age(pub u32);
+
+/// What every entity but a player is spawned with.
+#[derive(Bundle)]
+pub struct EntityBundle {
+    pub id: EntityId,
+    pub kind: EntityType,
+    pub uuid: Uuid,
+    pub position: Position,
+    pub rotation: Rotation,
+    pub velocity: Velocity,
+    pub on_ground: OnGround,
+    pub collider: Collider,
+    pub age: Age,
+}
+
+impl EntityBundle {
+    /// A new entity of `kind` standing still at `position`.
+    pub fn new(kind: EntityType, uuid: Uuid, position: Vec3) -> Self {
+        Self {
+            id: EntityId::next(),
+            kind,
+            uuid,
+            position: Position(position),
+            rotation: Rotation::default(),
+            velocity: Velocity::default(),
+            on_ground: OnGround::default(),
+            collider: Collider::of(kind),
+            age: Age::default(),
+        }
+    }
+}
+
+/// Every entity gets a tick older, in [`TickSet::EntityAi`].
+///
+/// [`TickSet::EntityAi`]: crate::tick::TickSet::EntityAi
+pub fn grow_older(mut ages: Query<&mut Age>) {
+    for mut age in &mut ages {
+        age.0 += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uuids_are_version_4_and_save_as_ints() {
+        let mut random = WorldRandom::new(1);
+        let uuid = Uuid::random(&mut random);
+        assert_eq!(uuid.0 >> 76 & 0xf, 4);
+        assert_eq!(uuid.0 >> 62 & 0b11, 0b10);
+        assert_ne!(Uuid::random(&mut random), uuid);
+        assert_eq!(Uuid::from_ints(uuid.to_ints()), uuid);
+        assert_eq!(Uuid(1 << 96 | 2).to_ints(), [1, 0, 0, 2]);
+    }
+}
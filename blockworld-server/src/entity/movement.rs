@@ -0,0 +1,407 @@
+//! ```text
+//! package net.minecraft.entity
+//! class LivingEntity
+//! version 1.16
+//! ```
+//!
+//! How a player moves in one tick, given the keys it holds. The client runs this to predict
+//! its own player and the server runs it to decide where the player really is, so it must only
+//! depend on its arguments: same body, same input and same blocks give the same result.
+//! Collisions, stepping up and sneaking at edges are those of `physics.rs`.
+//!
+//! Constants are Minecraft's, in blocks per tick.
+
+use anyhow::*;
+use glam::*;
+
+use crate::{
+    packet::{Decode, Encode},
+    world::chunk_access::WorldAccess,
+};
+
+use super::{
+    physics::{
+        back_off_from_edge, move_box, submerged, Collider, GROUND_SLIPPERINESS, STEP_HEIGHT,
+        VERTICAL_DRAG, WATER_DRAG,
+    },
+    EntityType,
+};
+
+const GRAVITY: f32 = 0.08;
+const AIR_FRICTION: f32 = 0.91;
+const WALK_SPEED: f32 = 0.1;
+const AIR_SPEED: f32 = 0.02;
+const SPRINT_FACTOR: f32 = 1.3;
+const SNEAK_FACTOR: f32 = 0.3;
+const JUMP_VELOCITY: f32 = 0.42;
+const SPRINT_JUMP_BOOST: f32 = 0.2;
+const FLY_SPEED: f32 = 0.05;
+const FLY_VERTICAL_SPEED: f32 = 0.15;
+const FLY_VERTICAL_DRAG: f32 = 0.6;
+const SWIM_SPEED: f32 = 0.02;
+const SWIM_UP_SPEED: f32 = 0.04;
+const SWIM_GRAVITY: f32 = 0.02;
+/// Held keys count a little less than fully, which is where 4.317 blocks a second comes from.
+const INPUT_FACTOR: f32 = 0.98;
+
+/// The keys held during one tick, and where the player looked.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MoveInput {
+    /// -1 back, 0 or 1 forward.
+    pub forward: i8,
+    /// -1 left, 0 or 1 right.
+    pub strafe: i8,
+    pub jump: bool,
+    pub sneak: bool,
+    pub sprint: bool,
+    pub flying: bool,
+    /// Through blocks, for spectators.
+    pub noclip: bool,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl MoveInput {
+    /// Where the keys push, horizontal and a little under 1 long at most.
+    fn direction(&self) -> Vec3 {
+        let keys = vec2(self.strafe.signum() as f32, self.forward.signum() as f32);
+        let keys = keys.clamp_length_max(1.0) * INPUT_FACTOR;
+        let forward = vec3(self.yaw.sin(), 0.0, self.yaw.cos());
+        let right = forward.cross(Vec3::Y);
+        forward * keys.y + right * keys.x
+    }
+}
+
+impl Encode for MoveInput {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let flags = self.jump as u8
+            | (self.sneak as u8) << 1
+            | (self.sprint as u8) << 2
+            | (self.flying as u8) << 3
+            | (self.noclip as u8) << 4;
+        flags.encode(buf);
+        self.forward.encode(buf);
+        self.strafe.encode(buf);
+        self.yaw.encode(buf);
+        self.pitch.encode(buf);
+    }
+}
+
+impl Decode for MoveInput {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        let flags = u8::decode(buf)?;
+        if flags >> 5 != 0 {
+            bail!("invalid input flags {:#04x}", flags);
+        }
+        Ok(Self {
+            jump: flags & 1 != 0,
+            sneak: flags & 2 != 0,
+            sprint: flags & 4 != 0,
+            flying: flags & 8 != 0,
+            noclip: flags & 16 != 0,
+            forward: Decode::decode(buf)?,
+            strafe: Decode::decode(buf)?,
+            yaw: Decode::decode(buf)?,
+            pitch: Decode::decode(buf)?,
+        })
+    }
+}
+
+#[cfg(test)]
+impl crate::packet::codec::Sample for MoveInput {
+    fn sample() -> Self {
+        MoveInput {
+            forward: 1,
+            strafe: -1,
+            jump: true,
+            sneak: false,
+            sprint: true,
+            flying: true,
+            noclip: false,
+            yaw: 1.5,
+            pitch: -0.25,
+        }
+    }
+}
+
+/// What movement needs to know about a player between ticks.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Body {
+    /// The feet.
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub on_ground: bool,
+}
+
+/// Advance a player by one tick.
+pub fn step<W: WorldAccess>(body: &mut Body, input: &MoveInput, world: &W) {
+    let direction = input.direction();
+    let collider = Collider::of(EntityType::PLAYER);
+    let aabb = collider.aabb(body.position);
+    let swimming = !input.flying && submerged(world, &aabb) > 0.0;
+    let friction;
+    if input.flying {
+        let speed = FLY_SPEED * if input.sprint { 2.0 } else { 1.0 };
+        body.velocity += direction * speed;
+        body.velocity.y += FLY_VERTICAL_SPEED * (input.jump as i32 - input.sneak as i32) as f32;
+        friction = AIR_FRICTION;
+    } else if swimming {
+        body.velocity += direction * SWIM_SPEED;
+        if input.jump {
+            body.velocity.y += SWIM_UP_SPEED;
+        }
+        friction = WATER_DRAG;
+    } else {
+        friction = match body.on_ground {
+            true => GROUND_SLIPPERINESS * AIR_FRICTION,
+            false => AIR_FRICTION,
+        };
+        let mut speed = match body.on_ground {
+            // friction taken out so walking speed is the same on every block
+            true => WALK_SPEED * 0.162_771_36 / (friction * friction * friction),
+            false => AIR_SPEED,
+        };
+        if input.sprint {
+            speed *= SPRINT_FACTOR;
+        }
+        if input.sneak {
+            speed *= SNEAK_FACTOR;
+        }
+        if input.jump && body.on_ground {
+            body.velocity.y = JUMP_VELOCITY;
+            if input.sprint {
+                body.velocity += direction * SPRINT_JUMP_BOOST;
+            }
+        }
+        body.velocity += direction * speed;
+    }
+
+    let mut wanted = body.velocity;
+    if input.noclip {
+        body.position += wanted;
+        body.on_ground = false;
+    } else {
+        if input.sneak && body.on_ground && !input.flying {
+            wanted = back_off_from_edge(world, &aabb, wanted, STEP_HEIGHT);
+        }
+        let moved = move_box(world, &aabb, wanted, STEP_HEIGHT, body.on_ground);
+        body.position += moved.movement;
+        body.on_ground = moved.on_ground;
+        for axis in 0..3 {
+            if moved.collided.test(axis) {
+                body.velocity[axis] = 0.0;
+            }
+        }
+    }
+
+    if input.flying {
+        body.velocity.y *= FLY_VERTICAL_DRAG;
+    } else if swimming {
+        body.velocity.y = body.velocity.y * WATER_DRAG - SWIM_GRAVITY;
+    } else {
+        body.velocity.y = (body.velocity.y - GRAVITY) * VERTICAL_DRAG;
+    }
+    body.velocity.x *= friction;
+    body.velocity.z *= friction;
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::world::disk_chunk_access::DiskChunkArray;
+
+    /// A stone floor with its top at y 64.
+    pub(crate) fn floor() -> DiskChunkArray {
+        let mut world = DiskChunkArray::new(1);
+        for x in -1..=1 {
+            for z in -1..=1 {
+                world.load_chunk(ivec3(x, 3, z));
+                world.load_chunk(ivec3(x, 4, z));
+            }
+        }
+        for x in -16..32 {
+            for z in -16..32 {
+                world.set_block(ivec3(x, 63, z), &"minecraft:stone".into());
+            }
+        }
+        world
+    }
+
+    fn run(world: &DiskChunkArray, body: &mut Body, input: MoveInput, ticks: usize) {
+        for _ in 0..ticks {
+            step(body, &input, world);
+        }
+    }
+
+    #[test]
+    fn falls_onto_the_floor() {
+        let world = floor();
+        let mut body = Body {
+            position: vec3(0.5, 70.0, 0.5),
+            ..Default::default()
+        };
+        run(&world, &mut body, MoveInput::default(), 40);
+        assert_eq!(body.position, vec3(0.5, 64.0, 0.5));
+        assert!(body.on_ground);
+        assert_eq!(body.velocity.y, -GRAVITY * VERTICAL_DRAG);
+    }
+
+    #[test]
+    fn walks_at_minecraft_speed() {
+        let world = floor();
+        let mut body = Body {
+            position: vec3(0.5, 64.0, 0.5),
+            on_ground: true,
+            ..Default::default()
+        };
+        let forward = MoveInput {
+            forward: 1,
+            ..Default::default()
+        };
+        run(&world, &mut body, forward, 20);
+        let start = body.position;
+        run(&world, &mut body, forward, 20);
+        // 4.317 blocks a second on foot
+        let speed = (body.position - start).length();
+        assert!((speed - 4.317).abs() < 0.01, "{}", speed);
+        assert_eq!(body.position.y, 64.0);
+    }
+
+    #[test]
+    fn ledges_stop_and_jumps_climb_them() {
+        let mut world = floor();
+        for x in -16..32 {
+            for z in 3..32 {
+                world.set_block(ivec3(x, 64, z), &"minecraft:stone".into());
+            }
+        }
+        let mut body = Body {
+            position: vec3(0.5, 64.0, 0.5),
+            on_ground: true,
+            ..Default::default()
+        };
+        // +z is forward at yaw 0
+        let forward = MoveInput {
+            forward: 1,
+            ..Default::default()
+        };
+        run(&world, &mut body, forward, 40);
+        assert!((body.position.z - 2.7).abs() < 1e-4, "{}", body.position);
+
+        let jump = MoveInput {
+            jump: true,
+            ..forward
+        };
+        run(&world, &mut body, jump, 1);
+        run(&world, &mut body, forward, 30);
+        assert!(body.position.z > 3.3, "{}", body.position);
+        assert_eq!(body.position.y, 65.0);
+    }
+
+    #[test]
+    fn flying_ignores_gravity() {
+        let world = floor();
+        let mut body = Body {
+            position: vec3(0.5, 70.0, 0.5),
+            ..Default::default()
+        };
+        let hover = MoveInput {
+            flying: true,
+            ..Default::default()
+        };
+        run(&world, &mut body, hover, 20);
+        assert_eq!(body.position.y, 70.0);
+        let down = MoveInput {
+            sneak: true,
+            ..hover
+        };
+        run(&world, &mut body, down, 40);
+        assert_eq!(body.position.y, 64.0);
+
+        // spectators go on through the floor
+        let through = MoveInput {
+            noclip: true,
+            ..down
+        };
+        run(&world, &mut body, through, 20);
+        assert!(body.position.y < 62.0, "{}", body.position);
+        assert!(!body.on_ground);
+    }
+
+    #[test]
+    fn sneaking_stops_at_the_edge() {
+        let world = floor();
+        let mut body = Body {
+            position: vec3(30.5, 64.0, 0.5),
+            on_ground: true,
+            ..Default::default()
+        };
+        // -yaw turns towards +x
+        let sneak = MoveInput {
+            forward: 1,
+            sneak: true,
+            yaw: std::f32::consts::FRAC_PI_2,
+            ..Default::default()
+        };
+        run(&world, &mut body, sneak, 60);
+        assert_eq!(body.position.y, 64.0);
+        assert!(
+            body.position.x > 32.0 && body.position.x < 32.3,
+            "{}",
+            body.position
+        );
+        assert!(body.on_ground);
+    }
+
+    #[test]
+    fn steps_up_slabs() {
+        let mut world = floor();
+        for x in -16..32 {
+            for z in 3..32 {
+                world.set_block(ivec3(x, 64, z), &"minecraft:smooth_stone_slab".into());
+            }
+        }
+        let mut body = Body {
+            position: vec3(0.5, 64.0, 0.5),
+            on_ground: true,
+            ..Default::default()
+        };
+        let forward = MoveInput {
+            forward: 1,
+            ..Default::default()
+        };
+        run(&world, &mut body, forward, 40);
+        assert!(body.position.z > 5.0, "{}", body.position);
+        assert_eq!(body.position.y, 64.5);
+    }
+
+    #[test]
+    fn swims_up_through_water() {
+        let mut world = floor();
+        for x in -4..4 {
+            for y in 64..70 {
+                for z in -4..4 {
+                    world.set_block(ivec3(x, y, z), &"minecraft:water".into());
+                }
+            }
+        }
+        let mut body = Body {
+            position: vec3(0.5, 68.0, 0.5),
+            ..Default::default()
+        };
+        // sinking slowly
+        run(&world, &mut body, MoveInput::default(), 10);
+        assert!(
+            body.position.y > 66.0 && body.position.y < 68.0,
+            "{}",
+            body.position
+        );
+        assert!(body.velocity.y > -0.1);
+        let swim = MoveInput {
+            jump: true,
+            ..Default::default()
+        };
+        run(&world, &mut body, swim, 40);
+        assert!(body.position.y > 68.5, "{}", body.position);
+    }
+}
@@ -0,0 +1,324 @@
+//! ```text
+//! package net.minecraft.world
+//! class server.ServerWorld (onChunkUnloading), chunk.storage.ChunkSerializer (entities)
+//! version 1.16
+//! ```
+//!
+//! Entities saved with the section they're in. Loading a section spawns the entities saved in
+//! it, and unloading one takes its entities out of the ECS world: those whose
+//! [`SpawnGroup`](super::SpawnGroup) is persistent are saved with it, the others are culled.
+//! Entities that end up where no section is loaded, fallen out of the world or off its loaded
+//! edge, are culled too, since there's nowhere to keep them. Players are saved apart, never
+//! with a chunk.
+//!
+//! [`DiskChunkArray`] keeps the entities of each section as they were last put there, and the
+//! ones waiting to be spawned, since it can't reach the ECS world itself.
+
+use std::collections::HashMap;
+
+use bevy_ecs::{
+    entity::Entity,
+    query::{With, Without},
+    system::{Commands, Query, Res},
+    world::{EntityRef, World},
+};
+use glam::*;
+
+use crate::{
+    components::{OnGround, Player, Position, Rotation, Velocity},
+    world::{
+        chunk::SUBCHUNK_SIZE,
+        chunk_access::WorldAccess,
+        disk_chunk_access::DiskChunkArray,
+        storage::chunk_serializer::{EntityData, ItemData, PaletteEntry},
+    },
+};
+
+use super::{
+    falling_block::{FallingBlock, FallingBlockBundle},
+    item::{ItemBundle, ItemEntity},
+    mob::{MobBundle, Wander},
+    physics::Physics,
+    Age, EntityBundle, EntityType, Uuid,
+};
+
+/// The entities saved with chunks: every one but players.
+type SavedEntities = (With<EntityType>, Without<Player>);
+
+/// The section `position` is in.
+fn section_of(position: Vec3) -> IVec3 {
+    position
+        .floor()
+        .as_ivec3()
+        .div_euclid(IVec3::splat(SUBCHUNK_SIZE as i32))
+}
+
+/// An entity of `kind` standing still at `position`, to spawn.
+pub fn new_data(kind: EntityType, uuid: Uuid, position: Vec3) -> EntityData {
+    EntityData {
+        id: kind.name().to_string(),
+        uuid: uuid.to_ints(),
+        position: position.to_array(),
+        velocity: [0.0; 3],
+        yaw: 0.0,
+        pitch: 0.0,
+        on_ground: false,
+        age: 0,
+        item: None,
+        block: None,
+    }
+}
+
+/// Spawn the entity `data` describes, with the components of its kind. `None` if it can't be,
+/// its kind being unknown, a player or missing what it's made of.
+pub fn spawn(ecs: &mut World, data: &EntityData) -> Option<Entity> {
+    let Some(kind) = EntityType::of(&data.id.as_str().into()) else {
+        log::warn!("Dropping entity of unknown type {}", data.id);
+        return None;
+    };
+    let entity = EntityBundle {
+        rotation: Rotation {
+            yaw: data.yaw,
+            pitch: data.pitch,
+        },
+        velocity: Velocity(Vec3::from_array(data.velocity)),
+        on_ground: OnGround(data.on_ground),
+        age: Age(data.age),
+        ..EntityBundle::new(kind, Uuid::from_ints(data.uuid), data.pos())
+    };
+    let physics = Physics::of(kind);
+    let spawned = match (kind, physics) {
+        (EntityType::ITEM, Some(physics)) => {
+            let Some(item) = &data.item else {
+                log::warn!("Dropping item entity without an item");
+                return None;
+            };
+            let item = ItemEntity {
+                item: item.id.as_str().into(),
+                count: item.count,
+            };
+            ecs.spawn(ItemBundle {
+                entity,
+                item,
+                physics,
+            })
+        }
+        (EntityType::FALLING_BLOCK, Some(physics)) => {
+            let block = data.block.as_ref().map_or_else(
+                || PaletteEntry::new("minecraft:sand").state(),
+                |b| b.state(),
+            );
+            ecs.spawn(FallingBlockBundle {
+                entity,
+                block: FallingBlock(block),
+                physics,
+            })
+        }
+        (EntityType::PLAYER, _) => {
+            log::warn!("Dropping player saved with a chunk");
+            return None;
+        }
+        (_, Some(physics)) => ecs.spawn(MobBundle {
+            entity,
+            physics,
+            wander: Wander::default(),
+        }),
+        (_, None) => ecs.spawn(entity),
+    };
+    Some(spawned.id())
+}
+
+/// The entity as it is saved, unless it's a player or not an entity of a kind.
+pub fn save(entity: EntityRef) -> Option<EntityData> {
+    if entity.contains::<Player>() {
+        return None;
+    }
+    let kind = *entity.get::<EntityType>()?;
+    let position = entity.get::<Position>()?.0;
+    let uuid = *entity.get::<Uuid>()?;
+    let mut data = new_data(kind, uuid, position);
+    if let Some(rotation) = entity.get::<Rotation>() {
+        data.yaw = rotation.yaw;
+        data.pitch = rotation.pitch;
+    }
+    if let Some(velocity) = entity.get::<Velocity>() {
+        data.velocity = velocity.0.to_array();
+    }
+    data.on_ground = entity.get::<OnGround>().is_some_and(|g| g.0);
+    data.age = entity.get::<Age>().map_or(0, |a| a.0);
+    data.item = entity.get::<ItemEntity>().map(|item| ItemData {
+        id: item.item.to_string(),
+        count: item.count,
+    });
+    data.block = entity
+        .get::<FallingBlock>()
+        .map(|block| PaletteEntry::of_state(block.0));
+    Some(data)
+}
+
+/// Every entity but players, by the section it's in.
+fn by_section(ecs: &mut World) -> HashMap<IVec3, Vec<Entity>> {
+    let mut sections: HashMap<IVec3, Vec<Entity>> = HashMap::new();
+    let mut query = ecs.query_filtered::<(Entity, &Position), SavedEntities>();
+    for (entity, position) in query.iter(ecs) {
+        sections
+            .entry(section_of(position.0))
+            .or_default()
+            .push(entity);
+    }
+    sections
+}
+
+/// Spawn the entities waiting in the chunks, those of the sections just loaded and those
+/// blocks made. Runs after the block ticks, and whenever [`Blockworld`](crate::Blockworld)
+/// loads a section.
+pub fn spawn_pending(ecs: &mut World) {
+    let pending = ecs.resource_mut::<DiskChunkArray>().take_pending_entities();
+    for data in &pending {
+        spawn(ecs, data);
+    }
+}
+
+/// Put the entities of every loaded section in the chunks, to be saved with them.
+pub fn store(ecs: &mut World) {
+    let mut sections = by_section(ecs);
+    let loaded: Vec<IVec3> = ecs
+        .resource::<DiskChunkArray>()
+        .chunks
+        .keys()
+        .copied()
+        .collect();
+    for section in loaded {
+        let entities = sections.remove(&section).unwrap_or_default();
+        let data = entities
+            .into_iter()
+            .filter_map(|e| save(ecs.entity(e)))
+            .collect();
+        ecs.resource_mut::<DiskChunkArray>()
+            .put_entities(section, data);
+    }
+}
+
+/// Take the entities of the section at `pos` out of the ECS world, before it unloads. Those
+/// that outlive it are put in the chunks to be saved with it.
+pub fn put_away(ecs: &mut World, pos: IVec3) {
+    let entities = by_section(ecs).remove(&pos).unwrap_or_default();
+    let mut kept = vec![];
+    for entity in entities {
+        let kind = *ecs.get::<EntityType>(entity).unwrap();
+        if kind.spawn_group().persistent() {
+            kept.extend(save(ecs.entity(entity)));
+        }
+        ecs.despawn(entity);
+    }
+    ecs.resource_mut::<DiskChunkArray>().put_entities(pos, kept);
+}
+
+/// Entities where no section is loaded go, in [`TickSet::EntityAi`].
+///
+/// [`TickSet::EntityAi`]: crate::tick::TickSet::EntityAi
+pub fn cull_unloaded(
+    mut commands: Commands,
+    chunks: Res<DiskChunkArray>,
+    entities: Query<(Entity, &Position), SavedEntities>,
+) {
+    for (entity, position) in &entities {
+        if !chunks.is_chunk_loaded(section_of(position.0)) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blockworld_utils::TempDir;
+
+    use super::*;
+    use crate::{entity::movement::tests::floor, Blockworld};
+
+    fn uuids(world: &mut Blockworld) -> Vec<(EntityType, Uuid)> {
+        let ecs = world.ecs_mut();
+        let mut found: Vec<_> = ecs
+            .query::<(&EntityType, &Uuid)>()
+            .iter(ecs)
+            .map(|(kind, uuid)| (*kind, *uuid))
+            .collect();
+        found.sort_by_key(|(_, uuid)| uuid.0);
+        found
+    }
+
+    #[test]
+    fn entities_are_saved_with_their_chunk() {
+        let root = TempDir::new("entities").unwrap();
+        let section = ivec3(0, 4, 0);
+        {
+            let mut world = Blockworld::open(&root).unwrap();
+            world.load_chunk(section);
+            let ecs = world.ecs_mut();
+            let mut item = new_data(EntityType::ITEM, Uuid(2), vec3(3.5, 70.0, 3.5));
+            item.item = Some(ItemData {
+                id: "minecraft:dirt".into(),
+                count: 5,
+            });
+            spawn(ecs, &item).unwrap();
+            spawn(
+                ecs,
+                &new_data(EntityType::PIG, Uuid(1), vec3(8.5, 70.0, 8.5)),
+            )
+            .unwrap();
+            world.step(2);
+            world.save().unwrap();
+            // unloading takes them out of the world
+            world.unload_chunk(section);
+            assert!(uuids(&mut world).is_empty());
+        }
+
+        let mut world = Blockworld::open(&root).unwrap();
+        world.load_chunk(section);
+        assert_eq!(
+            uuids(&mut world),
+            [(EntityType::PIG, Uuid(1)), (EntityType::ITEM, Uuid(2))]
+        );
+        let ecs = world.ecs_mut();
+        let (item, age) = ecs.query::<(&ItemEntity, &Age)>().single(ecs);
+        assert_eq!((&*item.item, item.count, age.0), ("minecraft:dirt", 5, 2));
+    }
+
+    #[test]
+    fn unloading_takes_only_its_own_entities() {
+        let mut world = Blockworld::new();
+        *world.chunks_mut() = floor();
+        let ecs = world.ecs_mut();
+        spawn(
+            ecs,
+            &new_data(EntityType::PIG, Uuid(1), vec3(0.5, 64.0, 0.5)),
+        )
+        .unwrap();
+        spawn(
+            ecs,
+            &new_data(EntityType::PIG, Uuid(2), vec3(16.5, 64.0, 0.5)),
+        )
+        .unwrap();
+        world.unload_chunk(ivec3(0, 4, 0));
+        assert_eq!(uuids(&mut world), [(EntityType::PIG, Uuid(2))]);
+    }
+
+    #[test]
+    fn entities_off_the_loaded_world_are_culled() {
+        let mut world = Blockworld::new();
+        *world.chunks_mut() = floor();
+        let ecs = world.ecs_mut();
+        spawn(
+            ecs,
+            &new_data(EntityType::PIG, Uuid(1), vec3(0.5, 64.0, 0.5)),
+        )
+        .unwrap();
+        spawn(
+            ecs,
+            &new_data(EntityType::PIG, Uuid(2), vec3(100.5, 64.0, 0.5)),
+        )
+        .unwrap();
+        world.step(1);
+        assert_eq!(uuids(&mut world), [(EntityType::PIG, Uuid(1))]);
+    }
+}
@@ -0,0 +1,441 @@
+//! ```text
+//! package net.minecraft.entity
+//! class Entity (move, collide), ItemEntity, FallingBlockEntity
+//! version 1.16
+//! ```
+//!
+//! Moving boxes through blocks. [`move_box`] is what every entity goes through: the wanted
+//! movement is clipped against the collision shapes of the blocks around, y first, then the
+//! longer of x and z, and a box on the ground that bumps into something low enough steps up
+//! onto it. Players are moved by their keys in `movement.rs`; everything else with a
+//! [`Physics`] falls, floats and slows down in the [`physics`] system.
+
+use bevy_ecs::{component::Component, system::Query, system::Res};
+use glam::*;
+
+use crate::{
+    block::shape::{collision_shape, Aabb},
+    components::{OnGround, Position, Velocity},
+    world::{chunk_access::WorldAccess, disk_chunk_access::DiskChunkArray},
+};
+
+use super::EntityType;
+
+/// How high players and mobs walk up without jumping, a slab but not a block.
+pub const STEP_HEIGHT: f32 = 0.6;
+/// Vertical drag in air.
+pub const VERTICAL_DRAG: f32 = 0.98;
+/// Drag in water, every way.
+pub const WATER_DRAG: f32 = 0.8;
+/// Horizontal slowdown on the usual blocks, on top of the drag.
+pub const GROUND_SLIPPERINESS: f32 = 0.6;
+/// How far sneaking looks ahead for an edge, and backs off by at a time.
+const EDGE_STEP: f32 = 0.05;
+
+/// The box of an entity, standing on its position.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Collider {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Collider {
+    pub fn of(kind: EntityType) -> Self {
+        let (width, height) = kind.size();
+        Self { width, height }
+    }
+
+    pub fn aabb(&self, position: Vec3) -> Aabb {
+        Aabb::from_feet(position, self.width, self.height)
+    }
+}
+
+/// How an entity without a will moves.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Physics {
+    /// Taken off the vertical velocity every tick.
+    pub gravity: f32,
+    /// Horizontal velocity kept every tick in air.
+    pub drag: f32,
+    pub step_height: f32,
+    /// Pushed up by this much per tick when under water, less when partly in.
+    pub buoyancy: f32,
+}
+
+impl Physics {
+    /// Items float.
+    pub const ITEM: Physics = Physics {
+        gravity: 0.04,
+        drag: 0.98,
+        step_height: 0.0,
+        buoyancy: 0.045,
+    };
+    /// Falling blocks sink.
+    pub const FALLING_BLOCK: Physics = Physics {
+        gravity: 0.04,
+        drag: 0.98,
+        step_height: 0.0,
+        buoyancy: 0.0,
+    };
+    /// Mobs walk up slabs and bob at the surface.
+    pub const MOB: Physics = Physics {
+        gravity: 0.08,
+        drag: 0.91,
+        step_height: STEP_HEIGHT,
+        buoyancy: 0.085,
+    };
+
+    /// What moves an entity of `kind` on its own, `None` for players who move by their keys.
+    pub fn of(kind: EntityType) -> Option<Self> {
+        kind.kind().physics
+    }
+}
+
+/// The result of [`move_box`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Moved {
+    /// How far the box really went.
+    pub movement: Vec3,
+    /// Landed on something.
+    pub on_ground: bool,
+    /// Axes the box was stopped on, whose velocity is gone.
+    pub collided: BVec3,
+}
+
+/// The collision boxes of the blocks `region` touches, in world coords. Those of the blocks
+/// under it count too, as fences stick out of the top of their block.
+pub fn block_boxes<W: WorldAccess>(world: &W, region: &Aabb) -> Vec<Aabb> {
+    let min = region.min.floor().as_ivec3() - IVec3::Y;
+    let max = region.max.ceil().as_ivec3();
+    let mut boxes = vec![];
+    for x in min.x..max.x {
+        for y in min.y..max.y {
+            for z in min.z..max.z {
+                let pos = ivec3(x, y, z);
+                for shape in collision_shape(world.get_state(pos)) {
+                    let shape = shape.offset(pos.as_vec3());
+                    if shape.intersects(region) {
+                        boxes.push(shape);
+                    }
+                }
+            }
+        }
+    }
+    boxes
+}
+
+/// True if `aabb` overlaps a block's collision shape.
+pub fn collides<W: WorldAccess>(world: &W, aabb: &Aabb) -> bool {
+    block_boxes(world, aabb).iter().any(|b| b.intersects(aabb))
+}
+
+/// How much of the height of `aabb` is under water, from 0 to 1.
+pub fn submerged<W: WorldAccess>(world: &W, aabb: &Aabb) -> f32 {
+    let min = aabb.min.floor().as_ivec3();
+    let max = aabb.max.ceil().as_ivec3();
+    let mut top = aabb.min.y;
+    for x in min.x..max.x {
+        for y in min.y..max.y {
+            for z in min.z..max.z {
+                if &*world.get_block(ivec3(x, y, z)) == "minecraft:water" {
+                    top = top.max(((y + 1) as f32).min(aabb.max.y));
+                }
+            }
+        }
+    }
+    (top - aabb.min.y) / aabb.size().y
+}
+
+/// `delta` clipped against `boxes`, one axis after the other.
+fn collide_boxes(aabb: &Aabb, delta: Vec3, boxes: &[Aabb]) -> Vec3 {
+    // y first so walking off an edge doesn't snag on the side of the block, then the longer
+    // way, like Minecraft
+    let order = match delta.x.abs() < delta.z.abs() {
+        true => [1, 2, 0],
+        false => [1, 0, 2],
+    };
+    let mut moved = Vec3::ZERO;
+    for axis in order {
+        let moving = aabb.offset(moved);
+        moved[axis] = boxes
+            .iter()
+            .fold(delta[axis], |d, b| b.clip(&moving, axis, d));
+    }
+    moved
+}
+
+fn collide<W: WorldAccess>(world: &W, aabb: &Aabb, delta: Vec3) -> Vec3 {
+    let boxes = block_boxes(world, &aabb.expand_towards(delta));
+    collide_boxes(aabb, delta, &boxes)
+}
+
+fn horizontal_length_squared(v: Vec3) -> f32 {
+    v.x * v.x + v.z * v.z
+}
+
+/// Move `aabb` by as much of `wanted` as the blocks allow. A box that was `on_ground`, or
+/// lands this tick, and is stopped sideways tries again up to `step_height` higher.
+pub fn move_box<W: WorldAccess>(
+    world: &W,
+    aabb: &Aabb,
+    wanted: Vec3,
+    step_height: f32,
+    on_ground: bool,
+) -> Moved {
+    let mut movement = collide(world, aabb, wanted);
+    let blocked_sideways = movement.x != wanted.x || movement.z != wanted.z;
+    let landing = wanted.y < 0.0 && movement.y != wanted.y;
+    if step_height > 0.0 && (on_ground || landing) && blocked_sideways {
+        // up, across, back down
+        let across = vec3(wanted.x, 0.0, wanted.z);
+        let region = aabb
+            .expand_towards(across)
+            .expand_towards(Vec3::Y * step_height)
+            .expand_towards(Vec3::Y * wanted.y.min(0.0));
+        let boxes = block_boxes(world, &region);
+        let up = collide_boxes(aabb, Vec3::Y * step_height, &boxes);
+        let mut stepped = collide_boxes(&aabb.offset(up), across, &boxes) + up;
+        // down as far as it went up, and as far as it was falling anyway
+        let down = Vec3::Y * (wanted.y - stepped.y);
+        stepped += collide_boxes(&aabb.offset(stepped), down, &boxes);
+        if horizontal_length_squared(stepped) > horizontal_length_squared(movement) {
+            movement = stepped;
+        }
+    }
+    let collided = bvec3(
+        movement.x != wanted.x,
+        movement.y != wanted.y,
+        movement.z != wanted.z,
+    );
+    Moved {
+        movement,
+        on_ground: collided.y && wanted.y < 0.0,
+        collided,
+    }
+}
+
+/// Shorten `movement` so a sneaking box on the ground doesn't go over an edge it would fall
+/// more than `step_height` from.
+pub fn back_off_from_edge<W: WorldAccess>(
+    world: &W,
+    aabb: &Aabb,
+    mut movement: Vec3,
+    step_height: f32,
+) -> Vec3 {
+    let below = |x: f32, z: f32| collides(world, &aabb.offset(vec3(x, -step_height, z)));
+    let toward_zero = |v: f32| match v {
+        _ if v.abs() < EDGE_STEP => 0.0,
+        _ => v - EDGE_STEP * v.signum(),
+    };
+    while movement.x != 0.0 && !below(movement.x, 0.0) {
+        movement.x = toward_zero(movement.x);
+    }
+    while movement.z != 0.0 && !below(0.0, movement.z) {
+        movement.z = toward_zero(movement.z);
+    }
+    while movement.x != 0.0 && movement.z != 0.0 && !below(movement.x, movement.z) {
+        movement.x = toward_zero(movement.x);
+        movement.z = toward_zero(movement.z);
+    }
+    movement
+}
+
+/// Advance an entity by one tick: buoyancy and gravity, the move, then drag.
+pub fn step_body<W: WorldAccess>(
+    world: &W,
+    physics: &Physics,
+    collider: &Collider,
+    position: &mut Vec3,
+    velocity: &mut Vec3,
+    on_ground: &mut bool,
+) {
+    let aabb = collider.aabb(*position);
+    let in_water = submerged(world, &aabb);
+    velocity.y += physics.buoyancy * in_water - physics.gravity;
+
+    let moved = move_box(world, &aabb, *velocity, physics.step_height, *on_ground);
+    *position += moved.movement;
+    *on_ground = moved.on_ground;
+    for axis in 0..3 {
+        if moved.collided.test(axis) {
+            velocity[axis] = 0.0;
+        }
+    }
+
+    if in_water > 0.0 {
+        *velocity *= WATER_DRAG;
+    } else {
+        let slowdown = match *on_ground {
+            true => physics.drag * GROUND_SLIPPERINESS,
+            false => physics.drag,
+        };
+        velocity.x *= slowdown;
+        velocity.z *= slowdown;
+        velocity.y *= VERTICAL_DRAG;
+    }
+}
+
+/// Moves everything with [`Physics`], in [`TickSet::Physics`].
+///
+/// [`TickSet::Physics`]: crate::tick::TickSet::Physics
+pub fn physics(
+    chunks: Res<DiskChunkArray>,
+    mut bodies: Query<(
+        &Physics,
+        &Collider,
+        &mut Position,
+        &mut Velocity,
+        &mut OnGround,
+    )>,
+) {
+    for (physics, collider, mut position, mut velocity, mut on_ground) in &mut bodies {
+        step_body(
+            &*chunks,
+            physics,
+            collider,
+            &mut position.0,
+            &mut velocity.0,
+            &mut on_ground.0,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entity::movement::tests::floor, Blockworld};
+
+    fn run(
+        world: &DiskChunkArray,
+        kind: EntityType,
+        position: Vec3,
+        velocity: Vec3,
+        ticks: u32,
+    ) -> (Vec3, Vec3, bool) {
+        let physics = Physics::of(kind).unwrap();
+        let collider = Collider::of(kind);
+        let (mut position, mut velocity, mut on_ground) = (position, velocity, false);
+        for _ in 0..ticks {
+            step_body(
+                world,
+                &physics,
+                &collider,
+                &mut position,
+                &mut velocity,
+                &mut on_ground,
+            );
+        }
+        (position, velocity, on_ground)
+    }
+
+    #[test]
+    fn items_land_on_slabs() {
+        let mut world = floor();
+        world.set_block(ivec3(0, 64, 0), &"minecraft:smooth_stone_slab".into());
+        let (position, velocity, on_ground) = run(
+            &world,
+            EntityType::ITEM,
+            vec3(0.5, 70.0, 0.5),
+            Vec3::ZERO,
+            60,
+        );
+        assert_eq!(position, vec3(0.5, 64.5, 0.5));
+        assert!(on_ground);
+        // resting, gravity is stopped by the slab every tick
+        assert_eq!(velocity.y, 0.0);
+    }
+
+    #[test]
+    fn mobs_step_up_slabs_but_not_blocks() {
+        let mut world = floor();
+        for z in -16..32 {
+            for x in 2..5 {
+                world.set_block(ivec3(x, 64, z), &"minecraft:smooth_stone_slab".into());
+            }
+            world.set_block(ivec3(5, 64, z), &"minecraft:stone".into());
+            world.set_block(ivec3(5, 65, z), &"minecraft:smooth_stone_slab".into());
+        }
+        let physics = Physics::MOB;
+        let collider = Collider::of(EntityType::PIG);
+        let (mut position, mut velocity, mut on_ground) = (vec3(0.5, 64.0, 0.5), Vec3::ZERO, true);
+        for _ in 0..60 {
+            // walking +x
+            velocity.x += 0.1;
+            step_body(
+                &world,
+                &physics,
+                &collider,
+                &mut position,
+                &mut velocity,
+                &mut on_ground,
+            );
+        }
+        // up the slab, and stopped by the block and a half after it
+        assert_eq!(position.y, 64.5);
+        assert!((position.x - (5.0 - 0.45)).abs() < 1e-4, "{}", position);
+        assert!(on_ground);
+    }
+
+    #[test]
+    fn items_float_and_falling_blocks_sink() {
+        let mut world = floor();
+        for x in -4..4 {
+            for y in 64..68 {
+                for z in -4..4 {
+                    world.set_block(ivec3(x, y, z), &"minecraft:water".into());
+                }
+            }
+        }
+        let (item, _, _) = run(
+            &world,
+            EntityType::ITEM,
+            vec3(0.5, 64.0, 0.5),
+            Vec3::ZERO,
+            200,
+        );
+        // bobbing at the surface
+        assert!((item.y - 68.0).abs() < 0.25, "{}", item);
+        let (block, _, on_ground) = run(
+            &world,
+            EntityType::FALLING_BLOCK,
+            vec3(0.5, 67.0, 0.5),
+            Vec3::ZERO,
+            200,
+        );
+        assert_eq!(block.y, 64.0);
+        assert!(on_ground);
+    }
+
+    #[test]
+    fn the_schedule_moves_bodies() {
+        let mut world = Blockworld::new();
+        *world.chunks_mut() = floor();
+        let item = world
+            .ecs_mut()
+            .spawn((
+                Physics::ITEM,
+                Collider::of(EntityType::ITEM),
+                Position(vec3(0.5, 66.0, 0.5)),
+                Velocity(vec3(0.2, 0.0, 0.0)),
+                OnGround(false),
+            ))
+            .id();
+        world.step(40);
+        let entity = world.ecs().entity(item);
+        assert_eq!(entity.get::<Position>().unwrap().0.y, 64.0);
+        assert!(entity.get::<OnGround>().unwrap().0);
+        // sliding to a stop on the ground
+        assert!(entity.get::<Velocity>().unwrap().0.x < 1e-3);
+    }
+
+    #[test]
+    fn back_off_keeps_sneakers_on_the_floor() {
+        let world = floor();
+        let aabb = Collider::of(EntityType::PLAYER).aabb(vec3(31.5, 64.0, 0.5));
+        // the floor ends at x 32, the box may hang over it but not leave it
+        let movement = back_off_from_edge(&world, &aabb, vec3(1.0, 0.0, 0.0), STEP_HEIGHT);
+        assert!((movement.x - 0.75).abs() < 1e-4, "{}", movement);
+        let inward = back_off_from_edge(&world, &aabb, vec3(-1.0, 0.0, 0.0), STEP_HEIGHT);
+        assert_eq!(inward.x, -1.0);
+    }
+}
@@ -1,27 +1,188 @@
-use bevy_ecs::{schedule::Schedule, world::World};
+use std::path::Path;
+
+use bevy_ecs::{
+    entity::Entity,
+    schedule::{IntoSystemConfigs, Schedule},
+    world::{Mut, World},
+};
 use components::{HasView, Player};
+use entity::{persistence::new_data, EntityType, Uuid};
 use glam::*;
-use world::disk_chunk_access::DiskChunkArray;
+use tick::{tick_schedule, TickSet};
+use world::{
+    block_tick::BlockTickContext,
+    block_update::run_block_updates,
+    chunk_access::{BlockPos, WorldAccess},
+    disk_chunk_access::DiskChunkArray,
+    random::WorldRandom,
+    storage::{LevelData, WorldStorage},
+};
 
 pub mod block;
 pub mod components;
+pub mod datafix;
+pub mod entity;
+pub mod network;
 pub mod packet;
+pub mod tick;
 pub mod world;
 
 pub struct Blockworld {
-    chunks: DiskChunkArray,
     ecs: World,
     schedule: Schedule,
 }
 
 impl Blockworld {
     pub fn new() -> Self {
+        Self::with_chunks(DiskChunkArray::new(8), LevelData::default())
+    }
+
+    /// Open the world saved in `path`, creating a new one if the folder is empty.
+    pub fn open<Q: AsRef<Path>>(path: Q) -> anyhow::Result<Self> {
+        let storage = WorldStorage::open(path)?;
+        let level = match storage.read_level()? {
+            Some(level) => level,
+            None => {
+                let level = LevelData::default();
+                storage.write_level(&level)?;
+                level
+            }
+        };
+        Ok(Self::with_chunks(
+            DiskChunkArray::with_storage(8, storage),
+            level,
+        ))
+    }
+
+    /// The chunks and the level are resources of the ECS world, for the systems that need
+    /// blocks, the time or the game rules.
+    fn with_chunks(chunks: DiskChunkArray, level: LevelData) -> Self {
         let mut ecs = World::default();
-        let schedule = Schedule::default();
-        Self {
-            chunks: DiskChunkArray::new(8),
+        ecs.insert_resource(chunks);
+        ecs.insert_resource(WorldRandom::new(level.seed));
+        ecs.insert_resource(level);
+        let mut world = Self {
             ecs,
-            schedule,
+            schedule: tick_schedule(),
+        };
+        world.add_systems(TickSet::Physics, entity::physics::physics);
+        world.add_systems(TickSet::BlockTicks, world::block_tick::block_ticks);
+        world.add_systems(
+            TickSet::BlockTicks,
+            entity::persistence::spawn_pending.after(world::block_tick::block_ticks),
+        );
+        world.add_systems(TickSet::EntityAi, entity::health::hunger);
+        world.add_systems(
+            TickSet::EntityAi,
+            (
+                entity::grow_older,
+                entity::item::expire,
+                entity::falling_block::land,
+                entity::mob::wander,
+                entity::persistence::cull_unloaded,
+            )
+                .chain(),
+        );
+        world
+    }
+
+    pub fn level(&self) -> &LevelData {
+        self.ecs.resource()
+    }
+
+    pub fn level_mut(&mut self) -> &mut LevelData {
+        self.ecs.resource_mut::<LevelData>().into_inner()
+    }
+
+    pub fn chunks(&self) -> &DiskChunkArray {
+        self.ecs.resource()
+    }
+
+    pub fn chunks_mut(&mut self) -> &mut DiskChunkArray {
+        self.ecs.resource_mut::<DiskChunkArray>().into_inner()
+    }
+
+    pub fn ecs(&self) -> &World {
+        &self.ecs
+    }
+
+    pub fn ecs_mut(&mut self) -> &mut World {
+        &mut self.ecs
+    }
+
+    /// Load the sub chunk at `pos` and spawn the entities saved in it.
+    pub fn load_chunk(&mut self, pos: IVec3) {
+        self.chunks_mut().load_chunk(pos);
+        entity::persistence::spawn_pending(&mut self.ecs);
+    }
+
+    /// Take the entities in the sub chunk at `pos` out of the world, keeping those that outlive
+    /// it, then unload it.
+    pub fn unload_chunk(&mut self, pos: IVec3) {
+        entity::persistence::put_away(&mut self.ecs, pos);
+        self.chunks_mut().unload_chunk(pos);
+    }
+
+    /// Spawn a new entity of `kind` standing at `position`. `None` for players and items,
+    /// which need more than a position.
+    pub fn summon(&mut self, kind: EntityType, position: Vec3) -> Option<Entity> {
+        if matches!(kind, EntityType::PLAYER | EntityType::ITEM) {
+            return None;
+        }
+        let uuid = Uuid::random(&mut self.ecs.resource_mut::<WorldRandom>());
+        entity::persistence::spawn(&mut self.ecs, &new_data(kind, uuid, position))
+    }
+
+    /// Run `f` with what block behaviours change the world with, at the current time.
+    pub fn block_context<R>(&mut self, f: impl FnOnce(&mut BlockTickContext) -> R) -> R {
+        let time = self.level().time;
+        self.ecs
+            .resource_scope(|ecs, mut chunks: Mut<DiskChunkArray>| {
+                let mut random = ecs.resource_mut::<WorldRandom>();
+                f(&mut BlockTickContext {
+                    chunks: &mut chunks,
+                    random: &mut random,
+                    time,
+                })
+            })
+    }
+
+    /// Use the block at `pos` as a player would, and run the updates it sets off. True if the
+    /// block did something.
+    pub fn use_block(&mut self, pos: BlockPos) -> bool {
+        self.block_context(|ctx| {
+            let state = ctx.chunks.get_state(pos);
+            let used = state.block().behaviour.use_block(ctx, pos, state);
+            run_block_updates(ctx);
+            used
+        })
+    }
+
+    /// Run `systems` every tick, in `set`.
+    pub fn add_systems<M>(&mut self, set: TickSet, systems: impl IntoSystemConfigs<M>) {
+        self.schedule.add_systems(systems.in_set(set));
+    }
+
+    /// Advance the world by one game tick.
+    pub fn tick(&mut self) {
+        self.schedule.run(&mut self.ecs);
+        self.level_mut().time += 1;
+    }
+
+    /// Run `ticks` ticks at once, whatever the time. Tests step a world this way.
+    pub fn step(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.tick();
+        }
+    }
+
+    /// Write the level data and every modified chunk, with the entities in it, to disk.
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        entity::persistence::store(&mut self.ecs);
+        self.chunks_mut().save_all()?;
+        if let Some(storage) = self.chunks().storage() {
+            storage.write_level(self.level())?;
         }
+        Ok(())
     }
 }
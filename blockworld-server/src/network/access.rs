@@ -0,0 +1,215 @@
+//! ```text
+//! package net.minecraft.server.management
+//! class PlayerList
+//! version 1.16
+//! ```
+//!
+//! Who may join, from two files in the server folder that operators edit by hand:
+//!
+//! ```text
+//! allowlist.json  [{"name": "Steve"}]
+//! banlist.json    {"players": [{"name": "Griefer", "reason": "Griefing"}],
+//!                  "ips": [{"ip": "203.0.113.7", "reason": "Spam"}]}
+//! ```
+//!
+//! Both are created empty if missing and read again whenever they changed, so edits apply to
+//! the next login without a restart. Names match whatever their case. The allow list only
+//! counts when the server was started with it on.
+
+use std::{
+    fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use anyhow::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::disconnect::DisconnectReason;
+
+pub const ALLOWLIST_FILE: &str = "allowlist.json";
+pub const BANLIST_FILE: &str = "banlist.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AllowEntry {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerBan {
+    pub name: String,
+    #[serde(default = "default_reason")]
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IpBan {
+    pub ip: IpAddr,
+    #[serde(default = "default_reason")]
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BanList {
+    #[serde(default)]
+    pub players: Vec<PlayerBan>,
+    #[serde(default)]
+    pub ips: Vec<IpBan>,
+}
+
+fn default_reason() -> String {
+    "Banned by an operator".to_string()
+}
+
+/// A list file and when it was read.
+#[derive(Debug)]
+struct ListFile<T> {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    value: T,
+}
+
+impl<T: DeserializeOwned + Serialize + Default> ListFile<T> {
+    fn open(path: PathBuf) -> Result<Self> {
+        if !path.exists() {
+            let empty = serde_json::to_string_pretty(&T::default())?;
+            fs::write(&path, empty)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+        }
+        let mut file = Self {
+            path,
+            modified: None,
+            value: T::default(),
+        };
+        file.reload()?;
+        Ok(file)
+    }
+
+    /// Read the file again if it changed. A broken file keeps the last good list.
+    fn refresh(&mut self) {
+        let modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        if modified != self.modified {
+            if let Err(e) = self.reload() {
+                log::error!("{:#}", e);
+            }
+        }
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        let modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        let text = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        self.value = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse {}", self.path.display()))?;
+        self.modified = modified;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct AccessLists {
+    enforce_allowlist: bool,
+    allowlist: Mutex<ListFile<Vec<AllowEntry>>>,
+    banlist: Mutex<ListFile<BanList>>,
+}
+
+impl AccessLists {
+    /// Read the lists in `dir`, creating empty ones where there are none.
+    pub fn open(dir: &Path, enforce_allowlist: bool) -> Result<Self> {
+        Ok(Self {
+            enforce_allowlist,
+            allowlist: Mutex::new(ListFile::open(dir.join(ALLOWLIST_FILE))?),
+            banlist: Mutex::new(ListFile::open(dir.join(BANLIST_FILE))?),
+        })
+    }
+
+    /// Whether `name` may join from `ip`, bans first.
+    pub fn check(&self, name: &str, ip: IpAddr) -> std::result::Result<(), DisconnectReason> {
+        let mut banlist = self.banlist.lock().unwrap();
+        banlist.refresh();
+        let bans = &banlist.value;
+        if let Some(ban) = bans
+            .players
+            .iter()
+            .find(|b| b.name.eq_ignore_ascii_case(name))
+        {
+            return Err(DisconnectReason::Banned(ban.reason.clone()));
+        }
+        if let Some(ban) = bans.ips.iter().find(|b| b.ip == ip) {
+            return Err(DisconnectReason::Banned(ban.reason.clone()));
+        }
+        drop(banlist);
+
+        if self.enforce_allowlist {
+            let mut allowlist = self.allowlist.lock().unwrap();
+            allowlist.refresh();
+            if !allowlist
+                .value
+                .iter()
+                .any(|e| e.name.eq_ignore_ascii_case(name))
+            {
+                return Err(DisconnectReason::NotAllowed);
+            }
+        }
+        Result::Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use blockworld_utils::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn bans_and_allowlist() {
+        let dir = TempDir::new("access").unwrap();
+        let home: IpAddr = "127.0.0.1".parse().unwrap();
+        let spammer: IpAddr = "203.0.113.7".parse().unwrap();
+
+        let open = AccessLists::open(&dir, false).unwrap();
+        assert_eq!(open.check("Steve", home), Result::Ok(()));
+        // created empty, so they're there to edit
+        assert!(dir.join(ALLOWLIST_FILE).exists());
+
+        fs::write(
+            dir.join(BANLIST_FILE),
+            r#"{"players": [{"name": "Griefer", "reason": "Griefing"}],
+                "ips": [{"ip": "203.0.113.7"}]}"#,
+        )
+        .unwrap();
+        let lists = AccessLists::open(&dir, true).unwrap();
+        assert_eq!(
+            lists.check("griefer", home),
+            Err(DisconnectReason::Banned("Griefing".into()))
+        );
+        assert_eq!(
+            lists.check("Steve", spammer),
+            Err(DisconnectReason::Banned(default_reason()))
+        );
+        assert_eq!(
+            lists.check("Steve", home),
+            Err(DisconnectReason::NotAllowed)
+        );
+
+        // edits count from the next login; mtimes can be coarse, so make sure it moved
+        fs::write(dir.join(ALLOWLIST_FILE), r#"[{"name": "steve"}]"#).unwrap();
+        let file = fs::File::options()
+            .write(true)
+            .open(dir.join(ALLOWLIST_FILE))
+            .unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(lists.check("Steve", home), Result::Ok(()));
+
+        // a broken edit keeps the last good list
+        fs::write(dir.join(ALLOWLIST_FILE), "[{").unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(lists.check("Steve", home), Result::Ok(()));
+    }
+}
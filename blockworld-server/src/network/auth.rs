@@ -0,0 +1,154 @@
+//! ```text
+//! package com.mojang.authlib.yggdrasil
+//! class YggdrasilMinecraftSessionService
+//! version 1.16
+//! ```
+//!
+//! Who a player is. Without an [`AuthProvider`] the server believes the name the client sends
+//! and derives the uuid from it ([`offline_uuid`]). With one, the client first tells the
+//! account service it is joining the server with a given server hash (see `encryption.rs`),
+//! then the server asks the service whether that name did, and takes the account's uuid and
+//! spelling of the name from the answer.
+//!
+//! There is no public account service for Blockworld; [`LocalAuthService`] stands in for one,
+//! in memory, so both sides of the check can be exercised.
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::*;
+
+/// An account as the auth service knows it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameProfile {
+    pub uuid: u128,
+    pub name: String,
+}
+
+/// The server's side of the account check. Called on a blocking thread, a real
+/// implementation can make HTTP requests.
+pub trait AuthProvider: Send + Sync + Debug {
+    /// The account of `name` if it joined the server that hashed to `server_hash`.
+    fn has_joined(&self, name: &str, server_hash: &str) -> Result<Option<GameProfile>>;
+}
+
+/// The client's side of the account check: what it is logged in as.
+pub trait Session: Send + Sync {
+    /// Tell the account service we are about to join the server that hashed to `server_hash`.
+    fn join(&self, name: &str, server_hash: &str) -> Result<()>;
+}
+
+/// Stands in for an account id when there is no account check: FNV-1a of the name, so a
+/// player keeps it across sessions.
+pub fn offline_uuid(name: &str) -> u128 {
+    let mut hash: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+    for b in format!("OfflinePlayer:{}", name).bytes() {
+        hash ^= b as u128;
+        hash = hash.wrapping_mul(0x0000_0000_0100_0000_0000_0000_0000_013b);
+    }
+    hash
+}
+
+/// An account service in memory. Clone it to hand the same service to a server and its
+/// clients.
+#[derive(Debug, Clone, Default)]
+pub struct LocalAuthService {
+    inner: Arc<Mutex<LocalAccounts>>,
+}
+
+#[derive(Debug, Default)]
+struct LocalAccounts {
+    /// By name in lower case, names are unique whatever their case.
+    profiles: HashMap<String, GameProfile>,
+    /// The server hash each account last joined.
+    joins: HashMap<String, String>,
+}
+
+impl LocalAuthService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an account, returning its profile.
+    pub fn register(&self, name: &str, uuid: u128) -> GameProfile {
+        let profile = GameProfile {
+            uuid,
+            name: name.to_string(),
+        };
+        let mut accounts = self.inner.lock().unwrap();
+        accounts
+            .profiles
+            .insert(name.to_lowercase(), profile.clone());
+        profile
+    }
+
+    /// The client side of the check for the account `name`.
+    pub fn session(&self, name: &str) -> LocalSession {
+        LocalSession {
+            service: self.clone(),
+            name: name.to_string(),
+        }
+    }
+}
+
+impl AuthProvider for LocalAuthService {
+    fn has_joined(&self, name: &str, server_hash: &str) -> Result<Option<GameProfile>> {
+        let accounts = self.inner.lock().unwrap();
+        let key = name.to_lowercase();
+        Ok(match accounts.joins.get(&key) {
+            Some(joined) if joined == server_hash => accounts.profiles.get(&key).cloned(),
+            _ => None,
+        })
+    }
+}
+
+/// Logged in to a [`LocalAuthService`] as one account.
+pub struct LocalSession {
+    service: LocalAuthService,
+    name: String,
+}
+
+impl Session for LocalSession {
+    fn join(&self, name: &str, server_hash: &str) -> Result<()> {
+        ensure!(
+            name.eq_ignore_ascii_case(&self.name),
+            "Logged in as {}, not {}",
+            self.name,
+            name
+        );
+        let mut accounts = self.service.inner.lock().unwrap();
+        let key = name.to_lowercase();
+        ensure!(accounts.profiles.contains_key(&key), "No account {}", name);
+        accounts.joins.insert(key, server_hash.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_uuids_are_stable() {
+        assert_eq!(offline_uuid("Steve"), offline_uuid("Steve"));
+        assert_ne!(offline_uuid("Steve"), offline_uuid("Alex"));
+    }
+
+    #[test]
+    fn only_the_joined_server_sees_the_account() {
+        let service = LocalAuthService::new();
+        let steve = service.register("Steve", 7);
+        assert_eq!(service.has_joined("Steve", "abc").unwrap(), None);
+
+        service.session("Steve").join("steve", "abc").unwrap();
+        assert_eq!(service.has_joined("STEVE", "abc").unwrap(), Some(steve));
+        assert_eq!(service.has_joined("Steve", "other").unwrap(), None);
+
+        // sessions only speak for their own account
+        assert!(service.session("Steve").join("Alex", "abc").is_err());
+        assert!(service.session("Alex").join("Alex", "abc").is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! Packet captures: every packet of one connection, both ways, with when it went, to look at
+//! a desync after the fact or to replay it (see `replay.rs`).
+//!
+//! Packets are recorded where they are encoded and decoded, so a capture holds plaintext even
+//! on an encrypted connection, and the same packets whichever side recorded it.
+//!
+//! ```text
+//! file   = "BWCP", u16 format version, i32 protocol version, record*
+//! record = VarInt length, u64 microseconds since the capture started,
+//!          u8 direction (0 serverbound, 1 clientbound),
+//!          u8 state (0 handshake, 1 login, 2 play, 3 status),
+//!          payload (packet id and body, what goes inside a frame)
+//! ```
+
+use std::{
+    fmt::Debug,
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::*;
+
+use crate::packet::{
+    handshake::HandshakeServerbound,
+    login::{LoginClientbound, LoginServerbound},
+    play::{PlayClientbound, PlayServerbound},
+    status::{StatusClientbound, StatusServerbound},
+    ConnectionState, Decode, Direction, Encode, PacketSet, VarInt, PROTOCOL_VERSION,
+};
+
+const MAGIC: &[u8; 4] = b"BWCP";
+const FORMAT_VERSION: u16 = 1;
+
+/// One recorded packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// Since the capture started.
+    pub at: Duration,
+    pub direction: Direction,
+    pub state: ConnectionState,
+    /// The packet id and body.
+    pub payload: Vec<u8>,
+}
+
+impl Record {
+    /// The packet, if it belongs to `P`'s state and direction.
+    pub fn decode<P: PacketSet>(&self) -> Option<Result<P>> {
+        (self.state == P::STATE && self.direction == P::DIRECTION).then(|| P::decode(&self.payload))
+    }
+
+    /// The packet's name and fields, for people.
+    pub fn describe(&self) -> String {
+        fn show<P: PacketSet + Debug>(payload: &[u8]) -> String {
+            match P::decode(payload) {
+                Result::Ok(packet) => format!("{:?}", packet),
+                Err(e) => format!("undecodable {} packet: {:#}", P::STATE.name(), e),
+            }
+        }
+        match (self.state, self.direction) {
+            (ConnectionState::Handshake, Direction::Serverbound) => {
+                show::<HandshakeServerbound>(&self.payload)
+            }
+            (ConnectionState::Status, Direction::Serverbound) => {
+                show::<StatusServerbound>(&self.payload)
+            }
+            (ConnectionState::Status, Direction::Clientbound) => {
+                show::<StatusClientbound>(&self.payload)
+            }
+            (ConnectionState::Login, Direction::Serverbound) => {
+                show::<LoginServerbound>(&self.payload)
+            }
+            (ConnectionState::Login, Direction::Clientbound) => {
+                show::<LoginClientbound>(&self.payload)
+            }
+            (ConnectionState::Play, Direction::Serverbound) => {
+                show::<PlayServerbound>(&self.payload)
+            }
+            (ConnectionState::Play, Direction::Clientbound) => {
+                show::<PlayClientbound>(&self.payload)
+            }
+            (state, direction) => format!("{:?} {:?} packet", direction, state),
+        }
+    }
+}
+
+struct CaptureFile {
+    out: File,
+    start: Instant,
+    failed: bool,
+}
+
+/// Where one connection records its packets. Both halves of the connection share it, so
+/// clones write to the same file. Every record is written as it comes, so a capture holds
+/// everything up to the last packet even if the process dies.
+#[derive(Clone)]
+pub struct Capture(Arc<Mutex<CaptureFile>>);
+
+impl Capture {
+    pub fn create(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut out =
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut header = MAGIC.to_vec();
+        FORMAT_VERSION.encode(&mut header);
+        PROTOCOL_VERSION.encode(&mut header);
+        out.write_all(&header)?;
+        Ok(Self(Arc::new(Mutex::new(CaptureFile {
+            out,
+            start: Instant::now(),
+            failed: false,
+        }))))
+    }
+
+    /// Record a packet of `P` with this payload. A capture that fails to write stops, the
+    /// connection goes on.
+    pub fn record<P: PacketSet>(&self, payload: &[u8]) {
+        let mut file = self.0.lock().unwrap();
+        if file.failed {
+            return;
+        }
+        let mut record = vec![];
+        (file.start.elapsed().as_micros() as u64).encode(&mut record);
+        record.push(match P::DIRECTION {
+            Direction::Serverbound => 0,
+            Direction::Clientbound => 1,
+        });
+        record.push(match P::STATE {
+            ConnectionState::Handshake => 0,
+            ConnectionState::Login => 1,
+            ConnectionState::Play => 2,
+            ConnectionState::Status => 3,
+        });
+        record.extend_from_slice(payload);
+        let mut framed = vec![];
+        VarInt(record.len() as i32).encode(&mut framed);
+        framed.extend(record);
+        if let Err(e) = file.out.write_all(&framed) {
+            log::error!("Failed to write the packet capture, stopping it: {}", e);
+            file.failed = true;
+        }
+    }
+}
+
+/// Every record of a capture file, in the order they were written.
+pub fn read_capture(path: &Path) -> Result<Vec<Record>> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut buf = bytes.as_slice();
+    ensure!(
+        buf.starts_with(MAGIC),
+        "{} is not a packet capture",
+        path.display()
+    );
+    buf = &buf[MAGIC.len()..];
+    let format = u16::decode(&mut buf)?;
+    ensure!(
+        format == FORMAT_VERSION,
+        "Unknown capture format {}",
+        format
+    );
+    let protocol = i32::decode(&mut buf)?;
+    if protocol != PROTOCOL_VERSION {
+        log::warn!(
+            "{} was captured with protocol {}, packets may not decode",
+            path.display(),
+            protocol
+        );
+    }
+
+    let mut records = vec![];
+    while !buf.is_empty() {
+        let len = VarInt::decode(&mut buf)?.0;
+        ensure!(
+            len >= 10 && len as usize <= buf.len(),
+            "Truncated capture after {} records",
+            records.len()
+        );
+        let (mut record, rest) = buf.split_at(len as usize);
+        buf = rest;
+        let at = Duration::from_micros(u64::decode(&mut record)?);
+        let direction = match u8::decode(&mut record)? {
+            0 => Direction::Serverbound,
+            1 => Direction::Clientbound,
+            other => bail!("Invalid direction {} in record {}", other, records.len()),
+        };
+        let state = match u8::decode(&mut record)? {
+            0 => ConnectionState::Handshake,
+            1 => ConnectionState::Login,
+            2 => ConnectionState::Play,
+            3 => ConnectionState::Status,
+            other => bail!("Invalid state {} in record {}", other, records.len()),
+        };
+        records.push(Record {
+            at,
+            direction,
+            state,
+            payload: record.to_vec(),
+        });
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use blockworld_utils::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn records_read_back() {
+        let dir = TempDir::new("capture").unwrap();
+        let path = dir.join("session.bwcap");
+        let keep_alive = PlayClientbound::KeepAlive { id: 7 };
+        let name = LoginServerbound::LoginStart {
+            name: "Steve".into(),
+        };
+        {
+            let capture = Capture::create(&path).unwrap();
+            capture.record::<LoginServerbound>(&name.encode());
+            capture
+                .clone()
+                .record::<PlayClientbound>(&keep_alive.encode());
+        }
+
+        let records = read_capture(&path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].direction, Direction::Serverbound);
+        assert_eq!(records[0].state, ConnectionState::Login);
+        assert_eq!(
+            records[0].decode::<LoginServerbound>().unwrap().unwrap(),
+            name
+        );
+        assert!(records[0].decode::<PlayServerbound>().is_none());
+        assert!(records[1].at >= records[0].at);
+        assert_eq!(records[1].describe(), "KeepAlive { id: 7 }");
+
+        // a capture cut short, like by a crash, is an error rather than a wrong record
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(read_capture(&path).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! The client's side of the handshake, the status query and login.
+
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::*;
+
+use crate::packet::{
+    handshake::{HandshakeServerbound, NextState},
+    login::{LoginClientbound, LoginServerbound},
+    status::{ServerStatus, StatusClientbound, StatusServerbound},
+    Direction, PacketSet,
+};
+
+use super::{
+    auth::Session,
+    encryption::KeyExchange,
+    transport::{PacketReader, PacketWriter},
+};
+
+/// Handshake and ask for the server's status, then ping it. Returns the status and the round
+/// trip of the ping. Works whatever protocol the server speaks.
+pub async fn query_status(
+    reader: &mut PacketReader,
+    writer: &mut PacketWriter,
+    server_address: &str,
+    server_port: u16,
+) -> Result<(ServerStatus, Duration)> {
+    writer
+        .send(&HandshakeServerbound::new(
+            server_address,
+            server_port,
+            NextState::Status,
+        ))
+        .await?;
+    writer.send(&StatusServerbound::StatusRequest {}).await?;
+    let status = match reader.recv::<StatusClientbound>().await? {
+        Some(StatusClientbound::StatusResponse { json }) => {
+            serde_json::from_str(&json).context("The server sent a status we don't understand")?
+        }
+        Some(packet) => bail!("Expected the status, got {}", packet.name()),
+        None => bail!("The server closed the connection before its status"),
+    };
+
+    // anything comes back as it went, the time like Minecraft does
+    let payload = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64);
+    let sent = Instant::now();
+    writer.send(&StatusServerbound::Ping { payload }).await?;
+    match reader.recv::<StatusClientbound>().await? {
+        Some(StatusClientbound::Pong { payload: p }) if p == payload => {
+            Ok((status, sent.elapsed()))
+        }
+        Some(packet) => bail!("Expected the pong, got {:?}", packet),
+        None => bail!("The server closed the connection before the pong"),
+    }
+}
+
+/// Handshake and log in as `name`, returning the uuid the server gave us. Both sides are in
+/// play afterwards, encrypted if the server asked for it.
+///
+/// `server_address` and `server_port` are what the player typed, the server may use them to
+/// tell virtual hosts apart. `session` is the account to prove `name` with, for servers that
+/// check accounts.
+pub async fn login(
+    reader: &mut PacketReader,
+    writer: &mut PacketWriter,
+    server_address: &str,
+    server_port: u16,
+    name: &str,
+    session: Option<&dyn Session>,
+) -> Result<u128> {
+    writer
+        .send(&HandshakeServerbound::new(
+            server_address,
+            server_port,
+            NextState::Login,
+        ))
+        .await?;
+    writer
+        .send(&LoginServerbound::LoginStart {
+            name: name.to_string(),
+        })
+        .await?;
+    loop {
+        match reader.recv::<LoginClientbound>().await? {
+            Some(LoginClientbound::EncryptionRequest {
+                public_key,
+                authenticate,
+            }) => {
+                let exchange = KeyExchange::new()?;
+                let our_key = exchange.public_key();
+                let secret = exchange.agree(&public_key)?;
+                if authenticate {
+                    let session = session.ok_or_else(|| {
+                        anyhow!("The server checks accounts and we aren't logged in to one")
+                    })?;
+                    session
+                        .join(name, &secret.server_hash(&public_key))
+                        .context("The account service didn't let us join")?;
+                }
+                writer
+                    .send(&LoginServerbound::EncryptionResponse {
+                        public_key: our_key,
+                    })
+                    .await?;
+                reader.enable_encryption(secret.cipher(Direction::Clientbound));
+                writer.enable_encryption(secret.cipher(Direction::Serverbound));
+            }
+            Some(LoginClientbound::LoginSuccess { uuid, .. }) => return Ok(uuid),
+            Some(LoginClientbound::Disconnect { reason }) => bail!("Disconnected: {}", reason),
+            None => bail!("The server closed the connection during login"),
+        }
+    }
+}
@@ -0,0 +1,337 @@
+//! ```text
+//! package net.minecraft.command
+//! class Commands
+//! version 1.16
+//! ```
+//!
+//! Operator commands, a line of words each. They run on the tick thread between ticks, like
+//! everything else touching the world, and answer with text for whoever ran them. There are
+//! no permissions: only operators can reach the dispatcher, through RCON or
+//! [`ServerHandle::command`].
+//!
+//! [`ServerHandle::command`]: super::ServerHandle::command
+
+use std::{fmt, str::FromStr};
+
+use anyhow::*;
+use blockworld_utils::ResourceLocation;
+use glam::*;
+
+use crate::{
+    block::{state::BlockState, BLOCK_REGISTRY},
+    entity::{
+        game_mode::{AdventureTags, GameMode},
+        EntityType,
+    },
+    world::{block_update::placement_state, chunk::SUBCHUNK_SIZE, chunk_access::WorldAccess},
+};
+
+use super::{disconnect::DisconnectReason, tick_loop::TickLoop};
+
+pub(super) struct Command {
+    name: &'static str,
+    usage: &'static str,
+    help: &'static str,
+    run: fn(&mut TickLoop, &[&str]) -> Result<String>,
+}
+
+pub(super) const COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        usage: "[command]",
+        help: "List the commands, or how to use one",
+        run: help,
+    },
+    Command {
+        name: "list",
+        usage: "",
+        help: "List the players online",
+        run: list,
+    },
+    Command {
+        name: "kick",
+        usage: "<player> [reason...]",
+        help: "Disconnect a player",
+        run: kick,
+    },
+    Command {
+        name: "gamemode",
+        usage: "<survival|creative|adventure|spectator> <player>",
+        help: "Change what a player may do",
+        run: gamemode,
+    },
+    Command {
+        name: "candestroy",
+        usage: "<player> [block...]",
+        help: "Set the blocks a player in adventure may break, none without blocks",
+        run: can_destroy,
+    },
+    Command {
+        name: "canplaceon",
+        usage: "<player> [block...]",
+        help: "Set the blocks a player in adventure may place against, none without blocks",
+        run: can_place_on,
+    },
+    Command {
+        name: "setblock",
+        usage: "<x> <y> <z> <block>",
+        help: "Change a block in a loaded chunk, fitting it to its neighbours. Properties go \
+               in brackets, as in repeater[facing=east,delay=2]",
+        run: setblock,
+    },
+    Command {
+        name: "summon",
+        usage: "<entity> <x> <y> <z>",
+        help: "Spawn an entity in a loaded chunk, as in summon pig 0.5 65 0.5",
+        run: summon,
+    },
+    Command {
+        name: "gamerule",
+        usage: "<rule> [value]",
+        help: "Show or change a game rule",
+        run: gamerule,
+    },
+    Command {
+        name: "time",
+        usage: "",
+        help: "Show the age of the world in ticks",
+        run: time,
+    },
+    Command {
+        name: "tps",
+        usage: "",
+        help: "Show how fast the server ticks",
+        run: tps,
+    },
+    Command {
+        name: "save-all",
+        usage: "",
+        help: "Save every player and the world",
+        run: save_all,
+    },
+];
+
+/// The arguments don't fit the command, answered with its usage.
+#[derive(Debug)]
+struct BadUsage;
+
+impl fmt::Display for BadUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Bad usage")
+    }
+}
+
+impl std::error::Error for BadUsage {}
+
+/// Run `line`, a command name and its arguments. A leading `/` is fine, as in chat.
+pub(super) fn dispatch(tick_loop: &mut TickLoop, line: &str) -> String {
+    let words: Vec<&str> = line
+        .trim()
+        .trim_start_matches('/')
+        .split_whitespace()
+        .collect();
+    let Some((name, args)) = words.split_first() else {
+        return "Type help for a list of commands".to_string();
+    };
+    let Some(command) = COMMANDS.iter().find(|c| c.name.eq_ignore_ascii_case(name)) else {
+        return format!("Unknown command {}, type help for a list of commands", name);
+    };
+    match (command.run)(tick_loop, args) {
+        Result::Ok(output) => output,
+        Err(e) if e.is::<BadUsage>() => format!("Usage: {} {}", command.name, command.usage),
+        Err(e) => format!("{:#}", e),
+    }
+}
+
+/// Argument `i`, parsed.
+fn arg<T: FromStr>(args: &[&str], i: usize) -> Result<T> {
+    args.get(i)
+        .and_then(|a| a.parse().ok())
+        .ok_or_else(|| Error::new(BadUsage))
+}
+
+fn help(_: &mut TickLoop, args: &[&str]) -> Result<String> {
+    if let Some(name) = args.first() {
+        let command = COMMANDS
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| anyhow!("Unknown command {}", name))?;
+        return Ok(format!(
+            "{}\nUsage: {} {}",
+            command.help, command.name, command.usage
+        ));
+    }
+    let lines: Vec<String> = COMMANDS
+        .iter()
+        .map(|c| format!("{} - {}", c.name, c.help))
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+fn list(tick_loop: &mut TickLoop, _: &[&str]) -> Result<String> {
+    let mut names = tick_loop.player_names();
+    names.sort();
+    Ok(format!(
+        "There are {} of a max of {} players online: {}",
+        names.len(),
+        tick_loop.max_players(),
+        names.join(", ")
+    ))
+}
+
+fn kick(tick_loop: &mut TickLoop, args: &[&str]) -> Result<String> {
+    let name: String = arg(args, 0)?;
+    let reason = match args.len() {
+        1 => "Kicked by an operator".to_string(),
+        _ => args[1..].join(" "),
+    };
+    ensure!(
+        tick_loop.kick(&name, DisconnectReason::Kicked(reason.clone())),
+        "No player named {} is online",
+        name
+    );
+    Ok(format!("Kicked {}: {}", name, reason))
+}
+
+/// A block id, where `stone` means `minecraft:stone`.
+fn block_arg(name: &str) -> Result<ResourceLocation> {
+    let id = match name.contains(':') {
+        true => name.into(),
+        false => format!("minecraft:{}", name).as_str().into(),
+    };
+    ensure!(BLOCK_REGISTRY.get(&id).is_some(), "Unknown block {}", name);
+    Ok(id)
+}
+
+fn gamemode(tick_loop: &mut TickLoop, args: &[&str]) -> Result<String> {
+    let mode: GameMode = arg(args, 0)?;
+    let name: String = arg(args, 1)?;
+    let player = tick_loop
+        .find_player(&name)
+        .ok_or_else(|| anyhow!("No player named {} is online", name))?;
+    let (_, tags) = tick_loop.abilities(player);
+    tick_loop.set_abilities(player, mode, tags);
+    Ok(format!("Set the game mode of {} to {}", name, mode))
+}
+
+fn can_destroy(tick_loop: &mut TickLoop, args: &[&str]) -> Result<String> {
+    set_tags(tick_loop, args, "break", |tags, blocks| {
+        tags.can_destroy = blocks
+    })
+}
+
+fn can_place_on(tick_loop: &mut TickLoop, args: &[&str]) -> Result<String> {
+    set_tags(tick_loop, args, "place blocks against", |tags, blocks| {
+        tags.can_place_on = blocks
+    })
+}
+
+fn set_tags(
+    tick_loop: &mut TickLoop,
+    args: &[&str],
+    what: &str,
+    set: fn(&mut AdventureTags, Vec<ResourceLocation>),
+) -> Result<String> {
+    let name: String = arg(args, 0)?;
+    let blocks = args[1..]
+        .iter()
+        .map(|b| block_arg(b))
+        .collect::<Result<Vec<_>>>()?;
+    let player = tick_loop
+        .find_player(&name)
+        .ok_or_else(|| anyhow!("No player named {} is online", name))?;
+    let (mode, mut tags) = tick_loop.abilities(player);
+    let listed: Vec<String> = blocks.iter().map(|b| b.to_string()).collect();
+    set(&mut tags, blocks);
+    tick_loop.set_abilities(player, mode, tags);
+    Ok(match listed.is_empty() {
+        true => format!("{} may {} nothing in adventure", name, what),
+        false => format!("{} may {} {} in adventure", name, what, listed.join(", ")),
+    })
+}
+
+fn setblock(tick_loop: &mut TickLoop, args: &[&str]) -> Result<String> {
+    let pos = ivec3(arg(args, 0)?, arg(args, 1)?, arg(args, 2)?);
+    let name: String = arg(args, 3)?;
+    let state = BlockState::parse(&name)?;
+    let chunks = tick_loop.world_mut().chunks_mut();
+    ensure!(
+        chunks.is_chunk_loaded(pos.div_euclid(IVec3::splat(SUBCHUNK_SIZE as i32))),
+        "{} {} {} is not loaded",
+        pos.x,
+        pos.y,
+        pos.z
+    );
+    let state = placement_state(chunks, pos, state);
+    chunks.set_state(pos, state);
+    Ok(format!(
+        "Changed the block at {} {} {} to {}",
+        pos.x, pos.y, pos.z, state
+    ))
+}
+
+fn summon(tick_loop: &mut TickLoop, args: &[&str]) -> Result<String> {
+    let name: String = arg(args, 0)?;
+    let position = vec3(arg(args, 1)?, arg(args, 2)?, arg(args, 3)?);
+    let id: ResourceLocation = match name.contains(':') {
+        true => name.as_str().into(),
+        false => format!("minecraft:{}", name).as_str().into(),
+    };
+    let kind = EntityType::of(&id).ok_or_else(|| anyhow!("Unknown entity type {}", name))?;
+    let world = tick_loop.world_mut();
+    let section = position
+        .floor()
+        .as_ivec3()
+        .div_euclid(IVec3::splat(SUBCHUNK_SIZE as i32));
+    ensure!(
+        world.chunks().is_chunk_loaded(section),
+        "{} {} {} is not loaded",
+        position.x,
+        position.y,
+        position.z
+    );
+    ensure!(
+        world.summon(kind, position).is_some(),
+        "Can't summon {}",
+        &*id
+    );
+    Ok(format!(
+        "Summoned {} at {} {} {}",
+        &*id, position.x, position.y, position.z
+    ))
+}
+
+fn gamerule(tick_loop: &mut TickLoop, args: &[&str]) -> Result<String> {
+    let name: String = arg(args, 0)?;
+    let rules = &mut tick_loop.world_mut().level_mut().game_rules;
+    match args.get(1) {
+        None => Ok(format!("Game rule {} is {}", name, rules.get(&name)?)),
+        Some(value) => {
+            rules.set(&name, value)?;
+            Ok(format!("Set game rule {} to {}", name, rules.get(&name)?))
+        }
+    }
+}
+
+fn time(tick_loop: &mut TickLoop, _: &[&str]) -> Result<String> {
+    Ok(format!(
+        "The time is {}",
+        tick_loop.world_mut().level().time
+    ))
+}
+
+fn tps(tick_loop: &mut TickLoop, _: &[&str]) -> Result<String> {
+    let metrics = tick_loop.metrics();
+    Ok(format!(
+        "TPS {:.1}, {:.2} ms per tick ({:.2} at most) over the last 5 seconds, {} ticks since start",
+        metrics.tps(),
+        metrics.mspt(),
+        metrics.max_mspt(),
+        metrics.ticks()
+    ))
+}
+
+fn save_all(tick_loop: &mut TickLoop, _: &[&str]) -> Result<String> {
+    tick_loop.save().context("Failed to save")?;
+    Ok("Saved the game".to_string())
+}
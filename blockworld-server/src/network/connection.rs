@@ -0,0 +1,326 @@
+//! One client connection on the server, from the handshake until it closes.
+
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{mpsc::Sender, Arc},
+    time::SystemTime,
+};
+
+use anyhow::*;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::packet::{
+    handshake::HandshakeServerbound,
+    login::{LoginClientbound, LoginServerbound},
+    play::{PlayClientbound, PlayServerbound},
+    status::{
+        PlayerSample, ServerStatus, StatusClientbound, StatusPlayers, StatusServerbound,
+        StatusVersion, MAX_SAMPLE,
+    },
+    ConnectionState, Direction, PacketSet, PROTOCOL_VERSION,
+};
+
+use super::{
+    access::AccessLists,
+    auth::{offline_uuid, AuthProvider, GameProfile},
+    capture::Capture,
+    disconnect::DisconnectReason,
+    encryption::KeyExchange,
+    transport::{PacketReader, PacketWriter},
+    ConnectionId, ServerEvent,
+};
+
+/// How connections decide who may join, what they tell server lists and whether they are
+/// recorded, the same for all of them.
+#[derive(Debug, Default)]
+pub(super) struct ConnectionSettings {
+    pub encryption: bool,
+    /// Checks accounts, and implies encryption.
+    pub auth: Option<Arc<dyn AuthProvider>>,
+    pub access: Option<AccessLists>,
+    /// Record every connection into a capture file here.
+    pub capture_dir: Option<PathBuf>,
+    pub motd: String,
+    pub max_players: u32,
+    /// A data URL, see [`crate::packet::status::favicon_url`].
+    pub favicon: Option<String>,
+}
+
+impl ConnectionSettings {
+    fn status(&self, players: Vec<GameProfile>) -> ServerStatus {
+        ServerStatus {
+            version: StatusVersion {
+                name: format!("Blockworld {}", env!("CARGO_PKG_VERSION")),
+                protocol: PROTOCOL_VERSION,
+            },
+            players: StatusPlayers {
+                max: self.max_players,
+                online: players.len() as u32,
+                sample: players
+                    .into_iter()
+                    .take(MAX_SAMPLE)
+                    .map(|p| PlayerSample {
+                        name: p.name,
+                        id: format!("{:032x}", p.uuid),
+                    })
+                    .collect(),
+            },
+            description: self.motd.clone(),
+            favicon: self.favicon.clone(),
+        }
+    }
+}
+
+/// Run the connection, logging why it ended if it wasn't a normal close.
+pub(super) async fn serve(
+    mut reader: PacketReader,
+    mut writer: PacketWriter,
+    peer: SocketAddr,
+    id: ConnectionId,
+    events: Sender<ServerEvent>,
+    settings: Arc<ConnectionSettings>,
+) {
+    if let Some(dir) = &settings.capture_dir {
+        let started = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let path = dir.join(format!("{}-{}.bwcap", started.as_secs(), id));
+        match Capture::create(&path) {
+            Result::Ok(capture) => {
+                log::info!("Recording {} into {}", peer, path.display());
+                reader.set_capture(capture.clone());
+                writer.set_capture(capture);
+            }
+            Err(e) => log::error!("Failed to record {}: {:#}", peer, e),
+        }
+    }
+    let next = match reader.recv::<HandshakeServerbound>().await {
+        Result::Ok(Some(handshake)) => handshake.accept(),
+        Result::Ok(None) => return,
+        Err(e) => {
+            log::warn!("{} sent a broken handshake: {:#}", peer, e);
+            return;
+        }
+    };
+    let joined = match next {
+        Result::Ok(ConnectionState::Status) => {
+            if let Err(e) = status(&mut reader, &mut writer, &events, &settings).await {
+                log::debug!("{} lost connection during a status query: {:#}", peer, e);
+            }
+            return;
+        }
+        Result::Ok(_) => log_in(&mut reader, &mut writer, peer, id, &events, &settings).await,
+        Err(reason) => refuse(&mut writer, reason.to_string()).await,
+    };
+    let joined = match joined {
+        Result::Ok(Some(outbound)) => outbound,
+        Result::Ok(None) => return,
+        Err(e) => {
+            log::warn!("{} lost connection during login: {:#}", peer, e);
+            return;
+        }
+    };
+    if let Err(e) = play(&mut reader, &mut writer, id, &events, joined).await {
+        log::warn!("{} lost connection: {:#}", peer, e);
+    }
+    let _ = events.send(ServerEvent::Leave { id });
+}
+
+/// Names are what player data files are named after, keep them boring.
+fn valid_name(name: &str) -> bool {
+    (1..=16).contains(&name.len()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Answer a server list: the status as often as asked, then the ping, then close.
+async fn status(
+    reader: &mut PacketReader,
+    writer: &mut PacketWriter,
+    events: &Sender<ServerEvent>,
+    settings: &ConnectionSettings,
+) -> Result<()> {
+    loop {
+        match reader.recv::<StatusServerbound>().await? {
+            Some(StatusServerbound::StatusRequest {}) => {
+                let (reply, players) = oneshot::channel();
+                events
+                    .send(ServerEvent::Status { reply })
+                    .map_err(|_| anyhow!("the server is stopping"))?;
+                let status = settings.status(players.await?);
+                writer
+                    .send(&StatusClientbound::StatusResponse {
+                        json: serde_json::to_string(&status)?,
+                    })
+                    .await?;
+            }
+            Some(StatusServerbound::Ping { payload }) => {
+                writer.send(&StatusClientbound::Pong { payload }).await?;
+                return writer.close().await;
+            }
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Login, after the handshake. Returns the queue of packets the tick thread sends us, or
+/// `None` if the client was refused.
+async fn log_in(
+    reader: &mut PacketReader,
+    writer: &mut PacketWriter,
+    peer: SocketAddr,
+    id: ConnectionId,
+    events: &Sender<ServerEvent>,
+    settings: &ConnectionSettings,
+) -> Result<Option<mpsc::UnboundedReceiver<PlayClientbound>>> {
+    let Some(LoginServerbound::LoginStart { name }) = reader.recv().await? else {
+        return Ok(None);
+    };
+    if !valid_name(&name) {
+        return refuse(writer, DisconnectReason::InvalidName.to_string()).await;
+    }
+
+    let mut profile = GameProfile {
+        uuid: offline_uuid(&name),
+        name,
+    };
+    if settings.encryption || settings.auth.is_some() {
+        let exchange = KeyExchange::new()?;
+        let public_key = exchange.public_key();
+        writer
+            .send(&LoginClientbound::EncryptionRequest {
+                public_key: public_key.clone(),
+                authenticate: settings.auth.is_some(),
+            })
+            .await?;
+        let client_key = match reader.recv().await? {
+            Some(LoginServerbound::EncryptionResponse { public_key }) => public_key,
+            Some(packet) => bail!("expected the encryption response, got {}", packet.name()),
+            None => return Ok(None),
+        };
+        let secret = match exchange.agree(&client_key) {
+            Result::Ok(secret) => secret,
+            Err(e) => {
+                log::warn!("{} sent a bad key: {:#}", peer, e);
+                return refuse(writer, DisconnectReason::EncryptionFailed.to_string()).await;
+            }
+        };
+        reader.enable_encryption(secret.cipher(Direction::Serverbound));
+        writer.enable_encryption(secret.cipher(Direction::Clientbound));
+
+        if let Some(auth) = &settings.auth {
+            let auth = auth.clone();
+            let name = profile.name.clone();
+            let hash = secret.server_hash(&public_key);
+            let checked =
+                tokio::task::spawn_blocking(move || auth.has_joined(&name, &hash)).await?;
+            profile = match checked {
+                Result::Ok(Some(account)) => account,
+                Result::Ok(None) => {
+                    return refuse(writer, DisconnectReason::NotAuthenticated.to_string()).await
+                }
+                Err(e) => {
+                    log::warn!("Failed to check the account of {}: {:#}", profile.name, e);
+                    return refuse(writer, DisconnectReason::NotAuthenticated.to_string()).await;
+                }
+            };
+        }
+    }
+
+    if let Some(access) = &settings.access {
+        if let Err(reason) = access.check(&profile.name, peer.ip()) {
+            log::info!("Refused {} from {}: {}", profile.name, peer, reason);
+            return refuse(writer, reason.to_string()).await;
+        }
+    }
+
+    let (outbound, queue) = mpsc::unbounded_channel();
+    let (reply, joined) = oneshot::channel();
+    events
+        .send(ServerEvent::Join {
+            id,
+            profile: profile.clone(),
+            outbound,
+            reply,
+        })
+        .map_err(|_| anyhow!("the server is stopping"))?;
+    match joined.await? {
+        Result::Ok(()) => {
+            writer
+                .send(&LoginClientbound::LoginSuccess {
+                    uuid: profile.uuid,
+                    name: profile.name,
+                })
+                .await?;
+            Ok(Some(queue))
+        }
+        Err(reason) => refuse(writer, reason.to_string()).await,
+    }
+}
+
+async fn refuse<T>(writer: &mut PacketWriter, reason: String) -> Result<Option<T>> {
+    writer
+        .send(&LoginClientbound::Disconnect { reason })
+        .await?;
+    writer.close().await?;
+    Ok(None)
+}
+
+/// Forward packets both ways until either side hangs up. A [`PlayClientbound::Disconnect`]
+/// from the tick thread is the last packet we send.
+async fn play(
+    reader: &mut PacketReader,
+    writer: &mut PacketWriter,
+    id: ConnectionId,
+    events: &Sender<ServerEvent>,
+    mut queue: mpsc::UnboundedReceiver<PlayClientbound>,
+) -> Result<()> {
+    loop {
+        tokio::select! {
+            packet = reader.recv::<PlayServerbound>() => {
+                let Some(packet) = packet? else {
+                    return Ok(());
+                };
+                if events.send(ServerEvent::Packet { id, packet }).is_err() {
+                    return Ok(());
+                }
+            }
+            packet = queue.recv() => {
+                let Some(packet) = packet else {
+                    return Ok(());
+                };
+                // write everything queued at once, chunks come in bursts
+                let mut packets = vec![];
+                let mut closing = false;
+                let mut next = Some(packet);
+                while let Some(packet) = next {
+                    closing = matches!(packet, PlayClientbound::Disconnect { .. });
+                    packets.push(packet);
+                    if closing {
+                        break;
+                    }
+                    next = queue.try_recv().ok();
+                }
+                writer.send_all(&packets).await?;
+                if closing {
+                    writer.close().await?;
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn player_names() {
+        assert!(valid_name("Steve"));
+        assert!(valid_name("a_b_1"));
+        assert!(!valid_name(""));
+        assert!(!valid_name("seventeen_letters"));
+        assert!(!valid_name("../level"));
+        assert!(!valid_name("名前"));
+    }
+}
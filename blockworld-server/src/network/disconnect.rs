@@ -0,0 +1,54 @@
+//! Why the server sends a player away. The text is what the player reads, so it says what
+//! happened and, where there is something to do about it, what.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisconnectReason {
+    InvalidName,
+    /// The allow list is on and the player isn't on it.
+    NotAllowed,
+    /// The player's name or address is banned, with the reason the ban gave.
+    Banned(String),
+    /// The server checks accounts and the account service didn't vouch for the player.
+    NotAuthenticated,
+    /// The key exchange went wrong, the client sent a bad key.
+    EncryptionFailed,
+    AlreadyPlaying(String),
+    /// As many players as the server takes are playing.
+    ServerFull,
+    /// An operator kicked the player, saying why.
+    Kicked(String),
+    /// A packet that can't be applied, the client is broken or cheating.
+    InvalidInput,
+    TimedOut,
+    ServerClosed,
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisconnectReason::InvalidName => write!(
+                f,
+                "Invalid player name, use 1 to 16 letters, digits or underscores"
+            ),
+            DisconnectReason::NotAllowed => write!(f, "You are not on the allow list"),
+            DisconnectReason::Banned(reason) => {
+                write!(f, "You are banned from this server: {}", reason)
+            }
+            DisconnectReason::NotAuthenticated => write!(
+                f,
+                "Failed to verify your account, log in again and restart the game"
+            ),
+            DisconnectReason::EncryptionFailed => write!(f, "Failed to set up encryption"),
+            DisconnectReason::AlreadyPlaying(name) => {
+                write!(f, "{} is already playing on this server", name)
+            }
+            DisconnectReason::ServerFull => write!(f, "The server is full"),
+            DisconnectReason::Kicked(reason) => write!(f, "{}", reason),
+            DisconnectReason::InvalidInput => write!(f, "Invalid input"),
+            DisconnectReason::TimedOut => write!(f, "Timed out"),
+            DisconnectReason::ServerClosed => write!(f, "Server closed"),
+        }
+    }
+}
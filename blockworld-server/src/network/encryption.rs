@@ -0,0 +1,146 @@
+//! ```text
+//! package net.minecraft.util
+//! class CryptManager
+//! version 1.16
+//! ```
+//!
+//! Key exchange and stream encryption. During login the server and the client each send a
+//! fresh X25519 public key; both derive the same shared secret from them, and from that one
+//! ChaCha20 key per direction. Everything after the exchange is encrypted, frames included.
+//!
+//! The shared secret also makes the server hash that accounts are checked against (see
+//! `auth.rs`). A man in the middle ends up with a different secret on each side, so the hash
+//! the client registered never matches the one the server asks about.
+
+use anyhow::*;
+use chacha20::{
+    cipher::{KeyIvInit, StreamCipher},
+    ChaCha20,
+};
+use sha2::{Digest, Sha256};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::packet::Direction;
+
+/// One side of a key exchange, good for a single connection.
+pub struct KeyExchange {
+    secret: StaticSecret,
+    public: PublicKey,
+}
+
+impl KeyExchange {
+    pub fn new() -> Result<Self> {
+        let mut bytes = [0; 32];
+        getrandom::getrandom(&mut bytes).map_err(|e| anyhow!("No randomness for a key: {}", e))?;
+        let secret = StaticSecret::from(bytes);
+        let public = PublicKey::from(&secret);
+        Ok(Self { secret, public })
+    }
+
+    /// What to send to the other side.
+    pub fn public_key(&self) -> Vec<u8> {
+        self.public.as_bytes().to_vec()
+    }
+
+    /// Combine our secret with the key the other side sent.
+    pub fn agree(self, peer: &[u8]) -> Result<SharedSecret> {
+        let peer: [u8; 32] = peer
+            .try_into()
+            .map_err(|_| anyhow!("A public key is 32 bytes, got {}", peer.len()))?;
+        let shared = self.secret.diffie_hellman(&PublicKey::from(peer));
+        // a low order point would give a secret anyone can guess
+        ensure!(shared.was_contributory(), "Invalid public key");
+        Ok(SharedSecret(shared.to_bytes()))
+    }
+}
+
+/// What both sides know after the exchange, and nobody else.
+pub struct SharedSecret([u8; 32]);
+
+impl SharedSecret {
+    /// Ties an account check to this connection and this server, hex of
+    /// SHA-256(shared secret, server public key).
+    pub fn server_hash(&self, server_public_key: &[u8]) -> String {
+        let mut hash = Sha256::new();
+        hash.update(self.0);
+        hash.update(server_public_key);
+        hash.finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// The cipher for packets going `direction`. Each direction has its own key, so the
+    /// keystream is never used twice.
+    pub fn cipher(&self, direction: Direction) -> Cipher {
+        let label: &[u8] = match direction {
+            Direction::Serverbound => b"blockworld serverbound",
+            Direction::Clientbound => b"blockworld clientbound",
+        };
+        let mut hash = Sha256::new();
+        hash.update(label);
+        hash.update(self.0);
+        let key = hash.finalize();
+        Cipher(ChaCha20::new(&key, &[0; 12].into()))
+    }
+}
+
+/// Encrypts or decrypts one direction of a connection, byte by byte in stream order.
+pub struct Cipher(ChaCha20);
+
+impl Cipher {
+    pub fn apply(&mut self, bytes: &mut [u8]) {
+        self.0.apply_keystream(bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn both_sides_agree() {
+        let server = KeyExchange::new().unwrap();
+        let client = KeyExchange::new().unwrap();
+        let server_key = server.public_key();
+        let client_key = client.public_key();
+        let on_server = server.agree(&client_key).unwrap();
+        let on_client = client.agree(&server_key).unwrap();
+        assert_eq!(
+            on_server.server_hash(&server_key),
+            on_client.server_hash(&server_key)
+        );
+
+        // however the stream is cut
+        let message = b"a frame and the start of another".to_vec();
+        let mut encrypted = message.clone();
+        on_client
+            .cipher(Direction::Serverbound)
+            .apply(&mut encrypted);
+        assert_ne!(encrypted, message);
+        let mut decrypt = on_server.cipher(Direction::Serverbound);
+        let (a, b) = encrypted.split_at_mut(7);
+        decrypt.apply(a);
+        decrypt.apply(b);
+        assert_eq!(encrypted, message);
+
+        // the other direction has another key
+        let mut serverbound = message.clone();
+        on_server
+            .cipher(Direction::Serverbound)
+            .apply(&mut serverbound);
+        let mut clientbound = message.clone();
+        on_server
+            .cipher(Direction::Clientbound)
+            .apply(&mut clientbound);
+        assert_ne!(serverbound, clientbound);
+    }
+
+    #[test]
+    fn bad_keys_are_refused() {
+        let exchange = KeyExchange::new().unwrap();
+        assert!(KeyExchange::new().unwrap().agree(&[1; 31]).is_err());
+        // the identity point
+        assert!(exchange.agree(&[0; 32]).is_err());
+    }
+}
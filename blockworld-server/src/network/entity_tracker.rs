@@ -0,0 +1,259 @@
+//! ```text
+//! package net.minecraft.world.server
+//! class TrackedEntity
+//! version 1.16
+//! ```
+//!
+//! Which players see which entities, and what they were last told about them.
+//!
+//! Every tick an entity is shown to the players that have its column and are within the
+//! tracking range of its type, and hidden from the others. Every `update_interval` ticks the
+//! players that see it are told how it moved since the last update, in 1/4096 blocks. A
+//! teleport goes instead when the move doesn't fit in an i16 (8 blocks), and every 20 seconds
+//! anyway so rounding on the client can't add up.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy_ecs::{entity::Entity, world::World};
+use glam::*;
+
+use crate::{
+    components::{Position, Rotation},
+    entity::{EntityId, EntityType, Uuid},
+    packet::{
+        play::{dequantize_position, quantize_position, Angle, PlayClientbound},
+        VarInt,
+    },
+    tick::TICKS_PER_SECOND,
+};
+
+use super::{
+    tick_loop::{column_of, Client},
+    ConnectionId,
+};
+
+const FORCE_TELEPORT_INTERVAL: u64 = 20 * TICKS_PER_SECOND;
+
+/// What the viewers of an entity were told last.
+struct TrackedEntity {
+    id: EntityId,
+    /// Position in 1/4096 blocks.
+    encoded: I64Vec3,
+    yaw: Angle,
+    pitch: Angle,
+    last_teleport: u64,
+    viewers: HashSet<ConnectionId>,
+}
+
+impl TrackedEntity {
+    fn new(id: EntityId, position: Vec3, rotation: &Rotation, ticks: u64) -> Self {
+        Self {
+            id,
+            encoded: quantize_position(position.as_dvec3()),
+            yaw: Angle::from_radians(rotation.yaw),
+            pitch: Angle::from_radians(rotation.pitch),
+            last_teleport: ticks,
+            viewers: HashSet::new(),
+        }
+    }
+
+    fn spawn(&self, uuid: Uuid, kind: EntityType) -> PlayClientbound {
+        PlayClientbound::SpawnEntity {
+            id: VarInt(self.id.0),
+            uuid: uuid.0,
+            kind,
+            position: dequantize_position(self.encoded),
+            yaw: self.yaw,
+            pitch: self.pitch,
+        }
+    }
+
+    /// The packet bringing viewers to the current state, `None` if nothing changed.
+    fn update(
+        &mut self,
+        position: Vec3,
+        rotation: &Rotation,
+        ticks: u64,
+    ) -> Option<PlayClientbound> {
+        let encoded = quantize_position(position.as_dvec3());
+        let (yaw, pitch) = (
+            Angle::from_radians(rotation.yaw),
+            Angle::from_radians(rotation.pitch),
+        );
+        let delta = encoded - self.encoded;
+        let fits = delta.to_array().iter().all(|d| i16::try_from(*d).is_ok());
+        let moved = delta != I64Vec3::ZERO;
+        let rotated = (yaw, pitch) != (self.yaw, self.pitch);
+
+        let id = VarInt(self.id.0);
+        let [dx, dy, dz] = delta.to_array().map(|d| d as i16);
+        let packet = if !fits || ticks - self.last_teleport >= FORCE_TELEPORT_INTERVAL {
+            self.last_teleport = ticks;
+            PlayClientbound::EntityTeleport {
+                id,
+                position: dequantize_position(encoded),
+                yaw,
+                pitch,
+            }
+        } else if moved && rotated {
+            PlayClientbound::EntityMoveRotate {
+                id,
+                dx,
+                dy,
+                dz,
+                yaw,
+                pitch,
+            }
+        } else if moved {
+            PlayClientbound::EntityMove { id, dx, dy, dz }
+        } else if rotated {
+            PlayClientbound::EntityRotate { id, yaw, pitch }
+        } else {
+            return None;
+        };
+        self.encoded = encoded;
+        self.yaw = yaw;
+        self.pitch = pitch;
+        Some(packet)
+    }
+}
+
+#[derive(Default)]
+pub(super) struct EntityTracker {
+    entries: HashMap<Entity, TrackedEntity>,
+}
+
+impl EntityTracker {
+    /// Send every player what changed about the entities around it, after the world ticked.
+    pub(super) fn tick(&mut self, ecs: &mut World, ticks: u64, view_distance: i32) {
+        let mut players = ecs.query::<(Entity, &Client, &Position)>();
+        let mut entities =
+            ecs.query::<(Entity, &EntityId, &EntityType, &Uuid, &Position, &Rotation)>();
+        let players: Vec<(Entity, &Client, IVec2)> = players
+            .iter(ecs)
+            .map(|(e, client, p)| (e, client, column_of(p.0)))
+            .collect();
+        let clients: HashMap<ConnectionId, &Client> =
+            players.iter().map(|(_, c, _)| (c.id, *c)).collect();
+        let send = |id: &ConnectionId, packet: PlayClientbound| {
+            if let Some(client) = clients.get(id) {
+                client.send(packet);
+            }
+        };
+
+        let mut destroyed: HashMap<ConnectionId, Vec<VarInt>> = HashMap::new();
+        let mut alive = HashSet::new();
+        for (entity, id, kind, uuid, position, rotation) in entities.iter(ecs) {
+            alive.insert(entity);
+            let entry = self
+                .entries
+                .entry(entity)
+                .or_insert_with(|| TrackedEntity::new(*id, position.0, rotation, ticks));
+
+            // those who see it already get the change, those who come in the new state
+            if ticks.is_multiple_of(kind.update_interval()) {
+                if let Some(packet) = entry.update(position.0, rotation, ticks) {
+                    for viewer in &entry.viewers {
+                        send(viewer, packet.clone());
+                    }
+                }
+            }
+
+            let block = position.0.floor().as_ivec3();
+            let column = column_of(position.0);
+            let range = kind.tracking_range().min(view_distance);
+            let viewers: HashSet<ConnectionId> = players
+                .iter()
+                .filter(|(e, client, center)| {
+                    *e != entity
+                        && client.sees(block)
+                        && (*center - column).abs().max_element() <= range
+                })
+                .map(|(_, client, _)| client.id)
+                .collect();
+            for viewer in viewers.difference(&entry.viewers) {
+                send(viewer, entry.spawn(*uuid, *kind));
+            }
+            for viewer in entry.viewers.difference(&viewers) {
+                destroyed.entry(*viewer).or_default().push(VarInt(id.0));
+            }
+            entry.viewers = viewers;
+        }
+
+        self.entries.retain(|entity, entry| {
+            if alive.contains(entity) {
+                return true;
+            }
+            for viewer in &entry.viewers {
+                destroyed
+                    .entry(*viewer)
+                    .or_default()
+                    .push(VarInt(entry.id.0));
+            }
+            false
+        });
+        for (viewer, ids) in destroyed {
+            send(&viewer, PlayClientbound::DestroyEntities { ids });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_moves_are_deltas() {
+        let rotation = Rotation::default();
+        let mut tracked = TrackedEntity::new(EntityId(7), vec3(0.5, 64.0, 0.5), &rotation, 0);
+        assert_eq!(tracked.update(vec3(0.5, 64.0, 0.5), &rotation, 2), None);
+        assert_eq!(
+            tracked.update(vec3(0.75, 63.5, 0.5), &rotation, 4),
+            Some(PlayClientbound::EntityMove {
+                id: VarInt(7),
+                dx: 1024,
+                dy: -2048,
+                dz: 0
+            })
+        );
+
+        let turned = Rotation {
+            yaw: std::f32::consts::PI,
+            pitch: 0.0,
+        };
+        assert!(matches!(
+            tracked.update(vec3(0.75, 63.5, 0.5), &turned, 6),
+            Some(PlayClientbound::EntityRotate {
+                yaw: Angle(128),
+                ..
+            })
+        ));
+        assert!(matches!(
+            tracked.update(vec3(1.0, 63.5, 0.5), &rotation, 8),
+            Some(PlayClientbound::EntityMoveRotate { dx: 1024, .. })
+        ));
+    }
+
+    #[test]
+    fn far_moves_and_old_positions_teleport() {
+        let rotation = Rotation::default();
+        let mut tracked = TrackedEntity::new(EntityId(7), vec3(0.0, 64.0, 0.0), &rotation, 0);
+        assert_eq!(
+            tracked.update(vec3(9.0, 64.0, 0.0), &rotation, 2),
+            Some(PlayClientbound::EntityTeleport {
+                id: VarInt(7),
+                position: dvec3(9.0, 64.0, 0.0),
+                yaw: Angle(0),
+                pitch: Angle(0)
+            })
+        );
+        assert!(matches!(
+            tracked.update(vec3(9.5, 64.0, 0.0), &rotation, 4),
+            Some(PlayClientbound::EntityMove { .. })
+        ));
+        assert!(matches!(
+            tracked.update(vec3(9.5, 64.0, 0.0), &rotation, 2 + FORCE_TELEPORT_INTERVAL),
+            Some(PlayClientbound::EntityTeleport { .. })
+        ));
+    }
+}
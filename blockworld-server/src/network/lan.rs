@@ -0,0 +1,251 @@
+//! ```text
+//! package net.minecraft.client.network
+//! class LanServerPinger, LanServerDetector
+//! version 1.16
+//! ```
+//!
+//! Servers on the local network announce themselves, so a client can list them without
+//! anybody typing an address. An announcing server sends a datagram to a multicast group
+//! every 1.5 seconds:
+//!
+//! ```text
+//! [MOTD]A Blockworld server[/MOTD][AD]25565[/AD]
+//! ```
+//!
+//! The port is the server's TCP port, the host is wherever the datagram came from. Any
+//! address works as the target, so tests announce to a listener on loopback instead of the
+//! group.
+
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    time::Duration,
+};
+
+use anyhow::*;
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::{net::UdpSocket, time::Instant};
+
+/// Where servers announce themselves, the group and port of Minecraft's "Open to LAN".
+pub const LAN_GROUP: SocketAddr =
+    SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(224, 0, 2, 60), 4445));
+const ANNOUNCE_INTERVAL: Duration = Duration::from_millis(1500);
+/// A server not heard from for this long is gone.
+const FORGET_AFTER: Duration = Duration::from_secs(5);
+/// Servers we keep track of at most. Anybody on the LAN can announce, so new ones past these
+/// are dropped rather than fill the list.
+pub const MAX_SERVERS: usize = 32;
+/// Servers on one host we keep track of at most, a few in case it runs more than one.
+pub const MAX_SERVERS_PER_HOST: usize = 4;
+
+/// One announcement.
+pub fn announcement(motd: &str, port: u16) -> String {
+    format!("[MOTD]{}[/MOTD][AD]{}[/AD]", motd, port)
+}
+
+/// The MOTD and port of an announcement.
+pub fn parse_announcement(text: &str) -> Option<(String, u16)> {
+    let between = |open: &str, close: &str| {
+        let start = text.find(open)? + open.len();
+        let end = start + text[start..].find(close)?;
+        Some(&text[start..end])
+    };
+    let motd = between("[MOTD]", "[/MOTD]")?;
+    let port = between("[AD]", "[/AD]")?.parse().ok()?;
+    Some((motd.to_string(), port))
+}
+
+/// Announce a server at `port` to `target` until the task is dropped.
+pub async fn announce(target: SocketAddr, motd: String, port: u16) {
+    let socket = match UdpSocket::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))).await {
+        Result::Ok(socket) => socket,
+        Err(e) => {
+            log::error!("Failed to open a socket to announce on the LAN: {}", e);
+            return;
+        }
+    };
+    let message = announcement(&motd, port);
+    let mut failing = false;
+    loop {
+        match socket.send_to(message.as_bytes(), target).await {
+            Result::Ok(_) => failing = false,
+            // a laptop switching networks, say so once
+            Err(e) if !failing => {
+                log::warn!("Failed to announce on the LAN: {}", e);
+                failing = true;
+            }
+            Err(_) => {}
+        }
+        tokio::time::sleep(ANNOUNCE_INTERVAL).await;
+    }
+}
+
+/// A server that announced itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanServer {
+    pub motd: String,
+    pub address: SocketAddr,
+}
+
+/// Listens for announcements.
+pub struct LanDiscovery {
+    socket: UdpSocket,
+    seen: HashMap<SocketAddr, (String, Instant)>,
+}
+
+impl LanDiscovery {
+    /// Listen on the port of `group`, joining it if it is a multicast group. Several listeners
+    /// may share a port, each client on a machine sees every server.
+    pub fn bind(group: SocketAddr) -> Result<Self> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        socket.set_nonblocking(true)?;
+        let bind_to = match group {
+            SocketAddr::V4(v4) if v4.ip().is_multicast() => {
+                SocketAddr::from((Ipv4Addr::UNSPECIFIED, v4.port()))
+            }
+            _ => group,
+        };
+        socket
+            .bind(&bind_to.into())
+            .with_context(|| format!("Failed to listen on {}", bind_to))?;
+        let socket = UdpSocket::from_std(socket.into())?;
+        if let SocketAddr::V4(v4) = group {
+            if v4.ip().is_multicast() {
+                socket.join_multicast_v4(*v4.ip(), Ipv4Addr::UNSPECIFIED)?;
+            }
+        }
+        Ok(Self {
+            socket,
+            seen: HashMap::new(),
+        })
+    }
+
+    /// Where announcements have to go to reach us.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Wait for the next announcement of a server, or one that changed its MOTD. Repeats of
+    /// what we know only count as the server still being there.
+    pub async fn next(&mut self) -> Result<LanServer> {
+        let mut buf = [0; 1024];
+        loop {
+            let (len, from) = self.socket.recv_from(&mut buf).await?;
+            let Some((motd, port)) = std::str::from_utf8(&buf[..len])
+                .ok()
+                .and_then(parse_announcement)
+            else {
+                log::debug!("Ignoring a LAN announcement from {}", from);
+                continue;
+            };
+            let address = SocketAddr::new(from.ip(), port);
+            if !self.seen.contains_key(&address) && !self.has_room_for(address) {
+                log::debug!("Ignoring LAN server {}, too many are announcing", address);
+                continue;
+            }
+            let now = Instant::now();
+            let known = self
+                .seen
+                .insert(address, (motd.clone(), now))
+                .is_some_and(|(old, _)| old == motd);
+            if !known {
+                return Ok(LanServer { motd, address });
+            }
+        }
+    }
+
+    /// Whether a server we don't know yet at `address` stays under the limits.
+    fn has_room_for(&mut self, address: SocketAddr) -> bool {
+        self.forget_gone();
+        let on_host = self.seen.keys().filter(|a| a.ip() == address.ip()).count();
+        self.seen.len() < MAX_SERVERS && on_host < MAX_SERVERS_PER_HOST
+    }
+
+    fn forget_gone(&mut self) {
+        self.seen.retain(|_, (_, at)| at.elapsed() < FORGET_AFTER);
+    }
+
+    /// Forget the server at `address`, so its next announcement counts as new.
+    pub fn forget(&mut self, address: SocketAddr) {
+        self.seen.remove(&address);
+    }
+
+    /// The servers heard from lately.
+    pub fn servers(&mut self) -> Vec<LanServer> {
+        self.forget_gone();
+        let mut servers: Vec<LanServer> = self
+            .seen
+            .iter()
+            .map(|(address, (motd, _))| LanServer {
+                motd: motd.clone(),
+                address: *address,
+            })
+            .collect();
+        servers.sort_by_key(|s| s.address);
+        servers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn announcements_parse() {
+        let text = announcement("Steve's world", 25565);
+        assert_eq!(
+            parse_announcement(&text),
+            Some(("Steve's world".to_string(), 25565))
+        );
+        assert_eq!(parse_announcement("[MOTD]x[/MOTD][AD]big[/AD]"), None);
+        assert_eq!(parse_announcement("[AD]25565[/AD]"), None);
+    }
+
+    #[tokio::test]
+    async fn hears_announcements_on_loopback() {
+        let mut discovery = LanDiscovery::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let target = discovery.local_addr().unwrap();
+        let announcer = tokio::spawn(announce(target, "Playtest".into(), 40000));
+
+        let found = tokio::time::timeout(Duration::from_secs(5), discovery.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.motd, "Playtest");
+        assert_eq!(found.address, "127.0.0.1:40000".parse().unwrap());
+        assert_eq!(discovery.servers(), vec![found]);
+        announcer.abort();
+    }
+
+    #[tokio::test]
+    async fn one_host_cant_flood_the_list() {
+        let mut discovery = LanDiscovery::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let target = discovery.local_addr().unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        for port in 40000..40010 {
+            let message = announcement("Spam", port);
+            socket.send_to(message.as_bytes(), target).await.unwrap();
+        }
+
+        for _ in 0..MAX_SERVERS_PER_HOST {
+            tokio::time::timeout(Duration::from_secs(5), discovery.next())
+                .await
+                .unwrap()
+                .unwrap();
+        }
+        let more = tokio::time::timeout(Duration::from_millis(200), discovery.next()).await;
+        assert!(more.is_err());
+        assert_eq!(discovery.servers().len(), MAX_SERVERS_PER_HOST);
+
+        // one forgotten makes room again
+        discovery.forget(SocketAddr::from(([127, 0, 0, 1], 40000)));
+        let message = announcement("Spam", 40009);
+        socket.send_to(message.as_bytes(), target).await.unwrap();
+        let found = tokio::time::timeout(Duration::from_secs(5), discovery.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.address.port(), 40009);
+    }
+}
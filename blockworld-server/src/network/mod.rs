@@ -0,0 +1,1361 @@
+//! The dedicated server: listeners, one task per connection and the tick thread.
+//!
+//! ```text
+//! listener ──accept──▶ connection task ──ServerEvent──▶ tick thread (owns Blockworld)
+//!                            ▲                               │
+//!                            └──────── PlayClientbound ──────┘
+//! ```
+//!
+//! Connections run on tokio and only decode and encode packets. Everything touching the world
+//! happens on the tick thread, between ticks, so game code never needs a lock.
+
+use std::{
+    fs,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc::{self, Sender},
+        Arc,
+    },
+    thread,
+};
+
+use anyhow::*;
+use tokio::{
+    net::TcpListener,
+    sync::{mpsc::UnboundedSender, oneshot},
+    task::JoinHandle,
+};
+
+use crate::{
+    entity::game_mode::GameMode,
+    packet::{
+        play::{PlayClientbound, PlayServerbound},
+        status,
+    },
+    Blockworld,
+};
+
+mod access;
+pub mod auth;
+pub mod capture;
+pub mod client;
+mod command;
+mod connection;
+mod disconnect;
+mod encryption;
+mod entity_tracker;
+pub mod lan;
+pub mod rcon;
+pub mod replay;
+mod tick_loop;
+pub mod transport;
+
+use access::AccessLists;
+use auth::{AuthProvider, GameProfile};
+use connection::ConnectionSettings;
+use disconnect::DisconnectReason;
+use rcon::{RconConfig, RconServer};
+
+pub use crate::tick::TICKS_PER_SECOND;
+pub use tick_loop::Client;
+
+/// Unique for the lifetime of the process.
+pub type ConnectionId = u32;
+
+static NEXT_CONNECTION_ID: AtomicU32 = AtomicU32::new(0);
+
+/// What connections and the [`ServerHandle`] tell the tick thread.
+pub(crate) enum ServerEvent {
+    /// A client logged in as `profile`. The reply is why it may not join, if it may not.
+    Join {
+        id: ConnectionId,
+        profile: GameProfile,
+        outbound: UnboundedSender<PlayClientbound>,
+        reply: oneshot::Sender<std::result::Result<(), DisconnectReason>>,
+    },
+    Packet {
+        id: ConnectionId,
+        packet: PlayServerbound,
+    },
+    /// The connection is gone, for whatever reason.
+    Leave {
+        id: ConnectionId,
+    },
+    /// A server list asks who is playing.
+    Status {
+        reply: oneshot::Sender<Vec<GameProfile>>,
+    },
+    /// An operator command, see `command.rs`. The reply is its output.
+    Command {
+        line: String,
+        reply: oneshot::Sender<String>,
+    },
+    Run(Box<dyn FnOnce(&mut Blockworld) + Send>),
+    Stop,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// The world folder, created if it doesn't exist.
+    pub world: PathBuf,
+    pub address: SocketAddr,
+    /// Also accept WebSocket connections here.
+    pub websocket_address: Option<SocketAddr>,
+    /// Radius in chunks of the area sent to every player.
+    pub view_distance: u32,
+    /// Encrypt connections once logged in.
+    pub encryption: bool,
+    /// Check accounts with this service, which needs encryption too. Without one the server
+    /// believes whatever name a client gives.
+    pub auth: Option<Arc<dyn AuthProvider>>,
+    /// The folder with `allowlist.json` and `banlist.json`, see `access.rs`. Without one
+    /// anybody may join.
+    pub access_lists: Option<PathBuf>,
+    /// Only let in the players on the allow list.
+    pub allowlist: bool,
+    /// Record every connection into its own capture file in this folder, see `capture.rs`.
+    pub capture_dir: Option<PathBuf>,
+    /// The message of the day, what server lists show.
+    pub motd: String,
+    /// Players beyond this many are refused.
+    pub max_players: u32,
+    /// The game mode of players joining for the first time. Others keep the one they left
+    /// with.
+    pub default_game_mode: GameMode,
+    /// A 64x64 PNG for server lists.
+    pub favicon: Option<PathBuf>,
+    /// Announce the server to this address, usually [`lan::LAN_GROUP`].
+    pub lan: Option<SocketAddr>,
+    /// Accept remote console connections, see `rcon.rs`.
+    pub rcon: Option<RconConfig>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            world: PathBuf::from("world"),
+            address: "0.0.0.0:25565".parse().unwrap(),
+            websocket_address: None,
+            view_distance: 8,
+            encryption: false,
+            auth: None,
+            access_lists: None,
+            allowlist: false,
+            capture_dir: None,
+            motd: "A Blockworld server".to_string(),
+            max_players: 20,
+            default_game_mode: GameMode::Survival,
+            favicon: None,
+            lan: None,
+            rcon: None,
+        }
+    }
+}
+
+/// A running server. Dropping it leaves the server running until the process exits, call
+/// [`ServerHandle::shutdown`] to save the world.
+pub struct ServerHandle {
+    events: Sender<ServerEvent>,
+    address: SocketAddr,
+    websocket_address: Option<SocketAddr>,
+    rcon_address: Option<SocketAddr>,
+    listeners: Vec<JoinHandle<()>>,
+    tick_thread: thread::JoinHandle<Result<()>>,
+}
+
+/// Open the world, bind the listeners and start ticking. Must be called inside a tokio runtime.
+pub async fn start(config: ServerConfig) -> Result<ServerHandle> {
+    let world = Blockworld::open(&config.world)
+        .with_context(|| format!("Failed to open world {}", config.world.display()))?;
+
+    let tcp = TcpListener::bind(config.address)
+        .await
+        .with_context(|| format!("Failed to bind {}", config.address))?;
+    let address = tcp.local_addr()?;
+    let ws = match config.websocket_address {
+        Some(addr) => Some(
+            TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Failed to bind {}", addr))?,
+        ),
+        None => None,
+    };
+    let websocket_address = ws.as_ref().map(|l| l.local_addr()).transpose()?;
+    let rcon = match &config.rcon {
+        Some(rcon) => Some((
+            RconServer::new(rcon)?,
+            TcpListener::bind(rcon.address)
+                .await
+                .with_context(|| format!("Failed to bind {}", rcon.address))?,
+        )),
+        None => None,
+    };
+    let rcon_address = rcon.as_ref().map(|(_, l)| l.local_addr()).transpose()?;
+
+    let access = match &config.access_lists {
+        Some(dir) => Some(AccessLists::open(dir, config.allowlist)?),
+        None if config.allowlist => bail!("The allow list needs a folder to read it from"),
+        None => None,
+    };
+    let favicon = match &config.favicon {
+        Some(path) => Some(
+            fs::read(path)
+                .map_err(Error::from)
+                .and_then(|png| status::favicon_url(&png))
+                .with_context(|| format!("Failed to load the favicon {}", path.display()))?,
+        ),
+        None => None,
+    };
+    let settings = Arc::new(ConnectionSettings {
+        encryption: config.encryption,
+        auth: config.auth.clone(),
+        access,
+        capture_dir: config.capture_dir.clone(),
+        motd: config.motd.clone(),
+        max_players: config.max_players,
+        favicon,
+    });
+
+    let (events, receiver) = mpsc::channel();
+    let tick_loop = tick_loop::TickLoop::new(
+        world,
+        receiver,
+        config.view_distance,
+        config.max_players,
+        config.default_game_mode,
+    );
+    let tick_thread = thread::Builder::new()
+        .name("Server thread".into())
+        .spawn(move || tick_loop.run())?;
+
+    let mut listeners = vec![tokio::spawn(accept_tcp(
+        tcp,
+        events.clone(),
+        settings.clone(),
+    ))];
+    log::info!("Listening on {}", address);
+    if let Some(ws) = ws {
+        log::info!(
+            "Listening for WebSocket connections on {}",
+            ws.local_addr()?
+        );
+        listeners.push(tokio::spawn(accept_websocket(ws, events.clone(), settings)));
+    }
+    if let Some((server, listener)) = rcon {
+        log::info!(
+            "Listening for RCON connections on {}",
+            listener.local_addr()?
+        );
+        listeners.push(tokio::spawn(rcon::accept(
+            listener,
+            Arc::new(server),
+            events.clone(),
+        )));
+    }
+    if let Some(target) = config.lan {
+        log::info!("Announcing the server to {}", target);
+        listeners.push(tokio::spawn(lan::announce(
+            target,
+            config.motd.clone(),
+            address.port(),
+        )));
+    }
+
+    Ok(ServerHandle {
+        events,
+        address,
+        websocket_address,
+        rcon_address,
+        listeners,
+        tick_thread,
+    })
+}
+
+/// Run an operator command on the tick thread and wait for its output.
+async fn run_command(events: &Sender<ServerEvent>, line: String) -> Result<String> {
+    let (reply, output) = oneshot::channel();
+    events
+        .send(ServerEvent::Command { line, reply })
+        .map_err(|_| anyhow!("The server is stopping"))?;
+    output.await.map_err(|_| anyhow!("The server is stopping"))
+}
+
+async fn accept_tcp(
+    listener: TcpListener,
+    events: Sender<ServerEvent>,
+    settings: Arc<ConnectionSettings>,
+) {
+    loop {
+        match listener.accept().await {
+            Result::Ok((stream, peer)) => {
+                let (reader, writer) = transport::split_tcp(stream);
+                let id = NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed);
+                let events = events.clone();
+                let settings = settings.clone();
+                tokio::spawn(connection::serve(
+                    reader, writer, peer, id, events, settings,
+                ));
+            }
+            Err(e) => log::warn!("Failed to accept a connection: {}", e),
+        }
+    }
+}
+
+async fn accept_websocket(
+    listener: TcpListener,
+    events: Sender<ServerEvent>,
+    settings: Arc<ConnectionSettings>,
+) {
+    loop {
+        match listener.accept().await {
+            Result::Ok((stream, peer)) => {
+                let events = events.clone();
+                let settings = settings.clone();
+                tokio::spawn(async move {
+                    match tokio_tungstenite::accept_async(stream).await {
+                        Result::Ok(ws) => {
+                            let (reader, writer) = transport::split_websocket(ws);
+                            let id = NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed);
+                            connection::serve(reader, writer, peer, id, events, settings).await;
+                        }
+                        Err(e) => log::warn!("WebSocket handshake with {} failed: {}", peer, e),
+                    }
+                });
+            }
+            Err(e) => log::warn!("Failed to accept a connection: {}", e),
+        }
+    }
+}
+
+impl ServerHandle {
+    /// Where the TCP listener ended up, useful when binding port 0.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn websocket_address(&self) -> Option<SocketAddr> {
+        self.websocket_address
+    }
+
+    pub fn rcon_address(&self) -> Option<SocketAddr> {
+        self.rcon_address
+    }
+
+    /// Run an operator command, as the console would, and return its output.
+    pub async fn command(&self, line: &str) -> Result<String> {
+        run_command(&self.events, line.to_string()).await
+    }
+
+    /// Run `f` on the tick thread before the next tick. Blocks changed by it are sent to the
+    /// players that see them at the end of that tick.
+    pub fn execute<F: FnOnce(&mut Blockworld) + Send + 'static>(&self, f: F) {
+        // the tick thread only goes away on shutdown, which consumes the handle
+        let _ = self.events.send(ServerEvent::Run(Box::new(f)));
+    }
+
+    /// Stop accepting connections, disconnect every player and save the world.
+    pub async fn shutdown(self) -> Result<()> {
+        for listener in &self.listeners {
+            listener.abort();
+        }
+        let _ = self.events.send(ServerEvent::Stop);
+        let tick_thread = self.tick_thread;
+        match tokio::task::spawn_blocking(move || tick_thread.join()).await? {
+            Result::Ok(result) => result,
+            Err(_) => bail!("The server thread panicked"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path, time::Duration};
+
+    use blockworld_utils::TempDir;
+    use glam::*;
+
+    use super::*;
+    use crate::{
+        block::{block_face_direction::BlockFaceDirection, state::BlockState},
+        entity::movement::MoveInput,
+        packet::{
+            handshake::HandshakeServerbound,
+            login::LoginClientbound,
+            play::{quantize_position, DigAction},
+            ConnectionState, Direction, VarInt,
+        },
+        world::{chunk_access::WorldAccess, storage::WorldStorage},
+    };
+
+    fn temp_world(name: &str) -> TempDir {
+        TempDir::new(&format!("network-{}", name)).unwrap()
+    }
+
+    fn test_config(world: &Path) -> ServerConfig {
+        ServerConfig {
+            world: world.to_path_buf(),
+            address: "127.0.0.1:0".parse().unwrap(),
+            websocket_address: Some("127.0.0.1:0".parse().unwrap()),
+            view_distance: 1,
+            // most tests fly around
+            default_game_mode: GameMode::Creative,
+            ..Default::default()
+        }
+    }
+
+    /// A stone floor under the spawn column, with its top at y 64 and dirt at 2 63 0.
+    fn stone_floor(root: &Path) {
+        let mut world = Blockworld::open(root).unwrap();
+        world.chunks_mut().load_chunk(ivec3(0, 3, 0));
+        world.chunks_mut().load_chunk(ivec3(0, 4, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                world
+                    .chunks_mut()
+                    .set_block(ivec3(x, 63, z), &"minecraft:stone".into());
+            }
+        }
+        world
+            .chunks_mut()
+            .set_block(ivec3(2, 63, 0), &"minecraft:dirt".into());
+        world.save().unwrap();
+    }
+
+    async fn start_test_server(world: &Path) -> ServerHandle {
+        start(test_config(world)).await.unwrap()
+    }
+
+    /// Read packets until one matches, failing after a few seconds.
+    async fn expect<F: Fn(&PlayClientbound) -> bool>(
+        reader: &mut transport::PacketReader,
+        what: &str,
+        f: F,
+    ) -> PlayClientbound {
+        let wait = async {
+            loop {
+                match reader.recv::<PlayClientbound>().await.unwrap() {
+                    Some(packet) if f(&packet) => return packet,
+                    Some(_) => {}
+                    None => panic!("connection closed while waiting for {}", what),
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), wait)
+            .await
+            .unwrap_or_else(|_| panic!("timed out waiting for {}", what))
+    }
+
+    /// Send `input` for `ticks` ticks, numbered from `sequence`, and wait for where the server
+    /// put the player.
+    async fn send_moves(
+        reader: &mut transport::PacketReader,
+        writer: &mut transport::PacketWriter,
+        sequence: i32,
+        input: MoveInput,
+        ticks: i32,
+    ) -> Vec3 {
+        // room in the input budget
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        for i in sequence..sequence + ticks {
+            writer
+                .send(&PlayServerbound::PlayerInput {
+                    sequence: VarInt(i),
+                    input,
+                })
+                .await
+                .unwrap();
+        }
+        let last = sequence + ticks - 1;
+        let state = expect(
+            reader,
+            "the moves",
+            |p| matches!(p, PlayClientbound::PlayerState { sequence, .. } if sequence.0 == last),
+        )
+        .await;
+        let PlayClientbound::PlayerState { position, .. } = state else {
+            unreachable!()
+        };
+        position
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn streams_chunks_and_block_updates_over_tcp() {
+        let root = temp_world("tcp");
+        {
+            let mut world = Blockworld::open(&root).unwrap();
+            world.chunks_mut().load_chunk(ivec3(0, 4, 0));
+            world
+                .chunks_mut()
+                .set_block(ivec3(1, 64, 1), &"minecraft:stone".into());
+            world.save().unwrap();
+        }
+        let server = start_test_server(&root).await;
+
+        let (mut reader, mut writer) = transport::connect_tcp(server.address()).await.unwrap();
+        client::login(&mut reader, &mut writer, "localhost", 25565, "Steve", None)
+            .await
+            .unwrap();
+        let spawn = expect(&mut reader, "spawn", |p| {
+            matches!(p, PlayClientbound::PlayerPosition { .. })
+        })
+        .await;
+        assert_eq!(
+            spawn,
+            PlayClientbound::PlayerPosition {
+                position: vec3(0.5, 64.0, 0.5),
+                yaw: 0.0,
+                pitch: 0.0
+            }
+        );
+
+        let PlayClientbound::ChunkData { column, data } = expect(
+            &mut reader,
+            "the spawn column",
+            |p| matches!(p, PlayClientbound::ChunkData { column, .. } if *column == ivec2(0, 0)),
+        )
+        .await
+        else {
+            unreachable!()
+        };
+        // the only section with something in it
+        assert_eq!(data.0.sections.len(), 1);
+        let section = data.0.sub_chunk(column, 4).unwrap().unwrap();
+        assert_eq!(section.get_blockid(ivec3(1, 0, 1)), "minecraft:stone");
+
+        server.execute(|world| {
+            world
+                .chunks_mut()
+                .set_block(ivec3(2, 64, 2), &"minecraft:dirt".into());
+        });
+        let update = expect(&mut reader, "the block update", |p| {
+            matches!(p, PlayClientbound::BlockUpdate { .. })
+        })
+        .await;
+        assert_eq!(
+            update,
+            PlayClientbound::BlockUpdate {
+                pos: ivec3(2, 64, 2),
+                block: VarInt(3)
+            }
+        );
+
+        // changes of the same tick in one section go together
+        server.execute(|world| {
+            for x in 3..6 {
+                world
+                    .chunks_mut()
+                    .set_block(ivec3(x, 65, 2), &"minecraft:sand".into());
+            }
+        });
+        let PlayClientbound::MultiBlockChange { section, changes } =
+            expect(&mut reader, "the multi block change", |p| {
+                matches!(p, PlayClientbound::MultiBlockChange { .. })
+            })
+            .await
+        else {
+            unreachable!()
+        };
+        assert_eq!(section, ivec3(0, 4, 0));
+        assert_eq!(changes.len(), 3);
+        let sand = BlockState::of(&"minecraft:sand".into()).0;
+        assert!(changes.iter().all(|c| c.block == sand && c.local.y == 1));
+
+        // flying away unloads the spawn chunks, one input a tick
+        let fly = MoveInput {
+            forward: 1,
+            sprint: true,
+            flying: true,
+            yaw: std::f32::consts::FRAC_PI_2,
+            pitch: 0.5,
+            ..Default::default()
+        };
+        for sequence in 0..60 {
+            writer
+                .send(&PlayServerbound::PlayerInput {
+                    sequence: VarInt(sequence),
+                    input: fly,
+                })
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        expect(&mut reader, "spawn to unload", |p| {
+            *p == PlayClientbound::UnloadChunk {
+                column: ivec2(0, 0),
+            }
+        })
+        .await;
+        let PlayClientbound::PlayerState { position, .. } = expect(
+            &mut reader,
+            "the last input acknowledged",
+            |p| matches!(p, PlayClientbound::PlayerState { sequence, .. } if sequence.0 == 59),
+        )
+        .await
+        else {
+            unreachable!()
+        };
+        assert!(position.x > 32.0);
+
+        server.shutdown().await.unwrap();
+        expect(&mut reader, "the disconnect", |p| {
+            *p == PlayClientbound::Disconnect {
+                reason: "Server closed".into(),
+            }
+        })
+        .await;
+        assert_eq!(reader.recv::<PlayClientbound>().await.unwrap(), None);
+
+        let storage = WorldStorage::open(&root).unwrap();
+        let player = storage.read_player("Steve").unwrap().unwrap();
+        assert_eq!(player.position, position.to_array());
+        assert_eq!(player.pitch, 0.5);
+        let mut world = Blockworld::open(&root).unwrap();
+        assert!(world.level().time > 0);
+        world.chunks_mut().load_chunk(ivec3(0, 4, 0));
+        assert_eq!(
+            world.chunks().get_block(ivec3(2, 64, 2)),
+            "minecraft:dirt".into()
+        );
+
+        drop(world);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn players_see_each_other_move() {
+        let root = temp_world("entities");
+        let server = start_test_server(&root).await;
+        let address = server.address();
+        let join = move |name: &'static str| async move {
+            let (mut reader, mut writer) = transport::connect_tcp(address).await.unwrap();
+            client::login(&mut reader, &mut writer, "localhost", 25565, name, None)
+                .await
+                .unwrap();
+            (reader, writer)
+        };
+        let (mut steve, mut steve_out) = join("Steve").await;
+        let (mut alex, _alex_out) = join("Alex").await;
+
+        let PlayClientbound::SpawnEntity {
+            id, kind, position, ..
+        } = expect(&mut alex, "Steve to appear", |p| {
+            matches!(p, PlayClientbound::SpawnEntity { .. })
+        })
+        .await
+        else {
+            unreachable!()
+        };
+        assert_eq!(kind, crate::entity::EntityType::PLAYER);
+        assert_eq!(position, dvec3(0.5, 64.0, 0.5));
+        expect(&mut steve, "Alex to appear", |p| {
+            matches!(p, PlayClientbound::SpawnEntity { .. })
+        })
+        .await;
+
+        // Steve flies off, and Alex adds up the deltas to where the server put him
+        let fly = MoveInput {
+            forward: 1,
+            flying: true,
+            ..Default::default()
+        };
+        for sequence in 0..10 {
+            steve_out
+                .send(&PlayServerbound::PlayerInput {
+                    sequence: VarInt(sequence),
+                    input: fly,
+                })
+                .await
+                .unwrap();
+        }
+        let PlayClientbound::PlayerState {
+            position: moved, ..
+        } = expect(
+            &mut steve,
+            "the inputs acknowledged",
+            |p| matches!(p, PlayClientbound::PlayerState { sequence, .. } if sequence.0 == 9),
+        )
+        .await
+        else {
+            unreachable!()
+        };
+        assert!(moved.z > 0.5);
+        let target = quantize_position(moved.as_dvec3());
+        let mut seen = quantize_position(position);
+        while seen != target {
+            match alex.recv::<PlayClientbound>().await.unwrap().unwrap() {
+                PlayClientbound::EntityMove { id: i, dx, dy, dz }
+                | PlayClientbound::EntityMoveRotate {
+                    id: i, dx, dy, dz, ..
+                } if i == id => seen += i64vec3(dx as i64, dy as i64, dz as i64),
+                PlayClientbound::EntityTeleport {
+                    id: i, position, ..
+                } if i == id => seen = quantize_position(position),
+                _ => {}
+            }
+        }
+
+        drop(steve_out);
+        expect(&mut alex, "Steve to go", |p| {
+            *p == PlayClientbound::DestroyEntities { ids: vec![id] }
+        })
+        .await;
+
+        server.shutdown().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn digs_and_placements_are_checked() {
+        let root = temp_world("interaction");
+        stone_floor(&root);
+        let server = start(ServerConfig {
+            default_game_mode: GameMode::Survival,
+            ..test_config(&root)
+        })
+        .await
+        .unwrap();
+        let (mut reader, mut writer) = transport::connect_tcp(server.address()).await.unwrap();
+        client::login(&mut reader, &mut writer, "localhost", 25565, "Steve", None)
+            .await
+            .unwrap();
+        expect(
+            &mut reader,
+            "the spawn column",
+            |p| matches!(p, PlayClientbound::ChunkData { column, .. } if *column == ivec2(0, 0)),
+        )
+        .await;
+        // standing on the floor, digging in the air is slower
+        for sequence in 0..2 {
+            writer
+                .send(&PlayServerbound::PlayerInput {
+                    sequence: VarInt(sequence),
+                    input: MoveInput::default(),
+                })
+                .await
+                .unwrap();
+        }
+        expect(&mut reader, "the landing", |p| {
+            matches!(
+                p,
+                PlayClientbound::PlayerState {
+                    on_ground: true,
+                    ..
+                }
+            )
+        })
+        .await;
+        let update = |x, y, z, block| PlayClientbound::BlockUpdate {
+            pos: ivec3(x, y, z),
+            block: VarInt(block),
+        };
+        let dig = |action| PlayServerbound::PlayerDig {
+            action,
+            pos: ivec3(2, 63, 0),
+            face: BlockFaceDirection::YP,
+        };
+
+        // dirt takes 15 ticks by hand, finishing at once puts it back
+        writer.send(&dig(DigAction::Start)).await.unwrap();
+        writer.send(&dig(DigAction::Finish)).await.unwrap();
+        let dirt = update(2, 63, 0, 3);
+        expect(&mut reader, "the dirt back", |p| *p == dirt).await;
+        writer.send(&dig(DigAction::Start)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(800)).await;
+        writer.send(&dig(DigAction::Finish)).await.unwrap();
+        let dug = update(2, 63, 0, 0);
+        expect(&mut reader, "the dirt broken", |p| *p == dug).await;
+
+        let place = |x, face| PlayServerbound::PlaceBlock {
+            pos: ivec3(x, 63, 0),
+            face,
+            block: VarInt(1),
+        };
+        // not where we stand, not against air and not out of reach
+        writer
+            .send(&place(0, BlockFaceDirection::YP))
+            .await
+            .unwrap();
+        let under_us = update(0, 64, 0, 0);
+        expect(&mut reader, "the placement refused", |p| *p == under_us).await;
+        writer
+            .send(&place(2, BlockFaceDirection::YP))
+            .await
+            .unwrap();
+        let in_the_hole = update(2, 64, 0, 0);
+        expect(&mut reader, "the click on air refused", |p| {
+            *p == in_the_hole
+        })
+        .await;
+        writer
+            .send(&place(12, BlockFaceDirection::YP))
+            .await
+            .unwrap();
+        let far = update(12, 63, 0, 1);
+        expect(&mut reader, "the far click refused", |p| *p == far).await;
+        writer
+            .send(&place(3, BlockFaceDirection::YP))
+            .await
+            .unwrap();
+        let placed = update(3, 64, 0, 1);
+        expect(&mut reader, "the block placed", |p| *p == placed).await;
+
+        writer
+            .send(&PlayServerbound::PlaceBlock {
+                pos: ivec3(3, 63, 0),
+                face: BlockFaceDirection::YP,
+                block: VarInt(9999),
+            })
+            .await
+            .unwrap();
+        expect(&mut reader, "the kick", |p| {
+            *p == PlayClientbound::Disconnect {
+                reason: "Invalid input".into(),
+            }
+        })
+        .await;
+
+        server.shutdown().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn game_modes_gate_flying_and_blocks() {
+        let root = temp_world("game-modes");
+        stone_floor(&root);
+        let server = start(ServerConfig {
+            default_game_mode: GameMode::Survival,
+            ..test_config(&root)
+        })
+        .await
+        .unwrap();
+        let (mut reader, mut writer) = transport::connect_tcp(server.address()).await.unwrap();
+        client::login(&mut reader, &mut writer, "localhost", 25565, "Steve", None)
+            .await
+            .unwrap();
+        let abilities = |game_mode, can_destroy| PlayClientbound::Abilities {
+            game_mode,
+            can_destroy,
+            can_place_on: vec![],
+        };
+        let survival = abilities(GameMode::Survival, vec![]);
+        expect(&mut reader, "survival", |p| *p == survival).await;
+        let healthy = PlayClientbound::UpdateHealth {
+            health: 20.0,
+            food: VarInt(20),
+            saturation: 5.0,
+        };
+        expect(&mut reader, "the health", |p| *p == healthy).await;
+        expect(
+            &mut reader,
+            "the spawn column",
+            |p| matches!(p, PlayClientbound::ChunkData { column, .. } if *column == ivec2(0, 0)),
+        )
+        .await;
+
+        // survival players only jump
+        let fly_up = MoveInput {
+            flying: true,
+            jump: true,
+            ..Default::default()
+        };
+        let jumped = send_moves(&mut reader, &mut writer, 0, fly_up, 8).await;
+        assert!(jumped.y < 65.5, "{}", jumped);
+
+        assert_eq!(
+            server.command("gamemode creative Steve").await.unwrap(),
+            "Set the game mode of Steve to creative"
+        );
+        let creative = abilities(GameMode::Creative, vec![]);
+        expect(&mut reader, "creative", |p| *p == creative).await;
+        let flew = send_moves(&mut reader, &mut writer, 8, fly_up, 8).await;
+        assert!(flew.y > jumped.y + 1.5, "{}", flew);
+        let update = |x, y, z, block| PlayClientbound::BlockUpdate {
+            pos: ivec3(x, y, z),
+            block: VarInt(block),
+        };
+        let dig = |x| PlayServerbound::PlayerDig {
+            action: DigAction::Start,
+            pos: ivec3(x, 63, 0),
+            face: BlockFaceDirection::YP,
+        };
+        // creative players break stone at once
+        writer.send(&dig(1)).await.unwrap();
+        let broken = update(1, 63, 0, 0);
+        expect(&mut reader, "the stone broken", |p| *p == broken).await;
+
+        // adventure players only break what their tags name
+        server.command("gamemode adventure Steve").await.unwrap();
+        writer.send(&dig(2)).await.unwrap();
+        let dirt = update(2, 63, 0, 3);
+        expect(&mut reader, "the dirt back", |p| *p == dirt).await;
+        assert_eq!(
+            server.command("candestroy Steve dirt").await.unwrap(),
+            "Steve may break minecraft:dirt in adventure"
+        );
+        let tagged = abilities(GameMode::Adventure, vec![VarInt(3)]);
+        expect(&mut reader, "the tags", |p| *p == tagged).await;
+        assert_eq!(
+            server.command("gamemode hardcore Steve").await.unwrap(),
+            "Usage: gamemode <survival|creative|adventure|spectator> <player>"
+        );
+
+        // spectators sink through the floor
+        server.command("gamemode spectator Steve").await.unwrap();
+        let down = MoveInput {
+            sneak: true,
+            ..Default::default()
+        };
+        send_moves(&mut reader, &mut writer, 16, down, 10).await;
+        let sunk = send_moves(&mut reader, &mut writer, 26, down, 10).await;
+        assert!(sunk.y < 63.0, "{}", sunk);
+        server.shutdown().await.unwrap();
+        let player = WorldStorage::open(&root)
+            .unwrap()
+            .read_player("Steve")
+            .unwrap()
+            .unwrap();
+        assert_eq!(player.game_mode, GameMode::Spectator);
+        assert_eq!(player.can_destroy, ["minecraft:dirt"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn websocket_clients_play_too() {
+        let root = temp_world("ws");
+        let server = start_test_server(&root).await;
+        let url = format!("ws://{}", server.websocket_address().unwrap());
+
+        let (mut reader, mut writer) = transport::connect_websocket(&url).await.unwrap();
+        client::login(&mut reader, &mut writer, "localhost", 25566, "Alex", None)
+            .await
+            .unwrap();
+        expect(&mut reader, "spawn", |p| {
+            matches!(p, PlayClientbound::PlayerPosition { .. })
+        })
+        .await;
+
+        // the same name twice is refused, whatever the transport
+        let (mut r2, mut w2) = transport::connect_tcp(server.address()).await.unwrap();
+        let refused = client::login(&mut r2, &mut w2, "localhost", 25565, "Alex", None).await;
+        assert!(refused.unwrap_err().to_string().contains("already playing"));
+        // and so is the same name in other case, kicks and bans count it as one player
+        let (mut r3, mut w3) = transport::connect_tcp(server.address()).await.unwrap();
+        let refused = client::login(&mut r3, &mut w3, "localhost", 25565, "alex", None).await;
+        assert!(refused.unwrap_err().to_string().contains("already playing"));
+
+        server.shutdown().await.unwrap();
+        expect(&mut reader, "the disconnect", |p| {
+            matches!(p, PlayClientbound::Disconnect { .. })
+        })
+        .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn accounts_encryption_and_access_lists() {
+        let root = temp_world("auth");
+        fs::write(
+            root.join("allowlist.json"),
+            r#"[{"name": "Steve"}, {"name": "Griefer"}]"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("banlist.json"),
+            r#"{"players": [{"name": "Griefer", "reason": "Griefing"}]}"#,
+        )
+        .unwrap();
+        let accounts = auth::LocalAuthService::new();
+        let steve = accounts.register("Steve", 42);
+        accounts.register("Alex", 43);
+        accounts.register("Griefer", 44);
+        let server = start(ServerConfig {
+            auth: Some(Arc::new(accounts.clone())),
+            access_lists: Some(root.to_path_buf()),
+            allowlist: true,
+            ..test_config(&root)
+        })
+        .await
+        .unwrap();
+        let address = server.address();
+        let join = |name: &'static str, account: Option<&'static str>| {
+            let session = account.map(|a| accounts.session(a));
+            async move {
+                let (mut reader, mut writer) = transport::connect_tcp(address).await.unwrap();
+                let session = session.as_ref().map(|s| s as &dyn auth::Session);
+                let joined =
+                    client::login(&mut reader, &mut writer, "localhost", 25565, name, session)
+                        .await;
+                joined.map(|uuid| (uuid, reader, writer))
+            }
+        };
+
+        // the account's uuid, and play is encrypted from the start
+        let (uuid, mut reader, _writer) = join("steve", Some("Steve")).await.unwrap();
+        assert_eq!(uuid, steve.uuid);
+        expect(&mut reader, "spawn", |p| {
+            matches!(p, PlayClientbound::PlayerPosition { .. })
+        })
+        .await;
+
+        fn refusal<T>(joined: Result<T>) -> String {
+            joined.err().unwrap().to_string()
+        }
+        assert!(refusal(join("Alex", None).await).contains("aren't logged in"));
+        assert!(refusal(join("Alex", Some("Steve")).await).contains("account service"));
+        assert!(refusal(join("Alex", Some("Alex")).await).contains("allow list"));
+        assert!(refusal(join("Griefer", Some("Griefer")).await).contains("banned"));
+
+        // a client that joined some other server hash isn't vouched for
+        struct Liar(auth::LocalSession);
+        impl auth::Session for Liar {
+            fn join(&self, name: &str, _: &str) -> Result<()> {
+                self.0.join(name, "another server")
+            }
+        }
+        let liar = Liar(accounts.session("Alex"));
+        let (mut reader, mut writer) = transport::connect_tcp(address).await.unwrap();
+        let lied = client::login(&mut reader, &mut writer, "h", 1, "Alex", Some(&liar));
+        assert!(refusal(lied.await).contains("verify your account"));
+
+        server.shutdown().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn encryption_without_accounts() {
+        let root = temp_world("encrypted");
+        let server = start(ServerConfig {
+            encryption: true,
+            ..test_config(&root)
+        })
+        .await
+        .unwrap();
+        let url = format!("ws://{}", server.websocket_address().unwrap());
+        let (mut reader, mut writer) = transport::connect_websocket(&url).await.unwrap();
+        let uuid = client::login(&mut reader, &mut writer, "localhost", 25566, "Alex", None)
+            .await
+            .unwrap();
+        assert_eq!(uuid, auth::offline_uuid("Alex"));
+        expect(&mut reader, "the spawn column", |p| {
+            matches!(p, PlayClientbound::ChunkData { .. })
+        })
+        .await;
+
+        server.shutdown().await.unwrap();
+        expect(&mut reader, "the disconnect", |p| {
+            *p == PlayClientbound::Disconnect {
+                reason: "Server closed".into(),
+            }
+        })
+        .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn captures_replay_against_a_fresh_server() {
+        let root = temp_world("recorded");
+        let captures = temp_world("captures");
+        let server = start(ServerConfig {
+            capture_dir: Some(captures.to_path_buf()),
+            ..test_config(&root)
+        })
+        .await
+        .unwrap();
+        let (mut reader, mut writer) = transport::connect_tcp(server.address()).await.unwrap();
+        client::login(&mut reader, &mut writer, "localhost", 25565, "Steve", None)
+            .await
+            .unwrap();
+        let walk = MoveInput {
+            forward: 1,
+            flying: true,
+            ..Default::default()
+        };
+        for sequence in 0..20 {
+            writer
+                .send(&PlayServerbound::PlayerInput {
+                    sequence: VarInt(sequence),
+                    input: walk,
+                })
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        expect(
+            &mut reader,
+            "the last input acknowledged",
+            |p| matches!(p, PlayClientbound::PlayerState { sequence, .. } if sequence.0 == 19),
+        )
+        .await;
+        server.shutdown().await.unwrap();
+        expect(&mut reader, "the disconnect", |p| {
+            matches!(p, PlayClientbound::Disconnect { .. })
+        })
+        .await;
+
+        let files: Vec<PathBuf> = fs::read_dir(&captures)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        let records = capture::read_capture(&files[0]).unwrap();
+        let count = |direction, state| {
+            records
+                .iter()
+                .filter(|r| r.direction == direction && r.state == state)
+                .count()
+        };
+        assert_eq!(count(Direction::Serverbound, ConnectionState::Handshake), 1);
+        assert_eq!(count(Direction::Clientbound, ConnectionState::Login), 1);
+        assert_eq!(count(Direction::Serverbound, ConnectionState::Play), 20);
+
+        // a fresh world answers the same
+        let again = temp_world("replayed");
+        let report = replay::replay_on_server(test_config(&again), &records, &[])
+            .await
+            .unwrap();
+        assert_eq!(report.sent, 20);
+        assert!(report.compared > 20);
+        assert!(!report.diverged(), "{:#?}", report);
+
+        // a world that differs doesn't
+        let changed = temp_world("changed");
+        {
+            let mut world = Blockworld::open(&changed).unwrap();
+            world.chunks_mut().load_chunk(ivec3(0, 4, 0));
+            world
+                .chunks_mut()
+                .set_block(ivec3(1, 64, 1), &"minecraft:stone".into());
+            world.save().unwrap();
+        }
+        let report = replay::replay_on_server(test_config(&changed), &records, &[])
+            .await
+            .unwrap();
+        assert!(report
+            .missing
+            .iter()
+            .any(|d| d.packet.starts_with("ChunkData { column: IVec2(0, 0)")));
+        assert_eq!(report.missing.len(), report.extra.len());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn rcon_runs_commands_for_operators() {
+        let root = temp_world("rcon");
+        let audit_log = root.join("rcon-audit.log");
+        let server = start(ServerConfig {
+            rcon: Some(RconConfig {
+                address: "127.0.0.1:0".parse().unwrap(),
+                password: "hunter2".into(),
+                audit_log: Some(audit_log.clone()),
+            }),
+            ..test_config(&root)
+        })
+        .await
+        .unwrap();
+        let address = server.rcon_address().unwrap();
+
+        let (mut reader, mut writer) = transport::connect_tcp(server.address()).await.unwrap();
+        client::login(&mut reader, &mut writer, "localhost", 25565, "Steve", None)
+            .await
+            .unwrap();
+        expect(
+            &mut reader,
+            "the spawn column",
+            |p| matches!(p, PlayClientbound::ChunkData { column, .. } if *column == ivec2(0, 0)),
+        )
+        .await;
+
+        let mut rcon = rcon::RconClient::connect(address, "hunter2").await.unwrap();
+        let list = rcon.command("list").await.unwrap();
+        assert_eq!(list, "There are 1 of a max of 20 players online: Steve");
+        assert_eq!(
+            rcon.command("setblock 1 64 1 dirt").await.unwrap(),
+            "Changed the block at 1 64 1 to minecraft:dirt"
+        );
+        let update = expect(&mut reader, "the block update", |p| {
+            matches!(p, PlayClientbound::BlockUpdate { .. })
+        })
+        .await;
+        assert_eq!(
+            update,
+            PlayClientbound::BlockUpdate {
+                pos: ivec3(1, 64, 1),
+                block: VarInt(3)
+            }
+        );
+        assert_eq!(
+            rcon.command("summon pig 3.5 65 3.5").await.unwrap(),
+            "Summoned minecraft:pig at 3.5 65 3.5"
+        );
+        let spawned = expect(&mut reader, "the pig", |p| {
+            matches!(p, PlayClientbound::SpawnEntity { .. })
+        })
+        .await;
+        let PlayClientbound::SpawnEntity { kind, .. } = spawned else {
+            unreachable!()
+        };
+        assert_eq!(kind, crate::entity::EntityType::PIG);
+        assert_eq!(
+            rcon.command("summon item 3.5 65 3.5").await.unwrap(),
+            "Can't summon minecraft:item"
+        );
+        assert_eq!(
+            rcon.command("setblock 1 64 1 bedrock").await.unwrap(),
+            "Unknown block bedrock"
+        );
+        assert_eq!(
+            rcon.command("setblock 1 64").await.unwrap(),
+            "Usage: setblock <x> <y> <z> <block>"
+        );
+        assert!(rcon
+            .command("fly")
+            .await
+            .unwrap()
+            .starts_with("Unknown command fly"));
+        assert!(rcon.command("help").await.unwrap().contains("kick - "));
+        assert_eq!(
+            rcon.command("gamerule randomTickSpeed 0").await.unwrap(),
+            "Set game rule randomTickSpeed to 0"
+        );
+        assert_eq!(
+            rcon.command("gamerule randomTickSpeed").await.unwrap(),
+            "Game rule randomTickSpeed is 0"
+        );
+        assert_eq!(
+            rcon.command("gamerule doDaylightCycle").await.unwrap(),
+            "Unknown game rule doDaylightCycle"
+        );
+
+        rcon.command("kick Steve Go to bed").await.unwrap();
+        let kicked = expect(&mut reader, "the kick", |p| {
+            matches!(p, PlayClientbound::Disconnect { .. })
+        })
+        .await;
+        assert_eq!(
+            kicked,
+            PlayClientbound::Disconnect {
+                reason: "Go to bed".into()
+            }
+        );
+        assert!(server
+            .command("list")
+            .await
+            .unwrap()
+            .starts_with("There are 0"));
+
+        // guessing gets an address locked out, even with the right password after
+        for _ in 0..rcon::MAX_LOGIN_FAILURES {
+            let wrong = rcon::RconClient::connect(address, "hunter3").await;
+            assert!(wrong.unwrap_err().to_string().contains("Wrong"));
+        }
+        assert!(rcon::RconClient::connect(address, "hunter2").await.is_err());
+        // who was in already stays in
+        assert!(rcon
+            .command("time")
+            .await
+            .unwrap()
+            .starts_with("The time is"));
+
+        server.shutdown().await.unwrap();
+        let level = WorldStorage::open(&root).unwrap().read_level().unwrap();
+        assert_eq!(level.unwrap().game_rules.random_tick_speed, 0);
+        let audit = fs::read_to_string(&audit_log).unwrap();
+        let events: Vec<String> = audit
+            .lines()
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap())
+            .map(|v| v["event"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(events.first().unwrap(), "login");
+        assert_eq!(events.iter().filter(|e| *e == "login_failed").count(), 3);
+        assert!(events.contains(&"locked_out".to_string()));
+        assert!(audit.contains(r#""command":"kick Steve Go to bed""#));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn status_queries_and_lan_announcements() {
+        let root = temp_world("status");
+        let mut icon = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        icon.extend(64u32.to_be_bytes());
+        icon.extend(64u32.to_be_bytes());
+        icon.extend([8, 6, 0, 0, 0]);
+        fs::write(root.join("server-icon.png"), &icon).unwrap();
+        let mut discovery = lan::LanDiscovery::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let server = start(ServerConfig {
+            motd: "Playtest 3".into(),
+            max_players: 1,
+            favicon: Some(root.join("server-icon.png")),
+            lan: Some(discovery.local_addr().unwrap()),
+            ..test_config(&root)
+        })
+        .await
+        .unwrap();
+
+        let found = tokio::time::timeout(Duration::from_secs(5), discovery.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.motd, "Playtest 3");
+        assert_eq!(found.address, server.address());
+
+        let (mut reader, mut writer) = transport::connect_tcp(found.address).await.unwrap();
+        client::login(&mut reader, &mut writer, "localhost", 25565, "Steve", None)
+            .await
+            .unwrap();
+        let (mut r2, mut w2) = transport::connect_tcp(found.address).await.unwrap();
+        let (status, ping) = client::query_status(&mut r2, &mut w2, "localhost", 25565)
+            .await
+            .unwrap();
+        assert_eq!(status.description, "Playtest 3");
+        assert_eq!(status.version.protocol, crate::packet::PROTOCOL_VERSION);
+        assert_eq!((status.players.online, status.players.max), (1, 1));
+        assert_eq!(status.players.sample[0].name, "Steve");
+        assert_eq!(status.favicon_png().unwrap(), icon);
+        assert!(ping < Duration::from_secs(1));
+        // the server hangs up after the pong
+        assert_eq!(r2.recv::<status::StatusClientbound>().await.unwrap(), None);
+
+        // a full server says so
+        let (mut r3, mut w3) = transport::connect_tcp(found.address).await.unwrap();
+        let full = client::login(&mut r3, &mut w3, "localhost", 25565, "Alex", None).await;
+        assert!(full.unwrap_err().to_string().contains("full"));
+
+        // any version may ask, and learns what the server speaks
+        let (mut r4, mut w4) = transport::connect_tcp(found.address).await.unwrap();
+        w4.send(&HandshakeServerbound::Handshake {
+            protocol_version: VarInt(1),
+            server_address: "localhost".into(),
+            server_port: 25565,
+            next_state: crate::packet::handshake::NextState::Status,
+        })
+        .await
+        .unwrap();
+        w4.send(&status::StatusServerbound::StatusRequest {})
+            .await
+            .unwrap();
+        let Some(status::StatusClientbound::StatusResponse { json }) = r4.recv().await.unwrap()
+        else {
+            panic!("no status");
+        };
+        assert!(json.contains(&format!("\"protocol\":{}", crate::packet::PROTOCOL_VERSION)));
+
+        server.shutdown().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn refuses_other_protocol_versions() {
+        let root = temp_world("version");
+        let server = start_test_server(&root).await;
+
+        let (mut reader, mut writer) = transport::connect_tcp(server.address()).await.unwrap();
+        writer
+            .send(&HandshakeServerbound::Handshake {
+                protocol_version: VarInt(crate::packet::PROTOCOL_VERSION + 1),
+                server_address: "localhost".into(),
+                server_port: 25565,
+                next_state: crate::packet::handshake::NextState::Login,
+            })
+            .await
+            .unwrap();
+        let Some(LoginClientbound::Disconnect { reason }) = reader.recv().await.unwrap() else {
+            panic!("not refused");
+        };
+        assert!(reason.starts_with("Outdated server"));
+        assert_eq!(reader.recv::<LoginClientbound>().await.unwrap(), None);
+
+        server.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,521 @@
+//! ```text
+//! package net.minecraft.network.rcon
+//! class MainThread, ClientThread
+//! version 1.16
+//! ```
+//!
+//! A remote console for operators, speaking the Source RCON protocol so the usual tools work
+//! too. Every packet is little endian:
+//!
+//! ```text
+//! i32  length     of the rest of the packet
+//! i32  id         chosen by the client, answers carry the same one
+//! i32  type       3 login, 2 command, 0 response; the answer to a login is a 2 too
+//! ...  body       text, then two zero bytes
+//! ```
+//!
+//! A connection logs in with the password first, a wrong one is answered with id -1. Each
+//! command line goes to the dispatcher in `command.rs` and its output comes back in responses
+//! of at most 4096 bytes each. Since nothing says how many, a client sends an empty response
+//! packet after each command, which the server echoes once the output is out.
+//!
+//! An address that fails to log in [`MAX_LOGIN_FAILURES`] times in a row is locked out for
+//! [`LOCKOUT`]. Its failures are forgotten once that long has passed since the last, locked
+//! out or not, so addresses that got it wrong once don't pile up. A connection running commands faster than [`COMMANDS_PER_SECOND`] gets
+//! told to slow down. Logins, failures and every command go to the audit log, one JSON object
+//! a line.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{File, OpenOptions},
+    io::Write,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::{mpsc::Sender, Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::*;
+use serde::Serialize;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+use super::{run_command, ServerEvent};
+
+/// Where the server and `blockworld-rcon` read the password from.
+pub const PASSWORD_VAR: &str = "BLOCKWORLD_RCON_PASSWORD";
+
+pub const LOGIN: i32 = 3;
+pub const COMMAND: i32 = 2;
+pub const LOGIN_RESPONSE: i32 = 2;
+pub const RESPONSE: i32 = 0;
+
+/// The longest body in a response, longer output is split.
+const MAX_RESPONSE_BODY: usize = 4096;
+/// id, type and the two zero bytes.
+const HEADER: usize = 10;
+pub const MAX_LOGIN_FAILURES: u32 = 3;
+pub const LOCKOUT: Duration = Duration::from_secs(60);
+pub const COMMANDS_PER_SECOND: f64 = 10.0;
+/// Commands a connection may send at once before the rate counts.
+const COMMAND_BURST: f64 = 20.0;
+
+#[derive(Clone)]
+pub struct RconConfig {
+    pub address: SocketAddr,
+    pub password: String,
+    /// Append who did what to this file.
+    pub audit_log: Option<PathBuf>,
+}
+
+impl fmt::Debug for RconConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RconConfig")
+            .field("address", &self.address)
+            .field("password", &"...")
+            .field("audit_log", &self.audit_log)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RconPacket {
+    pub id: i32,
+    pub kind: i32,
+    pub body: String,
+}
+
+/// The next packet, `None` once the other side closed between packets.
+pub async fn read_packet<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<RconPacket>> {
+    let mut length = [0; 4];
+    match reader.read_exact(&mut length).await {
+        Result::Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let length = i32::from_le_bytes(length);
+    ensure!(
+        (HEADER as i32..=(HEADER + MAX_RESPONSE_BODY) as i32).contains(&length),
+        "Bad RCON packet length {}",
+        length
+    );
+    let mut data = vec![0; length as usize];
+    reader.read_exact(&mut data).await?;
+    let id = i32::from_le_bytes(data[0..4].try_into().unwrap());
+    let kind = i32::from_le_bytes(data[4..8].try_into().unwrap());
+    let body = &data[8..data.len() - 2];
+    Ok(Some(RconPacket {
+        id,
+        kind,
+        body: String::from_utf8_lossy(body).into_owned(),
+    }))
+}
+
+pub async fn write_packet<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    packet: &RconPacket,
+) -> Result<()> {
+    let body = packet.body.as_bytes();
+    let mut data = Vec::with_capacity(4 + HEADER + body.len());
+    data.extend(((HEADER + body.len()) as i32).to_le_bytes());
+    data.extend(packet.id.to_le_bytes());
+    data.extend(packet.kind.to_le_bytes());
+    data.extend(body);
+    data.extend([0, 0]);
+    writer.write_all(&data).await?;
+    Ok(())
+}
+
+/// `output` as responses to request `id`, split where it is too long for one.
+async fn respond<W: AsyncWrite + Unpin>(writer: &mut W, id: i32, output: &str) -> Result<()> {
+    let mut rest = output;
+    loop {
+        let mut end = rest.len().min(MAX_RESPONSE_BODY);
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (body, tail) = rest.split_at(end);
+        write_packet(
+            writer,
+            &RconPacket {
+                id,
+                kind: RESPONSE,
+                body: body.to_string(),
+            },
+        )
+        .await?;
+        if tail.is_empty() {
+            return Ok(());
+        }
+        rest = tail;
+    }
+}
+
+/// Lets through `per_second` on average and `burst` at once.
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_second: f64,
+    burst: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(per_second: f64, burst: f64, now: Instant) -> Self {
+        Self {
+            per_second,
+            burst,
+            tokens: burst,
+            last: now,
+        }
+    }
+
+    /// True if one more may go at `now`.
+    pub fn allow(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.burst);
+        self.last = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct AuditEntry<'a> {
+    /// Unix seconds.
+    time: u64,
+    peer: SocketAddr,
+    event: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command: Option<&'a str>,
+}
+
+struct AuditLog(Mutex<File>);
+
+impl AuditLog {
+    fn open(path: &PathBuf) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open the audit log {}", path.display()))?;
+        Ok(Self(Mutex::new(file)))
+    }
+
+    fn record(&self, peer: SocketAddr, event: &str, command: Option<&str>) {
+        let entry = AuditEntry {
+            time: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            peer,
+            event,
+            command,
+        };
+        let mut line = serde_json::to_string(&entry).unwrap();
+        line.push('\n');
+        if let Err(e) = self.0.lock().unwrap().write_all(line.as_bytes()) {
+            log::error!("Failed to write the RCON audit log: {}", e);
+        }
+    }
+}
+
+/// Failed logins in a row from one address.
+#[derive(Debug)]
+struct Failures {
+    count: u32,
+    /// When the last one was, which the lockout counts from.
+    last: Instant,
+    locked: bool,
+}
+
+impl Failures {
+    fn expired(&self, now: Instant) -> bool {
+        now >= self.last + LOCKOUT
+    }
+}
+
+pub(super) struct RconServer {
+    password: String,
+    audit: Option<AuditLog>,
+    failures: Mutex<HashMap<IpAddr, Failures>>,
+}
+
+impl RconServer {
+    pub(super) fn new(config: &RconConfig) -> Result<Self> {
+        ensure!(!config.password.is_empty(), "RCON needs a password");
+        Ok(Self {
+            password: config.password.clone(),
+            audit: config.audit_log.as_ref().map(AuditLog::open).transpose()?,
+            failures: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn audit(&self, peer: SocketAddr, event: &str, command: Option<&str>) {
+        if let Some(audit) = &self.audit {
+            audit.record(peer, event, command);
+        }
+    }
+
+    fn locked_out(&self, ip: IpAddr, now: Instant) -> bool {
+        let mut failures = self.failures.lock().unwrap();
+        let Some(entry) = failures.get(&ip) else {
+            return false;
+        };
+        if entry.expired(now) {
+            failures.remove(&ip);
+            return false;
+        }
+        entry.locked
+    }
+
+    /// Check a login, counting failures.
+    fn log_in(&self, ip: IpAddr, password: &str, now: Instant) -> bool {
+        // the same time whatever the password, so it can't be guessed a byte at a time
+        let matches = password.len() == self.password.len()
+            && password
+                .bytes()
+                .zip(self.password.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0;
+        let mut failures = self.failures.lock().unwrap();
+        if matches {
+            failures.remove(&ip);
+            return true;
+        }
+        failures.retain(|_, f| !f.expired(now));
+        let entry = failures.entry(ip).or_insert(Failures {
+            count: 0,
+            last: now,
+            locked: false,
+        });
+        entry.count += 1;
+        entry.last = now;
+        if entry.count >= MAX_LOGIN_FAILURES {
+            entry.count = 0;
+            entry.locked = true;
+        }
+        false
+    }
+}
+
+pub(super) async fn accept(
+    listener: TcpListener,
+    server: Arc<RconServer>,
+    events: Sender<ServerEvent>,
+) {
+    loop {
+        match listener.accept().await {
+            Result::Ok((stream, peer)) => {
+                let server = server.clone();
+                let events = events.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, peer, &server, &events).await {
+                        log::debug!("RCON connection from {} failed: {:#}", peer, e);
+                    }
+                });
+            }
+            Err(e) => log::warn!("Failed to accept an RCON connection: {}", e),
+        }
+    }
+}
+
+async fn serve<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    peer: SocketAddr,
+    server: &RconServer,
+    events: &Sender<ServerEvent>,
+) -> Result<()> {
+    let mut logged_in = false;
+    let mut limiter = RateLimiter::new(COMMANDS_PER_SECOND, COMMAND_BURST, Instant::now());
+    while let Some(packet) = read_packet(&mut stream).await? {
+        match packet.kind {
+            LOGIN => {
+                if server.locked_out(peer.ip(), Instant::now()) {
+                    server.audit(peer, "locked_out", None);
+                    reply(&mut stream, -1, LOGIN_RESPONSE).await?;
+                    return Ok(());
+                }
+                logged_in = server.log_in(peer.ip(), &packet.body, Instant::now());
+                if logged_in {
+                    log::info!("RCON login from {}", peer);
+                    server.audit(peer, "login", None);
+                    reply(&mut stream, packet.id, LOGIN_RESPONSE).await?;
+                } else {
+                    log::warn!("Failed RCON login from {}", peer);
+                    server.audit(peer, "login_failed", None);
+                    reply(&mut stream, -1, LOGIN_RESPONSE).await?;
+                }
+            }
+            _ if !logged_in => reply(&mut stream, -1, LOGIN_RESPONSE).await?,
+            COMMAND if !limiter.allow(Instant::now()) => {
+                server.audit(peer, "rate_limited", Some(&packet.body));
+                respond(&mut stream, packet.id, "Too many commands, slow down").await?;
+            }
+            COMMAND => {
+                log::info!("RCON {} ran: {}", peer, packet.body);
+                server.audit(peer, "command", Some(&packet.body));
+                let output = run_command(events, packet.body)
+                    .await
+                    .unwrap_or_else(|e| format!("{:#}", e));
+                respond(&mut stream, packet.id, &output).await?;
+            }
+            // marks the end of the output of the commands before it
+            RESPONSE => respond(&mut stream, packet.id, "").await?,
+            kind => respond(&mut stream, packet.id, &format!("Unknown request {}", kind)).await?,
+        }
+    }
+    Ok(())
+}
+
+async fn reply<W: AsyncWrite + Unpin>(writer: &mut W, id: i32, kind: i32) -> Result<()> {
+    write_packet(
+        writer,
+        &RconPacket {
+            id,
+            kind,
+            body: String::new(),
+        },
+    )
+    .await
+}
+
+/// A logged in RCON connection.
+#[derive(Debug)]
+pub struct RconClient<S = TcpStream> {
+    stream: S,
+    next_id: i32,
+}
+
+impl RconClient<TcpStream> {
+    pub async fn connect<A: ToSocketAddrs>(address: A, password: &str) -> Result<Self> {
+        let stream = TcpStream::connect(address).await?;
+        stream.set_nodelay(true)?;
+        Self::log_in(stream, password).await
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> RconClient<S> {
+    pub async fn log_in(stream: S, password: &str) -> Result<Self> {
+        let mut client = Self { stream, next_id: 1 };
+        let id = client.send(LOGIN, password).await?;
+        let answer = read_packet(&mut client.stream)
+            .await?
+            .ok_or_else(|| anyhow!("The server closed the connection"))?;
+        ensure!(answer.id != -1, "Wrong RCON password");
+        ensure!(
+            answer.id == id && answer.kind == LOGIN_RESPONSE,
+            "Unexpected answer to the login"
+        );
+        Ok(client)
+    }
+
+    /// Run `line` on the server and return what it printed.
+    pub async fn command(&mut self, line: &str) -> Result<String> {
+        let id = self.send(COMMAND, line).await?;
+        let end = self.send(RESPONSE, "").await?;
+        let mut output = String::new();
+        loop {
+            let packet = read_packet(&mut self.stream)
+                .await?
+                .ok_or_else(|| anyhow!("The server closed the connection"))?;
+            match packet.id {
+                _ if packet.id == id => output.push_str(&packet.body),
+                _ if packet.id == end => return Ok(output),
+                -1 => bail!("Not logged in"),
+                other => bail!("Unexpected answer to request {}", other),
+            }
+        }
+    }
+
+    async fn send(&mut self, kind: i32, body: &str) -> Result<i32> {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1).max(1);
+        write_packet(
+            &mut self.stream,
+            &RconPacket {
+                id,
+                kind,
+                body: body.to_string(),
+            },
+        )
+        .await?;
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limits_after_the_burst() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(10.0, 3.0, start);
+        assert!((0..3).all(|_| limiter.allow(start)));
+        assert!(!limiter.allow(start));
+        // a tenth of a second buys one more
+        assert!(limiter.allow(start + Duration::from_millis(100)));
+        assert!(!limiter.allow(start + Duration::from_millis(100)));
+        // and waiting long doesn't save up more than the burst
+        let later = start + Duration::from_secs(60);
+        assert!((0..3).all(|_| limiter.allow(later)));
+        assert!(!limiter.allow(later));
+    }
+
+    #[test]
+    fn lockouts_expire_and_are_forgotten() {
+        let server = RconServer::new(&RconConfig {
+            address: "127.0.0.1:0".parse().unwrap(),
+            password: "hunter2".into(),
+            audit_log: None,
+        })
+        .unwrap();
+        let start = Instant::now();
+        let guesser: IpAddr = "203.0.113.7".parse().unwrap();
+        let typo: IpAddr = "203.0.113.8".parse().unwrap();
+        assert!(!server.log_in(typo, "hunter3", start));
+        for _ in 0..MAX_LOGIN_FAILURES {
+            assert!(!server.log_in(guesser, "password", start));
+        }
+        assert!(server.locked_out(guesser, start + LOCKOUT / 2));
+        assert!(!server.locked_out(typo, start));
+
+        let later = start + LOCKOUT;
+        assert!(!server.locked_out(guesser, later));
+        assert!(!server.failures.lock().unwrap().contains_key(&guesser));
+        // the next failure from anywhere sweeps out the rest
+        assert!(!server.log_in(guesser, "password", later));
+        let failures = server.failures.lock().unwrap();
+        assert_eq!(failures.keys().collect::<Vec<_>>(), [&guesser]);
+        assert_eq!(failures[&guesser].count, 1);
+    }
+
+    #[tokio::test]
+    async fn long_output_comes_in_pieces() {
+        let (mut server, client) = tokio::io::duplex(1 << 16);
+        let output = "x".repeat(MAX_RESPONSE_BODY * 2 + 10);
+        let expected = output.clone();
+        let serving = tokio::spawn(async move {
+            let login = read_packet(&mut server).await.unwrap().unwrap();
+            assert_eq!((login.kind, login.body.as_str()), (LOGIN, "hunter2"));
+            reply(&mut server, login.id, LOGIN_RESPONSE).await.unwrap();
+            let command = read_packet(&mut server).await.unwrap().unwrap();
+            assert_eq!(command.body, "help");
+            respond(&mut server, command.id, &output).await.unwrap();
+            let end = read_packet(&mut server).await.unwrap().unwrap();
+            respond(&mut server, end.id, "").await.unwrap();
+        });
+
+        let mut client = RconClient::log_in(client, "hunter2").await.unwrap();
+        assert_eq!(client.command("help").await.unwrap(), expected);
+        serving.await.unwrap();
+    }
+}
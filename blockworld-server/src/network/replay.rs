@@ -0,0 +1,273 @@
+//! Play a recorded session (see `capture.rs`) against a fresh server and compare what it
+//! answers with what was recorded.
+//!
+//! The replay logs in under the recorded name, then sends the recorded play packets on the
+//! recorded timeline, counted from the login success. It ends the session the way the recording
+//! did: it shuts the server down if the recording ends with a disconnect, otherwise it leaves.
+//! Keep alives are answered live and never compared.
+//!
+//! Packets are compared as a multiset, arrival order and timing vary from run to run. Two
+//! exceptions: a `PlayerState` is only compared when both runs acknowledged its sequence, as a
+//! tick may apply one input or two depending on when they arrive, and packets named in the
+//! ignore list aren't compared at all. Everything else recorded but not received is missing,
+//! everything received but not recorded is extra.
+//!
+//! The server only answers the same if it starts from the same world, so replay against a copy
+//! of the world as it was when the recording started.
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use anyhow::*;
+use tokio::time::{sleep_until, timeout, Instant};
+
+use crate::packet::{
+    login::{LoginClientbound, LoginServerbound},
+    play::{PlayClientbound, PlayServerbound},
+    PacketSet,
+};
+
+use super::{capture::Record, client, start, transport, ServerConfig};
+
+/// How long to wait for packets the recording has and the replay doesn't yet.
+const SETTLE: Duration = Duration::from_secs(2);
+/// Descriptions of chunk data go on for pages.
+const MAX_DESCRIPTION: usize = 200;
+
+/// A packet only one of the runs had.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// Since the login succeeded, in the run that had it.
+    pub at: Duration,
+    pub packet: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayReport {
+    /// Play packets sent to the server.
+    pub sent: usize,
+    /// Recorded clientbound packets that were compared.
+    pub compared: usize,
+    /// Recorded, but the server didn't send them this time.
+    pub missing: Vec<Divergence>,
+    /// Sent by the server this time, but not recorded.
+    pub extra: Vec<Divergence>,
+}
+
+impl ReplayReport {
+    pub fn diverged(&self) -> bool {
+        !self.missing.is_empty() || !self.extra.is_empty()
+    }
+}
+
+/// A clientbound play packet of either run.
+struct Received {
+    at: Duration,
+    payload: Vec<u8>,
+    packet: Option<PlayClientbound>,
+}
+
+impl Received {
+    fn name(&self) -> &'static str {
+        self.packet.as_ref().map_or("undecodable", |p| p.name())
+    }
+
+    fn divergence(&self) -> Divergence {
+        let mut packet = match &self.packet {
+            Some(packet) => format!("{:?}", packet),
+            None => format!("undecodable packet {:02x?}", self.payload),
+        };
+        if packet.len() > MAX_DESCRIPTION {
+            let mut end = MAX_DESCRIPTION;
+            while !packet.is_char_boundary(end) {
+                end -= 1;
+            }
+            packet.truncate(end);
+            packet.push('…');
+        }
+        Divergence {
+            at: self.at,
+            packet,
+        }
+    }
+}
+
+/// Start a server with `config`, replay `records` on it and shut it down again.
+pub async fn replay_on_server(
+    config: ServerConfig,
+    records: &[Record],
+    ignore: &[String],
+) -> Result<ReplayReport> {
+    let name = records
+        .iter()
+        .find_map(|r| match r.decode::<LoginServerbound>() {
+            Some(Result::Ok(LoginServerbound::LoginStart { name })) => Some(name),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("The capture has no login"))?;
+    let logged_in = records
+        .iter()
+        .find(|r| {
+            matches!(
+                r.decode::<LoginClientbound>(),
+                Some(Result::Ok(LoginClientbound::LoginSuccess { .. }))
+            )
+        })
+        .ok_or_else(|| anyhow!("The capture ends before {} logged in", name))?
+        .at;
+    let since_login = |r: &Record| r.at.saturating_sub(logged_in);
+
+    let mut recorded = vec![];
+    let mut inputs = vec![];
+    for record in records {
+        if let Some(packet) = record.decode::<PlayServerbound>() {
+            match packet? {
+                PlayServerbound::KeepAlive { .. } => {}
+                packet => inputs.push((since_login(record), packet)),
+            }
+        } else if let Some(packet) = record.decode::<PlayClientbound>() {
+            recorded.push(Received {
+                at: since_login(record),
+                payload: record.payload.clone(),
+                packet: packet.ok(),
+            });
+        }
+    }
+    let ends_in_disconnect = recorded
+        .iter()
+        .any(|r| matches!(r.packet, Some(PlayClientbound::Disconnect { .. })));
+    let end = records.last().map(since_login).unwrap_or_default();
+
+    let server = start(config).await?;
+    let (mut reader, mut writer) = transport::connect_tcp(server.address()).await?;
+    let port = server.address().port();
+    client::login(&mut reader, &mut writer, "localhost", port, &name, None).await?;
+    let started = Instant::now();
+
+    let mut received = vec![];
+    let receive = |packet: PlayClientbound| Received {
+        at: started.elapsed(),
+        payload: packet.encode(),
+        packet: Some(packet),
+    };
+    let mut inputs = inputs.into_iter().peekable();
+    let sent = inputs.len();
+    loop {
+        let next_send = match inputs.peek() {
+            Some((at, _)) => started + *at,
+            None => started + end,
+        };
+        tokio::select! {
+            packet = reader.recv::<PlayClientbound>() => match packet? {
+                Some(PlayClientbound::KeepAlive { id }) => {
+                    writer.send(&PlayServerbound::KeepAlive { id }).await?;
+                }
+                Some(packet) => received.push(receive(packet)),
+                None => bail!("The server closed the connection during the replay"),
+            },
+            _ = sleep_until(next_send) => match inputs.next() {
+                Some((_, packet)) => writer.send(&packet).await?,
+                None => break,
+            },
+        }
+    }
+
+    // give the last answers a moment, as long as some are still outstanding
+    let settled = Instant::now() + SETTLE;
+    while received.len() < recorded.len() {
+        match timeout(
+            settled.saturating_duration_since(Instant::now()),
+            reader.recv::<PlayClientbound>(),
+        )
+        .await
+        {
+            Result::Ok(packet) => match packet? {
+                Some(PlayClientbound::KeepAlive { id }) => {
+                    writer.send(&PlayServerbound::KeepAlive { id }).await?
+                }
+                Some(packet) => received.push(receive(packet)),
+                None => break,
+            },
+            Err(_) => break,
+        }
+    }
+
+    let server = if ends_in_disconnect {
+        server.shutdown().await?;
+        None
+    } else {
+        writer.close().await?;
+        Some(server)
+    };
+    while let Some(packet) = reader.recv::<PlayClientbound>().await? {
+        if !matches!(packet, PlayClientbound::KeepAlive { .. }) {
+            received.push(receive(packet));
+        }
+    }
+    if let Some(server) = server {
+        server.shutdown().await?;
+    }
+
+    let mut report = compare(recorded, received, ignore);
+    report.sent = sent;
+    Ok(report)
+}
+
+fn compare(recorded: Vec<Received>, received: Vec<Received>, ignore: &[String]) -> ReplayReport {
+    let sequences = |packets: &[Received]| -> HashSet<i32> {
+        packets
+            .iter()
+            .filter_map(|r| match &r.packet {
+                Some(PlayClientbound::PlayerState { sequence, .. }) => Some(sequence.0),
+                _ => None,
+            })
+            .collect()
+    };
+    let acknowledged_by_both: HashSet<i32> = sequences(&recorded)
+        .intersection(&sequences(&received))
+        .copied()
+        .collect();
+    let compared = |r: &Received| {
+        !ignore.iter().any(|name| name == r.name())
+            && match &r.packet {
+                Some(PlayClientbound::KeepAlive { .. }) => false,
+                Some(PlayClientbound::PlayerState { sequence, .. }) => {
+                    acknowledged_by_both.contains(&sequence.0)
+                }
+                _ => true,
+            }
+    };
+
+    let recorded: Vec<Received> = recorded.into_iter().filter(compared).collect();
+    let mut unmatched: HashMap<&[u8], Vec<&Received>> = HashMap::new();
+    for packet in &recorded {
+        unmatched
+            .entry(packet.payload.as_slice())
+            .or_default()
+            .push(packet);
+    }
+    let mut extra = vec![];
+    for packet in received.iter().filter(|r| compared(r)) {
+        match unmatched.get_mut(packet.payload.as_slice()) {
+            Some(same) if !same.is_empty() => {
+                same.remove(0);
+            }
+            _ => extra.push(packet.divergence()),
+        }
+    }
+    let mut missing: Vec<Divergence> = unmatched
+        .into_values()
+        .flatten()
+        .map(Received::divergence)
+        .collect();
+    missing.sort_by_key(|d| d.at);
+
+    ReplayReport {
+        sent: 0,
+        compared: recorded.len(),
+        missing,
+        extra,
+    }
+}
@@ -0,0 +1,951 @@
+//! ```text
+//! package net.minecraft.server
+//! class MinecraftServer
+//! version 1.16
+//! ```
+//!
+//! The server thread. Every tick it applies what connections sent since the last one, ticks
+//! the world, then tells every player about the blocks that changed, one packet per section,
+//! sends a few more of the chunk columns around them, and how the entities they see moved.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::mpsc::Receiver,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::*;
+use bevy_ecs::{component::Component, entity::Entity};
+use blockworld_utils::ResourceLocation;
+use glam::*;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    block::{
+        block_face_direction::BlockFaceDirection, shape::Aabb, state::BlockState, BLOCK_REGISTRY,
+    },
+    components::{MovementState, OnGround, Player, Position, Rotation, Velocity},
+    entity::{
+        game_mode::{AdventureTags, GameMode},
+        health::{
+            FallDistance, Health, Hunger, BREAK_EXHAUSTION, JUMP_EXHAUSTION, SPRINT_EXHAUSTION,
+            SPRINT_JUMP_EXHAUSTION,
+        },
+        interaction::{
+            digging_progress, obstructed, placement, within_reach, Digging, BREAK_TOLERANCE,
+            EYE_HEIGHT,
+        },
+        movement::{self, Body, MoveInput},
+        physics::{submerged, Collider},
+        EntityId, EntityType, Uuid,
+    },
+    packet::{
+        chunk_data::{ChunkColumnData, Compressed},
+        play::{
+            section_changes, BlockChange, DigAction, PlayClientbound, PlayServerbound,
+            PlayerCommandAction,
+        },
+        VarInt,
+    },
+    tick::{TickClock, TickMetrics, TICKS_PER_SECOND, TICK_DURATION},
+    world::{
+        block_update::placement_state,
+        chunk::{SubChunk, CHUNK_HEIGHT, SUBCHUNK_SIZE},
+        chunk_access::WorldAccess,
+        storage::PlayerData,
+    },
+    Blockworld,
+};
+
+use super::{
+    auth::GameProfile, command, disconnect::DisconnectReason, entity_tracker::EntityTracker,
+    ConnectionId, ServerEvent,
+};
+
+/// Ticks the server runs back to back to catch up, two seconds of them. After a longer stall
+/// it skips ahead and says it can't keep up.
+const MAX_CATCH_UP: u32 = 2 * TICKS_PER_SECOND as u32;
+/// Say the server can't keep up at most this often.
+const BEHIND_WARNING_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Columns sent to one player per tick, so a player joining doesn't stall everyone else.
+const COLUMNS_PER_TICK: usize = 4;
+const KEEP_ALIVE_INTERVAL: u64 = 15 * TICKS_PER_SECOND;
+const KEEP_ALIVE_TIMEOUT: u64 = 30 * TICKS_PER_SECOND;
+/// Inputs a player may send ahead of the ticks, half a second of them. More are dropped so a
+/// client can't move faster by sending more.
+const MAX_INPUT_BURST: u32 = 10;
+const AUTOSAVE_INTERVAL: u64 = 5 * 60 * TICKS_PER_SECOND;
+/// Sections in a column.
+const SECTIONS: i32 = (CHUNK_HEIGHT / SUBCHUNK_SIZE) as i32;
+
+/// The connection of a player entity.
+#[derive(Component)]
+pub struct Client {
+    pub id: ConnectionId,
+    pub name: String,
+    pub uuid: u128,
+    outbound: UnboundedSender<PlayClientbound>,
+    /// Column the view is centered on, `None` until the first chunks were queued.
+    center: Option<IVec2>,
+    /// Columns the client has. Each keeps its sections loaded.
+    sent: HashSet<IVec2>,
+    /// Columns in view not sent yet, nearest first.
+    pending: VecDeque<IVec2>,
+    /// Id of the keep alive we wait an answer for, and the tick it was sent.
+    keep_alive: Option<(i64, u64)>,
+    last_keep_alive: u64,
+    /// Inputs we may still apply before the next tick.
+    input_budget: u32,
+    /// The last input sequence number, until it was acknowledged.
+    acked: Option<i32>,
+    /// Health, food and saturation as last sent.
+    health_sent: Option<(f32, u32, f32)>,
+}
+
+impl Client {
+    /// Queue a packet. It's dropped if the connection closed already, the tick thread hears
+    /// about that separately.
+    pub fn send(&self, packet: PlayClientbound) {
+        let _ = self.outbound.send(packet);
+    }
+
+    /// True if the client has the column holding `pos`, in world coords.
+    pub fn sees(&self, pos: IVec3) -> bool {
+        self.sent
+            .contains(&ivec2(pos.x, pos.z).div_euclid(IVec2::splat(SUBCHUNK_SIZE as i32)))
+    }
+}
+
+pub(super) struct TickLoop {
+    world: Blockworld,
+    events: Receiver<ServerEvent>,
+    view_distance: i32,
+    max_players: u32,
+    default_game_mode: GameMode,
+    players: HashMap<ConnectionId, Entity>,
+    /// Players that have each column. Columns nobody has are unloaded.
+    viewers: HashMap<IVec2, u32>,
+    tracker: EntityTracker,
+    ticks: u64,
+    metrics: TickMetrics,
+    running: bool,
+}
+
+pub(super) fn column_of(position: Vec3) -> IVec2 {
+    let block = position.floor().as_ivec3();
+    ivec2(block.x, block.z).div_euclid(IVec2::splat(SUBCHUNK_SIZE as i32))
+}
+
+impl TickLoop {
+    pub(super) fn new(
+        world: Blockworld,
+        events: Receiver<ServerEvent>,
+        view_distance: u32,
+        max_players: u32,
+        default_game_mode: GameMode,
+    ) -> Self {
+        Self {
+            world,
+            events,
+            view_distance: view_distance as i32,
+            max_players,
+            default_game_mode,
+            players: HashMap::new(),
+            viewers: HashMap::new(),
+            tracker: EntityTracker::default(),
+            ticks: 0,
+            metrics: TickMetrics::default(),
+            running: true,
+        }
+    }
+
+    /// Tick until told to stop, then disconnect everyone and save.
+    pub(super) fn run(mut self) -> Result<()> {
+        let mut clock = TickClock::new(Instant::now(), MAX_CATCH_UP);
+        let mut last_warning: Option<Instant> = None;
+        while self.running {
+            let due = clock.due(Instant::now());
+            if due.skipped > 0
+                && last_warning.is_none_or(|at| at.elapsed() >= BEHIND_WARNING_INTERVAL)
+            {
+                let behind = TICK_DURATION * (due.run + due.skipped);
+                log::warn!(
+                    "Can't keep up! Is the server overloaded? Running {}ms or {} ticks behind",
+                    behind.as_millis(),
+                    due.run + due.skipped
+                );
+                last_warning = Some(Instant::now());
+            }
+            for _ in 0..due.run {
+                let start = Instant::now();
+                self.tick();
+                self.metrics.record(start.elapsed());
+                if !self.running {
+                    break;
+                }
+            }
+            thread::sleep(clock.until_next(Instant::now()));
+        }
+        self.shutdown()
+    }
+
+    fn tick(&mut self) {
+        while let Result::Ok(event) = self.events.try_recv() {
+            self.handle(event);
+            if !self.running {
+                return;
+            }
+        }
+
+        self.world.tick();
+        self.ticks += 1;
+
+        self.respawn_dead();
+        self.send_player_states();
+        self.send_health();
+        self.count_inhabited_time();
+        self.send_block_changes();
+        self.send_chunks();
+        self.tracker
+            .tick(self.world.ecs_mut(), self.ticks, self.view_distance);
+        self.keep_alive();
+        // only the client renders
+        self.world.chunks_mut().need_rerender.clear();
+
+        if self.ticks.is_multiple_of(AUTOSAVE_INTERVAL) {
+            if let Err(e) = self.save() {
+                log::error!("Autosave failed: {:#}", e);
+            }
+        }
+    }
+
+    fn handle(&mut self, event: ServerEvent) {
+        match event {
+            ServerEvent::Join {
+                id,
+                profile,
+                outbound,
+                reply,
+            } => {
+                let joined = self.join(id, profile, outbound);
+                // the connection went away while we were letting it in
+                if reply.send(joined).is_err() {
+                    self.leave(id);
+                }
+            }
+            ServerEvent::Packet { id, packet } => self.handle_packet(id, packet),
+            ServerEvent::Leave { id } => self.leave(id),
+            ServerEvent::Status { reply } => {
+                let ecs = self.world.ecs_mut();
+                let players = ecs
+                    .query::<&Client>()
+                    .iter(ecs)
+                    .map(|c| GameProfile {
+                        uuid: c.uuid,
+                        name: c.name.clone(),
+                    })
+                    .collect();
+                let _ = reply.send(players);
+            }
+            ServerEvent::Command { line, reply } => {
+                let output = command::dispatch(self, &line);
+                let _ = reply.send(output);
+            }
+            ServerEvent::Run(f) => f(&mut self.world),
+            ServerEvent::Stop => self.running = false,
+        }
+    }
+
+    fn join(
+        &mut self,
+        id: ConnectionId,
+        profile: GameProfile,
+        outbound: UnboundedSender<PlayClientbound>,
+    ) -> std::result::Result<(), DisconnectReason> {
+        let GameProfile { uuid, name } = profile;
+        let ecs = self.world.ecs_mut();
+        // names are one player whatever their case, like in kicks and the access lists
+        if ecs
+            .query::<&Client>()
+            .iter(ecs)
+            .any(|c| c.name.eq_ignore_ascii_case(&name))
+        {
+            return Err(DisconnectReason::AlreadyPlaying(name));
+        }
+        if self.players.len() >= self.max_players as usize {
+            return Err(DisconnectReason::ServerFull);
+        }
+
+        let saved = match self.world.chunks().storage() {
+            Some(storage) => storage.read_player(&name).unwrap_or_else(|e| {
+                log::error!("Failed to read player data of {}: {:#}", name, e);
+                None
+            }),
+            None => None,
+        };
+        let (position, rotation, mode, tags, health, hunger) = match saved {
+            Some(data) => (
+                Vec3::from_array(data.position),
+                Rotation {
+                    yaw: data.yaw,
+                    pitch: data.pitch,
+                },
+                data.game_mode,
+                AdventureTags::from_names(&data.can_destroy, &data.can_place_on),
+                Health(data.health),
+                Hunger::new(data.food, data.saturation, data.exhaustion),
+            ),
+            None => (
+                self.spawn_point(),
+                Rotation::default(),
+                self.default_game_mode,
+                AdventureTags::default(),
+                Health::default(),
+                Hunger::default(),
+            ),
+        };
+
+        let client = Client {
+            id,
+            name: name.clone(),
+            uuid,
+            outbound,
+            center: None,
+            sent: HashSet::new(),
+            pending: VecDeque::new(),
+            keep_alive: None,
+            last_keep_alive: self.ticks,
+            input_budget: MAX_INPUT_BURST,
+            acked: None,
+            health_sent: None,
+        };
+        client.send(PlayClientbound::PlayerPosition {
+            position,
+            yaw: rotation.yaw,
+            pitch: rotation.pitch,
+        });
+        client.send(tags.abilities(mode));
+        let entity = self
+            .world
+            .ecs_mut()
+            .spawn((
+                Player,
+                EntityId::next(),
+                EntityType::PLAYER,
+                Uuid(uuid),
+                Position(position),
+                Velocity::default(),
+                OnGround::default(),
+                rotation,
+                MovementState::default(),
+                (mode, tags, health, hunger, FallDistance::default()),
+                client,
+            ))
+            .id();
+        self.players.insert(id, entity);
+        self.update_view(entity);
+        log::info!("{} joined the game", name);
+        Result::Ok(())
+    }
+
+    /// Save and remove a player, if it still is here.
+    fn leave(&mut self, id: ConnectionId) {
+        let Some(entity) = self.players.remove(&id) else {
+            return;
+        };
+        if let Err(e) = self.save_player(entity) {
+            log::error!("{:#}", e);
+        }
+        let Some(client) = self.world.ecs_mut().entity_mut(entity).take::<Client>() else {
+            return;
+        };
+        for column in client.sent {
+            self.release(column);
+        }
+        self.world.ecs_mut().despawn(entity);
+        log::info!("{} left the game", client.name);
+    }
+
+    fn disconnect(&mut self, id: ConnectionId, reason: DisconnectReason) {
+        if let Some(client) = self.client(id) {
+            log::info!("Disconnecting {}: {}", client.name, reason);
+            client.send(PlayClientbound::Disconnect {
+                reason: reason.to_string(),
+            });
+        }
+        self.leave(id);
+    }
+
+    /// Disconnect the player called `name`, false if nobody is.
+    pub(super) fn kick(&mut self, name: &str, reason: DisconnectReason) -> bool {
+        let ecs = self.world.ecs_mut();
+        let Some(id) = ecs
+            .query::<&Client>()
+            .iter(ecs)
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+            .map(|c| c.id)
+        else {
+            return false;
+        };
+        self.disconnect(id, reason);
+        true
+    }
+
+    pub(super) fn player_names(&mut self) -> Vec<String> {
+        let ecs = self.world.ecs_mut();
+        ecs.query::<&Client>()
+            .iter(ecs)
+            .map(|c| c.name.clone())
+            .collect()
+    }
+
+    pub(super) fn metrics(&self) -> &TickMetrics {
+        &self.metrics
+    }
+
+    pub(super) fn max_players(&self) -> u32 {
+        self.max_players
+    }
+
+    pub(super) fn world_mut(&mut self) -> &mut Blockworld {
+        &mut self.world
+    }
+
+    /// The player called `name`, if it's online.
+    pub(super) fn find_player(&mut self, name: &str) -> Option<Entity> {
+        let ecs = self.world.ecs_mut();
+        ecs.query::<(Entity, &Client)>()
+            .iter(ecs)
+            .find(|(_, c)| c.name.eq_ignore_ascii_case(name))
+            .map(|(entity, _)| entity)
+    }
+
+    /// Change what a player may do, and tell it.
+    pub(super) fn set_abilities(&mut self, entity: Entity, mode: GameMode, tags: AdventureTags) {
+        let mut player = self.world.ecs_mut().entity_mut(entity);
+        if let Some(client) = player.get::<Client>() {
+            client.send(tags.abilities(mode));
+        }
+        player.insert((mode, tags, FallDistance::default()));
+        player.remove::<Digging>();
+    }
+
+    /// A player's game mode and adventure tags.
+    pub(super) fn abilities(&self, entity: Entity) -> (GameMode, AdventureTags) {
+        let player = self.world.ecs().entity(entity);
+        (
+            *player.get::<GameMode>().unwrap(),
+            player.get::<AdventureTags>().unwrap().clone(),
+        )
+    }
+
+    fn spawn_point(&self) -> Vec3 {
+        IVec3::from_array(self.world.level().spawn).as_vec3() + vec3(0.5, 0.0, 0.5)
+    }
+
+    fn client(&self, id: ConnectionId) -> Option<&Client> {
+        self.world.ecs().get::<Client>(*self.players.get(&id)?)
+    }
+
+    fn handle_packet(&mut self, id: ConnectionId, packet: PlayServerbound) {
+        let Some(&entity) = self.players.get(&id) else {
+            return;
+        };
+        match packet {
+            PlayServerbound::KeepAlive { id } => {
+                let mut client = self.world.ecs_mut().get_mut::<Client>(entity).unwrap();
+                if client.keep_alive.map(|(sent, _)| sent) == Some(id) {
+                    client.keep_alive = None;
+                }
+            }
+            PlayServerbound::PlayerInput { sequence, input } => {
+                if !input.yaw.is_finite() || !input.pitch.is_finite() {
+                    self.disconnect(id, DisconnectReason::InvalidInput);
+                    return;
+                }
+                let mut client = self.world.ecs_mut().get_mut::<Client>(entity).unwrap();
+                // acked even when dropped, so the client learns where it really is
+                client.acked = Some(sequence.0);
+                if client.input_budget == 0 {
+                    log::debug!("{} sends inputs too fast", client.name);
+                    return;
+                }
+                client.input_budget -= 1;
+                let mode = *self.world.ecs().get::<GameMode>(entity).unwrap();
+                self.move_player(entity, &mode.restrict(input));
+            }
+            PlayServerbound::PlayerCommand { action } => {
+                let ecs = self.world.ecs_mut();
+                let mut state = ecs.get_mut::<MovementState>(entity).unwrap();
+                match action {
+                    PlayerCommandAction::StartSneaking => state.sneaking = true,
+                    PlayerCommandAction::StopSneaking => state.sneaking = false,
+                    PlayerCommandAction::StartSprinting => state.sprinting = true,
+                    PlayerCommandAction::StopSprinting => state.sprinting = false,
+                }
+            }
+            PlayServerbound::PlayerDig { action, pos, .. } => self.dig(entity, action, pos),
+            PlayServerbound::PlaceBlock { pos, face, block } => {
+                let name = u32::try_from(block.0)
+                    .ok()
+                    .and_then(|id| BLOCK_REGISTRY.number_id_to_name(id))
+                    .filter(|name| **name != "minecraft:air".into())
+                    .cloned();
+                match name {
+                    Some(name) => self.place(entity, pos, face, &name),
+                    None => self.disconnect(id, DisconnectReason::InvalidInput),
+                }
+            }
+        }
+    }
+
+    /// A step of a player breaking the block at `pos`. Digs that don't add up are undone on
+    /// the client by sending it the block back.
+    fn dig(&mut self, entity: Entity, action: DigAction, pos: IVec3) {
+        let player = self.world.ecs().entity(entity);
+        let feet = player.get::<Position>().unwrap().0;
+        let on_ground = player.get::<OnGround>().unwrap().0;
+        let digging = player.get::<Digging>().copied();
+        let mode = *player.get::<GameMode>().unwrap();
+        let tags = player.get::<AdventureTags>().unwrap();
+        let progress = digging_progress(self.world.chunks(), pos, feet, on_ground, mode, tags);
+        let allowed = progress > 0.0 && within_reach(feet + Vec3::Y * EYE_HEIGHT, pos);
+        let broken = match action {
+            DigAction::Abort => {
+                self.world.ecs_mut().entity_mut(entity).remove::<Digging>();
+                return;
+            }
+            // out of reach, or a block its mode doesn't let it break
+            _ if !allowed => false,
+            DigAction::Start if progress >= 1.0 => true,
+            DigAction::Start => {
+                let since = self.ticks;
+                self.world
+                    .ecs_mut()
+                    .entity_mut(entity)
+                    .insert(Digging { pos, since });
+                return;
+            }
+            DigAction::Finish => {
+                self.world.ecs_mut().entity_mut(entity).remove::<Digging>();
+                // the tick it started counts, as it does on the client
+                digging.is_some_and(|d| {
+                    d.pos == pos && progress * (self.ticks - d.since + 1) as f32 >= BREAK_TOLERANCE
+                })
+            }
+        };
+        if !broken {
+            self.resend_block(entity, pos);
+            return;
+        }
+        let state = self.world.chunks().get_state(pos);
+        self.world
+            .chunks_mut()
+            .set_block(pos, &"minecraft:air".into());
+        // broken by hand, so blocks that need a tool drop nothing
+        let block = state.block();
+        if !mode.instant_break() && !block.requires_tool {
+            self.world
+                .block_context(|ctx| block.behaviour.spawn_drops(ctx, pos, state));
+        }
+        if !mode.invulnerable() {
+            let mut hunger = self.world.ecs_mut().get_mut::<Hunger>(entity).unwrap();
+            hunger.exhaust(BREAK_EXHAUSTION);
+        }
+    }
+
+    /// A player placing `block` against `face` of the block at `against`.
+    fn place(
+        &mut self,
+        entity: Entity,
+        against: IVec3,
+        face: BlockFaceDirection,
+        block: &ResourceLocation,
+    ) {
+        let player = self.world.ecs().entity(entity);
+        let feet = player.get::<Position>().unwrap().0;
+        let mode = *player.get::<GameMode>().unwrap();
+        let tags = player.get::<AdventureTags>().unwrap();
+        let target = placement(self.world.chunks(), against, face, mode, tags)
+            .filter(|_| within_reach(feet + Vec3::Y * EYE_HEIGHT, against));
+        let Some(pos) = target else {
+            self.resend_block(entity, against);
+            self.resend_block(entity, against + face.to_vec());
+            return;
+        };
+        let ecs = self.world.ecs_mut();
+        let entities: Vec<Aabb> = ecs
+            .query::<(&EntityType, &Position)>()
+            .iter(ecs)
+            .map(|(kind, position)| {
+                let (width, height) = kind.size();
+                Aabb::from_feet(position.0, width, height)
+            })
+            .collect();
+        let state = placement_state(self.world.chunks(), pos, BlockState::of(block));
+        match obstructed(state, pos, entities) {
+            true => self.resend_block(entity, pos),
+            false => self.world.chunks_mut().set_state(pos, state),
+        }
+    }
+
+    /// Tell a player what the block at `pos` really is, after it changed it on its side
+    /// when it shouldn't have.
+    fn resend_block(&self, entity: Entity, pos: IVec3) {
+        let chunks = self.world.chunks();
+        let section = pos.div_euclid(IVec3::splat(SUBCHUNK_SIZE as i32));
+        let Some(client) = self.world.ecs().get::<Client>(entity) else {
+            return;
+        };
+        if !chunks.is_chunk_loaded(section) || !client.sees(pos) {
+            return;
+        }
+        client.send(PlayClientbound::BlockUpdate {
+            pos,
+            block: VarInt(chunks.get_state(pos).0 as i32),
+        });
+    }
+
+    /// One tick of movement for a player, the same its client predicted. Falls hurt once they
+    /// land, and sprinting and jumping tire.
+    fn move_player(&mut self, entity: Entity, input: &MoveInput) {
+        let player = self.world.ecs().entity(entity);
+        let before = Body {
+            position: player.get::<Position>().unwrap().0,
+            velocity: player.get::<Velocity>().unwrap().0,
+            on_ground: player.get::<OnGround>().unwrap().0,
+        };
+        let mut body = before;
+        movement::step(&mut body, input, self.world.chunks());
+        let moved = body.position - before.position;
+        let aabb = Collider::of(EntityType::PLAYER).aabb(body.position);
+        let cushioned = input.flying || submerged(self.world.chunks(), &aabb) > 0.0;
+
+        let mut player = self.world.ecs_mut().entity_mut(entity);
+        let mode = *player.get::<GameMode>().unwrap();
+        let mut fall = player.get_mut::<FallDistance>().unwrap();
+        let damage = fall.update(moved.y, body.on_ground, cushioned);
+        player.get_mut::<Health>().unwrap().hurt(damage, mode);
+        if !mode.invulnerable() {
+            let mut hunger = player.get_mut::<Hunger>().unwrap();
+            if input.sprint {
+                hunger.exhaust(SPRINT_EXHAUSTION * moved.xz().length());
+            }
+            if input.jump && before.on_ground && !body.on_ground {
+                hunger.exhaust(match input.sprint {
+                    true => SPRINT_JUMP_EXHAUSTION,
+                    false => JUMP_EXHAUSTION,
+                });
+            }
+        }
+        player.insert((
+            Position(body.position),
+            Velocity(body.velocity),
+            OnGround(body.on_ground),
+            Rotation {
+                yaw: input.yaw,
+                pitch: input.pitch,
+            },
+        ));
+        self.update_view(entity);
+    }
+
+    /// Tell players that sent inputs where they ended up, and give them room for more.
+    fn send_player_states(&mut self) {
+        let ecs = self.world.ecs_mut();
+        let mut players = ecs.query::<(&mut Client, &Position, &Velocity, &OnGround)>();
+        for (mut client, position, velocity, on_ground) in players.iter_mut(ecs) {
+            client.input_budget = (client.input_budget + 1).min(MAX_INPUT_BURST);
+            if let Some(sequence) = client.acked.take() {
+                client.send(PlayClientbound::PlayerState {
+                    sequence: VarInt(sequence),
+                    position: position.0,
+                    velocity: velocity.0,
+                    on_ground: on_ground.0,
+                });
+            }
+        }
+    }
+
+    /// Players that died start again at the world spawn, healed and fed.
+    fn respawn_dead(&mut self) {
+        let ecs = self.world.ecs_mut();
+        let dead: Vec<Entity> = ecs
+            .query::<(Entity, &Health)>()
+            .iter(ecs)
+            .filter(|(_, health)| health.is_dead())
+            .map(|(entity, _)| entity)
+            .collect();
+        let spawn = self.spawn_point();
+        for entity in dead {
+            let mut player = self.world.ecs_mut().entity_mut(entity);
+            player.insert((
+                Position(spawn),
+                Velocity::default(),
+                OnGround::default(),
+                FallDistance::default(),
+                Health::default(),
+                Hunger::default(),
+            ));
+            player.remove::<Digging>();
+            let rotation = *player.get::<Rotation>().unwrap();
+            if let Some(client) = player.get::<Client>() {
+                log::info!("{} died", client.name);
+                client.send(PlayClientbound::PlayerPosition {
+                    position: spawn,
+                    yaw: rotation.yaw,
+                    pitch: rotation.pitch,
+                });
+            }
+            self.update_view(entity);
+        }
+    }
+
+    /// Tell players whose health or hunger changed.
+    fn send_health(&mut self) {
+        let ecs = self.world.ecs_mut();
+        let mut players = ecs.query::<(&mut Client, &Health, &Hunger)>();
+        for (mut client, health, hunger) in players.iter_mut(ecs) {
+            let now = (health.0, hunger.food, hunger.saturation);
+            if client.health_sent != Some(now) {
+                client.health_sent = Some(now);
+                client.send(PlayClientbound::UpdateHealth {
+                    health: health.0,
+                    food: VarInt(hunger.food as i32),
+                    saturation: hunger.saturation,
+                });
+            }
+        }
+    }
+
+    /// Queue the columns that came into view and drop the ones that left it.
+    fn update_view(&mut self, entity: Entity) {
+        let r = self.view_distance;
+        let position = self.world.ecs().get::<Position>(entity).unwrap().0;
+        let center = column_of(position);
+        let mut client = self.world.ecs_mut().get_mut::<Client>(entity).unwrap();
+        if client.center == Some(center) {
+            return;
+        }
+        client.center = Some(center);
+
+        let in_view = |c: &IVec2| (c.x - center.x).abs() <= r && (c.y - center.y).abs() <= r;
+        let released: Vec<IVec2> = client
+            .sent
+            .iter()
+            .filter(|c| !in_view(c))
+            .copied()
+            .collect();
+        for column in &released {
+            client.sent.remove(column);
+        }
+        client.pending.retain(in_view);
+
+        let queued: HashSet<IVec2> = client.pending.iter().copied().collect();
+        for x in -r..=r {
+            for z in -r..=r {
+                let column = center + ivec2(x, z);
+                if !client.sent.contains(&column) && !queued.contains(&column) {
+                    client.pending.push_back(column);
+                }
+            }
+        }
+        client
+            .pending
+            .make_contiguous()
+            .sort_by_key(|c| (*c - center).length_squared());
+
+        for column in &released {
+            client.send(PlayClientbound::UnloadChunk { column: *column });
+        }
+        for column in released {
+            self.release(column);
+        }
+    }
+
+    /// Load a column for one more player.
+    fn acquire(&mut self, column: IVec2) {
+        let viewers = self.viewers.entry(column).or_default();
+        *viewers += 1;
+        if *viewers == 1 {
+            for y in 0..SECTIONS {
+                self.world.load_chunk(ivec3(column.x, y, column.y));
+            }
+        }
+    }
+
+    /// One player less has the column, unload it once nobody does.
+    fn release(&mut self, column: IVec2) {
+        let Some(viewers) = self.viewers.get_mut(&column) else {
+            return;
+        };
+        *viewers -= 1;
+        if *viewers == 0 {
+            self.viewers.remove(&column);
+            for y in 0..SECTIONS {
+                self.world.unload_chunk(ivec3(column.x, y, column.y));
+            }
+        }
+    }
+
+    fn send_chunks(&mut self) {
+        let entities: Vec<Entity> = self.players.values().copied().collect();
+        for entity in entities {
+            let mut client = self.world.ecs_mut().get_mut::<Client>(entity).unwrap();
+            let n = client.pending.len().min(COLUMNS_PER_TICK);
+            let batch: Vec<IVec2> = client.pending.drain(..n).collect();
+
+            let mut packets = vec![];
+            for column in &batch {
+                self.acquire(*column);
+                let chunks = self.world.chunks();
+                let data =
+                    ChunkColumnData::new(|y| Some(chunks.get_chunk(ivec3(column.x, y, column.y))));
+                packets.push(PlayClientbound::ChunkData {
+                    column: *column,
+                    data: Compressed(data),
+                });
+            }
+
+            let mut client = self.world.ecs_mut().get_mut::<Client>(entity).unwrap();
+            client.sent.extend(batch);
+            for packet in packets {
+                client.send(packet);
+            }
+        }
+    }
+
+    /// Tell every player about the blocks changed this tick in the columns it has, one
+    /// packet per section.
+    fn send_block_changes(&mut self) {
+        let changed = self.world.chunks_mut().take_changed_blocks();
+        if changed.is_empty() {
+            return;
+        }
+        let chunks = self.world.chunks();
+        let mut seen = HashSet::new();
+        let mut sections: HashMap<IVec3, Vec<BlockChange>> = HashMap::new();
+        for pos in changed {
+            let section = pos.div_euclid(IVec3::splat(SUBCHUNK_SIZE as i32));
+            if !seen.insert(pos) || !chunks.is_chunk_loaded(section) {
+                continue;
+            }
+            let local = pos - section * SUBCHUNK_SIZE as i32;
+            let index = SubChunk::index(local.x, local.y, local.z);
+            sections.entry(section).or_default().push(BlockChange {
+                local,
+                block: chunks.get_chunk(section).get_state_id(index),
+            });
+        }
+        let packets: Vec<(IVec3, PlayClientbound)> = sections
+            .into_iter()
+            .map(|(section, changes)| (section * 16, section_changes(section, changes)))
+            .collect();
+
+        let ecs = self.world.ecs_mut();
+        for client in ecs.query::<&Client>().iter(ecs) {
+            for (corner, packet) in &packets {
+                if client.sees(*corner) {
+                    client.send(packet.clone());
+                }
+            }
+        }
+    }
+
+    fn count_inhabited_time(&mut self) {
+        let ecs = self.world.ecs_mut();
+        let columns: Vec<IVec2> = ecs
+            .query::<(&Position, &Client)>()
+            .iter(ecs)
+            .map(|(p, _)| column_of(p.0))
+            .collect();
+        for column in columns {
+            self.world.chunks_mut().add_inhabited_time(column, 1);
+        }
+    }
+
+    fn keep_alive(&mut self) {
+        let ticks = self.ticks;
+        let mut timed_out = vec![];
+        let ecs = self.world.ecs_mut();
+        for mut client in ecs.query::<&mut Client>().iter_mut(ecs) {
+            match client.keep_alive {
+                Some((_, sent)) if ticks - sent > KEEP_ALIVE_TIMEOUT => timed_out.push(client.id),
+                None if ticks - client.last_keep_alive >= KEEP_ALIVE_INTERVAL => {
+                    let id = ticks as i64;
+                    client.send(PlayClientbound::KeepAlive { id });
+                    client.keep_alive = Some((id, ticks));
+                    client.last_keep_alive = ticks;
+                }
+                _ => {}
+            }
+        }
+        for id in timed_out {
+            self.disconnect(id, DisconnectReason::TimedOut);
+        }
+    }
+
+    fn save_player(&mut self, entity: Entity) -> Result<()> {
+        let player = self.world.ecs().entity(entity);
+        let (Some(client), Some(position), Some(rotation)) = (
+            player.get::<Client>(),
+            player.get::<Position>(),
+            player.get::<Rotation>(),
+        ) else {
+            return Ok(());
+        };
+        let names = |ids: &[ResourceLocation]| ids.iter().map(|id| id.to_string()).collect();
+        let tags = player.get::<AdventureTags>().unwrap();
+        let hunger = player.get::<Hunger>().unwrap();
+        let data = PlayerData {
+            position: position.0.to_array(),
+            yaw: rotation.yaw,
+            pitch: rotation.pitch,
+            game_mode: *player.get::<GameMode>().unwrap(),
+            can_destroy: names(&tags.can_destroy),
+            can_place_on: names(&tags.can_place_on),
+            health: player.get::<Health>().unwrap().0,
+            food: hunger.food,
+            saturation: hunger.saturation,
+            exhaustion: hunger.exhaustion,
+        };
+        if let Some(storage) = self.world.chunks().storage() {
+            storage
+                .write_player(&client.name, &data)
+                .with_context(|| format!("Failed to save player data of {}", client.name))?;
+        }
+        Ok(())
+    }
+
+    /// Save every player and the world.
+    pub(super) fn save(&mut self) -> Result<()> {
+        let entities: Vec<Entity> = self.players.values().copied().collect();
+        for entity in entities {
+            self.save_player(entity)?;
+        }
+        self.world.save()
+    }
+
+    fn shutdown(mut self) -> Result<()> {
+        log::info!("Stopping the server");
+        let ids: Vec<ConnectionId> = self.players.keys().copied().collect();
+        for id in ids {
+            self.disconnect(id, DisconnectReason::ServerClosed);
+        }
+        log::info!("Saving the world");
+        self.world.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_round_down() {
+        assert_eq!(column_of(vec3(0.5, 64.0, 15.9)), ivec2(0, 0));
+        assert_eq!(column_of(vec3(-0.5, 64.0, 16.0)), ivec2(-1, 1));
+    }
+}
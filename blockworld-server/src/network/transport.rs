@@ -0,0 +1,231 @@
+//! A connection's byte stream as packets, over TCP or WebSocket.
+//!
+//! Over TCP frames simply follow each other. Over WebSocket every binary message holds one or
+//! more whole frames, so a browser speaks exactly the same protocol. After the login key exchange
+//! both halves may encrypt the bytes, see `encryption.rs`. Either half can also record the packets
+//! it handles into a [`Capture`].
+
+use std::pin::Pin;
+
+use anyhow::*;
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpStream, ToSocketAddrs,
+    },
+};
+use tokio_tungstenite::{
+    tungstenite::{Error as WsError, Message},
+    WebSocketStream,
+};
+
+use crate::packet::{write_frame, FrameDecoder, PacketSet};
+
+use super::{capture::Capture, encryption::Cipher};
+
+type WsStream = Pin<Box<dyn Stream<Item = Result<Message, WsError>> + Send>>;
+type WsSink = Pin<Box<dyn Sink<Message, Error = WsError> + Send>>;
+
+enum Source {
+    Tcp(OwnedReadHalf),
+    WebSocket(WsStream),
+}
+
+enum Destination {
+    Tcp(OwnedWriteHalf),
+    WebSocket(WsSink),
+}
+
+/// The receiving half of a connection.
+pub struct PacketReader {
+    source: Source,
+    decoder: FrameDecoder,
+    cipher: Option<Cipher>,
+    capture: Option<Capture>,
+}
+
+/// The sending half of a connection.
+pub struct PacketWriter {
+    destination: Destination,
+    cipher: Option<Cipher>,
+    capture: Option<Capture>,
+}
+
+pub fn split_tcp(stream: TcpStream) -> (PacketReader, PacketWriter) {
+    // packets are small and latency matters more than throughput
+    if let Err(e) = stream.set_nodelay(true) {
+        log::warn!("Failed to set TCP_NODELAY: {}", e);
+    }
+    let (read, write) = stream.into_split();
+    (
+        PacketReader::new(Source::Tcp(read)),
+        PacketWriter {
+            destination: Destination::Tcp(write),
+            cipher: None,
+            capture: None,
+        },
+    )
+}
+
+pub fn split_websocket<S>(ws: WebSocketStream<S>) -> (PacketReader, PacketWriter)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (write, read) = ws.split();
+    (
+        PacketReader::new(Source::WebSocket(Box::pin(read))),
+        PacketWriter {
+            destination: Destination::WebSocket(Box::pin(write)),
+            cipher: None,
+            capture: None,
+        },
+    )
+}
+
+/// Open a TCP connection to a server.
+pub async fn connect_tcp<A: ToSocketAddrs>(addr: A) -> Result<(PacketReader, PacketWriter)> {
+    Ok(split_tcp(TcpStream::connect(addr).await?))
+}
+
+/// Open a WebSocket connection to a server, `url` like `ws://localhost:25566`.
+pub async fn connect_websocket(url: &str) -> Result<(PacketReader, PacketWriter)> {
+    let (ws, _) = tokio_tungstenite::connect_async(url).await?;
+    Ok(split_websocket(ws))
+}
+
+impl PacketReader {
+    fn new(source: Source) -> Self {
+        Self {
+            source,
+            decoder: FrameDecoder::new(),
+            cipher: None,
+            capture: None,
+        }
+    }
+
+    /// Record every packet received from now on.
+    pub fn set_capture(&mut self, capture: Capture) {
+        self.capture = Some(capture);
+    }
+
+    /// Decrypt everything from the byte after the last frame returned.
+    pub fn enable_encryption(&mut self, mut cipher: Cipher) {
+        cipher.apply(self.decoder.buffered_mut());
+        self.cipher = Some(cipher);
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        match &mut self.cipher {
+            Some(cipher) => {
+                let mut bytes = bytes.to_vec();
+                cipher.apply(&mut bytes);
+                self.decoder.push(&bytes);
+            }
+            None => self.decoder.push(bytes),
+        }
+    }
+
+    /// The payload of the next frame, `None` once the other side closed the connection.
+    ///
+    /// Cancel safe: bytes already read stay buffered for the next call.
+    pub async fn next_frame(&mut self) -> Result<Option<Vec<u8>>> {
+        loop {
+            if let Some(frame) = self.decoder.next_frame()? {
+                return Ok(Some(frame));
+            }
+            match &mut self.source {
+                Source::Tcp(read) => {
+                    let mut buf = [0; 4096];
+                    let n = read.read(&mut buf).await?;
+                    if n == 0 {
+                        return self.closed();
+                    }
+                    self.push(&buf[..n]);
+                }
+                Source::WebSocket(read) => match read.next().await {
+                    None | Some(Result::Ok(Message::Close(_))) => return self.closed(),
+                    Some(Result::Ok(Message::Binary(bytes))) => self.push(&bytes),
+                    Some(Result::Ok(Message::Text(_))) => {
+                        bail!("text messages aren't part of the protocol")
+                    }
+                    // tungstenite answers pings by itself
+                    Some(Result::Ok(_)) => {}
+                    Some(Err(e)) => return Err(e.into()),
+                },
+            }
+        }
+    }
+
+    /// The next packet, `None` once the other side closed the connection.
+    pub async fn recv<P: PacketSet>(&mut self) -> Result<Option<P>> {
+        match self.next_frame().await? {
+            Some(frame) => {
+                // before decoding, so a capture shows what a broken packet was
+                if let Some(capture) = &self.capture {
+                    capture.record::<P>(&frame);
+                }
+                Ok(Some(P::decode(&frame)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn closed(&self) -> Result<Option<Vec<u8>>> {
+        if self.decoder.buffered() > 0 {
+            bail!("connection closed in the middle of a frame");
+        }
+        Ok(None)
+    }
+}
+
+impl PacketWriter {
+    pub async fn send<P: PacketSet>(&mut self, packet: &P) -> Result<()> {
+        self.send_all(std::slice::from_ref(packet)).await
+    }
+
+    /// Send packets together, as one WebSocket message.
+    pub async fn send_all<P: PacketSet>(&mut self, packets: &[P]) -> Result<()> {
+        let mut frames = vec![];
+        for packet in packets {
+            let payload = packet.encode();
+            if let Some(capture) = &self.capture {
+                capture.record::<P>(&payload);
+            }
+            write_frame(&mut frames, &payload);
+        }
+        self.send_frames(frames).await
+    }
+
+    /// Record every packet sent from now on.
+    pub fn set_capture(&mut self, capture: Capture) {
+        self.capture = Some(capture);
+    }
+
+    /// Encrypt everything sent from now on.
+    pub fn enable_encryption(&mut self, cipher: Cipher) {
+        self.cipher = Some(cipher);
+    }
+
+    /// Write bytes holding any number of whole frames, as one WebSocket message.
+    async fn send_frames(&mut self, mut frames: Vec<u8>) -> Result<()> {
+        if let Some(cipher) = &mut self.cipher {
+            cipher.apply(&mut frames);
+        }
+        match &mut self.destination {
+            Destination::Tcp(write) => write.write_all(&frames).await?,
+            Destination::WebSocket(write) => write.send(Message::Binary(frames)).await?,
+        }
+        Ok(())
+    }
+
+    /// Flush and close our side of the connection.
+    pub async fn close(&mut self) -> Result<()> {
+        match &mut self.destination {
+            Destination::Tcp(write) => write.shutdown().await?,
+            Destination::WebSocket(write) => write.close().await?,
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,496 @@
+//! ```text
+//! package net.minecraft.network.play.server
+//! class SChunkDataPacket
+//! version 1.16
+//! ```
+//!
+//! A chunk column as it goes over the wire, much smaller than the `SubChunk` arrays:
+//!
+//! ```text
+//! mask         u16, bit y set for every section that follows, the others are air
+//! section*     non-air count u16, blocks Paletted<VarInt> of 4096 state ids in YZX order
+//! sky light    16 x LightData, one per section from the bottom
+//! block light  16 x LightData
+//! biomes       Paletted<String> of 4x4x4 block cells, 1024 for the column in YZX order
+//! ```
+//!
+//! Blocks are state ids (see `block/state.rs`), the same on both sides as long as the protocol
+//! version is. The whole thing is zlib compressed when it is bigger than
+//! [`COMPRESSION_THRESHOLD`].
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    io::{Read, Write},
+};
+
+use anyhow::*;
+use blockworld_utils::BitStorage;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use glam::*;
+
+use super::{
+    codec::{Decode, Encode, VarInt},
+    frame::MAX_FRAME_LEN,
+};
+use crate::{
+    block::state::StateId,
+    world::chunk::{SubChunk, CHUNK_HEIGHT, SUBCHUNK_BLOCK_NUM, SUBCHUNK_SIZE},
+};
+
+/// Encoded payloads at least this long are compressed.
+pub const COMPRESSION_THRESHOLD: usize = 256;
+/// Largest payload we inflate, so a small packet can't expand into gigabytes.
+const MAX_UNCOMPRESSED_LEN: usize = 8 * MAX_FRAME_LEN;
+
+/// Sections in a column.
+pub const SECTIONS: usize = CHUNK_HEIGHT / SUBCHUNK_SIZE;
+/// Biomes are stored per 4x4x4 cell like 1.16.
+pub const BIOME_CELLS: usize = SECTIONS * 64;
+/// Every column is plains until the world stores biomes.
+const DEFAULT_BIOME: &str = "minecraft:plains";
+
+/// Light of a section, 0 to 15 per block. Uniform sections take one byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LightData {
+    Dark,
+    Full,
+    /// Two blocks per byte in YZX order, the first in the low nibble.
+    Nibbles(Vec<u8>),
+}
+
+const NIBBLES_LEN: usize = SUBCHUNK_BLOCK_NUM / 2;
+
+impl LightData {
+    /// Pack one level per block, collapsing uniform sections.
+    pub fn from_levels(levels: &[u8]) -> Self {
+        match levels {
+            l if l.iter().all(|l| *l == 0) => LightData::Dark,
+            l if l.iter().all(|l| *l == 15) => LightData::Full,
+            l => LightData::Nibbles(l.chunks(2).map(|p| p[0] | (p[1] << 4)).collect()),
+        }
+    }
+
+    /// The level at an index as in [`SubChunk::index`].
+    pub fn get(&self, index: usize) -> u8 {
+        match self {
+            LightData::Dark => 0,
+            LightData::Full => 15,
+            LightData::Nibbles(n) => (n[index / 2] >> ((index % 2) * 4)) & 0xf,
+        }
+    }
+}
+
+impl Encode for LightData {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            LightData::Dark => buf.push(0),
+            LightData::Full => buf.push(1),
+            LightData::Nibbles(n) => {
+                buf.push(2);
+                buf.extend_from_slice(n);
+            }
+        }
+    }
+}
+
+impl Decode for LightData {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        match u8::decode(buf)? {
+            0 => Ok(LightData::Dark),
+            1 => Ok(LightData::Full),
+            2 => {
+                if buf.len() < NIBBLES_LEN {
+                    bail!("unexpected end of packet in light data");
+                }
+                let (n, rest) = buf.split_at(NIBBLES_LEN);
+                *buf = rest;
+                Ok(LightData::Nibbles(n.to_vec()))
+            }
+            m => bail!("invalid light data mode {}", m),
+        }
+    }
+}
+
+/// A fixed-size array as a palette plus packed indices into it. When the palette has a
+/// single entry `bits` is 0 and no indices are sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paletted<T> {
+    pub palette: Vec<T>,
+    pub bits: u8,
+    pub data: Vec<u64>,
+}
+
+impl<T: Clone + Eq + Hash> Paletted<T> {
+    /// Pack `values`, using at least `min_bits` bits per index unless they are all equal.
+    pub fn pack<I: IntoIterator<Item = T>>(values: I, min_bits: u32) -> Self {
+        let mut palette = vec![];
+        let mut lookup = HashMap::new();
+        let indices: Vec<u32> = values
+            .into_iter()
+            .map(|v| {
+                *lookup.entry(v.clone()).or_insert_with(|| {
+                    palette.push(v);
+                    palette.len() as u32 - 1
+                })
+            })
+            .collect();
+        if palette.len() <= 1 {
+            return Self {
+                palette,
+                bits: 0,
+                data: vec![],
+            };
+        }
+
+        let bits = BitStorage::bits_for(palette.len(), min_bits);
+        let mut storage = BitStorage::new(bits, indices.len());
+        for (i, index) in indices.iter().enumerate() {
+            storage.set(i, *index);
+        }
+        Self {
+            palette,
+            bits: bits as u8,
+            data: storage.into_raw(),
+        }
+    }
+
+    /// All `size` values, checked against the palette.
+    pub fn unpack(&self, size: usize) -> Result<Vec<T>> {
+        if self.bits == 0 {
+            let Some(value) = self.palette.first() else {
+                bail!("empty palette");
+            };
+            return Ok(vec![value.clone(); size]);
+        }
+        let storage =
+            BitStorage::from_raw(self.bits as u32, size, self.data.clone()).ok_or_else(|| {
+                anyhow!(
+                    "{} words for {} values of {} bits",
+                    self.data.len(),
+                    size,
+                    self.bits
+                )
+            })?;
+        storage
+            .iter()
+            .map(|i| {
+                self.palette
+                    .get(i as usize)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("palette entry {} of {}", i, self.palette.len()))
+            })
+            .collect()
+    }
+}
+
+impl<T: Encode> Encode for Paletted<T> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.bits.encode(buf);
+        self.palette.encode(buf);
+        self.data.encode(buf);
+    }
+}
+
+impl<T: Decode> Decode for Paletted<T> {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        Ok(Self {
+            bits: Decode::decode(buf)?,
+            palette: Decode::decode(buf)?,
+            data: Decode::decode(buf)?,
+        })
+    }
+}
+
+/// A non-empty section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetSection {
+    pub y: i32,
+    /// Blocks that aren't air, so the client can skip meshing empty sections.
+    pub non_air: u16,
+    pub blocks: Paletted<VarInt>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkColumnData {
+    /// Sections that aren't all air, from the bottom.
+    pub sections: Vec<NetSection>,
+    pub sky_light: Vec<LightData>,
+    pub block_light: Vec<LightData>,
+    pub biomes: Paletted<String>,
+}
+
+impl ChunkColumnData {
+    /// Describe a column from its loaded sections, `section(y)` for y in `0..SECTIONS`.
+    ///
+    /// Sky light is 15 above the highest block of each x z and 0 below, without spreading
+    /// sideways. Block light is dark, nothing glows yet.
+    pub fn new<'a, F: Fn(i32) -> Option<&'a SubChunk>>(section: F) -> Self {
+        let columns: Vec<Option<&SubChunk>> = (0..SECTIONS as i32).map(section).collect();
+
+        let mut sections = vec![];
+        for (y, sc) in columns.iter().enumerate() {
+            let Some(sc) = sc.filter(|sc| !sc.is_empty()) else {
+                continue;
+            };
+            let ids = (0..SUBCHUNK_BLOCK_NUM).map(|i| sc.get_state_id(i));
+            sections.push(NetSection {
+                y: y as i32,
+                non_air: ids.clone().filter(|id| *id != 0).count() as u16,
+                blocks: Paletted::pack(ids.map(|id| VarInt(id as i32)), 4),
+            });
+        }
+
+        // lowest block of every x z that sees the sky
+        let mut sky_from = [0i32; SUBCHUNK_SIZE * SUBCHUNK_SIZE];
+        for (xz, from) in sky_from.iter_mut().enumerate() {
+            let (x, z) = ((xz % SUBCHUNK_SIZE) as i32, (xz / SUBCHUNK_SIZE) as i32);
+            *from = (0..CHUNK_HEIGHT as i32)
+                .rev()
+                .find(|y| {
+                    columns[(*y / 16) as usize]
+                        .is_some_and(|sc| sc.get_state_id(SubChunk::index(x, y % 16, z)) != 0)
+                })
+                .map_or(0, |y| y + 1);
+        }
+        let sky_light = (0..SECTIONS as i32)
+            .map(|sy| {
+                let levels: Vec<u8> = (0..SUBCHUNK_BLOCK_NUM)
+                    .map(|i| {
+                        let y = sy * 16 + (i / 256) as i32;
+                        if y >= sky_from[i % 256] {
+                            15
+                        } else {
+                            0
+                        }
+                    })
+                    .collect();
+                LightData::from_levels(&levels)
+            })
+            .collect();
+
+        Self {
+            sections,
+            sky_light,
+            block_light: vec![LightData::Dark; SECTIONS],
+            biomes: Paletted::pack(vec![DEFAULT_BIOME.to_string(); BIOME_CELLS], 1),
+        }
+    }
+
+    /// Rebuild the sub chunk of section `y` of column `column`, `None` if it is air.
+    pub fn sub_chunk(&self, column: IVec2, y: i32) -> Result<Option<SubChunk>> {
+        let Some(section) = self.sections.iter().find(|s| s.y == y) else {
+            return Ok(None);
+        };
+        let ids = section
+            .blocks
+            .unpack(SUBCHUNK_BLOCK_NUM)
+            .with_context(|| format!("in section {}", y))?;
+        let mut sc = SubChunk::new(ivec3(column.x, y, column.y));
+        for (i, id) in ids.into_iter().enumerate() {
+            sc.set_state_id(i, id.0 as StateId);
+        }
+        Ok(Some(sc))
+    }
+}
+
+impl Encode for ChunkColumnData {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let mask = self.sections.iter().fold(0u16, |m, s| m | (1 << s.y));
+        mask.encode(buf);
+        for section in &self.sections {
+            section.non_air.encode(buf);
+            section.blocks.encode(buf);
+        }
+        for light in self.sky_light.iter().chain(&self.block_light) {
+            light.encode(buf);
+        }
+        self.biomes.encode(buf);
+    }
+}
+
+impl Decode for ChunkColumnData {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        let mask = u16::decode(buf)?;
+        let mut sections = vec![];
+        for y in (0..SECTIONS as i32).filter(|y| mask & (1 << y) != 0) {
+            sections.push(NetSection {
+                y,
+                non_air: Decode::decode(buf)?,
+                blocks: Decode::decode(buf)?,
+            });
+        }
+        let mut light = |_| LightData::decode(buf);
+        let sky_light = (0..SECTIONS).map(&mut light).collect::<Result<_>>()?;
+        let block_light = (0..SECTIONS).map(&mut light).collect::<Result<_>>()?;
+        Ok(Self {
+            sections,
+            sky_light,
+            block_light,
+            biomes: Decode::decode(buf)?,
+        })
+    }
+}
+
+/// A field zlib compressed when its encoding reaches [`COMPRESSION_THRESHOLD`] bytes.
+///
+/// ```text
+/// uncompressed length VarInt, 0 if the bytes aren't compressed
+/// bytes               VarInt length, then the (maybe compressed) encoding
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Compressed<T>(pub T);
+
+impl<T: Encode> Encode for Compressed<T> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let mut raw = vec![];
+        self.0.encode(&mut raw);
+        let (len, bytes) = if raw.len() >= COMPRESSION_THRESHOLD {
+            let mut z = ZlibEncoder::new(Vec::new(), Compression::default());
+            z.write_all(&raw).unwrap();
+            (raw.len(), z.finish().unwrap())
+        } else {
+            (0, raw)
+        };
+        VarInt(len as i32).encode(buf);
+        VarInt(bytes.len() as i32).encode(buf);
+        buf.extend_from_slice(&bytes);
+    }
+}
+
+impl<T: Decode> Decode for Compressed<T> {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        let len = VarInt::decode(buf)?.0;
+        let n = VarInt::decode(buf)?.0;
+        if n < 0 || n as usize > buf.len() {
+            bail!("invalid compressed length {}", n);
+        }
+        let (bytes, rest) = buf.split_at(n as usize);
+        *buf = rest;
+
+        let raw = match len {
+            0 => bytes.to_vec(),
+            len if len < 0 || len as usize > MAX_UNCOMPRESSED_LEN => {
+                bail!("invalid uncompressed length {}", len)
+            }
+            len => {
+                let mut raw = Vec::with_capacity(len as usize);
+                ZlibDecoder::new(bytes)
+                    .take(len as u64)
+                    .read_to_end(&mut raw)?;
+                if raw.len() != len as usize {
+                    bail!("inflated to {} bytes instead of {}", raw.len(), len);
+                }
+                raw
+            }
+        };
+        let mut raw = &raw[..];
+        let value = T::decode(&mut raw)?;
+        if !raw.is_empty() {
+            bail!("{} trailing bytes in compressed data", raw.len());
+        }
+        Ok(Compressed(value))
+    }
+}
+
+#[cfg(test)]
+mod sample {
+    use super::*;
+    use crate::packet::codec::Sample;
+
+    impl Sample for ChunkColumnData {
+        fn sample() -> Self {
+            let mut sc = SubChunk::new(ivec3(0, 4, 0));
+            sc.set_blockid(ivec3(1, 2, 3), "minecraft:stone");
+            ChunkColumnData::new(|y| (y == 4).then_some(&sc))
+        }
+    }
+
+    impl<T: Sample> Sample for Compressed<T> {
+        fn sample() -> Self {
+            Compressed(T::sample())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_round_trip() {
+        let mut low = SubChunk::new(ivec3(3, 0, -2));
+        let mut high = SubChunk::new(ivec3(3, 4, -2));
+        for x in 0..16 {
+            for z in 0..16 {
+                low.set_blockid(ivec3(x, 15, z), "minecraft:stone");
+            }
+        }
+        high.set_blockid(ivec3(5, 7, 9), "minecraft:dirt");
+
+        let column = ChunkColumnData::new(|y| match y {
+            0 => Some(&low),
+            4 => Some(&high),
+            _ => None,
+        });
+        assert_eq!(column.sections.len(), 2);
+        assert_eq!(column.sections[0].non_air, 256);
+        assert_eq!(column.sections[1].non_air, 1);
+
+        let mut buf = vec![];
+        Compressed(column.clone()).encode(&mut buf);
+        let back = Compressed::<ChunkColumnData>::decode(&mut &buf[..])
+            .unwrap()
+            .0;
+        assert_eq!(back, column);
+
+        let sc = back.sub_chunk(ivec2(3, -2), 4).unwrap().unwrap();
+        assert_eq!(sc.pos(), ivec3(3, 4, -2));
+        assert_eq!(sc.get_blockid(ivec3(5, 7, 9)), "minecraft:dirt");
+        assert!(back.sub_chunk(ivec2(3, -2), 2).unwrap().is_none());
+
+        // open sky above the stone floor, except right under the dirt block
+        let index = |x, y, z| SubChunk::index(x, y, z);
+        assert_eq!(back.sky_light[0], LightData::Dark);
+        assert_eq!(back.sky_light[1].get(index(0, 0, 0)), 15);
+        assert_eq!(back.sky_light[3].get(index(5, 0, 9)), 0);
+        assert_eq!(back.sky_light[3].get(index(6, 0, 9)), 15);
+        assert_eq!(back.sky_light[5], LightData::Full);
+        assert_eq!(back.block_light[0], LightData::Dark);
+        assert_eq!(back.biomes.unpack(BIOME_CELLS).unwrap()[0], DEFAULT_BIOME);
+    }
+
+    #[test]
+    fn compresses_above_threshold() {
+        let encoded = |v: &Vec<u8>| {
+            let mut buf = vec![];
+            Compressed(v.clone()).encode(&mut buf);
+            buf
+        };
+        let small = vec![7u8; 10];
+        let small_buf = encoded(&small);
+        assert_eq!(small_buf[0], 0, "small payloads go as they are");
+
+        let big = vec![7u8; 10_000];
+        let big_buf = encoded(&big);
+        assert!(big_buf.len() < 100, "{} bytes", big_buf.len());
+        assert_eq!(
+            Compressed::<Vec<u8>>::decode(&mut &big_buf[..]).unwrap().0,
+            big
+        );
+    }
+
+    #[test]
+    fn single_value_palettes_send_no_indices() {
+        let p = Paletted::pack(vec![VarInt(3); 4096], 4);
+        assert_eq!((p.bits, p.data.len()), (0, 0));
+        assert_eq!(p.unpack(4096).unwrap(), vec![VarInt(3); 4096]);
+
+        let mut values = vec![1u8; 4096];
+        values[100] = 2;
+        let p = Paletted::pack(values.clone(), 4);
+        assert_eq!(p.bits, 4);
+        assert_eq!(p.unpack(4096).unwrap(), values);
+        assert!(p.unpack(8192).is_err());
+    }
+}
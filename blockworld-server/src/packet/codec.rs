@@ -0,0 +1,315 @@
+//! ```text
+//! package net.minecraft.network
+//! class FriendlyByteBuf
+//! version 1.16
+//! ```
+//!
+//! How each field type is written on the wire. Integers are big endian, lengths and ids are
+//! VarInts, strings are UTF-8 prefixed with their byte length.
+
+use anyhow::*;
+use glam::*;
+
+/// Longest string we accept, in bytes.
+pub const MAX_STRING_LEN: usize = 32767 * 4;
+/// Longest list we accept, so a bad length can't make us allocate gigabytes.
+pub const MAX_LIST_LEN: usize = 1 << 21;
+
+pub trait Encode {
+    fn encode(&self, buf: &mut Vec<u8>);
+}
+
+/// Decoding consumes bytes from the front of `buf`.
+pub trait Decode: Sized {
+    fn decode(buf: &mut &[u8]) -> Result<Self>;
+}
+
+fn take<'a>(buf: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+    if buf.len() < n {
+        bail!("unexpected end of packet, wanted {} more bytes", n);
+    }
+    let (head, rest) = buf.split_at(n);
+    *buf = rest;
+    Ok(head)
+}
+
+/// A variable length integer, 7 bits per byte with the high bit set on all but the last.
+/// Negative numbers always take the full 5 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct VarInt(pub i32);
+
+impl VarInt {
+    pub const MAX_LEN: usize = 5;
+
+    /// Bytes `value` takes when encoded.
+    pub fn len(value: i32) -> usize {
+        match value as u32 {
+            0..=0x7f => 1,
+            0x80..=0x3fff => 2,
+            0x4000..=0x1f_ffff => 3,
+            0x20_0000..=0xfff_ffff => 4,
+            _ => 5,
+        }
+    }
+
+    /// Decode from the front of `buf`, or `None` if it stops in the middle of the number.
+    pub fn peek(buf: &[u8]) -> Result<Option<(i32, usize)>> {
+        let mut value = 0u32;
+        for (i, b) in buf.iter().enumerate().take(Self::MAX_LEN) {
+            value |= ((b & 0x7f) as u32) << (7 * i);
+            if b & 0x80 == 0 {
+                return Ok(Some((value as i32, i + 1)));
+            }
+        }
+        if buf.len() >= Self::MAX_LEN {
+            bail!("VarInt is too long");
+        }
+        Ok(None)
+    }
+}
+
+impl Encode for VarInt {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let mut value = self.0 as u32;
+        loop {
+            if value & !0x7f == 0 {
+                buf.push(value as u8);
+                return;
+            }
+            buf.push((value & 0x7f) as u8 | 0x80);
+            value >>= 7;
+        }
+    }
+}
+
+impl Decode for VarInt {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        match VarInt::peek(buf)? {
+            Some((value, len)) => {
+                *buf = &buf[len..];
+                Ok(VarInt(value))
+            }
+            None => bail!("unexpected end of packet in a VarInt"),
+        }
+    }
+}
+
+macro_rules! impl_number {
+    ($($ty:ty),*) => {
+        $(
+            impl Encode for $ty {
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_be_bytes());
+                }
+            }
+
+            impl Decode for $ty {
+                fn decode(buf: &mut &[u8]) -> Result<Self> {
+                    let bytes = take(buf, std::mem::size_of::<$ty>())?;
+                    Ok(<$ty>::from_be_bytes(bytes.try_into().unwrap()))
+                }
+            }
+        )*
+    };
+}
+
+impl_number!(u8, i8, u16, i16, u32, i32, u64, i64, u128, f32, f64);
+
+impl Encode for bool {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(*self as u8);
+    }
+}
+
+impl Decode for bool {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        match u8::decode(buf)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => bail!("invalid bool {}", b),
+        }
+    }
+}
+
+fn decode_len(buf: &mut &[u8], max: usize) -> Result<usize> {
+    let len = VarInt::decode(buf)?.0;
+    if len < 0 || len as usize > max {
+        bail!("invalid length {}, at most {} allowed", len, max);
+    }
+    Ok(len as usize)
+}
+
+impl Encode for String {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        VarInt(self.len() as i32).encode(buf);
+        buf.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Decode for String {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        let len = decode_len(buf, MAX_STRING_LEN)?;
+        Ok(std::str::from_utf8(take(buf, len)?)?.to_string())
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        VarInt(self.len() as i32).encode(buf);
+        for item in self {
+            item.encode(buf);
+        }
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        let len = decode_len(buf, MAX_LIST_LEN)?;
+        // every item takes at least a byte, don't trust the length further than that
+        let mut items = Vec::with_capacity(len.min(buf.len()));
+        for _ in 0..len {
+            items.push(T::decode(buf)?);
+        }
+        Ok(items)
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.is_some().encode(buf);
+        if let Some(v) = self {
+            v.encode(buf);
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        if bool::decode(buf)? {
+            Ok(Some(T::decode(buf)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+macro_rules! impl_vector {
+    ($($ty:ty { $($field:ident),* }),*) => {
+        $(
+            impl Encode for $ty {
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    $( self.$field.encode(buf); )*
+                }
+            }
+
+            impl Decode for $ty {
+                fn decode(buf: &mut &[u8]) -> Result<Self> {
+                    Ok(Self { $( $field: Decode::decode(buf)?, )* })
+                }
+            }
+        )*
+    };
+}
+
+impl_vector!(
+    IVec2 { x, y },
+    IVec3 { x, y, z },
+    Vec2 { x, y },
+    Vec3 { x, y, z },
+    DVec3 { x, y, z }
+);
+
+/// A value of every field type, so tests can build every packet without listing them by hand.
+#[cfg(test)]
+pub trait Sample {
+    fn sample() -> Self;
+}
+
+#[cfg(test)]
+mod sample {
+    use super::*;
+
+    macro_rules! impl_sample {
+        ($($ty:ty => $value:expr),* $(,)?) => {
+            $( impl Sample for $ty { fn sample() -> Self { $value } } )*
+        };
+    }
+
+    impl_sample!(
+        u8 => 0xab,
+        i8 => -5,
+        u16 => 25565,
+        i16 => -1234,
+        u32 => 0xdead_beef,
+        i32 => -123_456,
+        u64 => 0x0123_4567_89ab_cdef,
+        i64 => -9_876_543_210,
+        u128 => 0x0011_2233_4455_6677_8899_aabb_ccdd_eeff,
+        f32 => -2.5,
+        f64 => 1.0e-3,
+        bool => true,
+        // negative, so it takes all five bytes
+        VarInt => VarInt(-1),
+        String => "minecraft:grass_block ✓".to_string(),
+        IVec2 => ivec2(-3, 7),
+        IVec3 => ivec3(-30, 64, 1_000_000),
+        Vec2 => vec2(0.5, -90.0),
+        Vec3 => vec3(1.5, -2.25, 3.0e4),
+        DVec3 => dvec3(-1.0e7, 64.000244140625, 0.5),
+    );
+
+    impl<T: Sample> Sample for Vec<T> {
+        fn sample() -> Self {
+            vec![T::sample(), T::sample()]
+        }
+    }
+
+    impl<T: Sample> Sample for Option<T> {
+        fn sample() -> Self {
+            Some(T::sample())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded<T: Encode>(v: T) -> Vec<u8> {
+        let mut buf = vec![];
+        v.encode(&mut buf);
+        buf
+    }
+
+    #[test]
+    fn var_int_bytes() {
+        for (value, bytes) in [
+            (0, vec![0x00]),
+            (1, vec![0x01]),
+            (127, vec![0x7f]),
+            (128, vec![0x80, 0x01]),
+            (255, vec![0xff, 0x01]),
+            (25565, vec![0xdd, 0xc7, 0x01]),
+            (2147483647, vec![0xff, 0xff, 0xff, 0xff, 0x07]),
+            (-1, vec![0xff, 0xff, 0xff, 0xff, 0x0f]),
+            (-2147483648, vec![0x80, 0x80, 0x80, 0x80, 0x08]),
+        ] {
+            assert_eq!(encoded(VarInt(value)), bytes, "{}", value);
+            assert_eq!(VarInt::len(value), bytes.len());
+            assert_eq!(VarInt::decode(&mut &bytes[..]).unwrap(), VarInt(value));
+        }
+        assert!(VarInt::decode(&mut &[0xff, 0xff, 0xff, 0xff, 0xff, 0x01][..]).is_err());
+        assert_eq!(VarInt::peek(&[0x80, 0x80]).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        // length says 10, only 3 bytes follow
+        assert!(String::decode(&mut &[10, b'a', b'b', b'c'][..]).is_err());
+        // invalid UTF-8
+        assert!(String::decode(&mut &[2, 0xc3, 0x28][..]).is_err());
+        assert!(bool::decode(&mut &[2][..]).is_err());
+        assert!(Vec::<u8>::decode(&mut &encoded(VarInt(-3))[..]).is_err());
+        assert!(i32::decode(&mut &[1, 2][..]).is_err());
+    }
+}
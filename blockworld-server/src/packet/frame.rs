@@ -0,0 +1,93 @@
+//! Frames on a byte stream: a VarInt length, then that many bytes of packet id and body.
+
+use anyhow::*;
+
+use super::codec::{Encode, VarInt};
+
+/// Largest frame either side accepts, 2 MiB like Minecraft.
+pub const MAX_FRAME_LEN: usize = (1 << 21) - 1;
+
+/// Append `payload` to `out` as one frame.
+pub fn write_frame(out: &mut Vec<u8>, payload: &[u8]) {
+    VarInt(payload.len() as i32).encode(out);
+    out.extend_from_slice(payload);
+}
+
+/// Cuts frames out of bytes as they arrive, however the stream splits them.
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// The next whole frame's payload, `None` until enough bytes arrived. An error means the
+    /// stream is corrupt and the connection should be dropped.
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>> {
+        let Some((len, header)) = VarInt::peek(&self.buf)? else {
+            return Ok(None);
+        };
+        if len < 0 || len as usize > MAX_FRAME_LEN {
+            bail!("invalid frame length {}", len);
+        }
+        let end = header + len as usize;
+        if self.buf.len() < end {
+            return Ok(None);
+        }
+        let payload = self.buf[header..end].to_vec();
+        self.buf.drain(..end);
+        Ok(Some(payload))
+    }
+
+    /// Bytes received but not yet part of a whole frame.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Bytes received but not yet part of a whole frame, to decrypt those that arrived before
+    /// decryption was turned on.
+    pub fn buffered_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_split_anywhere() {
+        let mut stream = vec![];
+        write_frame(&mut stream, b"hello");
+        write_frame(&mut stream, &[]);
+        write_frame(&mut stream, &[7; 300]);
+
+        // one byte at a time is the worst case
+        let mut decoder = FrameDecoder::new();
+        let mut frames = vec![];
+        for b in &stream {
+            decoder.push(&[*b]);
+            while let Some(frame) = decoder.next_frame().unwrap() {
+                frames.push(frame);
+            }
+        }
+        assert_eq!(frames, vec![b"hello".to_vec(), vec![], vec![7; 300]]);
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    #[test]
+    fn rejects_oversized_frames() {
+        let mut decoder = FrameDecoder::new();
+        let mut header = vec![];
+        VarInt(MAX_FRAME_LEN as i32 + 1).encode(&mut header);
+        decoder.push(&header);
+        assert!(decoder.next_frame().is_err());
+    }
+}
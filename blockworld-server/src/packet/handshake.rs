@@ -0,0 +1,123 @@
+//! The first packet of every connection, which picks the protocol version and the next state.
+
+use anyhow::*;
+
+use super::{
+    codec::{Decode, Encode, VarInt},
+    ConnectionState, PROTOCOL_VERSION,
+};
+
+/// What the client wants to do after the handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NextState {
+    Status = 1,
+    Login = 2,
+}
+
+impl Encode for NextState {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        VarInt(*self as i32).encode(buf);
+    }
+}
+
+impl Decode for NextState {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        match VarInt::decode(buf)?.0 {
+            1 => Ok(NextState::Status),
+            2 => Ok(NextState::Login),
+            s => bail!("invalid next state {}", s),
+        }
+    }
+}
+
+#[cfg(test)]
+impl super::codec::Sample for NextState {
+    fn sample() -> Self {
+        NextState::Login
+    }
+}
+
+impl From<NextState> for ConnectionState {
+    fn from(s: NextState) -> Self {
+        match s {
+            NextState::Status => ConnectionState::Status,
+            NextState::Login => ConnectionState::Login,
+        }
+    }
+}
+
+packets! {
+    pub enum HandshakeServerbound(Handshake, Serverbound) {
+        0x00 => Handshake {
+            protocol_version: VarInt,
+            /// The address and port the client connected to, as typed by the player.
+            server_address: String,
+            server_port: u16,
+            next_state: NextState,
+        },
+    }
+}
+
+impl HandshakeServerbound {
+    pub fn new(server_address: &str, server_port: u16, next_state: NextState) -> Self {
+        HandshakeServerbound::Handshake {
+            protocol_version: VarInt(PROTOCOL_VERSION),
+            server_address: server_address.to_string(),
+            server_port,
+            next_state,
+        }
+    }
+
+    /// The state to switch to. The error is the disconnect reason to send if we can't talk to
+    /// the client. Any version may ask for the status, which says what we speak.
+    pub fn accept(&self) -> Result<ConnectionState> {
+        let HandshakeServerbound::Handshake {
+            protocol_version,
+            next_state,
+            ..
+        } = self;
+        match protocol_version.0 {
+            _ if *next_state == NextState::Status => Ok(ConnectionState::Status),
+            v if v < PROTOCOL_VERSION => bail!(
+                "Outdated client! Please use a client speaking protocol {}",
+                PROTOCOL_VERSION
+            ),
+            v if v > PROTOCOL_VERSION => bail!(
+                "Outdated server! I'm still on protocol {}",
+                PROTOCOL_VERSION
+            ),
+            _ => Ok((*next_state).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_mismatch_is_refused() {
+        let ok = HandshakeServerbound::new("localhost", 25565, NextState::Login);
+        assert_eq!(ok.accept().unwrap(), ConnectionState::Login);
+
+        for (version, says) in [
+            (PROTOCOL_VERSION - 1, "Outdated client"),
+            (PROTOCOL_VERSION + 1, "Outdated server"),
+        ] {
+            let packet = HandshakeServerbound::Handshake {
+                protocol_version: VarInt(version),
+                server_address: "localhost".into(),
+                server_port: 25565,
+                next_state: NextState::Login,
+            };
+            assert!(packet.accept().unwrap_err().to_string().starts_with(says));
+            let status = HandshakeServerbound::Handshake {
+                protocol_version: VarInt(version),
+                server_address: "localhost".into(),
+                server_port: 25565,
+                next_state: NextState::Status,
+            };
+            assert_eq!(status.accept().unwrap(), ConnectionState::Status);
+        }
+    }
+}
@@ -0,0 +1,31 @@
+//! Between the handshake and play: the client says who it is, the server lets it in or not.
+//!
+//! ```text
+//! client                                  server
+//! LoginStart { name }             ──▶
+//!                                 ◀──     EncryptionRequest { public_key }   if it encrypts
+//! EncryptionResponse { public_key } ─▶
+//!          (both sides encrypt everything from here, see network/encryption.rs)
+//!                                 ◀──     LoginSuccess or Disconnect
+//! ```
+
+packets! {
+    pub enum LoginServerbound(Login, Serverbound) {
+        0x00 => LoginStart { name: String },
+        /// The client's half of the key exchange, sent after it joined the server hash with
+        /// its account, if it has one.
+        0x01 => EncryptionResponse { public_key: Vec<u8> },
+    }
+}
+
+packets! {
+    pub enum LoginClientbound(Login, Clientbound) {
+        /// Refused, the connection is closed after this.
+        0x00 => Disconnect { reason: String },
+        /// Accepted, both sides switch to play.
+        0x01 => LoginSuccess { uuid: u128, name: String },
+        /// The server's half of the key exchange. `authenticate` asks the client to join the
+        /// server hash with its account before answering.
+        0x02 => EncryptionRequest { public_key: Vec<u8>, authenticate: bool },
+    }
+}
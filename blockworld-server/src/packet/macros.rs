@@ -0,0 +1,95 @@
+/// Declare a packet set: an enum with one struct variant per packet, and its [`PacketSet`]
+/// impl with the id table.
+///
+/// ```text
+/// packets! {
+///     pub enum PlayServerbound(Play, Serverbound) {
+///         0x00 => KeepAlive { id: i64 },
+///     }
+/// }
+/// ```
+///
+/// Fields are encoded in the order they are declared, with their [`Encode`] and [`Decode`]
+/// impls.
+///
+/// [`PacketSet`]: crate::packet::PacketSet
+/// [`Encode`]: crate::packet::Encode
+/// [`Decode`]: crate::packet::Decode
+macro_rules! packets {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident($state:ident, $direction:ident) {
+            $(
+                $(#[$variant_meta:meta])*
+                $id:literal => $variant:ident {
+                    $( $(#[$field_meta:meta])* $field:ident: $ty:ty ),* $(,)?
+                }
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq)]
+        $vis enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant { $( $(#[$field_meta])* $field: $ty ),* },
+            )*
+        }
+
+        impl $crate::packet::PacketSet for $name {
+            const STATE: $crate::packet::ConnectionState = $crate::packet::ConnectionState::$state;
+            const DIRECTION: $crate::packet::Direction = $crate::packet::Direction::$direction;
+
+            fn id(&self) -> i32 {
+                match self {
+                    $( Self::$variant { .. } => $id, )*
+                }
+            }
+
+            fn name(&self) -> &'static str {
+                match self {
+                    $( Self::$variant { .. } => stringify!($variant), )*
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn encode_body(&self, buf: &mut Vec<u8>) {
+                match self {
+                    $(
+                        Self::$variant { $($field),* } => {
+                            $( $crate::packet::Encode::encode($field, buf); )*
+                        }
+                    )*
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn decode_body(id: i32, buf: &mut &[u8]) -> anyhow::Result<Self> {
+                use anyhow::Context;
+                match id {
+                    $(
+                        $id => Ok(Self::$variant {
+                            $(
+                                $field: $crate::packet::Decode::decode(buf).with_context(|| {
+                                    format!("in {}.{}", stringify!($variant), stringify!($field))
+                                })?,
+                            )*
+                        }),
+                    )*
+                    _ => anyhow::bail!("unknown {} packet id {:#04x}", stringify!($name), id),
+                }
+            }
+
+            #[cfg(test)]
+            fn samples() -> Vec<Self> {
+                vec![
+                    $(
+                        Self::$variant {
+                            $( $field: $crate::packet::codec::Sample::sample(), )*
+                        },
+                    )*
+                ]
+            }
+        }
+    };
+}
@@ -0,0 +1,157 @@
+//! The wire protocol between client and server.
+//!
+//! A connection starts in [`ConnectionState::Handshake`], where the client says which protocol
+//! version it speaks and what it wants: either the server's status, or to log in and then play. Each state
+//! has its own serverbound and clientbound packet set, so the same id means different packets
+//! in different states.
+//!
+//! ```text
+//! frame  = VarInt length, payload
+//! packet = VarInt id, fields in declaration order (see codec.rs)
+//! ```
+//!
+//! Packet sets are declared with `packets!`, which builds the id table, encoding and decoding
+//! from the list of variants. Adding a packet is adding a line there; ids are never reused
+//! within a set, bump [`PROTOCOL_VERSION`] whenever a packet changes.
+
+use anyhow::*;
+
+pub mod chunk_data;
+pub mod codec;
+pub mod frame;
+
+#[macro_use]
+mod macros;
+
+pub mod handshake;
+pub mod login;
+pub mod play;
+pub mod status;
+
+pub use codec::{Decode, Encode, VarInt};
+pub use frame::{write_frame, FrameDecoder};
+
+/// Version of the protocol as declared in this file, both sides must agree on it exactly.
+pub const PROTOCOL_VERSION: i32 = 13;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectionState {
+    Handshake,
+    Status,
+    Login,
+    Play,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Client to server.
+    Serverbound,
+    /// Server to client.
+    Clientbound,
+}
+
+/// All packets one side may send in one connection state.
+pub trait PacketSet: Sized {
+    const STATE: ConnectionState;
+    const DIRECTION: Direction;
+
+    fn id(&self) -> i32;
+    fn name(&self) -> &'static str;
+    fn encode_body(&self, buf: &mut Vec<u8>);
+    fn decode_body(id: i32, buf: &mut &[u8]) -> Result<Self>;
+
+    /// The id and body, what goes inside a frame.
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        VarInt(self.id()).encode(&mut buf);
+        self.encode_body(&mut buf);
+        buf
+    }
+
+    /// Decode the payload of one frame, which must hold exactly one packet.
+    fn decode(mut payload: &[u8]) -> Result<Self> {
+        let id = VarInt::decode(&mut payload)?.0;
+        let packet = Self::decode_body(id, &mut payload)?;
+        if !payload.is_empty() {
+            bail!(
+                "{} trailing bytes after {} packet {}",
+                payload.len(),
+                Self::STATE.name(),
+                packet.name()
+            );
+        }
+        Ok(packet)
+    }
+
+    /// Encode as a whole frame, ready to be written to the stream.
+    fn to_frame(&self) -> Vec<u8> {
+        let mut out = vec![];
+        write_frame(&mut out, &self.encode());
+        out
+    }
+
+    /// One of every packet in the set, see [`codec::Sample`].
+    #[cfg(test)]
+    fn samples() -> Vec<Self>;
+}
+
+impl ConnectionState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConnectionState::Handshake => "handshake",
+            ConnectionState::Status => "status",
+            ConnectionState::Login => "login",
+            ConnectionState::Play => "play",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, fmt::Debug};
+
+    use super::*;
+
+    fn round_trip<P: PacketSet + PartialEq + Debug>() {
+        let samples = P::samples();
+        let mut ids = HashSet::new();
+        for packet in samples {
+            assert!(
+                ids.insert(packet.id()),
+                "id {:#04x} used twice in {:?} {:?}",
+                packet.id(),
+                P::STATE,
+                P::DIRECTION
+            );
+
+            let mut decoder = FrameDecoder::new();
+            decoder.push(&packet.to_frame());
+            let payload = decoder.next_frame().unwrap().unwrap();
+            assert_eq!(P::decode(&payload).unwrap(), packet);
+
+            // a byte short or a byte too many are both errors
+            assert!(P::decode(&payload[..payload.len() - 1]).is_err());
+            let mut longer = payload.clone();
+            longer.push(0);
+            assert!(P::decode(&longer).is_err());
+        }
+    }
+
+    #[test]
+    fn every_packet_round_trips() {
+        round_trip::<handshake::HandshakeServerbound>();
+        round_trip::<status::StatusServerbound>();
+        round_trip::<status::StatusClientbound>();
+        round_trip::<login::LoginServerbound>();
+        round_trip::<login::LoginClientbound>();
+        round_trip::<play::PlayServerbound>();
+        round_trip::<play::PlayClientbound>();
+    }
+
+    #[test]
+    fn unknown_ids_are_rejected() {
+        let mut payload = vec![];
+        VarInt(0x7f).encode(&mut payload);
+        assert!(play::PlayServerbound::decode(&payload).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! Everything after login.
+
+use anyhow::*;
+use glam::*;
+
+use crate::{
+    block::{block_face_direction::BlockFaceDirection, state::StateId},
+    entity::{game_mode::GameMode, movement::MoveInput, EntityType},
+};
+
+use super::{
+    chunk_data::{ChunkColumnData, Compressed},
+    codec::{Decode, Encode, VarInt},
+};
+
+/// A change of how the player moves, sent when the key is pressed or released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerCommandAction {
+    StartSneaking = 0,
+    StopSneaking = 1,
+    StartSprinting = 2,
+    StopSprinting = 3,
+}
+
+impl Encode for PlayerCommandAction {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        VarInt(*self as i32).encode(buf);
+    }
+}
+
+impl Decode for PlayerCommandAction {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        Ok(match VarInt::decode(buf)?.0 {
+            0 => PlayerCommandAction::StartSneaking,
+            1 => PlayerCommandAction::StopSneaking,
+            2 => PlayerCommandAction::StartSprinting,
+            3 => PlayerCommandAction::StopSprinting,
+            a => bail!("invalid player command {}", a),
+        })
+    }
+}
+
+#[cfg(test)]
+impl super::codec::Sample for PlayerCommandAction {
+    fn sample() -> Self {
+        PlayerCommandAction::StopSprinting
+    }
+}
+
+/// A step of breaking a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigAction {
+    Start = 0,
+    Abort = 1,
+    /// The client's break time is over.
+    Finish = 2,
+}
+
+impl Encode for DigAction {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        VarInt(*self as i32).encode(buf);
+    }
+}
+
+impl Decode for DigAction {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        Ok(match VarInt::decode(buf)?.0 {
+            0 => DigAction::Start,
+            1 => DigAction::Abort,
+            2 => DigAction::Finish,
+            a => bail!("invalid dig action {}", a),
+        })
+    }
+}
+
+#[cfg(test)]
+impl super::codec::Sample for DigAction {
+    fn sample() -> Self {
+        DigAction::Finish
+    }
+}
+
+/// Faces go in Minecraft's order: down, up, north, south, west, east.
+impl Encode for BlockFaceDirection {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let id = match self {
+            BlockFaceDirection::YN => 0u8,
+            BlockFaceDirection::YP => 1,
+            BlockFaceDirection::ZN => 2,
+            BlockFaceDirection::ZP => 3,
+            BlockFaceDirection::XN => 4,
+            BlockFaceDirection::XP => 5,
+        };
+        id.encode(buf);
+    }
+}
+
+impl Decode for BlockFaceDirection {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        Ok(match u8::decode(buf)? {
+            0 => BlockFaceDirection::YN,
+            1 => BlockFaceDirection::YP,
+            2 => BlockFaceDirection::ZN,
+            3 => BlockFaceDirection::ZP,
+            4 => BlockFaceDirection::XN,
+            5 => BlockFaceDirection::XP,
+            f => bail!("invalid face {}", f),
+        })
+    }
+}
+
+#[cfg(test)]
+impl super::codec::Sample for BlockFaceDirection {
+    fn sample() -> Self {
+        BlockFaceDirection::ZN
+    }
+}
+
+/// One block of a [`PlayClientbound::MultiBlockChange`], sent as a single
+/// `VarInt(block << 12 | x << 8 | z << 4 | y)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockChange {
+    /// Position in the section, 0 to 15 on each axis.
+    pub local: IVec3,
+    /// State id, see `block/state.rs`.
+    pub block: StateId,
+}
+
+impl Encode for BlockChange {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let l = self.local & 0xf;
+        VarInt(((self.block as i32) << 12) | (l.x << 8) | (l.z << 4) | l.y).encode(buf);
+    }
+}
+
+impl Decode for BlockChange {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        let v = VarInt::decode(buf)?.0;
+        if v < 0 {
+            bail!("invalid block change {}", v);
+        }
+        Ok(Self {
+            local: ivec3((v >> 8) & 0xf, v & 0xf, (v >> 4) & 0xf),
+            block: (v >> 12) as StateId,
+        })
+    }
+}
+
+#[cfg(test)]
+impl super::codec::Sample for BlockChange {
+    fn sample() -> Self {
+        BlockChange {
+            local: ivec3(15, 0, 7),
+            block: 2,
+        }
+    }
+}
+
+/// A rotation in 256ths of a turn, how entity rotations are sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Angle(pub u8);
+
+impl Angle {
+    pub fn from_radians(radians: f32) -> Self {
+        Angle((radians / std::f32::consts::TAU * 256.0).round() as i32 as u8)
+    }
+
+    /// Between -pi and pi, so pitches come back as they were.
+    pub fn to_radians(self) -> f32 {
+        self.0 as i8 as f32 / 256.0 * std::f32::consts::TAU
+    }
+}
+
+impl Encode for Angle {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.0.encode(buf);
+    }
+}
+
+impl Decode for Angle {
+    fn decode(buf: &mut &[u8]) -> Result<Self> {
+        Ok(Angle(u8::decode(buf)?))
+    }
+}
+
+#[cfg(test)]
+impl super::codec::Sample for Angle {
+    fn sample() -> Self {
+        Angle(200)
+    }
+}
+
+packets! {
+    pub enum PlayServerbound(Play, Serverbound) {
+        /// Answer to the server's keep alive, with the same id.
+        0x00 => KeepAlive { id: i64 },
+        0x02 => PlayerCommand { action: PlayerCommandAction },
+        /// The keys held for one tick, numbered so the server can say which it applied.
+        0x03 => PlayerInput { sequence: VarInt, input: MoveInput },
+        /// Breaking the block at `pos`, clicked on `face`.
+        0x04 => PlayerDig { action: DigAction, pos: IVec3, face: BlockFaceDirection },
+        /// Place `block` against `face` of the block at `pos`. Players hold no items yet, so
+        /// the client says which block.
+        0x05 => PlaceBlock { pos: IVec3, face: BlockFaceDirection, block: VarInt },
+    }
+}
+
+packets! {
+    pub enum PlayClientbound(Play, Clientbound) {
+        /// Sent every few seconds, a client that doesn't answer is dropped.
+        0x00 => KeepAlive { id: i64 },
+        0x01 => Disconnect { reason: String },
+        /// A block changed, in world coords, to the block state with this id.
+        0x02 => BlockUpdate { pos: IVec3, block: VarInt },
+        /// Move the player, on join and whenever the server overrides the client.
+        0x03 => PlayerPosition { position: Vec3, yaw: f32, pitch: f32 },
+        /// A whole column with its light and biomes, see chunk_data.rs.
+        0x04 => ChunkData { column: IVec2, data: Compressed<ChunkColumnData> },
+        /// The client may forget every section of this column.
+        0x05 => UnloadChunk { column: IVec2 },
+        /// Several blocks of one section changed in the same tick.
+        0x06 => MultiBlockChange { section: IVec3, changes: Vec<BlockChange> },
+        /// An entity came into view. Positions of entities are multiples of 1/4096 block, the
+        /// moves after this one are relative to it.
+        0x07 => SpawnEntity { id: VarInt, uuid: u128, kind: EntityType, position: DVec3, yaw: Angle, pitch: Angle },
+        0x08 => DestroyEntities { ids: Vec<VarInt> },
+        /// Moved by this many 1/4096 blocks since the last position sent.
+        0x09 => EntityMove { id: VarInt, dx: i16, dy: i16, dz: i16 },
+        0x0a => EntityMoveRotate { id: VarInt, dx: i16, dy: i16, dz: i16, yaw: Angle, pitch: Angle },
+        0x0b => EntityRotate { id: VarInt, yaw: Angle, pitch: Angle },
+        /// Sent instead of a move when it doesn't fit, and every so often to fix any drift.
+        0x0c => EntityTeleport { id: VarInt, position: DVec3, yaw: Angle, pitch: Angle },
+        /// Where the player is after the inputs up to `sequence`, sent on the ticks inputs
+        /// arrived. The client starts again from here with the inputs it sent after.
+        0x0d => PlayerState { sequence: VarInt, position: Vec3, velocity: Vec3, on_ground: bool },
+        /// The player's game mode and, for adventure, the registry ids of the blocks it may
+        /// break and place against. Sent on join and on every change.
+        0x0e => Abilities { game_mode: GameMode, can_destroy: Vec<VarInt>, can_place_on: Vec<VarInt> },
+        /// Sent when the player's health or hunger changed.
+        0x0f => UpdateHealth { health: f32, food: VarInt, saturation: f32 },
+    }
+}
+
+/// Entity positions go in multiples of 1/4096 block.
+pub fn quantize_position(position: DVec3) -> I64Vec3 {
+    (position * 4096.0).round().as_i64vec3()
+}
+
+pub fn dequantize_position(position: I64Vec3) -> DVec3 {
+    position.as_dvec3() / 4096.0
+}
+
+/// Group the changes of one section, one [`PlayClientbound::BlockUpdate`] or a
+/// [`PlayClientbound::MultiBlockChange`] for all of them.
+pub fn section_changes(section: IVec3, mut changes: Vec<BlockChange>) -> PlayClientbound {
+    match changes.len() {
+        1 => {
+            let change = changes.pop().unwrap();
+            PlayClientbound::BlockUpdate {
+                pos: section * 16 + change.local,
+                block: VarInt(change.block as i32),
+            }
+        }
+        _ => PlayClientbound::MultiBlockChange { section, changes },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_changes_pack_into_one_varint() {
+        let change = BlockChange {
+            local: ivec3(15, 0, 7),
+            block: 5,
+        };
+        let mut buf = vec![];
+        change.encode(&mut buf);
+        assert_eq!(buf.len(), 3);
+        assert_eq!(BlockChange::decode(&mut &buf[..]).unwrap(), change);
+    }
+
+    #[test]
+    fn angles_wrap() {
+        assert_eq!(Angle::from_radians(0.0), Angle(0));
+        assert_eq!(Angle::from_radians(std::f32::consts::TAU), Angle(0));
+        assert_eq!(
+            Angle::from_radians(-std::f32::consts::FRAC_PI_2),
+            Angle(192)
+        );
+        let pitch = Angle::from_radians(-1.0).to_radians();
+        assert!((pitch + 1.0).abs() < 0.02, "{}", pitch);
+    }
+
+    #[test]
+    fn one_change_is_a_block_update() {
+        let change = |x| BlockChange {
+            local: ivec3(x, 2, 3),
+            block: 1,
+        };
+        assert_eq!(
+            section_changes(ivec3(-1, 4, 0), vec![change(1)]),
+            PlayClientbound::BlockUpdate {
+                pos: ivec3(-15, 66, 3),
+                block: VarInt(1)
+            }
+        );
+        assert!(matches!(
+            section_changes(ivec3(-1, 4, 0), vec![change(1), change(2)]),
+            PlayClientbound::MultiBlockChange { changes, .. } if changes.len() == 2
+        ));
+    }
+}
@@ -0,0 +1,194 @@
+//! ```text
+//! package net.minecraft.network.status
+//! class ServerStatusResponse
+//! version 1.16
+//! ```
+//!
+//! What a server list shows before joining. A client handshakes with [`NextState::Status`]
+//! instead of login, whatever protocol it speaks, so the list can say a server is too new or
+//! too old:
+//!
+//! ```text
+//! client                          server
+//! StatusRequest           ──▶
+//!                         ◀──     StatusResponse { json }     a ServerStatus
+//! Ping { payload }        ──▶
+//!                         ◀──     Pong { payload }            then the server closes
+//! ```
+//!
+//! [`NextState::Status`]: super::handshake::NextState::Status
+
+use anyhow::*;
+use serde::{Deserialize, Serialize};
+
+packets! {
+    pub enum StatusServerbound(Status, Serverbound) {
+        0x00 => StatusRequest {},
+        /// Answered with the same payload, to time the round trip.
+        0x01 => Ping { payload: i64 },
+    }
+}
+
+packets! {
+    pub enum StatusClientbound(Status, Clientbound) {
+        /// A [`ServerStatus`] as JSON.
+        0x00 => StatusResponse { json: String },
+        0x01 => Pong { payload: i64 },
+    }
+}
+
+/// Players in the sample, more is noise in a server list.
+pub const MAX_SAMPLE: usize = 12;
+/// Favicons are square PNGs of this size.
+pub const FAVICON_SIZE: u32 = 64;
+
+const FAVICON_PREFIX: &str = "data:image/png;base64,";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerStatus {
+    pub version: StatusVersion,
+    pub players: StatusPlayers,
+    /// The message of the day.
+    pub description: String,
+    /// A `data:image/png;base64,` URL, see [`favicon_url`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusVersion {
+    pub name: String,
+    pub protocol: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusPlayers {
+    pub max: u32,
+    pub online: u32,
+    /// Some of the players online.
+    #[serde(default)]
+    pub sample: Vec<PlayerSample>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerSample {
+    pub name: String,
+    /// The uuid as 32 hex digits.
+    pub id: String,
+}
+
+impl ServerStatus {
+    /// The favicon as PNG bytes, if there is one and it is valid.
+    pub fn favicon_png(&self) -> Option<Vec<u8>> {
+        let data = self.favicon.as_ref()?.strip_prefix(FAVICON_PREFIX)?;
+        base64_decode(data)
+    }
+}
+
+/// The favicon for a [`ServerStatus`], from the bytes of a [`FAVICON_SIZE`] square PNG.
+pub fn favicon_url(png: &[u8]) -> Result<String> {
+    // the size is in the IHDR chunk, which comes first
+    ensure!(
+        png.len() > 24 && png.starts_with(b"\x89PNG\r\n\x1a\n") && &png[12..16] == b"IHDR",
+        "The favicon is not a PNG"
+    );
+    let width = u32::from_be_bytes(png[16..20].try_into().unwrap());
+    let height = u32::from_be_bytes(png[20..24].try_into().unwrap());
+    ensure!(
+        width == FAVICON_SIZE && height == FAVICON_SIZE,
+        "The favicon is {}x{}, it must be {}x{}",
+        width,
+        height,
+        FAVICON_SIZE,
+        FAVICON_SIZE
+    );
+    Ok(format!("{}{}", FAVICON_PREFIX, base64_encode(png)))
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | ((*b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=').as_bytes();
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    for chunk in text.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let mut n = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let value = BASE64.iter().position(|b| b == c)? as u32;
+            n |= value << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            out.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(width.to_be_bytes());
+        png.extend(height.to_be_bytes());
+        png.extend([8, 6, 0, 0, 0]);
+        png
+    }
+
+    #[test]
+    fn base64_round_trips() {
+        assert_eq!(base64_encode(b"Man"), "TWFu");
+        assert_eq!(base64_encode(b"Ma"), "TWE=");
+        assert_eq!(base64_encode(b"M"), "TQ==");
+        for len in 0..10 {
+            let bytes: Vec<u8> = (0..len).map(|i| (i * 37 + 200) as u8).collect();
+            assert_eq!(base64_decode(&base64_encode(&bytes)).unwrap(), bytes);
+        }
+        assert_eq!(base64_decode("TQ=*"), None);
+    }
+
+    #[test]
+    fn favicons_are_small_pngs() {
+        let icon = png(64, 64);
+        let status = ServerStatus {
+            version: StatusVersion {
+                name: "Blockworld".into(),
+                protocol: 7,
+            },
+            players: StatusPlayers {
+                max: 20,
+                online: 0,
+                sample: vec![],
+            },
+            description: "A Blockworld server".into(),
+            favicon: Some(favicon_url(&icon).unwrap()),
+        };
+        let json = serde_json::to_string(&status).unwrap();
+        let parsed: ServerStatus = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.favicon_png().unwrap(), icon);
+
+        assert!(favicon_url(&png(128, 128)).is_err());
+        assert!(favicon_url(b"GIF89a, a long way from a PNG").is_err());
+    }
+}
@@ -0,0 +1,247 @@
+//! ```text
+//! package net.minecraft.server
+//! class MinecraftServer, FrameTimer
+//! version 1.16
+//! ```
+//!
+//! Game time. The world advances in ticks of 50 milliseconds, however long a tick takes to
+//! compute. [`TickClock`] says how many ticks are due: after a slow tick the next ones run back
+//! to back until the game is on time again, but past a limit the missed ticks are dropped, so
+//! a long stall doesn't turn into a long fast-forward. [`TickMetrics`] keeps how long the last
+//! ticks took.
+//!
+//! Within a tick, systems run in the order of [`TickSet`].
+
+use std::time::{Duration, Instant};
+
+use bevy_ecs::schedule::{IntoSystemSetConfigs, Schedule, SystemSet};
+
+pub const TICKS_PER_SECOND: u64 = 20;
+pub const TICK_DURATION: Duration = Duration::from_millis(1000 / TICKS_PER_SECOND);
+/// Ticks remembered by [`TickMetrics`], five seconds of them.
+const SAMPLES: usize = 100;
+
+/// The phases of a tick, run in this order.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TickSet {
+    /// Apply what players asked for since the last tick.
+    Input,
+    /// Move entities.
+    Physics,
+    /// Blocks that change by themselves.
+    BlockTicks,
+    /// Entities deciding what to do next.
+    EntityAi,
+    /// Tell players what changed. The tick loop sends chunks and entity moves right after.
+    NetworkSend,
+}
+
+/// A schedule with the [`TickSet`]s in order.
+pub fn tick_schedule() -> Schedule {
+    let mut schedule = Schedule::default();
+    schedule.configure_sets(
+        (
+            TickSet::Input,
+            TickSet::Physics,
+            TickSet::BlockTicks,
+            TickSet::EntityAi,
+            TickSet::NetworkSend,
+        )
+            .chain(),
+    );
+    schedule
+}
+
+/// Ticks to run now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Due {
+    pub run: u32,
+    /// Ticks dropped to get back on time.
+    pub skipped: u32,
+}
+
+/// Fixed time step over a real clock.
+#[derive(Debug, Clone)]
+pub struct TickClock {
+    /// When the next tick is due.
+    next: Instant,
+    /// Ticks that may be due at once, more are skipped.
+    max_due: u32,
+}
+
+impl TickClock {
+    /// A clock whose first tick is due at `now`.
+    pub fn new(now: Instant, max_due: u32) -> Self {
+        Self {
+            next: now,
+            max_due: max_due.max(1),
+        }
+    }
+
+    /// The ticks due at `now`, which are then taken to have run.
+    pub fn due(&mut self, now: Instant) -> Due {
+        if now < self.next {
+            return Due::default();
+        }
+        let behind = ((now - self.next).as_nanos() / TICK_DURATION.as_nanos()) as u32 + 1;
+        let run = behind.min(self.max_due);
+        self.next += TICK_DURATION * run;
+        let skipped = behind - run;
+        if skipped > 0 {
+            self.next = now + TICK_DURATION;
+        }
+        Due { run, skipped }
+    }
+
+    /// How long until the next tick is due.
+    pub fn until_next(&self, now: Instant) -> Duration {
+        self.next.saturating_duration_since(now)
+    }
+
+    /// How far `now` is between the last tick and the next, from 0 to 1.
+    pub fn partial(&self, now: Instant) -> f32 {
+        1.0 - self.until_next(now).as_secs_f32() / TICK_DURATION.as_secs_f32()
+    }
+}
+
+/// How long ticks took.
+#[derive(Debug, Clone)]
+pub struct TickMetrics {
+    ticks: u64,
+    times: [Duration; SAMPLES],
+}
+
+impl Default for TickMetrics {
+    fn default() -> Self {
+        Self {
+            ticks: 0,
+            times: [Duration::ZERO; SAMPLES],
+        }
+    }
+}
+
+impl TickMetrics {
+    pub fn record(&mut self, time: Duration) {
+        self.times[(self.ticks % SAMPLES as u64) as usize] = time;
+        self.ticks += 1;
+    }
+
+    /// Ticks run so far.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    fn samples(&self) -> &[Duration] {
+        &self.times[..(self.ticks as usize).min(SAMPLES)]
+    }
+
+    /// Milliseconds per tick, on average over the last ticks.
+    pub fn mspt(&self) -> f64 {
+        let samples = self.samples();
+        if samples.is_empty() {
+            return 0.0;
+        }
+        samples.iter().sum::<Duration>().as_secs_f64() * 1000.0 / samples.len() as f64
+    }
+
+    /// The slowest of the last ticks, in milliseconds.
+    pub fn max_mspt(&self) -> f64 {
+        let max = self.samples().iter().max().copied().unwrap_or_default();
+        max.as_secs_f64() * 1000.0
+    }
+
+    /// Ticks per second the game keeps up with, at most [`TICKS_PER_SECOND`].
+    pub fn tps(&self) -> f64 {
+        let budget = TICK_DURATION.as_secs_f64() * 1000.0;
+        TICKS_PER_SECOND as f64 * (budget / self.mspt().max(budget))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::system::{ResMut, Resource};
+
+    use super::*;
+    use crate::Blockworld;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn ticks_keep_time() {
+        let start = Instant::now();
+        let mut clock = TickClock::new(start, 10);
+        assert_eq!(clock.due(start).run, 1);
+        assert_eq!(clock.due(start + 10 * MS).run, 0);
+        assert_eq!(clock.until_next(start + 10 * MS), 40 * MS);
+        assert!((clock.partial(start + 10 * MS) - 0.2).abs() < 1e-6);
+        // a slow tick, the next ones catch up
+        assert_eq!(clock.due(start + 120 * MS).run, 2);
+        assert_eq!(clock.due(start + 140 * MS).run, 0);
+        assert_eq!(clock.due(start + 150 * MS).run, 1);
+        // a stall, the ticks beyond the limit are dropped
+        let stalled = start + 2 * TICK_DURATION + Duration::from_secs(2);
+        assert_eq!(
+            clock.due(stalled),
+            Due {
+                run: 10,
+                skipped: 29
+            }
+        );
+        assert_eq!(clock.until_next(stalled), TICK_DURATION);
+    }
+
+    #[test]
+    fn metrics_average_the_last_ticks() {
+        let mut metrics = TickMetrics::default();
+        assert_eq!(metrics.mspt(), 0.0);
+        assert_eq!(metrics.tps(), 20.0);
+        for _ in 0..SAMPLES {
+            metrics.record(100 * MS);
+        }
+        assert_eq!((metrics.mspt(), metrics.tps()), (100.0, 10.0));
+        // the slow ones age out
+        for _ in 0..SAMPLES - 1 {
+            metrics.record(10 * MS);
+        }
+        metrics.record(20 * MS);
+        assert!((metrics.mspt() - 10.1).abs() < 1e-9);
+        assert_eq!(metrics.max_mspt(), 20.0);
+        assert_eq!(metrics.tps(), 20.0);
+        assert_eq!(metrics.ticks(), 2 * SAMPLES as u64);
+    }
+
+    #[derive(Resource, Default)]
+    struct Ran(Vec<TickSet>);
+
+    #[test]
+    fn sets_run_in_order() {
+        let mut world = Blockworld::new();
+        world.ecs_mut().init_resource::<Ran>();
+        world.add_systems(TickSet::NetworkSend, |mut ran: ResMut<Ran>| {
+            ran.0.push(TickSet::NetworkSend)
+        });
+        world.add_systems(TickSet::EntityAi, |mut ran: ResMut<Ran>| {
+            ran.0.push(TickSet::EntityAi)
+        });
+        world.add_systems(TickSet::Input, |mut ran: ResMut<Ran>| {
+            ran.0.push(TickSet::Input)
+        });
+        world.add_systems(TickSet::Physics, |mut ran: ResMut<Ran>| {
+            ran.0.push(TickSet::Physics)
+        });
+        world.add_systems(TickSet::BlockTicks, |mut ran: ResMut<Ran>| {
+            ran.0.push(TickSet::BlockTicks)
+        });
+
+        world.step(2);
+        let order = [
+            TickSet::Input,
+            TickSet::Physics,
+            TickSet::BlockTicks,
+            TickSet::EntityAi,
+            TickSet::NetworkSend,
+        ];
+        assert_eq!(world.ecs().resource::<Ran>().0, [order, order].concat());
+        assert_eq!(world.level().time, 2);
+    }
+}
@@ -0,0 +1,258 @@
+//! ```text
+//! package net.minecraft.world
+//! class ServerTickList, NextTickListEntry, server.ServerWorld (tickChunk)
+//! version 1.16
+//! ```
+//!
+//! Blocks that change by themselves. A block schedules a tick for itself some ticks ahead, with
+//! a priority; each section keeps its own queue, saved with its chunk. Every tick the due ticks
+//! run in the order they are due, lower priorities first, then in the order they were
+//! scheduled, and a tick only runs if its block is still there. Then random ticks pick
+//! `randomTickSpeed` blocks of every loaded section, and those that
+//! [`ticks_randomly`](crate::block::behaviour::BlockBehaviour::ticks_randomly) get called.
+//!
+//! Times are game times, saved as they are, since the level saves the time with them.
+
+use bevy_ecs::system::{Res, ResMut};
+use blockworld_utils::ResourceLocation;
+use glam::*;
+
+use crate::block::BLOCK_REGISTRY;
+
+use super::{
+    block_update::run_block_updates,
+    chunk::SUBCHUNK_SIZE,
+    chunk_access::{BlockPos, WorldAccess},
+    disk_chunk_access::DiskChunkArray,
+    random::WorldRandom,
+    storage::{chunk_serializer::BlockTickData, LevelData},
+};
+
+/// Scheduled ticks run in one tick at most, the rest wait for the next.
+pub const MAX_TICKS_PER_TICK: usize = 65536;
+
+/// Runs before the ticks due at the same time with higher ones. Minecraft's go from -3 to 3.
+pub type TickPriority = i32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledTick {
+    pub pos: BlockPos,
+    pub block: ResourceLocation,
+    /// Game time the tick is due at.
+    pub due: u64,
+    pub priority: TickPriority,
+    /// Scheduled before the ticks with higher numbers.
+    pub order: u64,
+}
+
+impl ScheduledTick {
+    /// The tick saved as `data`, unless its block is gone from the registry.
+    pub fn from_data(data: &BlockTickData, order: u64) -> Option<Self> {
+        let block = data.block.as_str().into();
+        if BLOCK_REGISTRY.get(&block).is_none() {
+            log::warn!("Dropping the tick of unknown block {}", data.block);
+            return None;
+        }
+        Some(Self {
+            pos: data.pos(),
+            block,
+            due: data.due,
+            priority: data.priority,
+            order,
+        })
+    }
+
+    pub fn to_data(&self) -> BlockTickData {
+        BlockTickData {
+            x: self.pos.x,
+            y: self.pos.y,
+            z: self.pos.z,
+            block: self.block.to_string(),
+            due: self.due,
+            priority: self.priority,
+        }
+    }
+
+    /// Which runs first.
+    pub fn key(&self) -> (u64, TickPriority, u64) {
+        (self.due, self.priority, self.order)
+    }
+}
+
+/// What a block's behaviour gets to change the world with.
+pub struct BlockTickContext<'a> {
+    pub chunks: &'a mut DiskChunkArray,
+    pub random: &'a mut WorldRandom,
+    /// The game time of this tick.
+    pub time: u64,
+}
+
+impl BlockTickContext<'_> {
+    /// Tick `block` at `pos` in `delay` ticks, unless it already will be.
+    pub fn schedule_tick(
+        &mut self,
+        pos: BlockPos,
+        block: &ResourceLocation,
+        delay: u64,
+        priority: TickPriority,
+    ) {
+        self.chunks
+            .schedule_tick(pos, block, self.time + delay, priority);
+    }
+}
+
+/// Run the scheduled ticks that are due, then the random ticks, with the block updates
+/// waiting before each and those they cause after.
+pub fn block_ticks(
+    mut chunks: ResMut<DiskChunkArray>,
+    level: Res<LevelData>,
+    mut random: ResMut<WorldRandom>,
+) {
+    let mut ctx = BlockTickContext {
+        chunks: &mut chunks,
+        random: &mut random,
+        time: level.time,
+    };
+    run_block_updates(&mut ctx);
+    run_scheduled_ticks(&mut ctx);
+    run_block_updates(&mut ctx);
+    run_random_ticks(&mut ctx, level.game_rules.random_tick_speed);
+    run_block_updates(&mut ctx);
+}
+
+fn run_scheduled_ticks(ctx: &mut BlockTickContext) {
+    for tick in ctx.chunks.take_due_ticks(ctx.time, MAX_TICKS_PER_TICK) {
+        if ctx.chunks.get_block(tick.pos) != tick.block {
+            continue;
+        }
+        if let Some(block) = BLOCK_REGISTRY.get(&tick.block) {
+            block.behaviour.tick(ctx, tick.pos);
+        }
+    }
+}
+
+fn run_random_ticks(ctx: &mut BlockTickContext, speed: u32) {
+    if speed == 0 {
+        return;
+    }
+    // in a fixed order, so a seed always picks the same blocks
+    let mut sections: Vec<IVec3> = ctx.chunks.chunks.keys().copied().collect();
+    sections.sort_by_key(|p| (p.x, p.z, p.y));
+    for section in sections {
+        for _ in 0..speed {
+            let bits = ctx.random.next_u32() as i32;
+            let local = ivec3(bits & 15, (bits >> 16) & 15, (bits >> 8) & 15);
+            let pos = section * SUBCHUNK_SIZE as i32 + local;
+            let behaviour = &ctx.chunks.get_state(pos).block().behaviour;
+            if behaviour.ticks_randomly() {
+                behaviour.random_tick(ctx, pos);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blockworld_utils::TempDir;
+
+    use super::*;
+    use crate::Blockworld;
+
+    #[test]
+    fn due_ticks_run_by_time_priority_and_order() {
+        let mut chunks = DiskChunkArray::new(1);
+        chunks.load_chunk(IVec3::ZERO);
+        let stone: ResourceLocation = "minecraft:stone".into();
+        let at = |x| ivec3(x, 0, 0);
+        assert!(chunks.schedule_tick(at(0), &stone, 5, 0));
+        assert!(chunks.schedule_tick(at(1), &stone, 5, -1));
+        assert!(chunks.schedule_tick(at(2), &stone, 3, 2));
+        assert!(chunks.schedule_tick(at(3), &stone, 5, 0));
+        // once is enough, and not where nothing is loaded
+        assert!(!chunks.schedule_tick(at(0), &stone, 9, 0));
+        assert!(!chunks.schedule_tick(ivec3(0, -20, 0), &stone, 5, 0));
+
+        let positions =
+            |ticks: Vec<ScheduledTick>| ticks.iter().map(|t| t.pos.x).collect::<Vec<_>>();
+        assert_eq!(positions(chunks.take_due_ticks(4, 10)), [2]);
+        assert_eq!(positions(chunks.take_due_ticks(5, 2)), [1, 0]);
+        assert_eq!(positions(chunks.take_due_ticks(5, 2)), [3]);
+        assert!(chunks.scheduled_ticks(IVec3::ZERO).is_empty());
+    }
+
+    #[test]
+    fn scheduled_ticks_are_saved_with_the_chunk() {
+        let root = TempDir::new("ticks").unwrap();
+        let sand: ResourceLocation = "minecraft:sand".into();
+        {
+            let mut world = Blockworld::open(&root).unwrap();
+            world.chunks_mut().load_chunk(ivec3(0, 4, 0));
+            world.chunks_mut().set_block(ivec3(0, 70, 0), &sand);
+            world
+                .chunks_mut()
+                .set_block(ivec3(0, 64, 0), &"minecraft:stone".into());
+            let due = world.level().time + 10;
+            world
+                .chunks_mut()
+                .schedule_tick(ivec3(0, 70, 0), &sand, due, 0);
+            world.step(5);
+            world.save().unwrap();
+        }
+
+        let mut world = Blockworld::open(&root).unwrap();
+        world.chunks_mut().load_chunk(ivec3(0, 4, 0));
+        assert_eq!(world.chunks().scheduled_ticks(ivec3(0, 4, 0)).len(), 1);
+        world.step(5);
+        assert_eq!(world.chunks().get_block(ivec3(0, 70, 0)), sand);
+        world.step(1);
+        assert!(world.chunks().is_air(ivec3(0, 70, 0)));
+        // falling, until the stone
+        world.step(30);
+        assert_eq!(world.chunks().get_block(ivec3(0, 65, 0)), sand);
+        assert!(world.chunks().scheduled_ticks(ivec3(0, 4, 0)).is_empty());
+    }
+
+    /// A layer of dirt with grass in the middle and a covered grass block in a corner, after
+    /// `ticks` ticks of random ticks drawn from `seed`.
+    fn meadow(seed: u64, speed: u32, ticks: u32) -> Blockworld {
+        let mut world = Blockworld::new();
+        world.ecs_mut().insert_resource(WorldRandom::new(seed));
+        world.level_mut().game_rules.random_tick_speed = speed;
+        let chunks = world.chunks_mut();
+        chunks.load_chunk(ivec3(0, 4, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                chunks.set_block(ivec3(x, 64, z), &"minecraft:dirt".into());
+            }
+        }
+        chunks.set_block(ivec3(8, 64, 8), &"minecraft:grass_block".into());
+        chunks.set_block(ivec3(1, 64, 1), &"minecraft:grass_block".into());
+        chunks.set_block(ivec3(1, 65, 1), &"minecraft:stone".into());
+        world.step(ticks);
+        world
+    }
+
+    fn grass(world: &Blockworld) -> Vec<IVec2> {
+        let mut found = vec![];
+        for x in 0..16 {
+            for z in 0..16 {
+                if world.chunks().get_block(ivec3(x, 64, z)) == "minecraft:grass_block".into() {
+                    found.push(ivec2(x, z));
+                }
+            }
+        }
+        found
+    }
+
+    #[test]
+    fn random_ticks_follow_the_seed() {
+        let spread = grass(&meadow(7, 200, 100));
+        assert_eq!(spread, grass(&meadow(7, 200, 100)));
+        assert_ne!(spread, grass(&meadow(8, 200, 100)));
+        assert!(spread.len() > 10, "{:?}", spread);
+        // grass under the stone died instead
+        assert!(!spread.contains(&ivec2(1, 1)));
+
+        assert_eq!(grass(&meadow(7, 0, 100)), [ivec2(1, 1), ivec2(8, 8)]);
+    }
+}
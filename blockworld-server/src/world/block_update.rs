@@ -0,0 +1,346 @@
+//! ```text
+//! package net.minecraft.world
+//! class World (setBlock, updateNeighborsAt), block.AbstractBlock (updateNeighbourShapes)
+//! version 1.16
+//! ```
+//!
+//! What setting a block does to the block itself and those around it. The block that was
+//! there hears it was [removed](crate::block::behaviour::BlockBehaviour::on_remove) and the
+//! new one that it was [placed](crate::block::behaviour::BlockBehaviour::on_place). Then with
+//! [`UpdateFlag::Notify`] each of the six neighbours hears that it changed through
+//! [`neighbor_changed`](crate::block::behaviour::BlockBehaviour::neighbor_changed), and
+//! whatever the flags each neighbour gets a shape update, where it may
+//! [`update_shape`](crate::block::behaviour::BlockBehaviour::update_shape) to suit the new
+//! block, as a fence connects to it. A neighbour that changes shape sets off shape updates of
+//! its own, without notifying.
+//!
+//! Updates don't run inside the set: they wait in a queue of the chunk array and run in the
+//! order they came, every tick before and after the block ticks. Each counts how many updates
+//! led to it, and those more than [`MAX_UPDATE_DEPTH`] deep are dropped, so blocks that keep
+//! changing each other stop instead of running forever.
+
+use std::collections::VecDeque;
+
+use enumflags2::BitFlags;
+
+use crate::block::{block_face_direction::BlockFaceDirection, state::BlockState};
+
+use super::{
+    block_tick::BlockTickContext,
+    chunk_access::{BlockPos, UpdateFlag, WorldAccess},
+};
+
+/// Updates deeper than this are dropped, Minecraft's limit for shape updates.
+pub const MAX_UPDATE_DEPTH: u32 = 512;
+/// Updates run at most in a row, the rest are dropped.
+pub const MAX_CHAINED_UPDATES: usize = 1_000_000;
+
+/// The order neighbours are notified in: west, east, down, up, north, south.
+pub const NEIGHBOUR_ORDER: [BlockFaceDirection; 6] = [
+    BlockFaceDirection::XN,
+    BlockFaceDirection::XP,
+    BlockFaceDirection::YN,
+    BlockFaceDirection::YP,
+    BlockFaceDirection::ZN,
+    BlockFaceDirection::ZP,
+];
+
+/// The order neighbours update their shape in: west, east, north, south, down, up.
+pub const SHAPE_ORDER: [BlockFaceDirection; 6] = [
+    BlockFaceDirection::XN,
+    BlockFaceDirection::XP,
+    BlockFaceDirection::ZN,
+    BlockFaceDirection::ZP,
+    BlockFaceDirection::YN,
+    BlockFaceDirection::YP,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockUpdate {
+    /// The block at `pos` was set, in place of `old`.
+    Set { pos: BlockPos, old: BlockState },
+    /// The block at `from`, next to `pos`, changed.
+    Neighbour { pos: BlockPos, from: BlockPos },
+    /// The block towards `direction` of `pos` changed. The flags are those of the set that
+    /// changed it.
+    Shape {
+        pos: BlockPos,
+        direction: BlockFaceDirection,
+        flags: BitFlags<UpdateFlag>,
+    },
+}
+
+/// Block updates waiting to run, with how deep each is.
+#[derive(Debug, Default)]
+pub struct UpdateQueue {
+    queue: VecDeque<(BlockUpdate, u32)>,
+    /// Depth of the updates caused by the one running.
+    depth: u32,
+}
+
+impl UpdateQueue {
+    /// Queue what setting the block at `pos`, where `old` was, with `flags` sets off.
+    pub fn block_changed(&mut self, pos: BlockPos, old: BlockState, flags: BitFlags<UpdateFlag>) {
+        self.push(BlockUpdate::Set { pos, old });
+        if flags.contains(UpdateFlag::Notify) {
+            self.notify_neighbours(pos, None);
+        }
+        for direction in SHAPE_ORDER {
+            self.push(BlockUpdate::Shape {
+                pos: pos + direction.to_vec(),
+                direction: direction.opposite(),
+                flags,
+            });
+        }
+    }
+
+    /// Tell the neighbours of `pos` it changed, all but the one towards `except`.
+    pub fn notify_neighbours(&mut self, pos: BlockPos, except: Option<BlockFaceDirection>) {
+        for direction in NEIGHBOUR_ORDER {
+            if Some(direction) != except {
+                self.push(BlockUpdate::Neighbour {
+                    pos: pos + direction.to_vec(),
+                    from: pos,
+                });
+            }
+        }
+    }
+
+    /// Queue `update` behind the others, unless it's too deep. True if it was queued.
+    pub fn push(&mut self, update: BlockUpdate) -> bool {
+        if self.depth >= MAX_UPDATE_DEPTH {
+            log::debug!("Dropping {:?}, {} updates deep", update, self.depth);
+            return false;
+        }
+        self.queue.push_back((update, self.depth));
+        true
+    }
+
+    /// The next update to run. What it causes goes a level deeper.
+    pub fn pop(&mut self) -> Option<BlockUpdate> {
+        match self.queue.pop_front() {
+            Some((update, depth)) => {
+                self.depth = depth + 1;
+                Some(update)
+            }
+            None => {
+                self.depth = 0;
+                None
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.depth = 0;
+    }
+}
+
+/// The state `state` should be in at `pos` to fit its neighbours, as a block placed there.
+pub fn placement_state<W: WorldAccess>(world: &W, pos: BlockPos, state: BlockState) -> BlockState {
+    SHAPE_ORDER.into_iter().fold(state, |state, direction| {
+        let neighbour = world.get_state(pos + direction.to_vec());
+        state
+            .block()
+            .behaviour
+            .update_shape(state, direction, neighbour)
+    })
+}
+
+/// Run the queued updates, and those they cause, until there are none left.
+pub fn run_block_updates(ctx: &mut BlockTickContext) {
+    for _ in 0..MAX_CHAINED_UPDATES {
+        let Some(update) = ctx.chunks.updates.pop() else {
+            return;
+        };
+        match update {
+            BlockUpdate::Set { pos, old } => {
+                let state = ctx.chunks.get_state(pos);
+                old.block().behaviour.on_remove(ctx, pos, old, state);
+                state.block().behaviour.on_place(ctx, pos, state, old);
+            }
+            BlockUpdate::Neighbour { pos, from } => {
+                let state = ctx.chunks.get_state(pos);
+                state
+                    .block()
+                    .behaviour
+                    .neighbor_changed(ctx, pos, state, from);
+            }
+            BlockUpdate::Shape {
+                pos,
+                direction,
+                flags,
+            } => {
+                let state = ctx.chunks.get_state(pos);
+                let behaviour = &state.block().behaviour;
+                let neighbour = ctx.chunks.get_state(pos + direction.to_vec());
+                let new = behaviour.update_shape(state, direction, neighbour);
+                if new == state {
+                    continue;
+                }
+                if new.is_air() && !flags.contains(UpdateFlag::NoNeighbourDrops) {
+                    behaviour.spawn_drops(ctx, pos, state);
+                }
+                ctx.chunks
+                    .set_state_with(pos, new, flags & UpdateFlag::NoRerender);
+            }
+        }
+    }
+    log::warn!(
+        "Dropping {} block updates after {} in a row",
+        ctx.chunks.updates.len(),
+        MAX_CHAINED_UPDATES
+    );
+    ctx.chunks.updates.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+
+    use super::*;
+    use crate::{
+        block::behaviour::FALL_DELAY, world::disk_chunk_access::DiskChunkArray, Blockworld,
+    };
+
+    #[test]
+    fn chains_stop_at_the_depth_limit() {
+        let mut queue = UpdateQueue::default();
+        let update = BlockUpdate::Neighbour {
+            pos: IVec3::ZERO,
+            from: IVec3::X,
+        };
+        assert!(queue.push(update));
+        // every update causes another, like two blocks toggling each other
+        let mut ran = 0;
+        while let Some(update) = queue.pop() {
+            ran += 1;
+            queue.push(update);
+        }
+        assert_eq!(ran, MAX_UPDATE_DEPTH);
+        // and the next chain starts from the top again
+        assert!(queue.push(update));
+    }
+
+    #[test]
+    fn sets_queue_neighbours_in_order() {
+        let mut chunks = DiskChunkArray::new(1);
+        chunks.load_chunk(IVec3::ZERO);
+        let at = ivec3(5, 5, 5);
+        chunks.set_block(at, &"minecraft:stone".into());
+        let mut notified = vec![];
+        let mut shaped = vec![];
+        assert_eq!(
+            chunks.updates.pop(),
+            Some(BlockUpdate::Set {
+                pos: at,
+                old: BlockState::AIR
+            })
+        );
+        while let Some(update) = chunks.updates.pop() {
+            match update {
+                BlockUpdate::Set { .. } => panic!("set twice"),
+                BlockUpdate::Neighbour { pos, from } => {
+                    assert_eq!(from, at);
+                    notified.push(pos - at);
+                }
+                BlockUpdate::Shape { pos, direction, .. } => {
+                    assert_eq!(pos + direction.to_vec(), at);
+                    shaped.push(pos - at);
+                }
+            }
+        }
+        let offsets = |order: [BlockFaceDirection; 6]| order.map(|d| d.to_vec()).to_vec();
+        assert_eq!(notified, offsets(NEIGHBOUR_ORDER));
+        assert_eq!(shaped, offsets(SHAPE_ORDER));
+
+        // setting what's there is no change, and without Notify only shapes update
+        chunks.set_block(at, &"minecraft:stone".into());
+        assert!(chunks.updates.is_empty());
+        chunks.set_state_with(at, BlockState::AIR, BitFlags::empty());
+        assert_eq!(chunks.updates.len(), 7);
+    }
+
+    /// Place `block` as a player would, fitting its neighbours.
+    fn place(world: &mut Blockworld, pos: IVec3, block: &str) {
+        let state = placement_state(world.chunks(), pos, BlockState::of(&block.into()));
+        world.chunks_mut().set_state(pos, state);
+    }
+
+    fn state(world: &Blockworld, pos: IVec3) -> String {
+        world.chunks().get_state(pos).to_string()
+    }
+
+    #[test]
+    fn fences_connect_to_what_is_beside_them() {
+        let mut world = Blockworld::new();
+        world.chunks_mut().load_chunk(ivec3(0, 4, 0));
+        place(&mut world, ivec3(1, 65, 0), "minecraft:stone");
+        place(&mut world, ivec3(1, 65, 1), "minecraft:oak_fence");
+        place(&mut world, ivec3(2, 65, 1), "minecraft:oak_fence");
+        place(&mut world, ivec3(0, 65, 1), "minecraft:cobblestone_wall");
+        // slabs aren't full blocks
+        place(&mut world, ivec3(1, 65, 2), "minecraft:smooth_stone_slab");
+        world.step(1);
+        assert_eq!(
+            state(&world, ivec3(1, 65, 1)),
+            "minecraft:oak_fence[north=true,east=true,south=false,west=false]"
+        );
+        assert_eq!(
+            state(&world, ivec3(2, 65, 1)),
+            "minecraft:oak_fence[north=false,east=false,south=false,west=true]"
+        );
+        // walls don't take fences
+        assert_eq!(
+            state(&world, ivec3(0, 65, 1)),
+            "minecraft:cobblestone_wall[north=false,east=false,south=false,west=false]"
+        );
+
+        // the fence lets go of what's taken away
+        place(&mut world, ivec3(2, 65, 1), "minecraft:air");
+        place(&mut world, ivec3(1, 65, 0), "minecraft:air");
+        world.step(1);
+        assert_eq!(
+            world.chunks().get_state(ivec3(1, 65, 1)),
+            BlockState::of(&"minecraft:oak_fence".into())
+        );
+    }
+
+    #[test]
+    fn sand_falls_once_its_support_goes() {
+        let mut world = Blockworld::new();
+        let chunks = world.chunks_mut();
+        chunks.load_chunk(ivec3(0, 4, 0));
+        chunks.set_block(ivec3(0, 64, 0), &"minecraft:stone".into());
+        chunks.set_block(ivec3(0, 66, 0), &"minecraft:dirt".into());
+        chunks.set_block(ivec3(0, 67, 0), &"minecraft:sand".into());
+        world.step(10);
+        assert_eq!(
+            world.chunks().get_block(ivec3(0, 67, 0)),
+            "minecraft:sand".into()
+        );
+
+        world
+            .chunks_mut()
+            .set_block(ivec3(0, 66, 0), &"minecraft:air".into());
+        world.step(1);
+        assert!(world
+            .chunks()
+            .is_tick_scheduled(ivec3(0, 67, 0), &"minecraft:sand".into()));
+        // it falls as an entity, and becomes sand again where it lands
+        world.step(FALL_DELAY as u32 + 20);
+        assert_eq!(
+            world.chunks().get_block(ivec3(0, 65, 0)),
+            "minecraft:sand".into()
+        );
+        assert!(world.chunks().is_air(ivec3(0, 67, 0)));
+    }
+}
@@ -8,7 +8,7 @@ use blockworld_utils::ResourceLocation;
 use enumflags2::{BitFlag, BitFlags};
 use glam::*;
 
-use crate::block::*;
+use crate::block::state::{BlockState, StateId};
 
 pub const SUBCHUNK_SIZE: usize = 16;
 pub const SUBCHUNK_BLOCK_NUM: usize = SUBCHUNK_SIZE * SUBCHUNK_SIZE * SUBCHUNK_SIZE;
@@ -63,10 +63,9 @@ impl SubChunk {
         self.pos
     }
 
+    /// Set the default state of the block called `block_id`.
     pub fn set_blockid(&mut self, pos: IVec3, block_id: &str) {
-        let (x, y, z) = (pos.x, pos.y, pos.z);
-        let number_id = BLOCK_REGISTRY.name_to_number_id(&block_id.into());
-        self.blocks[Self::index(x, y, z)] = number_id;
+        self.set_state(pos, BlockState::of(&block_id.into()));
     }
 
     pub fn remove_block(&mut self, pos: IVec3) {
@@ -74,13 +73,31 @@ impl SubChunk {
         self.blocks[Self::index(x, y, z)] = 0;
     }
 
+    /// Raw state id at an index of the block array, see [`SubChunk::index`].
+    pub fn get_state_id(&self, index: usize) -> StateId {
+        self.blocks[index]
+    }
+
+    pub fn set_state_id(&mut self, index: usize, id: StateId) {
+        self.blocks[index] = id;
+    }
+
+    /// True if every block is air (state id 0), so the section needn't be saved.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.iter().all(|b| *b == 0)
+    }
+
+    pub fn get_state(&self, pos: IVec3) -> BlockState {
+        let id = self.blocks[Self::index(pos.x, pos.y, pos.z)];
+        BlockState::from_id(id).unwrap_or(BlockState::AIR)
+    }
+
+    pub fn set_state(&mut self, pos: IVec3, state: BlockState) {
+        self.blocks[Self::index(pos.x, pos.y, pos.z)] = state.0;
+    }
+
     pub fn get_blockid(&self, pos: IVec3) -> &'static str {
-        let (x, y, z) = (pos.x, pos.y, pos.z);
-        if let Some(r) = BLOCK_REGISTRY.number_id_to_name(self.blocks[Self::index(x, y, z)]) {
-            r
-        } else {
-            "minecraft:air"
-        }
+        self.get_state(pos).name()
     }
 }
 
@@ -1,9 +1,36 @@
 use std::slice::Iter;
 
 use blockworld_utils::ResourceLocation;
-use glam::IVec3;
+use enumflags2::{bitflags, make_bitflags, BitFlags};
+use glam::{IVec3, Vec3};
 
-use crate::{packet::Packet, world::chunk::SubChunk};
+use crate::{
+    block::state::BlockState,
+    packet::play::PlayClientbound,
+    world::{
+        chunk::SubChunk,
+        raycast::{self, BlockHit, RayContext},
+    },
+};
+
+/// Block coords, as opposed to chunk coords.
+pub type BlockPos = IVec3;
+
+/// What else happens when a block is set, Minecraft's `Constants.BlockFlags`.
+#[bitflags]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateFlag {
+    /// Tell the six neighbours it changed, see `block_update.rs`. Shape updates go out anyway.
+    Notify = 0b001,
+    /// Don't redraw its section.
+    NoRerender = 0b010,
+    /// Neighbours broken by the shape updates don't drop anything.
+    NoNeighbourDrops = 0b100,
+}
+
+/// The flags [`WorldAccess::set_block`] uses.
+pub const DEFAULT_UPDATE: BitFlags<UpdateFlag> = make_bitflags!(UpdateFlag::Notify);
 
 // readonly
 // if you need to modify the chunk, you need to send a packet to the server
@@ -19,12 +46,43 @@ pub trait WorldAccess {
 
     fn need_rerender(&self, pos: IVec3) -> bool;
 
-    fn update(&mut self, packet: Packet);
+    fn update(&mut self, packet: PlayClientbound);
     fn iter_loaded_chunks(&self) -> impl Iterator<Item = &SubChunk>;
 
     // block coord
-    fn is_air(&self, pos: IVec3) -> bool;
+    fn get_state(&self, pos: IVec3) -> BlockState;
+    /// Set the state at `pos`, if its section is loaded. Setting the state that's there does
+    /// nothing.
+    fn set_state_with(&mut self, pos: IVec3, state: BlockState, flags: BitFlags<UpdateFlag>);
+
+    fn set_state(&mut self, pos: IVec3, state: BlockState) {
+        self.set_state_with(pos, state, DEFAULT_UPDATE);
+    }
+
+    fn is_air(&self, pos: IVec3) -> bool {
+        self.get_state(pos).is_air()
+    }
+
+    fn get_block(&self, pos: IVec3) -> ResourceLocation {
+        self.get_state(pos).name().clone()
+    }
+
+    /// Set the default state of the block `id`.
+    fn set_block(&mut self, pos: IVec3, id: &ResourceLocation) {
+        self.set_state(pos, BlockState::of(id));
+    }
 
-    fn get_block(&self, pos: IVec3) -> ResourceLocation;
-    fn set_block(&mut self, pos: IVec3, id: &ResourceLocation);
+    /// The first block the ray from `origin` towards `direction` hits within `max_distance`.
+    fn raycast(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        context: RayContext,
+    ) -> Option<BlockHit>
+    where
+        Self: Sized,
+    {
+        raycast::raycast_blocks(self, origin, direction, max_distance, context)
+    }
 }
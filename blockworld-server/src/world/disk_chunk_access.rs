@@ -1,24 +1,42 @@
 //! net/minecraft/client/multiplayer/ClientChunkProvider.java
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use anyhow::*;
+use bevy_ecs::system::Resource;
+use blockworld_utils::ResourceLocation;
+use enumflags2::BitFlags;
 use glam::*;
 
-use crate::packet::Packet;
+use crate::{
+    block::state::{BlockState, StateId},
+    packet::play::PlayClientbound,
+};
 
-use super::{chunk::SubChunk, chunk_access::WorldAccess};
+use super::{
+    block_tick::{ScheduledTick, TickPriority},
+    block_update::UpdateQueue,
+    chunk::{SubChunk, CHUNK_HEIGHT},
+    chunk_access::{BlockPos, UpdateFlag, WorldAccess},
+    storage::{
+        chunk_serializer::{ChunkData, EntityData},
+        WorldStorage,
+    },
+};
 
 fn world_blockpos_to_chunkpos(pos: IVec3) -> (IVec3, IVec3) {
-    let x = pos.x / 16;
-    let y = pos.y / 16;
-    let z = pos.z / 16;
+    let x = pos.x.div_euclid(16);
+    let y = pos.y.div_euclid(16);
+    let z = pos.z.div_euclid(16);
     let sub_x = pos.x.rem_euclid(16);
     let sub_y = pos.y.rem_euclid(16);
     let sub_z = pos.z.rem_euclid(16);
     (IVec3::new(x, y, z), IVec3::new(sub_x, sub_y, sub_z))
 }
 
-/// The place which holds all loaded chunks
+/// The place which holds all loaded chunks. On the server it is a resource of the ECS world,
+/// so systems can look at blocks.
+#[derive(Resource)]
 pub struct DiskChunkArray {
     pub chunks: HashMap<IVec3, SubChunk>,
     /// Set this with the view distance
@@ -29,6 +47,26 @@ pub struct DiskChunkArray {
     loaded: u32,
 
     pub need_rerender: Vec<IVec3>,
+
+    /// Where chunks are loaded from and saved to. Without it chunks only live in memory.
+    storage: Option<WorldStorage>,
+    /// Chunks changed since they were loaded or last saved.
+    modified: HashSet<IVec3>,
+    /// Ticks players spent near each column since the last save.
+    inhabited_delta: HashMap<IVec2, u64>,
+    /// Blocks set since the last [`DiskChunkArray::take_changed_blocks`], in world coords.
+    changed_blocks: Vec<IVec3>,
+    /// Block ticks scheduled in each loaded sub chunk, saved with it.
+    block_ticks: HashMap<IVec3, Vec<ScheduledTick>>,
+    /// Order of the next tick scheduled.
+    next_tick_order: u64,
+    /// Updates the blocks set sent to their neighbours, run by `block_update.rs`.
+    pub(crate) updates: UpdateQueue,
+    /// Entities of each loaded sub chunk as they were last put there, saved with it. The ECS
+    /// world holds the live ones, see `entity/persistence.rs`.
+    entities: HashMap<IVec3, Vec<EntityData>>,
+    /// Entities waiting to be spawned into the ECS world.
+    pending_entities: Vec<EntityData>,
 }
 
 impl DiskChunkArray {
@@ -44,7 +82,198 @@ impl DiskChunkArray {
             center: IVec3::ZERO,
             loaded: 0,
             need_rerender: Vec::new(),
+            storage: None,
+            modified: HashSet::new(),
+            inhabited_delta: HashMap::new(),
+            changed_blocks: Vec::new(),
+            block_ticks: HashMap::new(),
+            next_tick_order: 0,
+            updates: UpdateQueue::default(),
+            entities: HashMap::new(),
+            pending_entities: Vec::new(),
+        }
+    }
+
+    /// Create a chunk array backed by a world folder.
+    pub fn with_storage(view_distance: u32, storage: WorldStorage) -> Self {
+        let mut s = Self::new(view_distance);
+        s.storage = Some(storage);
+        s
+    }
+
+    pub fn storage(&self) -> Option<&WorldStorage> {
+        self.storage.as_ref()
+    }
+
+    pub fn storage_mut(&mut self) -> Option<&mut WorldStorage> {
+        self.storage.as_mut()
+    }
+
+    /// Load the sub chunk, its block ticks and its entities from disk, or create an empty one
+    /// if it was never saved.
+    fn read_or_create(&mut self, pos: IVec3) -> SubChunk {
+        let Some(storage) = self.storage.as_mut() else {
+            return SubChunk::new(pos);
+        };
+        let column = ivec2(pos.x, pos.z);
+        let data = match storage.read_chunk(column) {
+            Result::Ok(Some(data)) => data,
+            Result::Ok(None) => return SubChunk::new(pos),
+            Err(e) => {
+                log::error!("Failed to load chunk {}: {:#}", pos, e);
+                return SubChunk::new(pos);
+            }
+        };
+        for tick in data.block_ticks(pos.y) {
+            if let Some(tick) = ScheduledTick::from_data(tick, self.next_tick_order) {
+                self.next_tick_order += 1;
+                self.block_ticks.entry(pos).or_default().push(tick);
+            }
+        }
+        let entities: Vec<EntityData> = data.entities(pos.y).cloned().collect();
+        self.pending_entities.extend(entities.iter().cloned());
+        self.entities.insert(pos, entities);
+        match data.section(pos.y).map(|s| s.to_sub_chunk(column)) {
+            Some(Result::Ok(sc)) => sc,
+            None => SubChunk::new(pos),
+            Some(Err(e)) => {
+                log::error!("Failed to load chunk {}: {:#}", pos, e);
+                SubChunk::new(pos)
+            }
+        }
+    }
+
+    /// Merge the given loaded sub chunks into their column on disk.
+    fn write_sections(&mut self, column: IVec2, sections: &[IVec3]) -> Result<()> {
+        let Some(storage) = self.storage.as_mut() else {
+            return Ok(());
+        };
+        let mut data = storage
+            .read_chunk(column)?
+            .unwrap_or_else(|| ChunkData::new(column));
+        for pos in sections {
+            if let Some(sc) = self.chunks.get(pos) {
+                data.put_section(sc);
+                let mut ticks = self.block_ticks.get(pos).cloned().unwrap_or_default();
+                ticks.sort_by_key(ScheduledTick::key);
+                data.put_block_ticks(pos.y, ticks.iter().map(ScheduledTick::to_data));
+                let entities = self.entities.get(pos).cloned().unwrap_or_default();
+                data.put_entities(pos.y, entities);
+            }
+        }
+        data.inhabited_time += self.inhabited_delta.remove(&column).unwrap_or(0);
+        storage.write_chunk(&data)
+    }
+
+    /// Count ticks a player spent near a column, saved as the column's inhabited time.
+    pub fn add_inhabited_time(&mut self, column: IVec2, ticks: u64) {
+        *self.inhabited_delta.entry(column).or_default() += ticks;
+    }
+
+    /// Put `entities` in the loaded sub chunk at `section`, to be saved with it.
+    pub fn put_entities(&mut self, section: IVec3, entities: Vec<EntityData>) {
+        if !self.is_chunk_loaded(section) {
+            return;
+        }
+        let old = self
+            .entities
+            .get(&section)
+            .map_or(&[][..], |e| e.as_slice());
+        if old != entities.as_slice() {
+            self.modified.insert(section);
         }
+        self.entities.insert(section, entities);
+    }
+
+    /// Spawn `entity` into the ECS world once the block ticks are done, for block behaviours
+    /// that can't reach it.
+    pub fn add_entity(&mut self, entity: EntityData) {
+        self.pending_entities.push(entity);
+    }
+
+    /// Entities to spawn, those of the sub chunks loaded since the last call and those added.
+    pub fn take_pending_entities(&mut self) -> Vec<EntityData> {
+        std::mem::take(&mut self.pending_entities)
+    }
+
+    /// Blocks set since the last call, so the server can tell the clients that see them.
+    pub fn take_changed_blocks(&mut self) -> Vec<IVec3> {
+        std::mem::take(&mut self.changed_blocks)
+    }
+
+    /// Tick `block` at `pos` at game time `due`, unless it already will be then or its chunk
+    /// isn't loaded. True if it was scheduled.
+    pub fn schedule_tick(
+        &mut self,
+        pos: BlockPos,
+        block: &ResourceLocation,
+        due: u64,
+        priority: TickPriority,
+    ) -> bool {
+        let (section, _) = world_blockpos_to_chunkpos(pos);
+        if !self.is_chunk_loaded(section) || self.is_tick_scheduled(pos, block) {
+            return false;
+        }
+        self.block_ticks
+            .entry(section)
+            .or_default()
+            .push(ScheduledTick {
+                pos,
+                block: block.clone(),
+                due,
+                priority,
+                order: self.next_tick_order,
+            });
+        self.next_tick_order += 1;
+        self.modified.insert(section);
+        true
+    }
+
+    pub fn is_tick_scheduled(&self, pos: BlockPos, block: &ResourceLocation) -> bool {
+        let (section, _) = world_blockpos_to_chunkpos(pos);
+        self.block_ticks
+            .get(&section)
+            .is_some_and(|ticks| ticks.iter().any(|t| t.pos == pos && t.block == *block))
+    }
+
+    /// The block ticks scheduled in the sub chunk at `section`, in no particular order.
+    pub fn scheduled_ticks(&self, section: IVec3) -> &[ScheduledTick] {
+        self.block_ticks.get(&section).map_or(&[], |t| t.as_slice())
+    }
+
+    /// Take up to `max` ticks due at `time` or before, in the order they run.
+    pub fn take_due_ticks(&mut self, time: u64, max: usize) -> Vec<ScheduledTick> {
+        let mut due = vec![];
+        for (section, ticks) in self.block_ticks.iter_mut() {
+            if ticks.iter().any(|t| t.due <= time) {
+                self.modified.insert(*section);
+                due.extend(ticks.extract_if(.., |t| t.due <= time));
+            }
+        }
+        self.block_ticks.retain(|_, ticks| !ticks.is_empty());
+        due.sort_by_key(ScheduledTick::key);
+        // those past the limit go back, to run first next time
+        for tick in due.split_off(max.min(due.len())) {
+            let (section, _) = world_blockpos_to_chunkpos(tick.pos);
+            self.block_ticks.entry(section).or_default().push(tick);
+        }
+        due
+    }
+
+    /// Save every modified chunk.
+    pub fn save_all(&mut self) -> Result<()> {
+        let mut columns: HashMap<IVec2, Vec<IVec3>> = HashMap::new();
+        for pos in self.modified.drain() {
+            columns.entry(ivec2(pos.x, pos.z)).or_default().push(pos);
+        }
+        for column in self.inhabited_delta.keys() {
+            columns.entry(*column).or_default();
+        }
+        for (column, sections) in columns {
+            self.write_sections(column, &sections)
+                .with_context(|| format!("Failed to save chunk column {}", column))?;
+        }
+        Ok(())
     }
 
     /// Check if the chunk [x, z] is in the view distance.
@@ -71,6 +300,11 @@ impl DiskChunkArray {
     pub fn recenter(&mut self, pos: IVec3) {
         self.center = pos;
     }
+
+    /// Set a block by state id, as packets carry them.
+    fn set_state_id(&mut self, pos: IVec3, id: StateId) {
+        self.set_state(pos, BlockState::from_id(id).unwrap_or_default());
+    }
 }
 
 impl WorldAccess for DiskChunkArray {
@@ -86,18 +320,24 @@ impl WorldAccess for DiskChunkArray {
         if self.chunks.get(&pos).is_none() {
             self.loaded += 1;
             self.need_rerender.push(pos);
-            let sc = SubChunk::new(pos);
+            let sc = self.read_or_create(pos);
 
             self.chunks.insert(pos, sc);
         }
     }
 
     fn unload_chunk(&mut self, pos: IVec3) {
-        self.need_rerender
-            .swap_remove(self.need_rerender.iter().position(|x| *x == pos).unwrap());
+        self.need_rerender.retain(|x| *x != pos);
+
+        if self.modified.remove(&pos) {
+            if let Err(e) = self.write_sections(ivec2(pos.x, pos.z), &[pos]) {
+                log::error!("Failed to save chunk {}: {:#}", pos, e);
+            }
+        }
 
-        if let Some(mut chunk) = self.chunks.remove(&pos) {
-            // TODO: serialize chunk to disk
+        self.block_ticks.remove(&pos);
+        self.entities.remove(&pos);
+        if self.chunks.remove(&pos).is_some() {
             self.loaded -= 1;
         } else {
             log::error!("Tried to unload non-existent chunk: {}", pos);
@@ -108,34 +348,73 @@ impl WorldAccess for DiskChunkArray {
         self.chunks.values()
     }
 
-    fn update(&mut self, packet: Packet) {
-        if let Packet::BlockUpdate(pos, id) = packet {
-            if self.is_chunk_loaded(pos) {
-                self.need_rerender.push(pos);
-                let chunk = self.chunks.get_mut(&pos).unwrap();
-                chunk.set_blockid(pos, &id);
+    fn update(&mut self, packet: PlayClientbound) {
+        match packet {
+            PlayClientbound::BlockUpdate { pos, block } => {
+                self.set_state_id(pos, block.0 as StateId);
             }
+            PlayClientbound::MultiBlockChange { section, changes } => {
+                for change in changes {
+                    self.set_state_id(section * 16 + change.local, change.block);
+                }
+            }
+            PlayClientbound::ChunkData { column, data } => {
+                for y in 0..CHUNK_HEIGHT as i32 / 16 {
+                    let pos = ivec3(column.x, y, column.y);
+                    let sc = match data.0.sub_chunk(column, y) {
+                        Result::Ok(sc) => sc.unwrap_or_else(|| SubChunk::new(pos)),
+                        Err(e) => {
+                            log::error!("Bad chunk section {}: {:#}", pos, e);
+                            continue;
+                        }
+                    };
+                    if self.chunks.insert(pos, sc).is_none() {
+                        self.loaded += 1;
+                    }
+                    self.need_rerender.push(pos);
+                }
+            }
+            PlayClientbound::UnloadChunk { column } => {
+                let sections: Vec<IVec3> = self
+                    .chunks
+                    .keys()
+                    .filter(|p| p.x == column.x && p.z == column.y)
+                    .copied()
+                    .collect();
+                for pos in sections {
+                    self.unload_chunk(pos);
+                }
+            }
+            _ => {}
         }
     }
 
-    fn is_air(&self, pos: IVec3) -> bool {
-        self.get_block(pos) == "minecraft:air".into()
-    }
-
-    fn get_block(&self, pos: IVec3) -> blockworld_utils::ResourceLocation {
+    /// Blocks in chunks that aren't loaded read as air.
+    fn get_state(&self, pos: IVec3) -> BlockState {
         let (a, b) = world_blockpos_to_chunkpos(pos);
-        self.get_chunk(a).get_blockid(b).into()
+        match self.chunks.get(&a) {
+            Some(chunk) => chunk.get_state(b),
+            None => BlockState::AIR,
+        }
     }
 
-    fn set_block(&mut self, pos: IVec3, id: &blockworld_utils::ResourceLocation) {
+    /// Queues the updates the change sends, see `block_update.rs`.
+    fn set_state_with(&mut self, pos: IVec3, state: BlockState, flags: BitFlags<UpdateFlag>) {
         let (a, b) = world_blockpos_to_chunkpos(pos);
-        if self.is_chunk_loaded(a) {
-            self.chunks
-                .get_mut(&a)
-                .unwrap()
-                .set_blockid(b, &id.to_string());
+        let Some(chunk) = self.chunks.get_mut(&a) else {
+            return;
+        };
+        let old = chunk.get_state(b);
+        if old == state {
+            return;
+        }
+        chunk.set_state(b, state);
+        if !flags.contains(UpdateFlag::NoRerender) {
             self.need_rerender.push(a);
         }
+        self.modified.insert(a);
+        self.changed_blocks.push(pos);
+        self.updates.block_changed(pos, old, flags);
     }
 
     fn need_rerender(&self, pos: IVec3) -> bool {
@@ -1,3 +1,8 @@
+pub mod block_tick;
+pub mod block_update;
 pub mod chunk;
 pub mod chunk_access;
 pub mod disk_chunk_access;
+pub mod random;
+pub mod raycast;
+pub mod storage;
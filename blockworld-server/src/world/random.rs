@@ -0,0 +1,72 @@
+//! ```text
+//! package java.util
+//! class Random
+//! version 1.16
+//! ```
+//!
+//! The 48-bit linear congruential generator Minecraft draws its world randomness from, so a
+//! world seeded the same way makes the same choices. Not for anything secret.
+
+use bevy_ecs::system::Resource;
+
+const MULTIPLIER: u64 = 0x5DEECE66D;
+const ADDEND: u64 = 0xB;
+const MASK: u64 = (1 << 48) - 1;
+
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct WorldRandom {
+    seed: u64,
+}
+
+impl WorldRandom {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed: (seed ^ MULTIPLIER) & MASK,
+        }
+    }
+
+    /// The next `bits` random bits, at most 32.
+    fn next(&mut self, bits: u32) -> u32 {
+        self.seed = self.seed.wrapping_mul(MULTIPLIER).wrapping_add(ADDEND) & MASK;
+        (self.seed >> (48 - bits)) as u32
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.next(32)
+    }
+
+    /// Uniform in `0..bound`, `bound` above 0.
+    pub fn next_int(&mut self, bound: u32) -> u32 {
+        assert!(bound > 0, "bound must be positive");
+        if bound.is_power_of_two() {
+            return ((bound as u64 * self.next(31) as u64) >> 31) as u32;
+        }
+        // drop the values past the last whole multiple of bound, so none is favoured
+        loop {
+            let bits = self.next(31);
+            let value = bits % bound;
+            if bits as i64 - value as i64 + (bound as i64 - 1) < 1 << 31 {
+                return value;
+            }
+        }
+    }
+
+    /// Uniform in `0.0..1.0`.
+    pub fn next_float(&mut self) -> f32 {
+        self.next(24) as f32 / (1 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_java() {
+        // new java.util.Random(42).nextInt(), nextInt(10), nextInt(16)
+        let mut random = WorldRandom::new(42);
+        assert_eq!(random.next_u32() as i32, -1170105035);
+        assert_eq!(random.next_int(10), 3);
+        assert_eq!(random.next_int(16), 10);
+    }
+}
@@ -0,0 +1,304 @@
+//! ```text
+//! package net.minecraft.world
+//! class IBlockReader (clip), RayTraceContext, util.math.BlockRayTraceResult
+//! version 1.16
+//! ```
+//!
+//! What a ray hits first. Blocks are walked one after the other along the ray, in the order
+//! the ray enters them (Amanatides and Woo's DDA), and each one's shape is clipped against the
+//! ray; the first hit ends the walk. Entities are found by clipping their boxes, nearest
+//! first.
+
+use bevy_ecs::{entity::Entity, world::World};
+use glam::*;
+
+use crate::{
+    block::{
+        block_face_direction::BlockFaceDirection,
+        shape::{collision_shape, fluid_shape, outline_shape, Aabb},
+    },
+    components::Position,
+    entity::EntityType,
+};
+
+use super::chunk_access::{BlockPos, WorldAccess};
+
+/// Which shape of blocks a ray stops at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlockMode {
+    /// What the player can point at.
+    #[default]
+    Outline,
+    /// What entities bump into, for line of sight.
+    Collider,
+}
+
+/// Whether a ray stops at fluids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FluidMode {
+    /// Through them.
+    #[default]
+    None,
+    /// At their surface, like a bucket does.
+    Any,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RayContext {
+    pub blocks: BlockMode,
+    pub fluids: FluidMode,
+}
+
+impl RayContext {
+    /// Blocks the player points at, through fluids.
+    pub const PICK: RayContext = RayContext {
+        blocks: BlockMode::Outline,
+        fluids: FluidMode::None,
+    };
+}
+
+/// A block a ray hit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockHit {
+    pub pos: BlockPos,
+    /// The face the ray went in through.
+    pub face: BlockFaceDirection,
+    /// Where, in world coords.
+    pub point: Vec3,
+    /// How far from the origin.
+    pub distance: f32,
+}
+
+/// An entity a ray hit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntityHit {
+    pub entity: Entity,
+    pub face: BlockFaceDirection,
+    pub point: Vec3,
+    pub distance: f32,
+}
+
+/// The nearest box of `shape`, placed at `pos`, the ray goes in.
+fn clip_shape(
+    shape: &[Aabb],
+    pos: BlockPos,
+    origin: Vec3,
+    direction: Vec3,
+) -> Option<(f32, BlockFaceDirection)> {
+    shape
+        .iter()
+        .filter_map(|b| b.offset(pos.as_vec3()).clip_ray(origin, direction))
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+/// The first block the ray from `origin` towards `direction` hits within `max_distance`.
+pub fn raycast_blocks<W: WorldAccess>(
+    world: &W,
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+    context: RayContext,
+) -> Option<BlockHit> {
+    let direction = direction.try_normalize()?;
+    let step = direction.signum().as_ivec3();
+    let mut pos = origin.floor().as_ivec3();
+    // how far along the ray the next boundary on each axis is, and between two of them
+    let mut next = Vec3::ZERO;
+    let mut delta = Vec3::ZERO;
+    for axis in 0..3 {
+        if direction[axis] == 0.0 {
+            next[axis] = f32::INFINITY;
+            delta[axis] = f32::INFINITY;
+            continue;
+        }
+        let boundary = match direction[axis] > 0.0 {
+            true => pos[axis] as f32 + 1.0,
+            false => pos[axis] as f32,
+        };
+        next[axis] = (boundary - origin[axis]) / direction[axis];
+        delta[axis] = 1.0 / direction[axis].abs();
+    }
+
+    loop {
+        let state = world.get_state(pos);
+        let shape = match context.blocks {
+            BlockMode::Outline => outline_shape(state),
+            BlockMode::Collider => collision_shape(state),
+        };
+        let fluid = match context.fluids {
+            FluidMode::None => &[][..],
+            FluidMode::Any => fluid_shape(state),
+        };
+        // the shapes are inside the block, so any hit is nearer than those of the next ones
+        let hit = [
+            clip_shape(shape, pos, origin, direction),
+            clip_shape(fluid, pos, origin, direction),
+        ]
+        .into_iter()
+        .flatten()
+        .min_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some((distance, face)) = hit {
+            return (distance <= max_distance).then(|| BlockHit {
+                pos,
+                face,
+                point: origin + direction * distance,
+                distance,
+            });
+        }
+
+        let axis = (0..3).fold(0, |min, i| if next[i] < next[min] { i } else { min });
+        if next[axis] > max_distance {
+            return None;
+        }
+        pos[axis] += step[axis];
+        next[axis] += delta[axis];
+    }
+}
+
+/// True if nothing solid is between `from` and `to`.
+pub fn line_of_sight<W: WorldAccess>(world: &W, from: Vec3, to: Vec3) -> bool {
+    let context = RayContext {
+        blocks: BlockMode::Collider,
+        fluids: FluidMode::None,
+    };
+    raycast_blocks(world, from, to - from, from.distance(to), context).is_none()
+}
+
+/// The nearest of `boxes` the ray from `origin` towards `direction` hits within
+/// `max_distance`, with where and through which face.
+pub fn raycast_boxes<T>(
+    boxes: impl IntoIterator<Item = (T, Aabb)>,
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+) -> Option<(T, f32, BlockFaceDirection)> {
+    let direction = direction.try_normalize()?;
+    boxes
+        .into_iter()
+        .filter_map(|(target, aabb)| {
+            let (distance, face) = aabb.clip_ray(origin, direction)?;
+            (distance <= max_distance).then_some((target, distance, face))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// The nearest entity the ray hits within `max_distance`, other than `except`, usually the
+/// one looking.
+pub fn raycast_entities(
+    ecs: &mut World,
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+    except: Option<Entity>,
+) -> Option<EntityHit> {
+    let mut entities = ecs.query::<(Entity, &EntityType, &Position)>();
+    let boxes = entities
+        .iter(ecs)
+        .filter(|(entity, _, _)| Some(*entity) != except)
+        .map(|(entity, kind, position)| {
+            let (width, height) = kind.size();
+            (entity, Aabb::from_feet(position.0, width, height))
+        });
+    let (entity, distance, face) = raycast_boxes(boxes, origin, direction, max_distance)?;
+    Some(EntityHit {
+        entity,
+        face,
+        point: origin + direction.normalize() * distance,
+        distance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entity::movement::tests::floor, world::disk_chunk_access::DiskChunkArray};
+
+    fn pond() -> DiskChunkArray {
+        let mut world = floor();
+        world.set_block(ivec3(0, 64, 0), &"minecraft:smooth_stone_slab".into());
+        for x in 2..4 {
+            world.set_block(ivec3(x, 64, 0), &"minecraft:water".into());
+        }
+        world
+    }
+
+    #[test]
+    fn rays_hit_the_face_they_go_in() {
+        let world = pond();
+        let down = world
+            .raycast(vec3(0.5, 66.0, 0.5), Vec3::NEG_Y, 5.0, RayContext::PICK)
+            .unwrap();
+        assert_eq!(down.pos, ivec3(0, 64, 0));
+        assert_eq!(down.face, BlockFaceDirection::YP);
+        assert_eq!((down.point, down.distance), (vec3(0.5, 64.5, 0.5), 1.5));
+
+        // over the slab and through the water, onto the floor
+        let across = world
+            .raycast(
+                vec3(-2.0, 66.2, 0.5),
+                vec3(1.0, -0.5, 0.0),
+                10.0,
+                RayContext::PICK,
+            )
+            .unwrap();
+        assert_eq!(
+            (across.pos, across.face),
+            (ivec3(2, 63, 0), BlockFaceDirection::YP)
+        );
+        assert!((across.point.x - 2.4).abs() < 1e-5, "{}", across.point);
+        // too far
+        let up = world.raycast(vec3(0.5, 66.0, 0.5), Vec3::NEG_Y, 1.4, RayContext::PICK);
+        assert_eq!(up, None);
+    }
+
+    #[test]
+    fn fluids_stop_rays_that_ask() {
+        let world = pond();
+        let origin = vec3(2.5, 66.0, 0.5);
+        let through = world
+            .raycast(origin, Vec3::NEG_Y, 5.0, RayContext::PICK)
+            .unwrap();
+        assert_eq!(through.pos, ivec3(2, 63, 0));
+        let bucket = RayContext {
+            fluids: FluidMode::Any,
+            ..RayContext::PICK
+        };
+        let surface = world.raycast(origin, Vec3::NEG_Y, 5.0, bucket).unwrap();
+        assert_eq!((surface.pos, surface.distance), (ivec3(2, 64, 0), 1.0));
+
+        assert!(line_of_sight(
+            &world,
+            vec3(2.5, 64.5, 0.5),
+            vec3(3.5, 64.5, 0.5)
+        ));
+        assert!(!line_of_sight(
+            &world,
+            vec3(-1.5, 64.25, 0.5),
+            vec3(1.5, 64.25, 0.5)
+        ));
+    }
+
+    #[test]
+    fn rays_hit_the_nearest_entity() {
+        let mut ecs = World::default();
+        let me = ecs
+            .spawn((EntityType::PLAYER, Position(vec3(0.5, 64.0, 0.5))))
+            .id();
+        ecs.spawn((EntityType::PIG, Position(vec3(0.5, 64.0, 6.5))));
+        let near = ecs
+            .spawn((EntityType::PIG, Position(vec3(0.5, 64.0, 3.5))))
+            .id();
+        let eye = vec3(0.5, 64.5, 0.5);
+        let hit = raycast_entities(&mut ecs, eye, Vec3::Z, 10.0, Some(me)).unwrap();
+        assert_eq!(hit.entity, near);
+        assert_eq!(hit.face, BlockFaceDirection::ZN);
+        assert!((hit.distance - 2.55).abs() < 1e-5);
+        assert_eq!(
+            raycast_entities(&mut ecs, eye, Vec3::Z, 2.0, Some(me)),
+            None
+        );
+        // looking from inside ourselves hits ourselves
+        let me_hit = raycast_entities(&mut ecs, eye, Vec3::Z, 10.0, None).unwrap();
+        assert_eq!((me_hit.entity, me_hit.distance), (me, 0.0));
+    }
+}
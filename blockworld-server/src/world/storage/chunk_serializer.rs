@@ -0,0 +1,385 @@
+//! ```text
+//! package net.minecraft.world.chunk.storage
+//! class ChunkSerializer
+//! version 1.16
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
+
+use anyhow::*;
+use blockworld_utils::{BitStorage, ResourceLocation};
+use glam::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    block::{
+        state::{BlockState, StateId},
+        BLOCK_REGISTRY,
+    },
+    world::chunk::{SubChunk, SUBCHUNK_BLOCK_NUM, SUBCHUNK_SIZE},
+};
+
+/// A chunk column as it is saved on disk: every non-empty section sharing the same x and z.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkData {
+    pub x: i32,
+    pub z: i32,
+    /// Ticks players spent near this column, summed over every visit.
+    pub inhabited_time: u64,
+    pub sections: Vec<SectionData>,
+    /// Block ticks scheduled in this column, in the order they run.
+    pub block_ticks: Vec<BlockTickData>,
+    /// Entities in this column, see `entity/persistence.rs`.
+    pub entities: Vec<EntityData>,
+}
+
+/// A scheduled block tick as it is saved, see `world/block_tick.rs`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockTickData {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub block: String,
+    /// Game time the tick is due at.
+    pub due: u64,
+    pub priority: i32,
+}
+
+impl BlockTickData {
+    pub fn pos(&self) -> IVec3 {
+        ivec3(self.x, self.y, self.z)
+    }
+}
+
+/// An entity as it is saved with the section it's in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntityData {
+    /// The registry name of its type.
+    pub id: String,
+    /// Its UUID as four ints, most significant first, like Minecraft's.
+    pub uuid: [i32; 4],
+    pub position: [f32; 3],
+    pub velocity: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    pub on_ground: bool,
+    /// Ticks it has lived.
+    pub age: u32,
+    /// What an item entity is a stack of.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<ItemData>,
+    /// The block a falling block entity is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block: Option<PaletteEntry>,
+}
+
+impl EntityData {
+    pub fn pos(&self) -> Vec3 {
+        Vec3::from_array(self.position)
+    }
+
+    /// The y of the section it's in.
+    fn section_y(&self) -> i32 {
+        (self.position[1].floor() as i32).div_euclid(SUBCHUNK_SIZE as i32)
+    }
+}
+
+/// A stack of items. There are no items besides blocks yet, so `id` names a block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemData {
+    pub id: String,
+    pub count: u32,
+}
+
+/// One 16x16x16 section, stored as a palette plus packed indices into it in YZX order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SectionData {
+    pub y: i32,
+    pub palette: Vec<PaletteEntry>,
+    pub bits: u32,
+    pub data: Vec<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaletteEntry {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, String>,
+}
+
+impl PaletteEntry {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            properties: BTreeMap::new(),
+        }
+    }
+
+    pub fn of_state(state: BlockState) -> Self {
+        Self {
+            name: state.name().to_string(),
+            properties: state
+                .properties()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    /// The state saved, the default of its block for the properties it doesn't know. Unknown
+    /// blocks are air.
+    pub fn state(&self) -> BlockState {
+        let name: ResourceLocation = self.name.as_str().into();
+        if BLOCK_REGISTRY.get(&name).is_none() {
+            log::warn!("Unknown block {} in saved chunk, loading as air", self.name);
+            return BlockState::AIR;
+        }
+        self.properties
+            .iter()
+            .fold(BlockState::of(&name), |state, (k, v)| state.with(k, v))
+    }
+
+    pub fn is_air(&self) -> bool {
+        self.name == "minecraft:air"
+    }
+}
+
+impl Display for PaletteEntry {
+    /// `minecraft:stone` or `minecraft:stone[variant=granite]`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.properties.is_empty() {
+            return write!(f, "{}", self.name);
+        }
+        let props: Vec<_> = self
+            .properties
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect();
+        write!(f, "{}[{}]", self.name, props.join(","))
+    }
+}
+
+impl ChunkData {
+    pub fn new(pos: IVec2) -> Self {
+        Self {
+            x: pos.x,
+            z: pos.y,
+            inhabited_time: 0,
+            sections: Vec::new(),
+            block_ticks: Vec::new(),
+            entities: Vec::new(),
+        }
+    }
+
+    pub fn pos(&self) -> IVec2 {
+        ivec2(self.x, self.z)
+    }
+
+    /// Replace the section at the sub chunk's y, or drop it if the sub chunk is all air.
+    pub fn put_section(&mut self, sub_chunk: &SubChunk) {
+        let y = sub_chunk.pos().y;
+        self.sections.retain(|s| s.y != y);
+        if !sub_chunk.is_empty() {
+            self.sections.push(SectionData::from_sub_chunk(sub_chunk));
+            self.sections.sort_by_key(|s| s.y);
+        }
+    }
+
+    pub fn section(&self, y: i32) -> Option<&SectionData> {
+        self.sections.iter().find(|s| s.y == y)
+    }
+
+    /// Replace the block ticks of the section at `y` with `ticks`.
+    pub fn put_block_ticks(&mut self, y: i32, ticks: impl IntoIterator<Item = BlockTickData>) {
+        self.block_ticks
+            .retain(|t| t.y.div_euclid(SUBCHUNK_SIZE as i32) != y);
+        self.block_ticks.extend(ticks);
+    }
+
+    /// The block ticks of the section at `y`.
+    pub fn block_ticks(&self, y: i32) -> impl Iterator<Item = &BlockTickData> {
+        self.block_ticks
+            .iter()
+            .filter(move |t| t.y.div_euclid(SUBCHUNK_SIZE as i32) == y)
+    }
+
+    /// Replace the entities of the section at `y` with `entities`.
+    pub fn put_entities(&mut self, y: i32, entities: impl IntoIterator<Item = EntityData>) {
+        self.entities.retain(|e| e.section_y() != y);
+        self.entities.extend(entities);
+    }
+
+    /// The entities of the section at `y`.
+    pub fn entities(&self, y: i32) -> impl Iterator<Item = &EntityData> {
+        self.entities.iter().filter(move |e| e.section_y() == y)
+    }
+
+    /// World y of the highest non-air block of every column, indexed `x + z * 16`.
+    pub fn heightmap(&self) -> Result<Vec<Option<i32>>> {
+        let mut heights = vec![None; SUBCHUNK_SIZE * SUBCHUNK_SIZE];
+        // sections are sorted by y, so walk them top down and keep the first hit
+        for section in self.sections.iter().rev() {
+            let indices = section.unpack()?;
+            for (i, index) in indices.iter().enumerate().rev() {
+                let column = i % (SUBCHUNK_SIZE * SUBCHUNK_SIZE);
+                if heights[column].is_none() && !section.palette[*index as usize].is_air() {
+                    let y = (i / (SUBCHUNK_SIZE * SUBCHUNK_SIZE)) as i32;
+                    heights[column] = Some(section.y * SUBCHUNK_SIZE as i32 + y);
+                }
+            }
+        }
+        Ok(heights)
+    }
+}
+
+impl SectionData {
+    pub fn from_sub_chunk(sub_chunk: &SubChunk) -> Self {
+        let mut palette: Vec<StateId> = Vec::new();
+        let mut lookup: HashMap<StateId, u32> = HashMap::new();
+        let mut indices = Vec::with_capacity(SUBCHUNK_BLOCK_NUM);
+        for i in 0..SUBCHUNK_BLOCK_NUM {
+            let id = sub_chunk.get_state_id(i);
+            let index = *lookup.entry(id).or_insert_with(|| {
+                palette.push(id);
+                palette.len() as u32 - 1
+            });
+            indices.push(index);
+        }
+
+        let palette = palette
+            .into_iter()
+            .map(|id| PaletteEntry::of_state(BlockState::from_id(id).unwrap_or_default()))
+            .collect();
+        Self::pack(sub_chunk.pos().y, palette, &indices)
+    }
+
+    pub fn pack(y: i32, palette: Vec<PaletteEntry>, indices: &[u32]) -> Self {
+        let bits = BitStorage::bits_for(palette.len(), 4);
+        let mut storage = BitStorage::new(bits, indices.len());
+        for (i, index) in indices.iter().enumerate() {
+            storage.set(i, *index);
+        }
+        Self {
+            y,
+            palette,
+            bits,
+            data: storage.into_raw(),
+        }
+    }
+
+    /// Palette indices of every block, checked against the palette length.
+    pub fn unpack(&self) -> Result<Vec<u32>> {
+        let storage = BitStorage::from_raw(self.bits, SUBCHUNK_BLOCK_NUM, self.data.clone())
+            .ok_or_else(|| {
+                anyhow!(
+                    "section {} has {} words for {} bits per block",
+                    self.y,
+                    self.data.len(),
+                    self.bits
+                )
+            })?;
+        let indices: Vec<u32> = storage.iter().collect();
+        if let Some(bad) = indices.iter().find(|i| **i as usize >= self.palette.len()) {
+            bail!(
+                "section {} refers to palette entry {} of {}",
+                self.y,
+                bad,
+                self.palette.len()
+            );
+        }
+        Ok(indices)
+    }
+
+    /// Decode into a sub chunk at column `column`.
+    ///
+    /// Unknown block ids load as air, see [`PaletteEntry::state`].
+    pub fn to_sub_chunk(&self, column: IVec2) -> Result<SubChunk> {
+        let ids: Vec<StateId> = self.palette.iter().map(|p| p.state().0).collect();
+
+        let mut sub_chunk = SubChunk::new(ivec3(column.x, self.y, column.y));
+        for (i, index) in self.unpack()?.into_iter().enumerate() {
+            sub_chunk.set_state_id(i, ids[index as usize]);
+        }
+        Ok(sub_chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sub_chunk_round_trip() {
+        let mut sc = SubChunk::new(ivec3(1, 2, 3));
+        sc.set_blockid(ivec3(0, 0, 0), "minecraft:stone");
+        sc.set_blockid(ivec3(15, 15, 15), "minecraft:stone");
+
+        let mut column = ChunkData::new(ivec2(1, 3));
+        column.put_section(&sc);
+        assert_eq!(column.sections[0].palette.len(), 2);
+
+        let json = serde_json::to_string(&column).unwrap();
+        let column: ChunkData = serde_json::from_str(&json).unwrap();
+        let loaded = column
+            .section(2)
+            .unwrap()
+            .to_sub_chunk(column.pos())
+            .unwrap();
+        assert_eq!(loaded.pos(), ivec3(1, 2, 3));
+        assert_eq!(loaded.get_blockid(ivec3(15, 15, 15)), "minecraft:stone");
+        assert_eq!(loaded.get_blockid(ivec3(1, 0, 0)), "minecraft:air");
+    }
+
+    #[test]
+    fn palettes_keep_properties() {
+        let fence = BlockState::of(&"minecraft:oak_fence".into()).with_bool("east", true);
+        let entry = PaletteEntry::of_state(fence);
+        assert_eq!(
+            entry.to_string(),
+            "minecraft:oak_fence[east=true,north=false,south=false,west=false]"
+        );
+        assert_eq!(entry.state(), fence);
+        // what the block doesn't have is dropped, what it has but isn't saved is the default
+        let mut entry = PaletteEntry::new("minecraft:oak_fence");
+        entry.properties.insert("up".into(), "true".into());
+        entry.properties.insert("west".into(), "true".into());
+        assert_eq!(
+            entry.state(),
+            BlockState::of(&"minecraft:oak_fence".into()).with_bool("west", true)
+        );
+        assert_eq!(
+            PaletteEntry::new("minecraft:chest").state(),
+            BlockState::AIR
+        );
+    }
+
+    #[test]
+    fn empty_sections_are_dropped() {
+        let mut column = ChunkData::new(IVec2::ZERO);
+        let mut sc = SubChunk::new(IVec3::ZERO);
+        sc.set_blockid(IVec3::ZERO, "minecraft:stone");
+        column.put_section(&sc);
+        sc.remove_block(IVec3::ZERO);
+        column.put_section(&sc);
+        assert!(column.sections.is_empty());
+    }
+
+    #[test]
+    fn heightmap() {
+        let mut column = ChunkData::new(IVec2::ZERO);
+        let mut sc = SubChunk::new(ivec3(0, -1, 0));
+        sc.set_blockid(ivec3(2, 3, 4), "minecraft:stone");
+        column.put_section(&sc);
+        let mut sc = SubChunk::new(ivec3(0, 1, 0));
+        sc.set_blockid(ivec3(2, 0, 4), "minecraft:stone");
+        sc.set_blockid(ivec3(5, 15, 5), "minecraft:stone");
+        column.put_section(&sc);
+
+        let heights = column.heightmap().unwrap();
+        assert_eq!(heights[2 + 4 * 16], Some(16));
+        assert_eq!(heights[5 + 5 * 16], Some(31));
+        assert_eq!(heights[0], None);
+    }
+}
@@ -0,0 +1,73 @@
+//! ```text
+//! package net.minecraft.world.storage
+//! class ServerWorldInfo, GameRules
+//! version 1.16
+//! ```
+
+use anyhow::*;
+use bevy_ecs::system::Resource;
+use serde::{Deserialize, Serialize};
+
+/// Global world state, saved as `level.json` in the world folder. A resource of the ECS world,
+/// so systems can read the time and the game rules.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelData {
+    pub seed: u64,
+    /// World spawn in block coords.
+    pub spawn: [i32; 3],
+    /// Ticks since the world was created.
+    pub time: u64,
+    pub game_rules: GameRules,
+}
+
+impl Default for LevelData {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            spawn: [0, 64, 0],
+            time: 0,
+            game_rules: GameRules::default(),
+        }
+    }
+}
+
+/// Settings operators change while the world runs, named as in Minecraft.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameRules {
+    /// Blocks picked for a random tick in every section, every tick. 0 turns random ticks off.
+    pub random_tick_speed: u32,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self {
+            random_tick_speed: 3,
+        }
+    }
+}
+
+impl GameRules {
+    pub const NAMES: &'static [&'static str] = &["randomTickSpeed"];
+
+    /// The value of the rule called `name`.
+    pub fn get(&self, name: &str) -> Result<String> {
+        match name {
+            "randomTickSpeed" => Ok(self.random_tick_speed.to_string()),
+            _ => bail!("Unknown game rule {}", name),
+        }
+    }
+
+    /// Set the rule called `name` from text.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "randomTickSpeed" => {
+                self.random_tick_speed = value
+                    .parse()
+                    .with_context(|| format!("{} is not a tick count", value))?
+            }
+            _ => bail!("Unknown game rule {}", name),
+        }
+        Ok(())
+    }
+}
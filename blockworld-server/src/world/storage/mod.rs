@@ -0,0 +1,301 @@
+//! ```text
+//! package net.minecraft.world.storage
+//! class SaveFormat.LevelSave
+//! version 1.16
+//! ```
+//!
+//! A world folder looks like:
+//!
+//! ```text
+//! <world>/level.json
+//! <world>/region/r.<x>.<z>.bwr
+//! <world>/playerdata/<name>.json
+//! ```
+//!
+//! Every document is JSON stamped with a [`DataVersion`](crate::datafix::DataVersion) and goes
+//! through the [`DATA_FIXER`] when it is read.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::*;
+use glam::*;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::datafix::{DataFixType, DataFixer, CURRENT_DATA_VERSION, DATA_FIXER};
+
+pub mod chunk_serializer;
+pub mod level_data;
+pub mod player_data;
+pub mod region_file;
+
+use chunk_serializer::ChunkData;
+pub use level_data::LevelData;
+pub use player_data::PlayerData;
+use region_file::{
+    local_pos, parse_region_file_name, region_file_name, region_pos, RegionFile, REGION_SIZE,
+};
+
+const LEVEL_FILE: &str = "level.json";
+const REGION_DIR: &str = "region";
+const PLAYER_DIR: &str = "playerdata";
+
+/// Decode a saved document, upgrading it to the current data version first.
+pub fn decode_document<T: DeserializeOwned>(ty: DataFixType, bytes: &[u8]) -> Result<T> {
+    let mut value: Value = serde_json::from_slice(bytes)?;
+    DATA_FIXER.update(ty, &mut value)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Encode a document stamped with the current data version.
+pub fn encode_document<T: Serialize>(doc: &T) -> Result<Vec<u8>> {
+    let mut value = serde_json::to_value(doc)?;
+    DataFixer::stamp(&mut value, CURRENT_DATA_VERSION)?;
+    Ok(serde_json::to_vec(&value)?)
+}
+
+/// Access to a world folder on disk.
+pub struct WorldStorage {
+    root: PathBuf,
+    regions: HashMap<IVec2, RegionFile>,
+    /// Opened with [`WorldStorage::open_read_only`], so nothing is created or written.
+    read_only: bool,
+}
+
+impl WorldStorage {
+    /// Open a world folder, creating it if it doesn't exist.
+    pub fn open<Q: AsRef<Path>>(root: Q) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(root.join(REGION_DIR))
+            .with_context(|| format!("Failed to create world folder {}", root.display()))?;
+        fs::create_dir_all(root.join(PLAYER_DIR))?;
+        Ok(Self {
+            root,
+            regions: HashMap::new(),
+            read_only: false,
+        })
+    }
+
+    /// Open an existing world folder to look at it. Nothing in the folder is created or
+    /// changed: regions that don't exist read as empty, and writes fail.
+    pub fn open_read_only<Q: AsRef<Path>>(root: Q) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        if !root.is_dir() {
+            bail!("World folder {} doesn't exist", root.display());
+        }
+        Ok(Self {
+            root,
+            regions: HashMap::new(),
+            read_only: true,
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn read_level(&self) -> Result<Option<LevelData>> {
+        read_file(&self.root.join(LEVEL_FILE), DataFixType::Level)
+    }
+
+    pub fn write_level(&self, level: &LevelData) -> Result<()> {
+        self.check_writable()?;
+        write_file(&self.root.join(LEVEL_FILE), level)
+    }
+
+    pub fn read_player(&self, name: &str) -> Result<Option<PlayerData>> {
+        read_file(&self.player_path(name), DataFixType::Player)
+    }
+
+    pub fn write_player(&self, name: &str, player: &PlayerData) -> Result<()> {
+        self.check_writable()?;
+        write_file(&self.player_path(name), player)
+    }
+
+    fn player_path(&self, name: &str) -> PathBuf {
+        self.root.join(PLAYER_DIR).join(format!("{}.json", name))
+    }
+
+    fn check_writable(&self) -> Result<()> {
+        if self.read_only {
+            bail!("World folder {} is opened read-only", self.root.display());
+        }
+        Ok(())
+    }
+
+    /// The region file at `region`, `None` if it doesn't exist and we may not create it.
+    fn region(&mut self, region: IVec2) -> Result<Option<&mut RegionFile>> {
+        if !self.regions.contains_key(&region) {
+            let path = self.root.join(REGION_DIR).join(region_file_name(region));
+            let file = if !self.read_only {
+                RegionFile::open(path)?
+            } else if path.exists() {
+                RegionFile::open_read_only(path)?
+            } else {
+                return Ok(None);
+            };
+            self.regions.insert(region, file);
+        }
+        Ok(self.regions.get_mut(&region))
+    }
+
+    fn region_mut(&mut self, region: IVec2) -> Result<&mut RegionFile> {
+        self.check_writable()?;
+        Ok(self
+            .region(region)?
+            .expect("writable storage creates missing regions"))
+    }
+
+    /// Read the chunk column at `pos`, upgrading it if it was saved by an older version.
+    pub fn read_chunk(&mut self, pos: IVec2) -> Result<Option<ChunkData>> {
+        let Some(region) = self.region(region_pos(pos))? else {
+            return Ok(None);
+        };
+        let Some(bytes) = region.read_chunk(local_pos(pos))? else {
+            return Ok(None);
+        };
+        let chunk: ChunkData = decode_document(DataFixType::Chunk, &bytes)
+            .with_context(|| format!("Failed to decode chunk {}", pos))?;
+        if chunk.pos() != pos {
+            bail!("chunk at {} claims to be at {}", pos, chunk.pos());
+        }
+        Ok(Some(chunk))
+    }
+
+    pub fn write_chunk(&mut self, chunk: &ChunkData) -> Result<()> {
+        let bytes = encode_document(chunk)?;
+        let pos = chunk.pos();
+        self.region_mut(region_pos(pos))?
+            .write_chunk(local_pos(pos), &bytes)
+    }
+
+    pub fn delete_chunk(&mut self, pos: IVec2) -> Result<()> {
+        self.region_mut(region_pos(pos))?
+            .delete_chunk(local_pos(pos))
+    }
+
+    /// Seconds since the unix epoch the chunk was last written, `None` if it was never saved.
+    pub fn chunk_timestamp(&mut self, pos: IVec2) -> Result<Option<u32>> {
+        let local = local_pos(pos);
+        Ok(self
+            .region(region_pos(pos))?
+            .filter(|r| r.has_chunk(local))
+            .map(|r| r.timestamp(local)))
+    }
+
+    /// Checksum of the stored chunk, `None` if it was never saved.
+    pub fn chunk_checksum(&mut self, pos: IVec2) -> Result<Option<u32>> {
+        let local = local_pos(pos);
+        Ok(self
+            .region(region_pos(pos))?
+            .filter(|r| r.has_chunk(local))
+            .map(|r| r.checksum(local)))
+    }
+
+    /// Positions of every region file in the world folder.
+    pub fn list_regions(&self) -> Result<Vec<IVec2>> {
+        let mut regions = Vec::new();
+        let dir = self.root.join(REGION_DIR);
+        if !dir.exists() {
+            return Ok(regions);
+        }
+        for entry in fs::read_dir(dir)? {
+            let name = entry?.file_name();
+            if let Some(pos) = name.to_str().and_then(parse_region_file_name) {
+                regions.push(pos);
+            }
+        }
+        regions.sort_by_key(|r| (r.x, r.y));
+        Ok(regions)
+    }
+
+    /// Positions of every saved chunk column.
+    pub fn list_chunks(&mut self) -> Result<Vec<IVec2>> {
+        let mut chunks = Vec::new();
+        for region_pos in self.list_regions()? {
+            let Some(region) = self.region(region_pos)? else {
+                continue;
+            };
+            chunks.extend(
+                region
+                    .chunks()
+                    .map(|local| region_pos * REGION_SIZE + local),
+            );
+        }
+        Ok(chunks)
+    }
+}
+
+fn read_file<T: DeserializeOwned>(path: &Path, ty: DataFixType) -> Result<Option<T>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let bytes = fs::read(path)?;
+    decode_document(ty, &bytes)
+        .with_context(|| format!("Failed to decode {}", path.display()))
+        .map(Some)
+}
+
+/// Write through a temporary file so a crash never leaves a half written document.
+fn write_file<T: Serialize>(path: &Path, doc: &T) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, encode_document(doc)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use blockworld_utils::TempDir;
+
+    use super::*;
+    use crate::world::chunk::SubChunk;
+
+    #[test]
+    fn world_folder_round_trip() {
+        let root = TempDir::new("storage").unwrap();
+        let mut storage = WorldStorage::open(&root).unwrap();
+
+        let level = LevelData {
+            seed: 42,
+            ..Default::default()
+        };
+        storage.write_level(&level).unwrap();
+        assert_eq!(storage.read_level().unwrap(), Some(level));
+
+        let mut sc = SubChunk::new(ivec3(-40, 1, 7));
+        sc.set_blockid(ivec3(3, 4, 5), "minecraft:stone");
+        let mut chunk = ChunkData::new(ivec2(-40, 7));
+        chunk.put_section(&sc);
+        storage.write_chunk(&chunk).unwrap();
+        assert_eq!(storage.read_chunk(ivec2(-40, 7)).unwrap(), Some(chunk));
+        assert_eq!(storage.read_chunk(ivec2(-41, 7)).unwrap(), None);
+        assert_eq!(storage.list_chunks().unwrap(), [ivec2(-40, 7)]);
+        storage.delete_chunk(ivec2(-40, 7)).unwrap();
+        assert!(storage.list_chunks().unwrap().is_empty());
+
+        let raw = fs::read(root.join(LEVEL_FILE)).unwrap();
+        let raw: Value = serde_json::from_slice(&raw).unwrap();
+        assert_eq!(DataFixer::version_of(&raw).unwrap(), CURRENT_DATA_VERSION);
+    }
+
+    #[test]
+    fn read_only_leaves_the_folder_alone() {
+        let root = TempDir::new("storage-read-only").unwrap();
+        let mut storage = WorldStorage::open_read_only(&root).unwrap();
+        assert_eq!(storage.read_level().unwrap(), None);
+        assert_eq!(storage.read_chunk(ivec2(3, -2)).unwrap(), None);
+        assert_eq!(storage.chunk_timestamp(ivec2(3, -2)).unwrap(), None);
+        assert!(storage.list_chunks().unwrap().is_empty());
+        assert!(storage.write_level(&LevelData::default()).is_err());
+        assert!(storage.write_chunk(&ChunkData::new(ivec2(3, -2))).is_err());
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 0);
+
+        assert!(WorldStorage::open_read_only(root.join("missing")).is_err());
+        assert!(!root.join("missing").exists());
+    }
+}
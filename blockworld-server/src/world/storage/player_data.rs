@@ -0,0 +1,26 @@
+//! ```text
+//! package net.minecraft.world.storage
+//! class PlayerData
+//! version 1.16
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::entity::game_mode::GameMode;
+
+/// Per player state, saved as `playerdata/<name>.json` in the world folder.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerData {
+    pub position: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    pub game_mode: GameMode,
+    /// Block ids an adventure player may break.
+    pub can_destroy: Vec<String>,
+    /// Block ids an adventure player may place against.
+    pub can_place_on: Vec<String>,
+    pub health: f32,
+    pub food: u32,
+    pub saturation: f32,
+    pub exhaustion: f32,
+}
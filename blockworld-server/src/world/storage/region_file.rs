@@ -0,0 +1,398 @@
+//! ```text
+//! package net.minecraft.world.chunk.storage
+//! class RegionFile
+//! version 1.16
+//! ```
+//!
+//! A region file holds 32x32 chunk columns. Layout, all integers big endian:
+//!
+//! - sector 0: 1024 locations, `offset_in_sectors << 8 | sector_count`
+//! - sector 1: 1024 timestamps, seconds since the unix epoch of the last write
+//! - sector 2: 1024 CRC-32 checksums of the stored (compressed) payloads
+//! - then the payloads, each starting on a sector boundary:
+//!   `u32 length`, `u8 compression`, `length - 1` bytes of data
+//!
+//! Unlike Minecraft we keep the checksums in the header, so a whole world can be verified
+//! without decoding a single chunk.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::*;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder};
+use glam::*;
+
+/// Columns per region side.
+pub const REGION_SIZE: i32 = 32;
+pub const SECTOR_BYTES: usize = 4096;
+const ENTRIES: usize = (REGION_SIZE * REGION_SIZE) as usize;
+const HEADER_SECTORS: usize = 3;
+/// The sector count is stored in a single byte.
+const MAX_SECTORS_PER_CHUNK: usize = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Compression {
+    Zlib = 1,
+    None = 3,
+}
+
+impl Compression {
+    fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            1 => Some(Self::Zlib),
+            3 => Some(Self::None),
+            _ => None,
+        }
+    }
+}
+
+/// Region coordinate holding a chunk column.
+pub fn region_pos(column: IVec2) -> IVec2 {
+    ivec2(
+        column.x.div_euclid(REGION_SIZE),
+        column.y.div_euclid(REGION_SIZE),
+    )
+}
+
+/// Position of a chunk column inside its region.
+pub fn local_pos(column: IVec2) -> IVec2 {
+    ivec2(
+        column.x.rem_euclid(REGION_SIZE),
+        column.y.rem_euclid(REGION_SIZE),
+    )
+}
+
+pub fn region_file_name(region: IVec2) -> String {
+    format!("r.{}.{}.bwr", region.x, region.y)
+}
+
+/// Parse a name produced by [`region_file_name`].
+pub fn parse_region_file_name(name: &str) -> Option<IVec2> {
+    let mut parts = name.strip_prefix("r.")?.strip_suffix(".bwr")?.split('.');
+    let x = parts.next()?.parse().ok()?;
+    let z = parts.next()?.parse().ok()?;
+    parts.next().is_none().then_some(ivec2(x, z))
+}
+
+pub struct RegionFile {
+    path: PathBuf,
+    file: File,
+    locations: [u32; ENTRIES],
+    timestamps: [u32; ENTRIES],
+    checksums: [u32; ENTRIES],
+    /// Which sectors of the file are taken, header included.
+    used_sectors: Vec<bool>,
+}
+
+impl RegionFile {
+    /// Open a region file, creating an empty one if it doesn't exist.
+    pub fn open<Q: AsRef<Path>>(path: Q) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open region file {}", path.display()))?;
+        Self::load(path, file, true)
+    }
+
+    /// Open an existing region file to read it only. A truncated header is an error here,
+    /// since it can't be reset.
+    pub fn open_read_only<Q: AsRef<Path>>(path: Q) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path)
+            .with_context(|| format!("Failed to open region file {}", path.display()))?;
+        Self::load(path, file, false)
+    }
+
+    fn load(path: PathBuf, mut file: File, writable: bool) -> Result<Self> {
+        let len = file.metadata()?.len() as usize;
+        let mut header = vec![0u8; HEADER_SECTORS * SECTOR_BYTES];
+        if len < header.len() {
+            if !writable {
+                bail!("Region file {} has a truncated header", path.display());
+            }
+            if len != 0 {
+                log::warn!(
+                    "Region file {} has a truncated header, resetting it",
+                    path.display()
+                );
+            }
+            file.set_len(0)?;
+            file.write_all(&header)?;
+        } else {
+            file.read_exact(&mut header)?;
+        }
+
+        let table = |i: usize| -> [u32; ENTRIES] {
+            std::array::from_fn(|k| {
+                let at = i * SECTOR_BYTES + k * 4;
+                u32::from_be_bytes(header[at..at + 4].try_into().unwrap())
+            })
+        };
+        let locations = table(0);
+        let timestamps = table(1);
+        let checksums = table(2);
+
+        let total_sectors = file.metadata()?.len().div_ceil(SECTOR_BYTES as u64) as usize;
+        let mut used_sectors = vec![false; total_sectors.max(HEADER_SECTORS)];
+        used_sectors[..HEADER_SECTORS].fill(true);
+        for location in locations.iter().filter(|l| **l != 0) {
+            let (offset, count) = Self::split_location(*location);
+            if offset + count <= used_sectors.len() {
+                used_sectors[offset..offset + count].fill(true);
+            } else {
+                log::warn!(
+                    "Region file {} has a chunk past the end of the file",
+                    path.display()
+                );
+            }
+        }
+
+        Ok(Self {
+            path,
+            file,
+            locations,
+            timestamps,
+            checksums,
+            used_sectors,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn index(local: IVec2) -> usize {
+        assert!(
+            local.x >= 0 && local.x < REGION_SIZE && local.y >= 0 && local.y < REGION_SIZE,
+            "local chunk position out of region: {}",
+            local
+        );
+        (local.x + local.y * REGION_SIZE) as usize
+    }
+
+    fn split_location(location: u32) -> (usize, usize) {
+        ((location >> 8) as usize, (location & 0xff) as usize)
+    }
+
+    pub fn has_chunk(&self, local: IVec2) -> bool {
+        self.locations[Self::index(local)] != 0
+    }
+
+    /// Seconds since the unix epoch of the last write, 0 if never written.
+    pub fn timestamp(&self, local: IVec2) -> u32 {
+        self.timestamps[Self::index(local)]
+    }
+
+    /// CRC-32 of the stored payload, changes whenever the chunk is rewritten with new content.
+    pub fn checksum(&self, local: IVec2) -> u32 {
+        self.checksums[Self::index(local)]
+    }
+
+    /// Local positions of every stored chunk.
+    pub fn chunks(&self) -> impl Iterator<Item = IVec2> + '_ {
+        (0..ENTRIES)
+            .filter(|i| self.locations[*i] != 0)
+            .map(|i| ivec2(i as i32 % REGION_SIZE, i as i32 / REGION_SIZE))
+    }
+
+    /// Read and decompress a chunk payload, verifying its checksum.
+    pub fn read_chunk(&mut self, local: IVec2) -> Result<Option<Vec<u8>>> {
+        let index = Self::index(local);
+        if self.locations[index] == 0 {
+            return Ok(None);
+        }
+        let (offset, count) = Self::split_location(self.locations[index]);
+
+        self.file
+            .seek(SeekFrom::Start((offset * SECTOR_BYTES) as u64))?;
+        let mut head = [0u8; 5];
+        self.file.read_exact(&mut head)?;
+        let length = u32::from_be_bytes(head[..4].try_into().unwrap()) as usize;
+        if length == 0 || length + 4 > count * SECTOR_BYTES {
+            bail!("chunk {} has invalid length {}", local, length);
+        }
+        let compression = Compression::from_u8(head[4])
+            .ok_or_else(|| anyhow!("chunk {} has unknown compression {}", local, head[4]))?;
+
+        let mut stored = vec![0u8; length - 1];
+        self.file.read_exact(&mut stored)?;
+        let checksum = crc32fast::hash(&stored);
+        if checksum != self.checksums[index] {
+            bail!(
+                "chunk {} checksum mismatch: stored {:08x}, computed {:08x}",
+                local,
+                self.checksums[index],
+                checksum
+            );
+        }
+
+        let data = match compression {
+            Compression::None => stored,
+            Compression::Zlib => {
+                let mut out = Vec::new();
+                ZlibDecoder::new(&stored[..]).read_to_end(&mut out)?;
+                out
+            }
+        };
+        Ok(Some(data))
+    }
+
+    /// Compress and write a chunk payload, reusing its old sectors if it still fits.
+    pub fn write_chunk(&mut self, local: IVec2, data: &[u8]) -> Result<()> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data)?;
+        let stored = encoder.finish()?;
+
+        let mut payload = Vec::with_capacity(stored.len() + 5);
+        payload.extend_from_slice(&(stored.len() as u32 + 1).to_be_bytes());
+        payload.push(Compression::Zlib as u8);
+        payload.extend_from_slice(&stored);
+
+        let count = payload.len().div_ceil(SECTOR_BYTES);
+        if count > MAX_SECTORS_PER_CHUNK {
+            bail!(
+                "chunk {} is too large: {} bytes after compression",
+                local,
+                stored.len()
+            );
+        }
+
+        let index = Self::index(local);
+        self.free(index);
+        let offset = self.allocate(count);
+
+        payload.resize(count * SECTOR_BYTES, 0);
+        self.file
+            .seek(SeekFrom::Start((offset * SECTOR_BYTES) as u64))?;
+        self.file.write_all(&payload)?;
+
+        self.locations[index] = ((offset as u32) << 8) | count as u32;
+        self.timestamps[index] = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or(0);
+        self.checksums[index] = crc32fast::hash(&stored);
+        self.write_header_entry(index)
+    }
+
+    /// Remove a chunk. Its sectors are reused by later writes.
+    pub fn delete_chunk(&mut self, local: IVec2) -> Result<()> {
+        let index = Self::index(local);
+        self.free(index);
+        self.locations[index] = 0;
+        self.timestamps[index] = 0;
+        self.checksums[index] = 0;
+        self.write_header_entry(index)
+    }
+
+    fn free(&mut self, index: usize) {
+        let (offset, count) = Self::split_location(self.locations[index]);
+        if count != 0 && offset + count <= self.used_sectors.len() {
+            self.used_sectors[offset..offset + count].fill(false);
+        }
+    }
+
+    /// First fit, growing the file if no gap is big enough.
+    fn allocate(&mut self, count: usize) -> usize {
+        let mut run = 0;
+        for (i, used) in self.used_sectors.iter().enumerate() {
+            run = if *used { 0 } else { run + 1 };
+            if run == count {
+                let start = i + 1 - count;
+                self.used_sectors[start..=i].fill(true);
+                return start;
+            }
+        }
+        let start = self.used_sectors.len() - run;
+        self.used_sectors.resize(start + count, true);
+        self.used_sectors[start..].fill(true);
+        start
+    }
+
+    fn write_header_entry(&mut self, index: usize) -> Result<()> {
+        for (table, value) in [
+            self.locations[index],
+            self.timestamps[index],
+            self.checksums[index],
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            self.file
+                .seek(SeekFrom::Start((table * SECTOR_BYTES + index * 4) as u64))?;
+            self.file.write_all(&value.to_be_bytes())?;
+        }
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blockworld_utils::TempDir;
+
+    use super::*;
+
+    /// A folder for one test, and the path of the region file at the origin in it.
+    fn temp_region(name: &str) -> (TempDir, PathBuf) {
+        let dir = TempDir::new(&format!("region-{}", name)).unwrap();
+        let path = dir.join(region_file_name(IVec2::ZERO));
+        (dir, path)
+    }
+
+    #[test]
+    fn write_read_reopen() {
+        let (_dir, path) = temp_region("reopen");
+        {
+            let mut region = RegionFile::open(&path).unwrap();
+            region.write_chunk(ivec2(1, 2), b"hello").unwrap();
+            region
+                .write_chunk(ivec2(31, 31), &vec![7u8; 20000])
+                .unwrap();
+        }
+        let mut region = RegionFile::open(&path).unwrap();
+        assert_eq!(region.chunks().count(), 2);
+        assert_eq!(region.read_chunk(ivec2(1, 2)).unwrap().unwrap(), b"hello");
+        assert_eq!(
+            region.read_chunk(ivec2(31, 31)).unwrap().unwrap().len(),
+            20000
+        );
+        assert!(region.read_chunk(ivec2(0, 0)).unwrap().is_none());
+        assert!(region.timestamp(ivec2(1, 2)) > 0);
+
+        region.delete_chunk(ivec2(1, 2)).unwrap();
+        assert!(!region.has_chunk(ivec2(1, 2)));
+    }
+
+    #[test]
+    fn detects_corruption() {
+        let (_dir, path) = temp_region("corrupt");
+        let mut region = RegionFile::open(&path).unwrap();
+        region.write_chunk(ivec2(0, 0), b"some chunk data").unwrap();
+        drop(region);
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[HEADER_SECTORS * SECTOR_BYTES + 6] ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+
+        let mut region = RegionFile::open(&path).unwrap();
+        assert!(region.read_chunk(ivec2(0, 0)).is_err());
+    }
+
+    #[test]
+    fn region_coords() {
+        assert_eq!(region_pos(ivec2(-1, 32)), ivec2(-1, 1));
+        assert_eq!(local_pos(ivec2(-1, 32)), ivec2(31, 0));
+        assert_eq!(parse_region_file_name("r.-1.4.bwr"), Some(ivec2(-1, 4)));
+        assert_eq!(parse_region_file_name("r.1.bwr"), None);
+    }
+}
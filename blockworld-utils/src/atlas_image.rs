@@ -1,4 +1,4 @@
-//! ```
+//! ```text
 //! package net.minecraft.client.renderer.texture
 //! class SpriteContents
 //! version 1.21
@@ -6,10 +6,10 @@
 
 use std::{collections::HashMap, fmt::Display, path::Path};
 
-use blockworld_utils::ResourceLocation;
-use glam::{ivec2, uvec2, vec2, IVec2, UVec2, Vec2};
-use image::{GenericImage, GenericImageView, ImageBuffer};
-use wgpu::hal::auxil::db;
+use glam::{uvec2, vec2, UVec2, Vec2};
+use image::{GenericImage, GenericImageView, ImageBuffer, SubImage};
+
+use crate::ResourceLocation;
 
 /// This is a wrapper around an image::RgbaImage that contains the contents of a sprite. It also will handle its mipmaps.
 pub struct Atlas {
@@ -137,6 +137,16 @@ impl Atlas {
         let xy = self.name_to_xy_map.get(name).cloned()?;
         Some(self.from_xy_to_uvs(xy))
     }
+
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// The pixels of one sprite, for sampling on the CPU.
+    pub fn query_sprite(&self, name: &ResourceLocation) -> Option<SubImage<&image::RgbaImage>> {
+        let xy = self.name_to_xy_map.get(name).cloned()? * self.tile_size;
+        Some(self.atlas.view(xy.x, xy.y, self.tile_size, self.tile_size))
+    }
 }
 
 impl Display for Atlas {
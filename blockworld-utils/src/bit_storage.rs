@@ -0,0 +1,117 @@
+//! ```text
+//! package net.minecraft.util
+//! class BitStorage
+//! version 1.16
+//! ```
+
+/// A fixed-size array of unsigned integers packed into `u64` words.
+///
+/// Entries never span two words, so `64 / bits` entries fit into each word and
+/// the remaining high bits are left as zero (the 1.16+ layout).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitStorage {
+    bits: u32,
+    size: usize,
+    data: Vec<u64>,
+}
+
+impl BitStorage {
+    /// Create an all-zero storage holding `size` entries of `bits` bits each.
+    pub fn new(bits: u32, size: usize) -> Self {
+        assert!((1..=32).contains(&bits), "bits must be in 1..=32");
+        let per_word = (64 / bits) as usize;
+        Self {
+            bits,
+            size,
+            data: vec![0; size.div_ceil(per_word)],
+        }
+    }
+
+    /// Wrap already packed words. Returns `None` if the word count doesn't match.
+    pub fn from_raw(bits: u32, size: usize, data: Vec<u64>) -> Option<Self> {
+        if !(1..=32).contains(&bits) {
+            return None;
+        }
+        let per_word = (64 / bits) as usize;
+        if data.len() != size.div_ceil(per_word) {
+            return None;
+        }
+        Some(Self { bits, size, data })
+    }
+
+    /// The smallest bit width able to store `count` distinct values, but at least `min`.
+    pub fn bits_for(count: usize, min: u32) -> u32 {
+        let needed = usize::BITS - count.saturating_sub(1).leading_zeros();
+        needed.max(min)
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn raw(&self) -> &[u64] {
+        &self.data
+    }
+
+    pub fn into_raw(self) -> Vec<u64> {
+        self.data
+    }
+
+    fn locate(&self, index: usize) -> (usize, u32) {
+        assert!(index < self.size, "index {} out of bounds", index);
+        let per_word = (64 / self.bits) as usize;
+        (index / per_word, (index % per_word) as u32 * self.bits)
+    }
+
+    fn mask(&self) -> u64 {
+        (1u64 << self.bits) - 1
+    }
+
+    pub fn get(&self, index: usize) -> u32 {
+        let (word, shift) = self.locate(index);
+        ((self.data[word] >> shift) & self.mask()) as u32
+    }
+
+    pub fn set(&mut self, index: usize, value: u32) {
+        let mask = self.mask();
+        assert!(value as u64 <= mask, "value {} doesn't fit", value);
+        let (word, shift) = self.locate(index);
+        self.data[word] = (self.data[word] & !(mask << shift)) | ((value as u64) << shift);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.size).map(|i| self.get(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut s = BitStorage::new(5, 4096);
+        for i in 0..4096 {
+            s.set(i, (i % 31) as u32);
+        }
+        // 12 entries per word
+        assert_eq!(s.raw().len(), 342);
+        assert!(s.iter().enumerate().all(|(i, v)| v == (i % 31) as u32));
+    }
+
+    #[test]
+    fn bits_for() {
+        assert_eq!(BitStorage::bits_for(1, 4), 4);
+        assert_eq!(BitStorage::bits_for(16, 4), 4);
+        assert_eq!(BitStorage::bits_for(17, 4), 5);
+        assert_eq!(BitStorage::bits_for(2, 1), 1);
+    }
+}
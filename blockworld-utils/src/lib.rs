@@ -4,14 +4,19 @@ use std::{
     sync::{Arc, Mutex},
 };
 
+pub mod atlas_image;
+mod bit_storage;
 mod constants;
 mod registry;
 mod resource;
+mod temp_dir;
 
+pub use bit_storage::BitStorage;
 pub use constants::*;
 pub use registry::Registry;
 pub use resource::resource_location::HasResourceLocation;
 pub use resource::resource_location::ResourceLocation;
+pub use temp_dir::TempDir;
 
 pub type AM<T> = Arc<Mutex<T>>;
 pub type RR<T> = Rc<RefCell<T>>;
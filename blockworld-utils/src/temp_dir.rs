@@ -0,0 +1,60 @@
+use std::{
+    fs, io,
+    ops::Deref,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// A folder of our own in the system temp dir, `blockworld-{name}-{pid}`. It starts empty and
+/// is removed with everything in it when dropped, also when a test panics halfway.
+#[derive(Debug)]
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Make the folder, clearing whatever an earlier run of the same process id left there.
+    pub fn new(name: &str) -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!("blockworld-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path)?;
+        Ok(Self(path))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_when_dropped() {
+        let dir = TempDir::new("temp-dir").unwrap();
+        let path = dir.to_path_buf();
+        fs::write(dir.join("file"), "x").unwrap();
+        assert!(path.join("file").exists());
+        drop(dir);
+        assert!(!path.exists());
+    }
+}